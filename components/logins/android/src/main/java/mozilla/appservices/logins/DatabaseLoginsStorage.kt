@@ -332,6 +332,13 @@ class DatabaseLoginsStorage(private val dbPath: String) : AutoCloseable, LoginsS
         }
     }
 
+    @Throws(LoginsStorageException::class)
+    override fun generatePassword(rules: String?): String {
+        return rustCall { error ->
+            PasswordSyncAdapter.INSTANCE.sync15_passwords_generate_password(rules, error)
+        }.getAndConsumeRustString()
+    }
+
     @Synchronized
     @Throws(LoginsStorageException::class)
     override fun close() {
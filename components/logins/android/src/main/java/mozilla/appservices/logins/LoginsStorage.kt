@@ -169,7 +169,7 @@ interface LoginsStorage : AutoCloseable {
      *
      * This will return an error result if a GUID is provided but
      * collides with an existing record, or if the provided record
-     * is invalid (missing password, hostname, or doesn't have exactly
+     * is invalid (missing password, origin, or doesn't have exactly
      * one of formSubmitURL and httpRealm).
      *
      * @throws [IdCollisionException] if a nonempty id is provided, and
@@ -193,7 +193,7 @@ interface LoginsStorage : AutoCloseable {
      *
      * This will return an error if `login.id` does not refer to
      * a record that exists in the database, or if the provided record
-     * is invalid (missing password, hostname, or doesn't have exactly
+     * is invalid (missing password, origin, or doesn't have exactly
      * one of formSubmitURL and httpRealm).
      *
      * Like `add`, this function will ignore values in metadata
@@ -252,9 +252,22 @@ interface LoginsStorage : AutoCloseable {
      * Get the list of potential duplciates of `login`, with the exception of the
      * username field, which is entirely ignored.
      *
-     * For clarity, only the record's `hostname`, `httpRealm`, and `formSubmitURL`
+     * For clarity, only the record's `origin`, `httpRealm`, and `formSubmitURL`
      * are inspected.
      */
     @Throws(LoginsStorageException::class)
     fun potentialDupesIgnoringUsername(login: ServerPassword): List<ServerPassword>
+
+    /**
+     * Generates a random password which satisfies `rules`, given in the
+     * `passwordrules` attribute syntax, like `"minlength: 20; required: digit;"`.
+     * Null or empty rules use the defaults. This works whether or not the
+     * database is locked.
+     *
+     * @throws [InvalidPasswordRulesException] if no password can satisfy the rules, or they
+     * ask for non-ASCII characters.
+     * @throws [LoginsStorageException] On unexpected errors (rust panics, etc)
+     */
+    @Throws(LoginsStorageException::class)
+    fun generatePassword(rules: String? = null): String
 }
@@ -54,6 +54,13 @@ class RequestFailedException(msg: String) : LoginsStorageException(msg)
  */
 class InterruptedException(msg: String) : LoginsStorageException(msg)
 
+/**
+ * This error is emitted if no password can satisfy the rules passed to
+ * `generatePassword()`, for example if they require more character classes
+ * than the maximum length allows.
+ */
+class InvalidPasswordRulesException(msg: String) : LoginsStorageException(msg)
+
 /**
  * A reason a login may be invalid
  */
@@ -19,10 +19,10 @@ data class ServerPassword(
    val id: String,
 
     /**
-     * The hostname this record corresponds to. It is an error to
-     * attempt to insert or update a record to have a blank hostname.
+     * The origin this record corresponds to, for example "https://www.example.com".
+     * It is an error to attempt to insert or update a record to have a blank origin.
      */
-    val hostname: String,
+    val origin: String,
 
     val username: String,
 
@@ -65,13 +65,24 @@ data class ServerPassword(
     val timePasswordChanged: Long = 0L,
 
     val usernameField: String,
-    val passwordField: String
+    val passwordField: String,
+
+    /**
+     * A version of [origin] suitable for showing to the user, with any punycode
+     * decoded into unicode. This is filled in when reading records, and ignored when
+     * writing them.
+     */
+    val displayOrigin: String? = null
 ) {
 
+    @Deprecated("Renamed to origin", ReplaceWith("origin"))
+    val hostname: String
+        get() = origin
+
     fun toProtobuf(): MsgTypes.PasswordInfo {
         val builder = MsgTypes.PasswordInfo.newBuilder()
                 .setId(this.id)
-                .setHostname(this.hostname)
+                .setOrigin(this.origin)
                 .setPassword(this.password)
                 .setUsername(this.username)
                 .setUsernameField(this.usernameField)
@@ -90,7 +101,7 @@ data class ServerPassword(
         fun fromMessage(msg: MsgTypes.PasswordInfo): ServerPassword {
             return ServerPassword(
                 id = msg.id,
-                hostname = msg.hostname,
+                origin = msg.origin,
                 username = msg.username,
                 password = msg.password,
                 httpRealm = if (msg.hasHttpRealm()) msg.httpRealm else null,
@@ -100,7 +111,8 @@ data class ServerPassword(
                 timeLastUsed = msg.timeLastUsed,
                 timePasswordChanged = msg.timePasswordChanged,
                 usernameField = msg.usernameField,
-                passwordField = msg.passwordField
+                passwordField = msg.passwordField,
+                displayOrigin = if (msg.hasDisplayOrigin()) msg.displayOrigin else null
             )
         }
 
@@ -75,6 +75,10 @@ internal interface PasswordSyncAdapter : Library {
     // Returns a JSON string containing import metrics
     fun sync15_passwords_import(handle: LoginsDbHandle, data: Pointer, len: Int, error: RustError.ByReference): Pointer?
 
+    // Returns a newly generated password. `rules` uses the `passwordrules` attribute syntax, and
+    // may be null to use the default rules.
+    fun sync15_passwords_generate_password(rules: String?, error: RustError.ByReference): Pointer?
+
     fun sync15_passwords_destroy_string(p: Pointer)
     fun sync15_passwords_destroy_buffer(b: RustBuffer.ByValue)
 
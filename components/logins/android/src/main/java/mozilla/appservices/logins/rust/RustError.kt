@@ -8,6 +8,7 @@ import com.sun.jna.Pointer
 import com.sun.jna.Structure
 import mozilla.appservices.logins.IdCollisionException
 import mozilla.appservices.logins.InvalidKeyException
+import mozilla.appservices.logins.InvalidPasswordRulesException
 import mozilla.appservices.logins.InvalidRecordException
 import mozilla.appservices.logins.InvalidLoginReason
 import mozilla.appservices.logins.LoginsStorageException
@@ -51,6 +52,7 @@ open class RustError : Structure() {
             4 -> return InvalidKeyException(message)
             5 -> return RequestFailedException(message)
             6 -> return InterruptedException(message)
+            8 -> return InvalidPasswordRulesException(message)
 
             64 -> return InvalidRecordException(message, InvalidLoginReason.EMPTY_ORIGIN)
             65 -> return InvalidRecordException(message, InvalidLoginReason.EMPTY_PASSWORD)
@@ -46,7 +46,7 @@ class DatabaseLoginsStorageTest {
 
         store.add(ServerPassword(
                 id = "aaaaaaaaaaaa",
-                hostname = "https://www.example.com",
+                origin = "https://www.example.com",
                 httpRealm = "Something",
                 username = "Foobar2000",
                 password = "hunter2",
@@ -57,7 +57,7 @@ class DatabaseLoginsStorageTest {
         store.add(ServerPassword(
                 id = "bbbbbbbbbbbb",
                 username = "Foobar2000",
-                hostname = "https://www.example.org",
+                origin = "https://www.example.org",
                 formSubmitURL = "https://www.example.org/login",
                 password = "MyVeryCoolPassword",
                 usernameField = "users_name",
@@ -98,7 +98,7 @@ class DatabaseLoginsStorageTest {
 
         store.add(ServerPassword(
                 id = "aaaaaaaaaaaa",
-                hostname = "https://www.example.com",
+                origin = "https://www.example.com",
                 httpRealm = "Something",
                 username = "Foobar2000",
                 password = "hunter2",
@@ -184,7 +184,7 @@ class DatabaseLoginsStorageTest {
 
         store.add(ServerPassword(
                 id = "aaaaaaaaaaaa",
-                hostname = "https://www.example.com",
+                origin = "https://www.example.com",
                 httpRealm = "Something",
                 username = "Foobar2000",
                 password = "hunter2",
@@ -200,7 +200,7 @@ class DatabaseLoginsStorageTest {
             // N.B. this is invalid due to `formSubmitURL` being an invalid url.
             store.add(ServerPassword(
                     id = "bbbbbbbbbbbb",
-                    hostname = "https://test.example.com",
+                    origin = "https://test.example.com",
                     formSubmitURL = "not a url",
                     username = "Foobar2000",
                     password = "hunter2",
@@ -221,7 +221,7 @@ class DatabaseLoginsStorageTest {
         assert(!LoginsStoreMetrics.readQueryErrorCount["storage_error"].testHasValue())
 
         val record = store.get("aaaaaaaaaaaa")!!
-        assertEquals(record.hostname, "https://www.example.com")
+        assertEquals(record.origin, "https://www.example.com")
 
         assert(LoginsStoreMetrics.readQueryTime.testHasValue())
         assertEquals(LoginsStoreMetrics.readQueryCount.testGetValue(), 1)
@@ -347,7 +347,7 @@ class DatabaseLoginsStorageTest {
         expectException(IdCollisionException::class.java) {
             test.add(ServerPassword(
                     id = "aaaaaaaaaaaa",
-                    hostname = "https://www.foo.org",
+                    origin = "https://www.foo.org",
                     httpRealm = "Some Realm",
                     password = "MyPassword",
                     username = "MyUsername",
@@ -364,7 +364,7 @@ class DatabaseLoginsStorageTest {
 
         val toInsert = ServerPassword(
                 id = "",
-                hostname = "https://www.foo.org",
+                origin = "https://www.foo.org",
                 httpRealm = "Some Realm",
                 password = "MyPassword",
                 username = "Foobar2000",
@@ -376,7 +376,7 @@ class DatabaseLoginsStorageTest {
 
         val record = test.get(generatedID)!!
         assertEquals(generatedID, record.id)
-        assertEquals(toInsert.hostname, record.hostname)
+        assertEquals(toInsert.origin, record.origin)
         assertEquals(toInsert.httpRealm, record.httpRealm)
         assertEquals(toInsert.password, record.password)
         assertEquals(toInsert.username, record.username)
@@ -391,7 +391,7 @@ class DatabaseLoginsStorageTest {
 
         val specificID = test.add(ServerPassword(
                 id = "123412341234",
-                hostname = "http://www.bar.com",
+                origin = "http://www.bar.com",
                 formSubmitURL = "http://login.bar.com",
                 password = "DummyPassword",
                 username = "DummyUsername",
@@ -411,7 +411,7 @@ class DatabaseLoginsStorageTest {
 
         test.add(ServerPassword(
                 id = "bbbbb",
-                hostname = "https://www.foo.org",
+                origin = "https://www.foo.org",
                 httpRealm = "Some Realm",
                 password = "MyPassword",
                 username = "MyUsername",
@@ -421,7 +421,7 @@ class DatabaseLoginsStorageTest {
 
         val dupeLogin = ServerPassword(
                 id = "",
-                hostname = "https://www.foo.org",
+                origin = "https://www.foo.org",
                 httpRealm = "Some Realm",
                 password = "MyPassword",
                 username = "MyUsername",
@@ -431,7 +431,7 @@ class DatabaseLoginsStorageTest {
 
         val nullValueLogin = ServerPassword(
                 id = "",
-                hostname = "https://www.test.org",
+                origin = "https://www.test.org",
                 httpRealm = "Some Other Realm",
                 password = "MyPassword",
                 username = "\u0000MyUsername2",
@@ -457,7 +457,7 @@ class DatabaseLoginsStorageTest {
 
         val savedLogin1 = ServerPassword(
                 id = "bbbbb",
-                hostname = "https://www.foo.org",
+                origin = "https://www.foo.org",
                 httpRealm = "Some Realm",
                 password = "MyPassword",
                 username = "MyUsername",
@@ -469,7 +469,7 @@ class DatabaseLoginsStorageTest {
 
         val dupeLogin = ServerPassword(
                 id = "",
-                hostname = "https://www.foo.org",
+                origin = "https://www.foo.org",
                 httpRealm = "Some Realm",
                 password = "MyPassword",
                 username = "MySecondUsername",
@@ -492,7 +492,7 @@ class DatabaseLoginsStorageTest {
         expectException(NoSuchRecordException::class.java) {
             test.update(ServerPassword(
                     id = "123412341234",
-                    hostname = "https://www.foo.org",
+                    origin = "https://www.foo.org",
                     httpRealm = "Some Realm",
                     password = "MyPassword",
                     username = "MyUsername",
@@ -519,7 +519,7 @@ class DatabaseLoginsStorageTest {
         test.update(toUpdate)
 
         val record = test.get(toUpdate.id)!!
-        assertEquals(toUpdate.hostname, record.hostname)
+        assertEquals(toUpdate.origin, record.origin)
         assertEquals(toUpdate.httpRealm, record.httpRealm)
         assertEquals(toUpdate.password, record.password)
         assertEquals(toUpdate.username, record.username)
@@ -536,7 +536,7 @@ class DatabaseLoginsStorageTest {
 
         val specificID = test.add(ServerPassword(
                 id = "123412341234",
-                hostname = "http://www.bar.com",
+                origin = "http://www.bar.com",
                 formSubmitURL = "http://login.bar.com",
                 password = "DummyPassword",
                 username = "DummyUsername",
@@ -549,6 +549,23 @@ class DatabaseLoginsStorageTest {
         finishAndClose(test)
     }
 
+    @Test
+    fun testGeneratePassword() {
+        val test = getTestStore()
+
+        // This doesn't need the database to be unlocked.
+        assertEquals(15, test.generatePassword().length)
+        val password = test.generatePassword("minlength: 20; required: digit;")
+        assertEquals(20, password.length)
+        assertTrue(password.any { it.isDigit() })
+
+        expectException(InvalidPasswordRulesException::class.java) {
+            test.generatePassword("maxlength: 2; required: upper; required: lower; required: digit;")
+        }
+
+        finishAndClose(test)
+    }
+
     @Test
     @Suppress("DEPRECATION")
     fun testUnlockAfterError() {
@@ -570,7 +587,7 @@ class DatabaseLoginsStorageTest {
                 // Invalid formSubmitURL
                 ServerPassword(
                         id = "",
-                        hostname = "https://www.foo.org",
+                        origin = "https://www.foo.org",
                         formSubmitURL = "invalid\u0000value",
                         password = "MyPassword",
                         username = "MyUsername",
@@ -580,7 +597,7 @@ class DatabaseLoginsStorageTest {
                 // Neither formSubmitURL nor httpRealm
                 ServerPassword(
                         id = "",
-                        hostname = "https://www.foo.org",
+                        origin = "https://www.foo.org",
                         password = "MyPassword",
                         username = "MyUsername",
                         usernameField = "",
@@ -589,17 +606,17 @@ class DatabaseLoginsStorageTest {
                 // Empty password
                 ServerPassword(
                         id = "",
-                        hostname = "https://www.foo.org",
+                        origin = "https://www.foo.org",
                         httpRealm = "Some Realm",
                         password = "",
                         username = "MyUsername",
                         usernameField = "",
                         passwordField = ""
                 ),
-                // Empty hostname
+                // Empty origin
                 ServerPassword(
                         id = "",
-                        hostname = "",
+                        origin = "",
                         httpRealm = "Some Realm",
                         password = "MyPassword",
                         username = "MyUsername",
@@ -24,7 +24,7 @@ fn read_login() -> Login {
     let username = prompt_string("username").unwrap_or_default();
     let password = prompt_string("password").unwrap_or_default();
     let form_submit_url = prompt_string("form_submit_url");
-    let hostname = prompt_string("hostname").unwrap_or_default();
+    let origin = prompt_string("origin").unwrap_or_default();
     let http_realm = prompt_string("http_realm");
     let username_field = prompt_string("username_field").unwrap_or_default();
     let password_field = prompt_string("password_field").unwrap_or_default();
@@ -36,7 +36,7 @@ fn read_login() -> Login {
         password_field,
         form_submit_url,
         http_realm,
-        hostname,
+        origin,
         ..Login::default()
     };
 
@@ -67,7 +67,7 @@ fn string_opt_or<'a>(o: &'a Option<String>, or: &'a str) -> &'a str {
 fn update_login(record: &mut Login) {
     update_string("username", &mut record.username, ", leave blank to keep");
     update_string("password", &mut record.password, ", leave blank to keep");
-    update_string("hostname", &mut record.hostname, ", leave blank to keep");
+    update_string("origin", &mut record.origin, ", leave blank to keep");
 
     update_string(
         "username_field",
@@ -192,7 +192,7 @@ fn show_all(engine: &PasswordEngine) -> Result<Vec<Guid>> {
             &rec.username,
             Fd->&rec.password,
 
-            &rec.hostname,
+            &rec.origin,
             string_opt_or(&rec.form_submit_url, ""),
             string_opt_or(&rec.http_realm, ""),
 
@@ -298,7 +298,7 @@ fn main() -> Result<()> {
     }
 
     loop {
-        match prompt_char("[A]dd, [D]elete, [U]pdate, [S]ync, [V]iew, [B]ase-domain search, [R]eset, [W]ipe, [T]ouch, E[x]ecute SQL Query, or [Q]uit").unwrap_or('?') {
+        match prompt_char("[A]dd, [D]elete, [U]pdate, [S]ync, [V]iew, [B]ase-domain search, [R]eset, [W]ipe, [T]ouch, [C]heck consistency, E[x]ecute SQL Query, or [Q]uit").unwrap_or('?') {
             'A' | 'a' => {
                 log::info!("Adding new record");
                 let record = read_login();
@@ -404,6 +404,24 @@ fn main() -> Result<()> {
                     _ => {}
                 }
             }
+            'C' | 'c' => {
+                log::info!("Checking the consistency of the store");
+                let repair = prompt_bool("Repair any problems found? [yN]").unwrap_or(false);
+                match engine.validate_and_repair(repair) {
+                    Err(e) => {
+                        log::warn!("Consistency check failed! {}", e);
+                    }
+                    Ok(problems) if problems.is_empty() => {
+                        log::info!("No problems found.");
+                    }
+                    Ok(problems) => {
+                        log::info!("Found {} problems{}: {}",
+                                   problems.len(),
+                                   if repair { " (repaired)" } else { "" },
+                                   serde_json::to_string_pretty(&problems).unwrap());
+                    }
+                }
+            }
             'x' | 'X' => {
                 log::info!("Running arbitrary SQL, there's no way this could go wrong!");
                 if let Some(sql) = prompt_string("SQL (one line only, press enter when done):\n") {
@@ -14,7 +14,7 @@ use ffi_support::{
     define_string_destructor, ByteBuffer, ExternError, FfiStr,
 };
 use logins::msg_types::{PasswordInfo, PasswordInfos};
-use logins::{Login, LoginDb, PasswordEngine, Result};
+use logins::{generate_password, Login, LoginDb, PasswordEngine, PasswordRules, Result};
 use std::os::raw::c_char;
 use std::sync::{Arc, Mutex};
 
@@ -225,6 +225,21 @@ pub extern "C" fn sync15_passwords_touch(handle: u64, id: FfiStr<'_>, error: &mu
     })
 }
 
+/// Generates a password which satisfies `rules`, given in the
+/// `passwordrules` attribute syntax. A null or empty `rules` uses the
+/// default rules. This doesn't need an engine handle.
+#[no_mangle]
+pub extern "C" fn sync15_passwords_generate_password(
+    rules: FfiStr<'_>,
+    error: &mut ExternError,
+) -> *mut c_char {
+    log::debug!("sync15_passwords_generate_password");
+    ffi_support::call_with_result(error, || {
+        let rules = PasswordRules::parse(rules.as_opt_str().unwrap_or_default())?;
+        generate_password(&rules)
+    })
+}
+
 // Should we put this function in ffi_support as a `unsafe pub fn`?
 unsafe fn get_buffer<'a>(data: *const u8, len: i32) -> &'a [u8] {
     assert!(len >= 0, "Bad buffer len: {}", len);
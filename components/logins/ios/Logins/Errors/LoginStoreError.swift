@@ -45,6 +45,10 @@ public enum LoginsStoreError: LocalizedError {
     /// database was invalid.
     case invalidSalt(message: String)
 
+    /// This error is emitted if no password can satisfy the rules passed to
+    /// `generatePassword(rules:)`.
+    case invalidPasswordRules(message: String)
+
     /// Our implementation of the localizedError protocol -- (This shows up in Sentry)
     public var errorDescription: String? {
         switch self {
@@ -68,6 +72,8 @@ public enum LoginsStoreError: LocalizedError {
             return "LoginsStoreError.interrupted: \(message)"
         case let .invalidSalt(message):
             return "LoginsStoreError.invalidSalt: \(message)"
+        case let .invalidPasswordRules(message):
+            return "LoginsStoreError.invalidPasswordRules: \(message)"
         }
     }
 
@@ -126,6 +132,9 @@ public enum LoginsStoreError: LocalizedError {
         case Sync15Passwords_InvalidSaltError:
             return .invalidSalt(message: String(freeingRustString: message!))
 
+        case Sync15Passwords_InvalidPasswordRulesError:
+            return .invalidPasswordRules(message: String(freeingRustString: message!))
+
         default:
             return .unspecified(message: String(freeingRustString: message!))
         }
@@ -11,10 +11,21 @@ open class LoginRecord {
     /// will be emitted.
     public var id: String
 
-    /// This record's hostname. Required. Attempting to insert
-    /// or update a record to have a blank hostname, will result in a
-    /// `LoginsStoreError.InvalidLogin`.
-    public var hostname: String
+    /// This record's origin, for example "https://www.example.com". Required.
+    /// Attempting to insert or update a record to have a blank origin, will
+    /// result in a `LoginsStoreError.InvalidLogin`.
+    public var origin: String
+
+    @available(*, deprecated, renamed: "origin")
+    public var hostname: String {
+        get { return origin }
+        set { origin = newValue }
+    }
+
+    /// A version of `origin` suitable for showing to the user, with any
+    /// punycode decoded into unicode. This is filled in when reading records,
+    /// and ignored by `add` and `update`.
+    public var displayOrigin: String?
 
     /// This record's password. Required. Attempting to insert
     /// or update a record to have a blank password, will result in a
@@ -76,7 +87,7 @@ open class LoginRecord {
         var dict: [String: Any] = [
             "id": id,
             "password": password,
-            "hostname": hostname,
+            "origin": origin,
 
             "timesUsed": timesUsed,
             "timeCreated": timeCreated,
@@ -102,7 +113,7 @@ open class LoginRecord {
     internal func toProtobuf() -> MsgTypes_PasswordInfo {
         var buf = MsgTypes_PasswordInfo()
         buf.id = id
-        buf.hostname = hostname
+        buf.origin = origin
         buf.password = password
         buf.username = username
         buf.timesUsed = Int64(timesUsed)
@@ -128,7 +139,8 @@ open class LoginRecord {
         self.init(
             id: dict["id"] as? String ?? "",
             password: dict["password"] as? String ?? "",
-            hostname: dict["hostname"] as? String ?? "",
+            // Older versions wrote `hostname`.
+            origin: dict["origin"] as? String ?? dict["hostname"] as? String ?? "",
 
             username: dict["username"] as? String ?? "",
 
@@ -147,7 +159,7 @@ open class LoginRecord {
 
     init(id: String,
          password: String,
-         hostname: String,
+         origin: String,
          username: String,
          formSubmitURL: String?,
          httpRealm: String?,
@@ -156,10 +168,11 @@ open class LoginRecord {
          timeCreated: Int64?,
          timePasswordChanged: Int64?,
          usernameField: String,
-         passwordField: String) {
+         passwordField: String,
+         displayOrigin: String? = nil) {
         self.id = id
         self.password = password
-        self.hostname = hostname
+        self.origin = origin
         self.username = username
         self.formSubmitURL = formSubmitURL
         self.httpRealm = httpRealm
@@ -169,6 +182,7 @@ open class LoginRecord {
         self.timePasswordChanged = timePasswordChanged ?? 0
         self.usernameField = usernameField
         self.passwordField = passwordField
+        self.displayOrigin = displayOrigin
     }
 
     public convenience init(fromJSONString json: String) throws {
@@ -192,7 +206,7 @@ internal func unpackProtobufInfo(msg: MsgTypes_PasswordInfo) -> LoginRecord {
     return LoginRecord(
         id: msg.id,
         password: msg.password,
-        hostname: msg.hostname,
+        origin: msg.origin,
         username: msg.username,
         formSubmitURL: msg.hasFormSubmitURL ? msg.formSubmitURL : nil,
         httpRealm: msg.hasHTTPRealm ? msg.httpRealm : nil,
@@ -201,7 +215,8 @@ internal func unpackProtobufInfo(msg: MsgTypes_PasswordInfo) -> LoginRecord {
         timeCreated: msg.timeCreated,
         timePasswordChanged: msg.timePasswordChanged,
         usernameField: msg.usernameField,
-        passwordField: msg.passwordField
+        passwordField: msg.passwordField,
+        displayOrigin: msg.hasDisplayOrigin ? msg.displayOrigin : nil
     )
 }
 
@@ -32,6 +32,20 @@ open class LoginsStorage {
         }
     }
 
+    /// Generate a random password which satisfies `rules`, given in the
+    /// `passwordrules` attribute syntax, like `"minlength: 20; required: digit;"`.
+    /// Nil or empty rules use the defaults. This doesn't need an unlocked
+    /// database.
+    ///
+    /// Throws `LoginStoreError.invalidPasswordRules` if no password can satisfy
+    /// the rules, or they ask for non-ASCII characters.
+    public static func generatePassword(rules: String? = nil) throws -> String {
+        let ptr = try LoginsStoreError.unwrap { err in
+            sync15_passwords_generate_password(rules, err)
+        }
+        return String(freeingRustString: ptr)
+    }
+
     private func doDestroy() {
         let raw = self.raw
         self.raw = 0
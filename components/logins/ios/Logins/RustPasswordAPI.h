@@ -16,6 +16,7 @@ typedef enum Sync15PasswordsErrorCode {
     Sync15Passwords_NetworkError     = 5,
     Sync15Passwords_InterruptedError = 6,
     Sync15Passwords_InvalidSaltError = 7,
+    Sync15Passwords_InvalidPasswordRulesError = 8,
 
     Sync15Passwords_InvalidLogin_EmptyOrigin = 64 + 0,
     Sync15Passwords_InvalidLogin_EmptyPassword = 64 + 1,
@@ -135,6 +136,9 @@ void sync15_passwords_update(Sync15PasswordEngineHandle handle,
                              int32_t len,
                              Sync15PasswordsError *_Nonnull error);
 
+char *_Nullable sync15_passwords_generate_password(char const *_Nullable rules,
+                                                   Sync15PasswordsError *_Nonnull error);
+
 void sync15_passwords_destroy_buffer(Sync15PasswordsRustBuffer bb);
 
 void sync15_passwords_destroy_string(char const *_Nonnull str);
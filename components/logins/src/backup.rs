@@ -0,0 +1,333 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Portable, passphrase-protected backups of the logins store.
+//!
+//! Unlike the database itself (which is encrypted with a key that's tied to
+//! the device), a backup is encrypted with a key derived from a passphrase
+//! the user chooses, so it can be restored anywhere.
+//!
+//! A backup file has the following layout:
+//!
+//! - `MAGIC` (8 bytes, which include the format version)
+//! - The PBKDF2 salt (`SALT_LEN` bytes)
+//! - The PBKDF2 iteration count (a big-endian u32)
+//! - The AES-256-GCM nonce
+//! - The AES-256-GCM ciphertext and tag
+//!
+//! Everything before the ciphertext is authenticated as the AAD. The
+//! plaintext is a JSON-serialized `BackupContents`, where each login uses the
+//! same representation as a sync record.
+//!
+//! Restoring a backup merges it into the store through the same code paths
+//! as `add` and `update`, so the mirror is never touched and the restored
+//! changes are uploaded on the next sync.
+
+use crate::db::LoginDb;
+use crate::error::*;
+use crate::login::Login;
+use crate::util;
+use rc_crypto::{aead, digest, pbkdf2, rand};
+use serde_derive::*;
+use std::convert::TryInto;
+use std::time::SystemTime;
+use sync_guid::Guid;
+
+const MAGIC: &[u8; 8] = b"mozlgns\x01";
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const DEFAULT_ITERATIONS: u32 = 100_000;
+// The iteration count comes from the file, so we cap it to keep a crafted
+// one from making `restore_backup` run for hours before we can check it.
+const MAX_ITERATIONS: u32 = 10 * DEFAULT_ITERATIONS;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + 4;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BackupContents {
+    // Milliseconds since the unix epoch.
+    time_created: i64,
+    logins: Vec<Login>,
+}
+
+/// The result of restoring a backup.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct RestoreMetrics {
+    pub num_processed: u64,
+    /// Logins which didn't exist in the store, and were added.
+    pub num_added: u64,
+    /// Logins which existed in the store, but whose password was changed
+    /// more recently in the backup.
+    pub num_updated: u64,
+    /// Logins which were already present (or were a dupe of an existing
+    /// login), and so were left alone.
+    pub num_unchanged: u64,
+    pub num_failed: u64,
+    /// Error labels for each failed login. Never contains login data.
+    pub errors: Vec<String>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<Vec<u8>> {
+    let mut key = vec![0u8; KEY_LEN];
+    pbkdf2::derive(
+        &digest::SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    )?;
+    Ok(key)
+}
+
+fn encrypt(plaintext: &[u8], passphrase: &str, iterations: u32) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    rand::fill(&mut salt)?;
+    let mut nonce = vec![0u8; aead::AES_256_GCM.nonce_len()];
+    rand::fill(&mut nonce)?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN + nonce.len() + plaintext.len() + 16);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&iterations.to_be_bytes());
+    out.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt, iterations)?;
+    let sealing_key = aead::SealingKey::new(&aead::AES_256_GCM, &key)?;
+    let nonce = aead::Nonce::try_assume_unique_for_key(&aead::AES_256_GCM, &nonce)?;
+    let ciphertext = aead::seal(&sealing_key, nonce, aead::Aad::from(&out), plaintext)?;
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let nonce_len = aead::AES_256_GCM.nonce_len();
+    let aad_len = HEADER_LEN + nonce_len;
+    if data.len() < aad_len + aead::AES_256_GCM.tag_len() {
+        throw!(ErrorKind::InvalidBackup("File is truncated".into()));
+    }
+    if &data[..MAGIC.len()] != MAGIC {
+        throw!(ErrorKind::InvalidBackup(
+            "Not a logins backup, or an unsupported version".into()
+        ));
+    }
+    let salt = &data[MAGIC.len()..MAGIC.len() + SALT_LEN];
+    let iterations = u32::from_be_bytes(
+        data[MAGIC.len() + SALT_LEN..HEADER_LEN]
+            .try_into()
+            .expect("slice has the right length"),
+    );
+    if iterations == 0 || iterations > MAX_ITERATIONS {
+        throw!(ErrorKind::InvalidBackup("Invalid iteration count".into()));
+    }
+    let (aad, ciphertext) = data.split_at(aad_len);
+
+    let key = derive_key(passphrase, salt, iterations)?;
+    let opening_key = aead::OpeningKey::new(&aead::AES_256_GCM, &key)?;
+    let nonce = aead::Nonce::try_assume_unique_for_key(&aead::AES_256_GCM, &aad[HEADER_LEN..])?;
+    // A failure here means either the passphrase is wrong or the file was
+    // tampered with - we can't tell which.
+    aead::open(&opening_key, nonce, aead::Aad::from(aad), ciphertext)
+        .map_err(|_| ErrorKind::BackupDecryptionFailed.into())
+}
+
+pub(crate) fn create_backup(db: &LoginDb, passphrase: &str) -> Result<Vec<u8>> {
+    create_backup_with_iterations(db, passphrase, DEFAULT_ITERATIONS)
+}
+
+fn create_backup_with_iterations(
+    db: &LoginDb,
+    passphrase: &str,
+    iterations: u32,
+) -> Result<Vec<u8>> {
+    let contents = BackupContents {
+        time_created: util::system_time_ms_i64(SystemTime::now()),
+        logins: db.get_all()?,
+    };
+    let plaintext = serde_json::to_vec(&contents)?;
+    encrypt(&plaintext, passphrase, iterations)
+}
+
+pub(crate) fn restore_backup(
+    db: &LoginDb,
+    data: &[u8],
+    passphrase: &str,
+) -> Result<RestoreMetrics> {
+    let plaintext = decrypt(data, passphrase)?;
+    let contents: BackupContents = serde_json::from_slice(&plaintext)?;
+    log::info!(
+        "Restoring {} logins from a backup created at {}",
+        contents.logins.len(),
+        contents.time_created
+    );
+    let mut metrics = RestoreMetrics::default();
+    // Each login is restored in its own transaction (by `add` or `update`),
+    // so a failure part way through leaves the store in a consistent state.
+    for login in contents.logins {
+        metrics.num_processed += 1;
+        match restore_login(db, login) {
+            Ok(RestoreOutcome::Added) => metrics.num_added += 1,
+            Ok(RestoreOutcome::Updated) => metrics.num_updated += 1,
+            Ok(RestoreOutcome::Unchanged) => metrics.num_unchanged += 1,
+            Err(e) => {
+                log::warn!("Failed to restore a login: {}", e.label());
+                metrics.num_failed += 1;
+                metrics.errors.push(e.label().into());
+            }
+        }
+    }
+    log::info!("Finished restoring backup: {:?}", metrics);
+    Ok(metrics)
+}
+
+enum RestoreOutcome {
+    Added,
+    Updated,
+    Unchanged,
+}
+
+fn restore_login(db: &LoginDb, login: Login) -> Result<RestoreOutcome> {
+    let mut login = login.fixup()?;
+    if let Some(existing) = db.get_by_id(login.guid_str())? {
+        // The record still exists, so only take the backed-up version if its
+        // password was changed more recently.
+        if login.time_password_changed <= existing.time_password_changed
+            || login.password == existing.password
+        {
+            return Ok(RestoreOutcome::Unchanged);
+        }
+        return match db.update(login) {
+            Ok(()) => Ok(RestoreOutcome::Updated),
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidLogin(InvalidLogin::DuplicateLogin) => {
+                    Ok(RestoreOutcome::Unchanged)
+                }
+                _ => Err(e),
+            },
+        };
+    }
+    // If the GUID belongs to a tombstone we need a new one, as the record was
+    // deleted after the backup was made, and we must not resurrect the
+    // tombstone's GUID without the server knowing.
+    if login.guid.is_empty() || db.guid_in_use(&login.guid)? {
+        login.guid = Guid::random();
+    }
+    if db.dupe_exists(&login)? {
+        return Ok(RestoreOutcome::Unchanged);
+    }
+    db.add(login)?;
+    Ok(RestoreOutcome::Added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Keep the tests fast.
+    const TEST_ITERATIONS: u32 = 10;
+
+    fn login(guid: &str, origin: &str, password: &str) -> Login {
+        Login {
+            guid: guid.into(),
+            origin: origin.into(),
+            form_submit_url: Some(origin.into()),
+            username: "user".into(),
+            password: password.into(),
+            ..Login::default()
+        }
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let data = encrypt(b"some data", "passphrase", TEST_ITERATIONS).unwrap();
+        assert!(data.starts_with(MAGIC));
+        assert_eq!(decrypt(&data, "passphrase").unwrap(), b"some data");
+
+        match decrypt(&data, "wrong passphrase").unwrap_err().kind() {
+            ErrorKind::BackupDecryptionFailed => {}
+            e => panic!("Unexpected error {:?}", e),
+        }
+
+        // Tampering with the header must be detected too.
+        let mut tampered = data.clone();
+        tampered[MAGIC.len()] ^= 1;
+        assert!(decrypt(&tampered, "passphrase").is_err());
+
+        let mut wrong_magic = data;
+        wrong_magic[0] = b'x';
+        match decrypt(&wrong_magic, "passphrase").unwrap_err().kind() {
+            ErrorKind::InvalidBackup(_) => {}
+            e => panic!("Unexpected error {:?}", e),
+        }
+        assert!(decrypt(b"mozlgns\x01", "passphrase").is_err());
+    }
+
+    #[test]
+    fn test_iteration_count_checked() {
+        let data = encrypt(b"some data", "passphrase", TEST_ITERATIONS).unwrap();
+        for &iterations in &[0, MAX_ITERATIONS + 1, u32::max_value()] {
+            let mut crafted = data.clone();
+            crafted[MAGIC.len() + SALT_LEN..HEADER_LEN].copy_from_slice(&iterations.to_be_bytes());
+            match decrypt(&crafted, "passphrase").unwrap_err().kind() {
+                ErrorKind::InvalidBackup(_) => {}
+                e => panic!("Unexpected error {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let db = LoginDb::open_in_memory(Some("testing")).unwrap();
+        db.add(login("aaaaaaaaaaaa", "https://www.example.com", "p1"))
+            .unwrap();
+        db.add(login("bbbbbbbbbbbb", "https://www.example2.com", "p2"))
+            .unwrap();
+        db.add(login("cccccccccccc", "https://www.example3.com", "p3"))
+            .unwrap();
+        let backup = create_backup_with_iterations(&db, "hunter2", TEST_ITERATIONS).unwrap();
+
+        // Restoring into an empty store brings everything back.
+        let other = LoginDb::open_in_memory(Some("other key")).unwrap();
+        let metrics = restore_backup(&other, &backup, "hunter2").unwrap();
+        assert_eq!(metrics.num_processed, 3);
+        assert_eq!(metrics.num_added, 3);
+        let mut restored = other.get_all().unwrap();
+        restored.sort_by(|a, b| a.guid.cmp(&b.guid));
+        let mut expected = db.get_all().unwrap();
+        expected.sort_by(|a, b| a.guid.cmp(&b.guid));
+        assert_eq!(restored, expected);
+
+        // Now change the original store, and restore into it.
+        db.delete("aaaaaaaaaaaa").unwrap();
+        let mut changed = db.get_by_id("bbbbbbbbbbbb").unwrap().unwrap();
+        changed.password = "p2-changed".into();
+        db.update(changed).unwrap();
+        let metrics = restore_backup(&db, &backup, "hunter2").unwrap();
+        assert_eq!(
+            metrics,
+            RestoreMetrics {
+                num_processed: 3,
+                num_added: 1,
+                num_updated: 0,
+                num_unchanged: 2,
+                num_failed: 0,
+                errors: vec![],
+            }
+        );
+        // The deleted login is back, but under a new GUID since its old one
+        // is still used by the tombstone.
+        assert!(db.get_by_id("aaaaaaaaaaaa").unwrap().is_none());
+        let all = db.get_all().unwrap();
+        assert_eq!(all.len(), 3);
+        assert!(all
+            .iter()
+            .any(|l| l.origin == "https://www.example.com" && l.password == "p1"));
+        // The local change is newer than the backup, so it was kept.
+        assert_eq!(
+            db.get_by_id("bbbbbbbbbbbb").unwrap().unwrap().password,
+            "p2-changed"
+        );
+
+        assert!(restore_backup(&db, &backup, "hunter3").is_err());
+    }
+}
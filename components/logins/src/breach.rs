@@ -0,0 +1,260 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Checking saved passwords against a breach corpus using k-anonymity, in the
+//! style of the "Have I Been Pwned" range API.
+//!
+//! For each password we compute the uppercase hex SHA-1, and only send the
+//! first `PREFIX_LEN` characters to the corpus. The corpus responds with
+//! every hash suffix it knows for that prefix (one `SUFFIX:COUNT` per line),
+//! and we look for our suffix locally, so the full hash never leaves the
+//! device.
+//!
+//! How the range is fetched is left to the application, via
+//! `BreachRangeFetcher`. The results are stored per login in the
+//! `loginsBreaches` table (see the `schema` module).
+
+use crate::db::LoginDb;
+use crate::error::*;
+use crate::login::Login;
+use crate::util;
+use rc_crypto::digest;
+use rusqlite::{named_params, NO_PARAMS};
+use serde_derive::*;
+use sql_support::ConnExt;
+use std::collections::HashMap;
+use std::time::SystemTime;
+use sync_guid::Guid;
+
+/// The number of hex characters of the SHA-1 hash we send to the corpus.
+pub const PREFIX_LEN: usize = 5;
+
+/// Fetches the range of known breached hashes for a prefix.
+pub trait BreachRangeFetcher {
+    /// Returns the corpus response for `prefix` (`PREFIX_LEN` uppercase hex
+    /// characters): one `SUFFIX:COUNT` line per hash which starts with the
+    /// prefix, where `SUFFIX` is the remainder of the uppercase hex SHA-1.
+    fn fetch_range(&self, prefix: &str) -> Result<String>;
+}
+
+/// The stored result of checking a login's password.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BreachStatus {
+    pub guid: Guid,
+    /// How many times the password appears in the corpus.
+    pub breach_count: i64,
+    /// When the check was done, in milliseconds since the unix epoch.
+    pub time_checked: i64,
+}
+
+fn sha1_hex(password: &str) -> Result<String> {
+    let hash = digest::digest(&digest::SHA1, password.as_bytes())?;
+    Ok(hash.as_ref().iter().map(|b| format!("{:02X}", b)).collect())
+}
+
+/// Finds `suffix` in a range response, returning its count (or 0).
+fn count_in_range(range: &str, suffix: &str) -> i64 {
+    range
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.trim().splitn(2, ':');
+            let line_suffix = parts.next()?;
+            let count = parts.next()?.trim().parse::<i64>().ok()?;
+            if line_suffix.eq_ignore_ascii_case(suffix) {
+                Some(count)
+            } else {
+                None
+            }
+        })
+        .next()
+        .unwrap_or(0)
+}
+
+/// Checks the password of every login against the corpus, storing the
+/// results. Only one request is made for each distinct prefix. Returns the
+/// status of every login which appears in the corpus.
+pub(crate) fn check_for_breaches(
+    db: &LoginDb,
+    fetcher: &dyn BreachRangeFetcher,
+) -> Result<Vec<BreachStatus>> {
+    let logins = db.get_all()?;
+    // prefix -> [(login, suffix)]
+    let mut by_prefix: HashMap<String, Vec<(&Login, String)>> = HashMap::new();
+    for login in &logins {
+        let mut hash = sha1_hex(&login.password)?;
+        let suffix = hash.split_off(PREFIX_LEN);
+        by_prefix.entry(hash).or_default().push((login, suffix));
+    }
+
+    let now_ms = util::system_time_ms_i64(SystemTime::now());
+    let mut results = Vec::with_capacity(logins.len());
+    for (prefix, entries) in &by_prefix {
+        let range = fetcher.fetch_range(prefix)?;
+        for (login, suffix) in entries {
+            results.push((*login, count_in_range(&range, suffix)));
+        }
+    }
+
+    // Only write once everything has been fetched, so a failure part way
+    // through doesn't leave a mix of old and new results.
+    let tx = db.unchecked_transaction()?;
+    db.execute("DELETE FROM loginsBreaches", NO_PARAMS)?;
+    for (login, breach_count) in &results {
+        db.execute_named_cached(
+            "INSERT INTO loginsBreaches (guid, breach_count, time_checked, time_password_changed)
+             VALUES (:guid, :breach_count, :time_checked, :time_password_changed)",
+            named_params! {
+                ":guid": login.guid,
+                ":breach_count": breach_count,
+                ":time_checked": now_ms,
+                ":time_password_changed": login.time_password_changed,
+            },
+        )?;
+    }
+    tx.commit()?;
+
+    let mut breached: Vec<BreachStatus> = results
+        .into_iter()
+        .filter(|(_, breach_count)| *breach_count > 0)
+        .map(|(login, breach_count)| BreachStatus {
+            guid: login.guid.clone(),
+            breach_count,
+            time_checked: now_ms,
+        })
+        .collect();
+    breached.sort_by(|a, b| a.guid.cmp(&b.guid));
+    Ok(breached)
+}
+
+/// Returns the stored status of every login which was found in the corpus
+/// by the last check, skipping logins whose password has changed since.
+pub(crate) fn get_breached_logins(db: &LoginDb) -> Result<Vec<BreachStatus>> {
+    let current: HashMap<Guid, i64> = db
+        .get_all()?
+        .into_iter()
+        .map(|login| (login.guid, login.time_password_changed))
+        .collect();
+    let mut stmt = db.prepare_cached(
+        "SELECT guid, breach_count, time_checked, time_password_changed
+         FROM loginsBreaches
+         WHERE breach_count > 0
+         ORDER BY guid",
+    )?;
+    let rows = stmt.query_and_then(NO_PARAMS, |row| -> Result<_> {
+        Ok((
+            BreachStatus {
+                guid: row.get("guid")?,
+                breach_count: row.get("breach_count")?,
+                time_checked: row.get("time_checked")?,
+            },
+            row.get::<_, i64>("time_password_changed")?,
+        ))
+    })?;
+    let mut breached = Vec::new();
+    for row in rows {
+        let (status, time_password_changed) = row?;
+        if current.get(&status.guid) == Some(&time_password_changed) {
+            breached.push(status);
+        }
+    }
+    Ok(breached)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    // SHA-1("password") = 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8
+    struct FakeFetcher {
+        requested: RefCell<Vec<String>>,
+    }
+
+    impl BreachRangeFetcher for FakeFetcher {
+        fn fetch_range(&self, prefix: &str) -> Result<String> {
+            self.requested.borrow_mut().push(prefix.to_owned());
+            Ok(match prefix {
+                "5BAA6" => "003D68EB55068C33ACE09247EE4C639306B:3\r\n\
+                            1E4C9B93F3F0682250B6CF8331B7EE68FD8:3861493\r\n\
+                            011053FD0102E94D6AE2F8B83D76FAF94F6:0\r\n"
+                    .to_owned(),
+                _ => String::new(),
+            })
+        }
+    }
+
+    fn add_login(db: &LoginDb, guid: &str, origin: &str, password: &str) {
+        db.add(Login {
+            guid: guid.into(),
+            origin: origin.into(),
+            form_submit_url: Some(origin.into()),
+            username: "user".into(),
+            password: password.into(),
+            ..Login::default()
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_sha1_hex() {
+        assert_eq!(
+            sha1_hex("password").unwrap(),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+    }
+
+    #[test]
+    fn test_count_in_range() {
+        let range = "1E4C9B93F3F0682250B6CF8331B7EE68FD8:12\nABC:3\n";
+        assert_eq!(
+            count_in_range(range, "1E4C9B93F3F0682250B6CF8331B7EE68FD8"),
+            12
+        );
+        assert_eq!(
+            count_in_range(range, "1e4c9b93f3f0682250b6cf8331b7ee68fd8"),
+            12
+        );
+        assert_eq!(count_in_range(range, "DEF"), 0);
+        assert_eq!(count_in_range("garbage", "DEF"), 0);
+    }
+
+    #[test]
+    fn test_check_for_breaches() {
+        let db = LoginDb::open_in_memory(Some("testing")).unwrap();
+        add_login(&db, "breached0001", "https://www.example.com", "password");
+        add_login(&db, "breached0002", "https://www.example2.com", "password");
+        add_login(&db, "notbreached1", "https://www.example3.com", "k8#Lq!2vX");
+        let fetcher = FakeFetcher {
+            requested: RefCell::new(vec![]),
+        };
+
+        let breached = check_for_breaches(&db, &fetcher).unwrap();
+        assert_eq!(
+            breached
+                .iter()
+                .map(|b| (b.guid.as_str(), b.breach_count))
+                .collect::<Vec<_>>(),
+            vec![("breached0001", 3_861_493), ("breached0002", 3_861_493)]
+        );
+        // The two identical passwords only needed one request, and we never
+        // send more than the prefix.
+        let requested = fetcher.requested.borrow();
+        assert_eq!(requested.len(), 2);
+        assert!(requested.iter().all(|p| p.len() == PREFIX_LEN));
+        assert_eq!(get_breached_logins(&db).unwrap(), breached);
+
+        // Changing the password makes the stored result stale.
+        let mut login = db.get_by_id("breached0001").unwrap().unwrap();
+        login.password = "something else".into();
+        db.update(login).unwrap();
+        let stored = get_breached_logins(&db).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].guid, "breached0002");
+
+        // As does deleting the login.
+        db.delete("breached0002").unwrap();
+        assert!(get_breached_logins(&db).unwrap().is_empty());
+    }
+}
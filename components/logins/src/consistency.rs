@@ -0,0 +1,265 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Checking (and repairing) the consistency of the local and mirror tables.
+//!
+//! Each login has at most one row in `loginsL` and at most one in `loginsM`
+//! (see the `schema` module), and the two have to agree with each other for
+//! reads and syncs to do the right thing. Bugs have left some stores where
+//! they don't, so this finds those rows and, optionally, fixes them up.
+
+use crate::error::*;
+use crate::login::SyncStatus;
+use lazy_static::lazy_static;
+use rusqlite::{Connection, NO_PARAMS};
+use serde_derive::*;
+use sync_guid::Guid;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ConsistencyProblemKind {
+    /// A local record is marked as changed, which means it's known to exist
+    /// on the server, but there's no mirror record for it. It's repaired by
+    /// marking it as new, so it's uploaded as a new record.
+    MissingMirror,
+    /// A mirror record is marked as overridden, but there's no local record
+    /// overriding it, so the login is invisible. It's repaired by clearing
+    /// the flag.
+    OverriddenWithoutLocal,
+    /// There's both a local and a mirror record, but the mirror isn't marked
+    /// as overridden, so the login is returned twice. It's repaired by
+    /// setting the flag.
+    LocalNotOverridingMirror,
+    /// A local record has a `sync_status` which means it will never be
+    /// uploaded - either `Synced`, which local records never should be, or
+    /// a value which isn't a `SyncStatus` at all. It's repaired by marking it
+    /// as changed if there's a mirror record, or as new otherwise.
+    StuckSyncStatus,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyProblem {
+    pub kind: ConsistencyProblemKind,
+    pub guid: Guid,
+}
+
+lazy_static! {
+    // Each check is the query which finds the problem, and the statement
+    // which repairs it. The repairs run in this order, after all the checks.
+    static ref CHECKS: Vec<(ConsistencyProblemKind, String, String)> = vec![
+        (
+            ConsistencyProblemKind::StuckSyncStatus,
+            format!(
+                "SELECT guid FROM loginsL
+                 WHERE sync_status NOT IN ({changed}, {new})
+                 ORDER BY guid",
+                changed = SyncStatus::Changed as u8,
+                new = SyncStatus::New as u8,
+            ),
+            format!(
+                "UPDATE loginsL
+                 SET sync_status = CASE
+                     WHEN EXISTS(SELECT 1 FROM loginsM m WHERE m.guid = loginsL.guid)
+                     THEN {changed}
+                     ELSE {new}
+                 END
+                 WHERE sync_status NOT IN ({changed}, {new})",
+                changed = SyncStatus::Changed as u8,
+                new = SyncStatus::New as u8,
+            ),
+        ),
+        (
+            ConsistencyProblemKind::MissingMirror,
+            format!(
+                "SELECT guid FROM loginsL l
+                 WHERE is_deleted = 0
+                   AND sync_status = {changed}
+                   AND NOT EXISTS(SELECT 1 FROM loginsM m WHERE m.guid = l.guid)
+                 ORDER BY guid",
+                changed = SyncStatus::Changed as u8,
+            ),
+            format!(
+                "UPDATE loginsL
+                 SET sync_status = {new}
+                 WHERE is_deleted = 0
+                   AND sync_status = {changed}
+                   AND NOT EXISTS(SELECT 1 FROM loginsM m WHERE m.guid = loginsL.guid)",
+                changed = SyncStatus::Changed as u8,
+                new = SyncStatus::New as u8,
+            ),
+        ),
+        (
+            ConsistencyProblemKind::OverriddenWithoutLocal,
+            "SELECT guid FROM loginsM m
+             WHERE is_overridden = 1
+               AND NOT EXISTS(SELECT 1 FROM loginsL l WHERE l.guid = m.guid)
+             ORDER BY guid"
+                .into(),
+            "UPDATE loginsM
+             SET is_overridden = 0
+             WHERE is_overridden = 1
+               AND NOT EXISTS(SELECT 1 FROM loginsL l WHERE l.guid = loginsM.guid)"
+                .into(),
+        ),
+        (
+            ConsistencyProblemKind::LocalNotOverridingMirror,
+            "SELECT guid FROM loginsM m
+             WHERE is_overridden IS NOT 1
+               AND EXISTS(SELECT 1 FROM loginsL l WHERE l.guid = m.guid)
+             ORDER BY guid"
+                .into(),
+            "UPDATE loginsM
+             SET is_overridden = 1
+             WHERE is_overridden IS NOT 1
+               AND EXISTS(SELECT 1 FROM loginsL l WHERE l.guid = loginsM.guid)"
+                .into(),
+        ),
+    ];
+}
+
+/// Finds every consistency problem, and repairs them if `repair` is true.
+/// Problems are returned in the order of `CHECKS`, then by guid. This should
+/// be run in a transaction, so the repairs match what was found.
+pub(crate) fn validate_and_repair(
+    conn: &Connection,
+    repair: bool,
+) -> Result<Vec<ConsistencyProblem>> {
+    let mut problems = vec![];
+    for (kind, check_sql, _) in CHECKS.iter() {
+        let mut stmt = conn.prepare(check_sql)?;
+        let guids = stmt.query_and_then(NO_PARAMS, |row| row.get::<_, Guid>(0))?;
+        for guid in guids {
+            problems.push(ConsistencyProblem {
+                kind: *kind,
+                guid: guid?,
+            });
+        }
+    }
+    if repair {
+        for (kind, _, repair_sql) in CHECKS.iter() {
+            let repaired = conn.execute(repair_sql, NO_PARAMS)?;
+            if repaired > 0 {
+                log::warn!("Repaired {} logins with problem {:?}", repaired, kind);
+            }
+        }
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::LoginDb;
+    use crate::login::Login;
+
+    fn add_login(db: &LoginDb, guid: &str) {
+        db.add(Login {
+            guid: guid.into(),
+            origin: "https://www.example.com".into(),
+            form_submit_url: Some("https://www.example.com".into()),
+            username: guid.into(),
+            password: "password".into(),
+            ..Login::default()
+        })
+        .unwrap();
+    }
+
+    fn add_mirror(db: &LoginDb, guid: &str, is_overridden: bool) {
+        db.execute_named(
+            "INSERT INTO loginsM (guid, origin, formSubmitURL, username, password,
+                                  timeCreated, timePasswordChanged, server_modified,
+                                  is_overridden)
+             VALUES (:guid, 'https://www.example.com', '', :guid, 'password', 1, 1, 1,
+                     :is_overridden)",
+            rusqlite::named_params! { ":guid": guid, ":is_overridden": is_overridden },
+        )
+        .unwrap();
+    }
+
+    fn set_sync_status(db: &LoginDb, guid: &str, status: u8) {
+        db.execute_named(
+            "UPDATE loginsL SET sync_status = :status WHERE guid = :guid",
+            rusqlite::named_params! { ":guid": guid, ":status": status },
+        )
+        .unwrap();
+    }
+
+    fn kinds(problems: Vec<ConsistencyProblem>) -> Vec<(ConsistencyProblemKind, String)> {
+        problems
+            .into_iter()
+            .map(|p| (p.kind, p.guid.into_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_and_repair() {
+        let db = LoginDb::open_in_memory(Some("testing")).unwrap();
+        // A healthy local-only record, and a healthy mirror-only record.
+        add_login(&db, "healthy_0001");
+        add_mirror(&db, "healthy_0002", false);
+        assert!(db.validate_and_repair(false).unwrap().is_empty());
+
+        add_login(&db, "stuck_000001");
+        set_sync_status(&db, "stuck_000001", SyncStatus::Synced as u8);
+        add_login(&db, "stuck_000002");
+        add_mirror(&db, "stuck_000002", true);
+        set_sync_status(&db, "stuck_000002", 7);
+        add_login(&db, "orphan_00001");
+        set_sync_status(&db, "orphan_00001", SyncStatus::Changed as u8);
+        add_mirror(&db, "hidden_00001", true);
+        add_login(&db, "twice_000001");
+        add_mirror(&db, "twice_000001", false);
+
+        let expected = vec![
+            (
+                ConsistencyProblemKind::StuckSyncStatus,
+                "stuck_000001".to_string(),
+            ),
+            (
+                ConsistencyProblemKind::StuckSyncStatus,
+                "stuck_000002".to_string(),
+            ),
+            (
+                ConsistencyProblemKind::MissingMirror,
+                "orphan_00001".to_string(),
+            ),
+            (
+                ConsistencyProblemKind::OverriddenWithoutLocal,
+                "hidden_00001".to_string(),
+            ),
+            (
+                ConsistencyProblemKind::LocalNotOverridingMirror,
+                "twice_000001".to_string(),
+            ),
+        ];
+        assert_eq!(kinds(db.validate_and_repair(false).unwrap()), expected);
+        // Only checking doesn't change anything.
+        assert_eq!(db.validate_and_repair(false).unwrap().len(), expected.len());
+
+        assert_eq!(db.validate_and_repair(true).unwrap().len(), expected.len());
+        assert!(db.validate_and_repair(false).unwrap().is_empty());
+
+        let status = |guid: &str| -> u8 {
+            db.query_row_named(
+                "SELECT sync_status FROM loginsL WHERE guid = :guid",
+                rusqlite::named_params! { ":guid": guid },
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(status("stuck_000001"), SyncStatus::New as u8);
+        assert_eq!(status("stuck_000002"), SyncStatus::Changed as u8);
+        assert_eq!(status("orphan_00001"), SyncStatus::New as u8);
+        assert!(db.get_by_id("hidden_00001").unwrap().is_some());
+        assert_eq!(
+            db.get_all()
+                .unwrap()
+                .iter()
+                .filter(|l| l.guid == "twice_000001")
+                .count(),
+            1
+        );
+    }
+}
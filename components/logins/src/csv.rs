@@ -0,0 +1,383 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! CSV import and export of logins.
+//!
+//! We use the same column layout as desktop Firefox's "Export Logins" feature:
+//!
+//! ```text
+//! "url","username","password","httpRealm","formActionOrigin","guid","timeCreated","timeLastUsed","timePasswordChanged"
+//! ```
+//!
+//! On import, columns are matched by (case-insensitive) header name and
+//! unknown columns are ignored, which means files exported from Chrome
+//! (`name,url,username,password`) can be imported too. Only `url` and
+//! `password` are required.
+
+use crate::db::MigrationMetrics;
+use crate::error::*;
+use crate::login::Login;
+use serde_derive::*;
+use sync_guid::Guid;
+
+/// The header row we write on export.
+pub const CSV_HEADER: [&str; 9] = [
+    "url",
+    "username",
+    "password",
+    "httpRealm",
+    "formActionOrigin",
+    "guid",
+    "timeCreated",
+    "timeLastUsed",
+    "timePasswordChanged",
+];
+
+/// Describes why a single row of a CSV file could not be imported.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct CsvImportFailure {
+    /// The 1-based line number the row starts on (the header is line 1).
+    pub line: u64,
+    /// A short description of the problem. Never contains login data.
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct CsvImportResult {
+    /// Metrics for the rows which could be parsed, in the same format as
+    /// `PasswordEngine::import_multiple` returns.
+    pub metrics: MigrationMetrics,
+    /// Every row which wasn't imported, along with the reason why.
+    pub failures: Vec<CsvImportFailure>,
+}
+
+fn write_field(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        if c == '"' {
+            out.push('"');
+        }
+        out.push(c);
+    }
+    out.push('"');
+}
+
+fn write_row<'a>(out: &mut String, fields: impl IntoIterator<Item = &'a str>) {
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_field(out, field);
+    }
+    // Desktop uses CRLF line endings, as recommended by RFC 4180.
+    out.push_str("\r\n");
+}
+
+pub(crate) fn logins_to_csv(logins: &[Login]) -> String {
+    let mut out = String::new();
+    write_row(&mut out, CSV_HEADER.iter().copied());
+    for login in logins {
+        let time_created = login.time_created.to_string();
+        let time_last_used = login.time_last_used.to_string();
+        let time_password_changed = login.time_password_changed.to_string();
+        write_row(
+            &mut out,
+            vec![
+                login.origin.as_str(),
+                login.username.as_str(),
+                login.password.as_str(),
+                login.http_realm.as_deref().unwrap_or_default(),
+                login.form_submit_url.as_deref().unwrap_or_default(),
+                login.guid.as_str(),
+                &time_created,
+                &time_last_used,
+                &time_password_changed,
+            ],
+        );
+    }
+    out
+}
+
+/// Splits `data` into records of fields, following RFC 4180 (quoted fields
+/// may contain commas, doubled quotes and line breaks). Each record is
+/// returned along with the line number it started on. Blank lines are skipped.
+fn split_records(data: &str) -> Result<Vec<(u64, Vec<String>)>> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    // Tracks whether the current record has any content, so that we can
+    // tell an empty line apart from a line with a single empty field.
+    let mut record_started = false;
+    let mut line = 1u64;
+    let mut record_line = 1u64;
+    let mut chars = data.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                c => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => {
+                in_quotes = true;
+                record_started = true;
+            }
+            ',' => {
+                fields.push(std::mem::take(&mut field));
+                record_started = true;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                if record_started {
+                    fields.push(std::mem::take(&mut field));
+                    records.push((record_line, std::mem::take(&mut fields)));
+                }
+                record_started = false;
+                line += 1;
+                record_line = line;
+            }
+            c => {
+                field.push(c);
+                record_started = true;
+            }
+        }
+    }
+    if in_quotes {
+        throw!(ErrorKind::InvalidCsv(format!(
+            "Unterminated quoted field starting on line {}",
+            record_line
+        )));
+    }
+    if record_started {
+        fields.push(field);
+        records.push((record_line, fields));
+    }
+    Ok(records)
+}
+
+/// Maps the columns we understand onto their index in each record.
+#[derive(Default)]
+struct Columns {
+    url: Option<usize>,
+    username: Option<usize>,
+    password: Option<usize>,
+    http_realm: Option<usize>,
+    form_action_origin: Option<usize>,
+    guid: Option<usize>,
+    time_created: Option<usize>,
+    time_last_used: Option<usize>,
+    time_password_changed: Option<usize>,
+}
+
+impl Columns {
+    fn from_header(header: &[String]) -> Result<Self> {
+        let mut columns = Columns::default();
+        for (i, name) in header.iter().enumerate() {
+            let slot = match name.trim().to_ascii_lowercase().as_str() {
+                "url" | "origin" | "hostname" => &mut columns.url,
+                "username" => &mut columns.username,
+                "password" => &mut columns.password,
+                "httprealm" => &mut columns.http_realm,
+                "formactionorigin" | "formsubmiturl" => &mut columns.form_action_origin,
+                "guid" => &mut columns.guid,
+                "timecreated" => &mut columns.time_created,
+                "timelastused" => &mut columns.time_last_used,
+                "timepasswordchanged" => &mut columns.time_password_changed,
+                _ => continue,
+            };
+            if slot.is_some() {
+                throw!(ErrorKind::InvalidCsv(format!(
+                    "Duplicate column `{}` in header",
+                    name
+                )));
+            }
+            *slot = Some(i);
+        }
+        if columns.url.is_none() || columns.password.is_none() {
+            throw!(ErrorKind::InvalidCsv(
+                "Header must contain `url` and `password` columns".into()
+            ));
+        }
+        Ok(columns)
+    }
+
+    fn login_from_record(&self, record: &[String]) -> std::result::Result<Login, String> {
+        let get = |col: Option<usize>| -> Option<&str> {
+            col.and_then(|i| record.get(i)).map(String::as_str)
+        };
+        // An empty string isn't a valid timestamp in the file, but we treat
+        // it the same as a missing column so that `add` fills it in.
+        let get_time = |col: Option<usize>, name: &str| -> std::result::Result<i64, String> {
+            match get(col) {
+                None | Some("") => Ok(0),
+                Some(s) => s
+                    .trim()
+                    .parse::<i64>()
+                    .map(|t| t.max(0))
+                    .map_err(|_| format!("`{}` is not a valid timestamp", name)),
+            }
+        };
+        let http_realm = get(self.http_realm)
+            .filter(|s| !s.is_empty())
+            .map(ToOwned::to_owned);
+        // Like desktop, a row with neither target is treated as a form login
+        // that matches any form action.
+        let form_submit_url = match get(self.form_action_origin) {
+            Some(s) if !s.is_empty() => Some(s.to_owned()),
+            _ if http_realm.is_none() => Some(String::new()),
+            _ => None,
+        };
+        Ok(Login {
+            guid: Guid::from(get(self.guid).unwrap_or_default()),
+            origin: get(self.url).unwrap_or_default().to_owned(),
+            username: get(self.username).unwrap_or_default().to_owned(),
+            password: get(self.password).unwrap_or_default().to_owned(),
+            http_realm,
+            form_submit_url,
+            time_created: get_time(self.time_created, "timeCreated")?,
+            time_last_used: get_time(self.time_last_used, "timeLastUsed")?,
+            time_password_changed: get_time(self.time_password_changed, "timePasswordChanged")?,
+            ..Login::default()
+        })
+    }
+}
+
+/// Parses a CSV file into logins, returning each login with the line it was
+/// read from, as well as a failure for each row we couldn't make sense of.
+/// The returned logins have not been validated.
+pub(crate) fn logins_from_csv(data: &str) -> Result<(Vec<(u64, Login)>, Vec<CsvImportFailure>)> {
+    let mut records = split_records(data)?.into_iter();
+    let columns = match records.next() {
+        Some((_, header)) => Columns::from_header(&header)?,
+        None => throw!(ErrorKind::InvalidCsv("File is empty".into())),
+    };
+    let mut logins = Vec::new();
+    let mut failures = Vec::new();
+    for (line, record) in records {
+        match columns.login_from_record(&record) {
+            Ok(login) => logins.push((line, login)),
+            Err(reason) => {
+                log::warn!("Skipping CSV row on line {}: {}", line, reason);
+                failures.push(CsvImportFailure { line, reason });
+            }
+        }
+    }
+    Ok((logins, failures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_records() {
+        let records = split_records(
+            "a,b,c\r\n\"quoted, with comma\",\"has \"\"quotes\"\"\",\"multi\nline\"\n\n,,\nlast",
+        )
+        .unwrap();
+        assert_eq!(
+            records,
+            vec![
+                (1, vec!["a".to_string(), "b".into(), "c".into()]),
+                (
+                    2,
+                    vec![
+                        "quoted, with comma".to_string(),
+                        "has \"quotes\"".into(),
+                        "multi\nline".into()
+                    ]
+                ),
+                (5, vec![String::new(), String::new(), String::new()]),
+                (6, vec!["last".to_string()]),
+            ]
+        );
+        assert!(split_records("a,\"b\nc").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let logins = vec![
+            Login {
+                guid: "aaaaaaaaaaaa".into(),
+                origin: "https://www.example.com".into(),
+                form_submit_url: Some("https://www.example.com".into()),
+                username: "user, with \"comma\"".into(),
+                password: "multi\r\nline".into(),
+                time_created: 1000,
+                time_last_used: 2000,
+                time_password_changed: 3000,
+                ..Login::default()
+            },
+            Login {
+                guid: "bbbbbbbbbbbb".into(),
+                origin: "https://www.example2.com".into(),
+                http_realm: Some("My Realm".into()),
+                username: "".into(),
+                password: "p".into(),
+                time_created: 1000,
+                time_last_used: 2000,
+                time_password_changed: 3000,
+                ..Login::default()
+            },
+        ];
+        let csv = logins_to_csv(&logins);
+        assert!(csv.starts_with("\"url\",\"username\",\"password\",\"httpRealm\","));
+        let (parsed, failures) = logins_from_csv(&csv).unwrap();
+        assert!(failures.is_empty());
+        assert_eq!(
+            parsed.into_iter().map(|(_, l)| l).collect::<Vec<_>>(),
+            logins
+        );
+    }
+
+    #[test]
+    fn test_chrome_format() {
+        let (parsed, failures) = logins_from_csv(
+            "name,url,username,password\n\
+             example.com,https://example.com/login,user,pass\n",
+        )
+        .unwrap();
+        assert!(failures.is_empty());
+        assert_eq!(parsed.len(), 1);
+        let (line, login) = &parsed[0];
+        assert_eq!(*line, 2);
+        assert_eq!(login.origin, "https://example.com/login");
+        assert_eq!(login.form_submit_url, Some("".into()));
+        assert_eq!(login.http_realm, None);
+        assert!(login.guid.is_empty());
+    }
+
+    #[test]
+    fn test_bad_rows_and_headers() {
+        let (parsed, failures) = logins_from_csv(
+            "url,password,timeCreated\n\
+             https://a.com,pass,123\n\
+             https://b.com,pass,yesterday\n",
+        )
+        .unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(
+            failures,
+            vec![CsvImportFailure {
+                line: 3,
+                reason: "`timeCreated` is not a valid timestamp".into(),
+            }]
+        );
+        assert!(logins_from_csv("").is_err());
+        assert!(logins_from_csv("username,password\n").is_err());
+        assert!(logins_from_csv("url,url,password\n").is_err());
+    }
+}
@@ -2,8 +2,12 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use crate::consistency::{self, ConsistencyProblem};
 use crate::error::*;
-use crate::login::{LocalLogin, Login, MirrorLogin, SyncLoginData, SyncStatus};
+use crate::health::{self, HashedLogin, PasswordHealthReport};
+use crate::history::{self, RevisionSource};
+use crate::login::{LocalLogin, Login, MergeConflict, MirrorLogin, SyncLoginData, SyncStatus};
+use crate::matching;
 use crate::schema;
 use crate::update_plan::UpdatePlan;
 use crate::util;
@@ -16,7 +20,7 @@ use rusqlite::{
 use serde_derive::*;
 use sql_support::{self, ConnExt};
 use sql_support::{SqlInterruptHandle, SqlInterruptScope};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::ops::Deref;
 use std::path::Path;
 use std::result;
@@ -284,7 +288,7 @@ impl LoginDb {
     // Fetch all the data for the provided IDs.
     // TODO: Might be better taking a fn instead of returning all of it... But that func will likely
     // want to insert stuff while we're doing this so ugh.
-    fn fetch_login_data(
+    pub(crate) fn fetch_login_data(
         &self,
         records: &[(sync15::Payload, ServerTimestamp)],
         telem: &mut telemetry::EngineIncoming,
@@ -386,7 +390,7 @@ impl LoginDb {
             .as_ref()
             .and_then(|s| util::url_host_port(&s));
         let args = named_params! {
-            ":hostname": l.hostname,
+            ":origin": l.origin,
             ":http_realm": l.http_realm,
             ":username": l.username,
             ":form_submit": form_submit_host_port,
@@ -394,7 +398,7 @@ impl LoginDb {
         let mut query = format!(
             "SELECT {common}
              FROM loginsL
-             WHERE hostname IS :hostname
+             WHERE origin IS :origin
                AND httpRealm IS :http_realm
                AND username IS :username",
             common = schema::COMMON_COLS,
@@ -414,6 +418,31 @@ impl LoginDb {
         rows.collect::<Result<_>>()
     }
 
+    /// Analyzes every login for password reuse and other problems. Logins
+    /// whose password hasn't changed for longer than `max_password_age` are
+    /// reported as stale.
+    ///
+    /// Passwords are hashed as soon as they're read, and only the hashes are
+    /// compared, so plaintext passwords never leave this layer.
+    pub fn get_password_health(&self, max_password_age: Duration) -> Result<PasswordHealthReport> {
+        let mut stmt = self.db.prepare_cached(&GET_ALL_SQL)?;
+        let rows = stmt.query_and_then(NO_PARAMS, |row| HashedLogin::new(Login::from_row(row)?))?;
+        let logins = rows.collect::<Result<Vec<_>>>()?;
+        let now_ms = util::system_time_ms_i64(SystemTime::now());
+        let stale_before_ms = now_ms.saturating_sub(util::duration_ms_i64(max_password_age));
+        Ok(health::analyze(logins, stale_before_ms))
+    }
+
+    /// Checks that the local and mirror records agree with each other, and
+    /// returns any problems found. If `repair` is true, the problems are also
+    /// fixed, in the same transaction.
+    pub fn validate_and_repair(&self, repair: bool) -> Result<Vec<ConsistencyProblem>> {
+        let tx = self.unchecked_transaction()?;
+        let problems = consistency::validate_and_repair(&tx, repair)?;
+        tx.commit()?;
+        Ok(problems)
+    }
+
     pub fn get_by_base_domain(&self, base_domain: &str) -> Result<Vec<Login>> {
         // We first parse the input string as a host so it is normalized.
         let base_host = match Host::parse(base_domain) {
@@ -437,13 +466,13 @@ impl LoginDb {
                 let login = r
                     .as_ref()
                     .ok()
-                    .and_then(|login| Url::parse(&login.hostname).ok());
+                    .and_then(|login| Url::parse(&login.origin).ok());
                 let this_host = login.as_ref().and_then(|url| url.host());
                 match (&base_host, this_host) {
                     (Host::Domain(base), Some(Host::Domain(look))) => {
                         // a fairly long-winded way of saying
-                        // `login.hostname == base_domain ||
-                        //  login.hostname.ends_with('.' + base_domain);`
+                        // `login.origin == base_domain ||
+                        //  login.origin.ends_with('.' + base_domain);`
                         let mut rev_input = base.chars().rev();
                         let mut rev_host = look.chars().rev();
                         loop {
@@ -465,6 +494,28 @@ impl LoginDb {
         rows.collect::<Result<_>>()
     }
 
+    /// Returns the logins which can be filled into a form on `origin` that
+    /// submits to `form_action_origin`, ranked best-first, using the same
+    /// rules as desktop (see the `matching` module). An empty
+    /// `form_action_origin` matches any form login.
+    pub fn find_logins_for_form(
+        &self,
+        origin: &str,
+        form_action_origin: &str,
+    ) -> Result<Vec<Login>> {
+        Ok(matching::find_for_form(
+            self.get_all()?,
+            origin,
+            form_action_origin,
+        ))
+    }
+
+    /// Returns the logins which can be used for HTTP auth in `realm` on
+    /// `origin`, ranked best-first.
+    pub fn find_logins_for_http_auth(&self, origin: &str, realm: &str) -> Result<Vec<Login>> {
+        Ok(matching::find_for_http_auth(self.get_all()?, origin, realm))
+    }
+
     pub fn get_by_id(&self, id: &str) -> Result<Option<Login>> {
         self.try_query_row(
             &GET_BY_GUID_SQL,
@@ -526,7 +577,7 @@ impl LoginDb {
 
         let sql = format!(
             "INSERT OR IGNORE INTO loginsL (
-                hostname,
+                origin,
                 httpRealm,
                 formSubmitURL,
                 usernameField,
@@ -542,7 +593,7 @@ impl LoginDb {
                 is_deleted,
                 sync_status
             ) VALUES (
-                :hostname,
+                :origin,
                 :http_realm,
                 :form_submit_url,
                 :username_field,
@@ -564,7 +615,7 @@ impl LoginDb {
         let rows_changed = self.execute_named(
             &sql,
             named_params! {
-                ":hostname": login.hostname,
+                ":origin": login.origin,
                 ":http_realm": login.http_realm,
                 ":form_submit_url": login.form_submit_url,
                 ":username_field": login.username_field,
@@ -599,12 +650,24 @@ impl LoginDb {
         if num_existing_logins > 0 {
             return Err(ErrorKind::NonEmptyTable.into());
         }
+        let (metrics, _) = self.import_logins(logins)?;
+        Ok(metrics)
+    }
+
+    /// Imports logins into a (possibly non-empty) store, returning the
+    /// import metrics along with the index and error label of every login
+    /// which failed to import. Logins whose GUID is already in use are given
+    /// a new one.
+    pub(crate) fn import_logins(
+        &self,
+        logins: &[Login],
+    ) -> Result<(MigrationMetrics, Vec<(usize, String)>)> {
         let tx = self.unchecked_transaction()?;
         let now_ms = util::system_time_ms_i64(SystemTime::now());
         let import_start = Instant::now();
         let sql = format!(
             "INSERT OR IGNORE INTO loginsL (
-                hostname,
+                origin,
                 httpRealm,
                 formSubmitURL,
                 usernameField,
@@ -620,7 +683,7 @@ impl LoginDb {
                 is_deleted,
                 sync_status
             ) VALUES (
-                :hostname,
+                :origin,
                 :http_realm,
                 :form_submit_url,
                 :username_field,
@@ -644,8 +707,9 @@ impl LoginDb {
         let mut fixup_phase_duration = Duration::new(0, 0);
         let mut fixup_errors: Vec<String> = Vec::new();
         let mut insert_errors: Vec<String> = Vec::new();
+        let mut failures: Vec<(usize, String)> = Vec::new();
 
-        for login in logins {
+        for (index, login) in logins.iter().enumerate() {
             // This is a little bit of hoop-jumping to avoid cloning each borrowed item
             // in order to *possibly* created a fixed-up version.
             let mut login = login;
@@ -665,13 +729,14 @@ impl LoginDb {
                 Err(e) => {
                     log::warn!("Skipping login {} as it is invalid ({}).", login.guid, e);
                     fixup_errors.push(e.label().into());
+                    failures.push((index, e.label().into()));
                     num_failed_fixup += 1;
                     continue;
                 }
             };
             // Now we can safely insert it, knowing that it's valid data.
             let old_guid = &login.guid; // Keep the old GUID around so we can debug errors easily.
-            let guid = if old_guid.is_valid_for_sync_server() {
+            let guid = if old_guid.is_valid_for_sync_server() && !self.guid_in_use(old_guid)? {
                 old_guid.clone()
             } else {
                 Guid::random()
@@ -680,7 +745,7 @@ impl LoginDb {
             match self.execute_named_cached(
                 &sql,
                 named_params! {
-                    ":hostname": login.hostname,
+                    ":origin": login.origin,
                     ":http_realm": login.http_realm,
                     ":form_submit_url": login.form_submit_url,
                     ":username_field": login.username_field,
@@ -698,7 +763,9 @@ impl LoginDb {
                 Ok(_) => log::info!("Imported {} (new GUID {}) successfully.", old_guid, guid),
                 Err(e) => {
                     log::warn!("Could not import {} ({}).", old_guid, e);
-                    insert_errors.push(Error::from(e).label().into());
+                    let label = Error::from(e).label();
+                    insert_errors.push(label.into());
+                    failures.push((index, label.into()));
                     num_failed_insert += 1;
                 }
             };
@@ -743,7 +810,21 @@ impl LoginDb {
             "Finished importing logins with the following metrics: {:#?}",
             metrics
         );
-        Ok(metrics)
+        Ok((metrics, failures))
+    }
+
+    // Unlike `exists`, this also considers tombstones and overridden mirror
+    // records, since they still occupy the GUID.
+    pub(crate) fn guid_in_use(&self, guid: &Guid) -> Result<bool> {
+        Ok(self.db.query_row_named(
+            "SELECT EXISTS(
+                 SELECT 1 FROM loginsL WHERE guid = :guid
+                 UNION ALL
+                 SELECT 1 FROM loginsM WHERE guid = :guid
+             )",
+            named_params! { ":guid": guid },
+            |row| row.get(0),
+        )?)
     }
 
     pub fn update(&self, login: Login) -> Result<()> {
@@ -756,6 +837,22 @@ impl LoginDb {
 
         let now_ms = util::system_time_ms_i64(SystemTime::now());
 
+        history::record_overwrite(
+            &self.db,
+            login.guid_str(),
+            login.guid_str(),
+            &login.password,
+            RevisionSource::LocalEdit,
+        )?;
+
+        // A breach check for the old password says nothing about the new one.
+        self.execute_named_cached(
+            "DELETE FROM loginsBreaches
+             WHERE guid = :guid
+               AND EXISTS(SELECT 1 FROM loginsL WHERE guid = :guid AND password <> :password)",
+            named_params! { ":guid": login.guid, ":password": login.password },
+        )?;
+
         let sql = format!(
             "UPDATE loginsL
              SET local_modified      = :now_millis,
@@ -773,7 +870,7 @@ impl LoginDb {
                  timesUsed           = timesUsed + 1,
                  username            = :username,
                  password            = :password,
-                 hostname            = :hostname,
+                 origin              = :origin,
                  -- leave New records as they are, otherwise update them to `changed`
                  sync_status         = max(sync_status, {changed})
              WHERE guid = :guid",
@@ -783,7 +880,7 @@ impl LoginDb {
         self.db.execute_named(
             &sql,
             named_params! {
-                ":hostname": login.hostname,
+                ":origin": login.origin,
                 ":username": login.username,
                 ":password": login.password,
                 ":http_realm": login.http_realm,
@@ -824,7 +921,7 @@ impl LoginDb {
                 SELECT 1 FROM loginsL
                 WHERE is_deleted = 0
                     AND guid <> :guid
-                    AND hostname = :hostname
+                    AND origin = :origin
                     AND NULLIF(username, '') = :username
                     AND (
                         formSubmitURL = :form_submit
@@ -837,7 +934,7 @@ impl LoginDb {
                 SELECT 1 FROM loginsM
                 WHERE is_overridden = 0
                     AND guid <> :guid
-                    AND hostname = :hostname
+                    AND origin = :origin
                     AND NULLIF(username, '') = :username
                     AND (
                         formSubmitURL = :form_submit
@@ -847,7 +944,7 @@ impl LoginDb {
              )",
             named_params! {
                 ":guid": &login.guid,
-                ":hostname": &login.hostname,
+                ":origin": &login.origin,
                 ":username": &login.username,
                 ":http_realm": login.http_realm.as_ref(),
                 ":form_submit": login.form_submit_url.as_ref(),
@@ -862,7 +959,7 @@ impl LoginDb {
             static ref DUPES_IGNORING_USERNAME_SQL: String = format!(
                 "SELECT {common_cols} FROM loginsL
                 WHERE is_deleted = 0
-                    AND hostname = :hostname
+                    AND origin = :origin
                     AND (
                         formSubmitURL = :form_submit
                         OR
@@ -873,7 +970,7 @@ impl LoginDb {
 
                 SELECT {common_cols} FROM loginsM
                 WHERE is_overridden = 0
-                    AND hostname = :hostname
+                    AND origin = :origin
                     AND (
                         formSubmitURL = :form_submit
                         OR
@@ -885,7 +982,7 @@ impl LoginDb {
         }
         let mut stmt = self.db.prepare_cached(&DUPES_IGNORING_USERNAME_SQL)?;
         let params = named_params! {
-            ":hostname": &login.hostname,
+            ":origin": &login.origin,
             ":http_realm": login.http_realm.as_ref(),
             ":form_submit": login.form_submit_url.as_ref(),
         };
@@ -923,7 +1020,7 @@ impl LoginDb {
                      sync_status = {status_changed},
                      is_deleted = 1,
                      password = '',
-                     hostname = '',
+                     origin = '',
                      username = ''
                  WHERE guid = :guid",
                 status_changed = SyncStatus::Changed as u8
@@ -941,12 +1038,18 @@ impl LoginDb {
         // insert a tombstone.
         self.execute_named(&format!("
             INSERT OR IGNORE INTO loginsL
-                    (guid, local_modified, is_deleted, sync_status, hostname, timeCreated, timePasswordChanged, password, username)
+                    (guid, local_modified, is_deleted, sync_status, origin, timeCreated, timePasswordChanged, password, username)
             SELECT   guid, :now_ms,        1,          {changed},   '',       timeCreated, :now_ms,                   '',       ''
             FROM loginsM
             WHERE guid = :guid",
             changed = SyncStatus::Changed as u8),
             named_params! { ":now_ms": now_ms, ":guid": id })?;
+
+        self.execute_named(
+            "DELETE FROM loginsBreaches WHERE guid = :guid",
+            named_params! { ":guid": id },
+        )?;
+        history::forget(&self.db, id)?;
         tx.commit()?;
         Ok(exists)
     }
@@ -1021,7 +1124,7 @@ impl LoginDb {
                     sync_status = {changed},
                     is_deleted = 1,
                     password = '',
-                    hostname = '',
+                    origin = '',
                     username = ''
                 WHERE is_deleted = 0",
                 changed = SyncStatus::Changed as u8
@@ -1036,12 +1139,14 @@ impl LoginDb {
         self.execute_named(
             &format!("
                 INSERT OR IGNORE INTO loginsL
-                      (guid, local_modified, is_deleted, sync_status, hostname, timeCreated, timePasswordChanged, password, username)
+                      (guid, local_modified, is_deleted, sync_status, origin, timeCreated, timePasswordChanged, password, username)
                 SELECT guid, :now_ms,        1,          {changed},   '',       timeCreated, :now_ms,             '',       ''
                 FROM loginsM",
                 changed = SyncStatus::Changed as u8),
             named_params! { ":now_ms": now_ms })?;
         scope.err_if_interrupted()?;
+
+        self.execute_all(&["DELETE FROM loginsBreaches", "DELETE FROM loginsHistory"])?;
         tx.commit()?;
         Ok(())
     }
@@ -1053,12 +1158,14 @@ impl LoginDb {
             "DELETE FROM loginsL",
             "DELETE FROM loginsM",
             "DELETE FROM loginsSyncMeta",
+            "DELETE FROM loginsBreaches",
+            "DELETE FROM loginsHistory",
         ])?;
         tx.commit()?;
         Ok(())
     }
 
-    fn reconcile(
+    pub(crate) fn reconcile(
         &self,
         records: Vec<SyncLoginData>,
         server_now: ServerTimestamp,
@@ -1113,7 +1220,7 @@ impl LoginDb {
         Ok(plan)
     }
 
-    fn execute_plan(&self, plan: UpdatePlan, scope: &SqlInterruptScope) -> Result<()> {
+    pub(crate) fn execute_plan(&self, plan: UpdatePlan, scope: &SqlInterruptScope) -> Result<()> {
         // Because rusqlite want a mutable reference to create a transaction
         // (as a way to save us from ourselves), we side-step that by creating
         // it manually.
@@ -1165,6 +1272,9 @@ impl LoginDb {
             telem.incoming(incoming_telemetry);
             result
         }?;
+        if !plan.conflicts.is_empty() {
+            telem.validation(conflict_validation(&plan.conflicts));
+        }
         self.execute_plan(plan, scope)?;
         Ok(self.fetch_outgoing(inbound.timestamp, scope)?)
     }
@@ -1239,6 +1349,20 @@ impl LoginDb {
     }
 }
 
+/// Summarizes the fields which conflicted during a sync as validation
+/// telemetry, with one problem per field and resolution.
+fn conflict_validation(conflicts: &[MergeConflict]) -> telemetry::Validation {
+    let mut counts = BTreeMap::new();
+    for conflict in conflicts {
+        *counts.entry(conflict.problem_name()).or_insert(0) += 1;
+    }
+    let mut validation = telemetry::Validation::with_version(1);
+    for (name, count) in counts {
+        validation.problem(name, count);
+    }
+    validation
+}
+
 pub struct LoginStore<'a> {
     pub db: &'a LoginDb,
     pub scope: sql_support::SqlInterruptScope,
@@ -1333,7 +1457,7 @@ lazy_static! {
          FROM loginsM
          WHERE is_overridden IS NOT 1
            AND guid = :guid
-         ORDER BY hostname ASC
+         ORDER BY origin ASC
 
          LIMIT 1",
         common_cols = schema::COMMON_COLS,
@@ -1403,7 +1527,7 @@ mod tests {
         db.add(Login {
             guid: "dummy_000001".into(),
             form_submit_url: Some("https://www.example.com".into()),
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: None,
             username: "test".into(),
             password: "test".into(),
@@ -1414,7 +1538,7 @@ mod tests {
         let unique_login = Login {
             guid: Guid::empty(),
             form_submit_url: None,
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1424,7 +1548,7 @@ mod tests {
         let duplicate_login = Login {
             guid: Guid::empty(),
             form_submit_url: Some("https://www.example.com".into()),
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: None,
             username: "test".into(),
             password: "test2".into(),
@@ -1467,7 +1591,7 @@ mod tests {
         db.add(Login {
             guid: "dummy_000001".into(),
             form_submit_url: Some("http://😍.com".into()),
-            hostname: "http://😍.com".into(),
+            origin: "http://😍.com".into(),
             http_realm: None,
             username: "😍".into(),
             username_field: "😍".into(),
@@ -1480,7 +1604,8 @@ mod tests {
             .get_by_id("dummy_000001")
             .expect("should work")
             .expect("should get a record");
-        assert_eq!(fetched.hostname, "http://xn--r28h.com");
+        assert_eq!(fetched.origin, "http://xn--r28h.com");
+        assert_eq!(fetched.display_origin(), "http://😍.com");
         assert_eq!(fetched.form_submit_url.unwrap(), "http://xn--r28h.com");
         assert_eq!(fetched.username, "😍");
         assert_eq!(fetched.username_field, "😍");
@@ -1494,7 +1619,7 @@ mod tests {
         db.add(Login {
             guid: "dummy_000001".into(),
             form_submit_url: None,
-            hostname: "http://😍.com".into(),
+            origin: "http://😍.com".into(),
             http_realm: Some("😍😍".into()),
             username: "😍".into(),
             password: "😍".into(),
@@ -1505,7 +1630,7 @@ mod tests {
             .get_by_id("dummy_000001")
             .expect("should work")
             .expect("should get a record");
-        assert_eq!(fetched.hostname, "http://xn--r28h.com");
+        assert_eq!(fetched.origin, "http://xn--r28h.com");
         assert_eq!(fetched.http_realm.unwrap(), "😍😍");
     }
 
@@ -1514,7 +1639,7 @@ mod tests {
             .get_by_base_domain(query)
             .unwrap()
             .into_iter()
-            .map(|l| l.hostname)
+            .map(|l| l.origin)
             .collect::<Vec<String>>();
         results.sort();
         let mut sorted = expected.to_owned();
@@ -1531,7 +1656,7 @@ mod tests {
         let db = LoginDb::open_in_memory(Some("testing")).unwrap();
         for h in good.iter().chain(bad.iter()) {
             db.add(Login {
-                hostname: (*h).into(),
+                origin: (*h).into(),
                 http_realm: Some((*h).into()),
                 password: "test".into(),
                 ..Login::default()
@@ -1609,12 +1734,144 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_find_logins() {
+        let db = LoginDb::open_in_memory(Some("testing")).unwrap();
+        let add = |guid: &str, origin: &str, form_submit_url: Option<&str>, realm: Option<&str>| {
+            db.add(Login {
+                guid: guid.into(),
+                origin: origin.into(),
+                form_submit_url: form_submit_url.map(Into::into),
+                http_realm: realm.map(Into::into),
+                username: guid.into(),
+                password: "password".into(),
+                ..Login::default()
+            })
+            .unwrap();
+        };
+        add(
+            "form_exact",
+            "https://www.example.com",
+            Some("https://www.example.com"),
+            None,
+        );
+        add("form_upgrade", "http://www.example.com", Some(""), None);
+        add("form_sub", "https://accounts.example.com", Some(""), None);
+        add(
+            "form_other",
+            "https://www.example.com",
+            Some("https://other.com"),
+            None,
+        );
+        add("auth", "https://www.example.com", None, Some("Members"));
+
+        let guids = |logins: Vec<Login>| -> Vec<String> {
+            logins.into_iter().map(|l| l.guid.into_string()).collect()
+        };
+        assert_eq!(
+            guids(
+                db.find_logins_for_form("https://www.example.com", "https://www.example.com")
+                    .unwrap()
+            ),
+            vec!["form_exact", "form_upgrade", "form_sub"]
+        );
+        // No upgrades from https to http.
+        assert_eq!(
+            guids(
+                db.find_logins_for_form("http://www.example.com", "")
+                    .unwrap()
+            ),
+            vec!["form_upgrade"]
+        );
+        assert_eq!(
+            guids(
+                db.find_logins_for_http_auth("https://www.example.com", "Members")
+                    .unwrap()
+            ),
+            vec!["auth"]
+        );
+        assert!(db
+            .find_logins_for_http_auth("https://www.example.com", "members")
+            .unwrap()
+            .is_empty());
+
+        // Deleted logins aren't returned.
+        db.delete("form_exact").unwrap();
+        assert_eq!(
+            guids(
+                db.find_logins_for_form("https://www.example.com", "")
+                    .unwrap()
+            ),
+            vec!["form_other", "form_upgrade", "form_sub"]
+        );
+    }
+
+    #[test]
+    fn test_password_health() {
+        let db = LoginDb::open_in_memory(Some("testing")).unwrap();
+        let now_ms = util::system_time_ms_i64(SystemTime::now());
+        let year_ms = 365 * 24 * 60 * 60 * 1000;
+        let add = |guid: &str, origin: &str, username: &str, password: &str, changed| {
+            db.add(Login {
+                guid: guid.into(),
+                origin: origin.into(),
+                form_submit_url: Some(origin.into()),
+                username: username.into(),
+                password: password.into(),
+                time_password_changed: changed,
+                ..Login::default()
+            })
+            .unwrap();
+        };
+        // Reused across base domains.
+        add(
+            "reused000001",
+            "https://www.example.com",
+            "a",
+            "hunter2",
+            now_ms,
+        );
+        add(
+            "reused000002",
+            "https://login.example2.com",
+            "a",
+            "hunter2",
+            now_ms,
+        );
+        // Reused, but on the same base domain, so not reported.
+        add("samedomain01", "https://a.example3.com", "a", "p3", now_ms);
+        add("samedomain02", "https://b.example3.com", "b", "p3", now_ms);
+        // An empty username dupe, which is also insecure and stale.
+        add(
+            "emptyuser001",
+            "http://example4.com",
+            "",
+            "p4",
+            now_ms - 2 * year_ms,
+        );
+        add("fulluser0001", "http://example4.com", "user", "p4", now_ms);
+        add("secure000001", "https://example4.com", "user", "p5", now_ms);
+
+        let report = db
+            .get_password_health(Duration::from_secs(365 * 24 * 60 * 60))
+            .unwrap();
+        assert_eq!(
+            report,
+            PasswordHealthReport {
+                reused_passwords: vec![vec!["reused000001".into(), "reused000002".into()]],
+                empty_username_dupes: vec!["emptyuser001".into()],
+                insecure_origins: vec!["emptyuser001".into(), "fulluser0001".into()],
+                stale_passwords: vec!["emptyuser001".into()],
+            }
+        );
+    }
+
     #[test]
     fn test_delete() {
         let db = LoginDb::open_in_memory(Some("testing")).unwrap();
         let _login = db
             .add(Login {
-                hostname: "https://www.example.com".into(),
+                origin: "https://www.example.com".into(),
                 http_realm: Some("https://www.example.com".into()),
                 username: "test_user".into(),
                 password: "test_password".into(),
@@ -1644,7 +1901,7 @@ mod tests {
         let db = LoginDb::open_in_memory(Some("testing")).unwrap();
         let login1 = db
             .add(Login {
-                hostname: "https://www.example.com".into(),
+                origin: "https://www.example.com".into(),
                 http_realm: Some("https://www.example.com".into()),
                 username: "test_user_1".into(),
                 password: "test_password_1".into(),
@@ -1654,7 +1911,7 @@ mod tests {
 
         let login2 = db
             .add(Login {
-                hostname: "https://www.example2.com".into(),
+                origin: "https://www.example2.com".into(),
                 http_realm: Some("https://www.example2.com".into()),
                 username: "test_user_1".into(),
                 password: "test_password_2".into(),
@@ -1711,7 +1968,7 @@ mod tests {
         // Adding login to trigger non-empty table error
         let login = db
             .add(Login {
-                hostname: "https://www.example.com".into(),
+                origin: "https://www.example.com".into(),
                 http_realm: Some("https://www.example.com".into()),
                 username: "test_user_1".into(),
                 password: "test_password_1".into(),
@@ -1734,7 +1991,7 @@ mod tests {
         let valid_login1 = Login {
             guid: valid_login_guid1,
             form_submit_url: Some("https://www.example.com".into()),
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: None,
             username: "test".into(),
             password: "test".into(),
@@ -1744,7 +2001,7 @@ mod tests {
         let valid_login2 = Login {
             guid: valid_login_guid2,
             form_submit_url: Some("https://www.example2.com".into()),
-            hostname: "https://www.example2.com".into(),
+            origin: "https://www.example2.com".into(),
             http_realm: None,
             username: "test2".into(),
             password: "test2".into(),
@@ -1754,7 +2011,7 @@ mod tests {
         let valid_login3 = Login {
             guid: valid_login_guid3,
             form_submit_url: Some("https://www.example3.com".into()),
-            hostname: "https://www.example3.com".into(),
+            origin: "https://www.example3.com".into(),
             http_realm: None,
             username: "test3".into(),
             password: "test3".into(),
@@ -1764,7 +2021,7 @@ mod tests {
         let duplicate_login = Login {
             guid: duplicate_login_guid,
             form_submit_url: Some("https://www.example.com".into()),
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: None,
             username: "test".into(),
             password: "test2".into(),
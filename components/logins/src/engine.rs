@@ -1,15 +1,23 @@
 /* This Source Code Form is subject to the terms of the Mozilla Public
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+use crate::backup::{self, RestoreMetrics};
+use crate::breach::{self, BreachRangeFetcher, BreachStatus};
+use crate::consistency::ConsistencyProblem;
+use crate::csv::{self, CsvImportFailure, CsvImportResult};
 use crate::db::{LoginDb, LoginStore, MigrationMetrics};
 use crate::error::*;
+use crate::health::PasswordHealthReport;
+use crate::history::{self, PasswordRevision};
 use crate::login::Login;
 use std::cell::Cell;
 use std::path::Path;
+use std::time::Duration;
 use sync15::{
     sync_multiple, telemetry, KeyBundle, MemoryCachedState, StoreSyncAssociation,
     Sync15StorageClientInit,
 };
+use sync_guid::Guid;
 
 // This isn't really an engine in the firefox sync15 desktop sense -- it's
 // really a bundle of state that contains the sync storage client, the sync
@@ -56,6 +64,57 @@ impl PasswordEngine {
         self.db.get_by_base_domain(base_domain)
     }
 
+    pub fn find_logins_for_form(
+        &self,
+        origin: &str,
+        form_action_origin: &str,
+    ) -> Result<Vec<Login>> {
+        self.db.find_logins_for_form(origin, form_action_origin)
+    }
+
+    pub fn find_logins_for_http_auth(&self, origin: &str, realm: &str) -> Result<Vec<Login>> {
+        self.db.find_logins_for_http_auth(origin, realm)
+    }
+
+    pub fn password_health(&self, max_password_age: Duration) -> Result<PasswordHealthReport> {
+        self.db.get_password_health(max_password_age)
+    }
+
+    /// Checks every saved password against a breach corpus, using `fetcher`
+    /// to look up hash ranges. Only a short prefix of each password's hash is
+    /// ever passed to the fetcher. Returns the logins which were found.
+    pub fn check_for_breaches(
+        &self,
+        fetcher: &dyn BreachRangeFetcher,
+    ) -> Result<Vec<BreachStatus>> {
+        breach::check_for_breaches(&self.db, fetcher)
+    }
+
+    /// Returns the logins which were found by the last `check_for_breaches`,
+    /// and whose password hasn't changed since.
+    pub fn get_breached_logins(&self) -> Result<Vec<BreachStatus>> {
+        breach::get_breached_logins(&self.db)
+    }
+
+    /// Returns the previous passwords of the login with `id`, most recently
+    /// replaced first.
+    pub fn password_history(&self, id: &str) -> Result<Vec<PasswordRevision>> {
+        history::get_revisions(&self.db, id)
+    }
+
+    /// Sets the password of the login with `id` back to the one in a
+    /// revision returned by `password_history`. The current password is
+    /// recorded in the history, so this can be undone.
+    pub fn restore_password_revision(&self, id: &str, revision_id: i64) -> Result<()> {
+        history::restore_revision(&self.db, id, revision_id)
+    }
+
+    /// Checks the store for inconsistent local and mirror records, repairing
+    /// them if `repair` is true. See `LoginDb::validate_and_repair`.
+    pub fn validate_and_repair(&self, repair: bool) -> Result<Vec<ConsistencyProblem>> {
+        self.db.validate_and_repair(repair)
+    }
+
     pub fn potential_dupes_ignoring_username(&self, login: Login) -> Result<Vec<Login>> {
         self.db.potential_dupes_ignoring_username(&login)
     }
@@ -97,6 +156,61 @@ impl PasswordEngine {
         self.db.import_multiple(logins)
     }
 
+    /// Exports every login to a CSV string using the same column layout as
+    /// desktop Firefox.
+    pub fn export_csv(&self) -> Result<String> {
+        Ok(csv::logins_to_csv(&self.db.get_all()?))
+    }
+
+    /// Imports logins from a CSV string, as produced by `export_csv` (or by
+    /// desktop Firefox or Chrome). Unlike `import_multiple`, the store does
+    /// not need to be empty. Rows which can't be parsed, are invalid or are
+    /// duplicates of existing logins are skipped and reported in the result.
+    pub fn import_csv(&self, data: &str) -> Result<CsvImportResult> {
+        let (rows, mut failures) = csv::logins_from_csv(data)?;
+        let (lines, mut logins): (Vec<u64>, Vec<Login>) = rows.into_iter().unzip();
+        // Re-importing an export of this store shouldn't let logins claim
+        // the GUIDs of existing records (which would also hide them from the
+        // dupe check), so treat those as if no GUID had been provided.
+        for login in &mut logins {
+            if !login.guid.is_empty() && self.db.guid_in_use(&login.guid)? {
+                login.guid = Guid::empty();
+            }
+        }
+        let (metrics, import_failures) = self.db.import_logins(&logins)?;
+        failures.extend(
+            import_failures
+                .into_iter()
+                .map(|(index, reason)| CsvImportFailure {
+                    line: lines[index],
+                    reason,
+                }),
+        );
+        failures.sort_by_key(|f| f.line);
+        Ok(CsvImportResult { metrics, failures })
+    }
+
+    /// Writes a backup of every login to `path`, encrypted with a key
+    /// derived from `passphrase`. Unlike the database itself, the backup
+    /// can be restored on any device which knows the passphrase.
+    pub fn write_backup(&self, path: impl AsRef<Path>, passphrase: &str) -> Result<()> {
+        let data = backup::create_backup(&self.db, passphrase)?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    /// Merges the logins from a backup written by `write_backup` into this
+    /// store. Logins which already exist are only replaced if the backup has
+    /// a more recently changed password.
+    pub fn restore_backup(
+        &self,
+        path: impl AsRef<Path>,
+        passphrase: &str,
+    ) -> Result<RestoreMetrics> {
+        let data = std::fs::read(path)?;
+        backup::restore_backup(&self.db, &data, passphrase)
+    }
+
     pub fn disable_mem_security(&self) -> Result<()> {
         self.db.disable_mem_security()
     }
@@ -165,11 +279,10 @@ mod test {
     use crate::util;
     use more_asserts::*;
     use std::time::SystemTime;
-    use sync_guid::Guid;
     // Doesn't check metadata fields
     fn assert_logins_equiv(a: &Login, b: &Login) {
         assert_eq!(b.guid, a.guid);
-        assert_eq!(b.hostname, a.hostname);
+        assert_eq!(b.origin, a.origin);
         assert_eq!(b.form_submit_url, a.form_submit_url);
         assert_eq!(b.http_realm, a.http_realm);
         assert_eq!(b.username, a.username);
@@ -187,7 +300,7 @@ mod test {
 
         let a = Login {
             guid: "aaaaaaaaaaaa".into(),
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             form_submit_url: Some("https://www.example.com".into()),
             username: "coolperson21".into(),
             password: "p4ssw0rd".into(),
@@ -198,7 +311,7 @@ mod test {
 
         let b = Login {
             // Note: no ID, should be autogenerated for us
-            hostname: "https://www.example2.com".into(),
+            origin: "https://www.example2.com".into(),
             http_realm: Some("Some String Here".into()),
             username: "asdf".into(),
             password: "fdsa".into(),
@@ -261,7 +374,7 @@ mod test {
 
         let list = engine
             .get_by_base_domain("example2.com")
-            .expect("Expect a list for this hostname");
+            .expect("Expect a list for this origin");
         assert_eq!(list.len(), 1);
         assert_eq!(list[0], b_from_db);
 
@@ -293,6 +406,60 @@ mod test {
         assert_eq!(b_after_update.times_used, 2);
     }
 
+    #[test]
+    fn test_csv_import_export() {
+        let engine = PasswordEngine::new_in_memory(Some("secret")).unwrap();
+        engine
+            .add(Login {
+                guid: "aaaaaaaaaaaa".into(),
+                origin: "https://www.example.com".into(),
+                form_submit_url: Some("https://www.example.com".into()),
+                username: "coolperson21".into(),
+                password: "p4ssw0rd".into(),
+                ..Login::default()
+            })
+            .unwrap();
+        let exported = engine.export_csv().unwrap();
+
+        let csv_data = format!(
+            "{}\
+             \"https://www.example2.com\",\"user\",\"pass\",\"Realm\",\"\",\"\",\"\",\"\",\"\"\n\
+             \"https://www.example3.com\",\"user\",\"\",\"\",\"\",\"\",\"\",\"\",\"\"\n\
+             \"https://www.example4.com\",\"user\",\"pass\",\"\",\"\",\"\",\"later\",\"\",\"\"\n",
+            exported
+        );
+
+        let result = engine.import_csv(&csv_data).unwrap();
+        // The first login is a dupe of the existing one, and the last two
+        // are invalid, so only `example2.com` gets imported.
+        assert_eq!(
+            result.failures,
+            vec![
+                CsvImportFailure {
+                    line: 2,
+                    reason: "InvalidLogin::DuplicateLogin".into(),
+                },
+                CsvImportFailure {
+                    line: 4,
+                    reason: "InvalidLogin::EmptyPassword".into(),
+                },
+                CsvImportFailure {
+                    line: 5,
+                    reason: "`timeCreated` is not a valid timestamp".into(),
+                },
+            ]
+        );
+        let list = engine.list().unwrap();
+        assert_eq!(list.len(), 2);
+        let imported = list
+            .iter()
+            .find(|l| l.origin == "https://www.example2.com")
+            .expect("should have imported a login");
+        assert_eq!(imported.http_realm, Some("Realm".into()));
+        assert_eq!(imported.form_submit_url, None);
+        assert!(imported.guid.is_valid_for_sync_server());
+    }
+
     #[test]
     fn test_rekey() {
         let engine = PasswordEngine::new_in_memory(Some("secret")).unwrap();
@@ -56,6 +56,30 @@ pub enum ErrorKind {
 
     #[fail(display = "Protobuf decode error: {}", _0)]
     ProtobufDecodeError(#[fail(cause)] prost::DecodeError),
+
+    #[fail(display = "Invalid CSV data: {}", _0)]
+    InvalidCsv(String),
+
+    #[fail(display = "Invalid backup: {}", _0)]
+    InvalidBackup(String),
+
+    #[fail(display = "Failed to decrypt backup (wrong passphrase, or corrupted)")]
+    BackupDecryptionFailed,
+
+    #[fail(display = "Crypto error: {}", _0)]
+    CryptoError(#[fail(cause)] rc_crypto::Error),
+
+    #[fail(display = "IO error: {}", _0)]
+    IoError(#[fail(cause)] std::io::Error),
+
+    #[fail(display = "Invalid password rules: {}", _0)]
+    InvalidPasswordRules(String),
+
+    #[fail(display = "The `source` column in DB has an illegal value: {}", _0)]
+    BadRevisionSource(u8),
+
+    #[fail(display = "No password revision with id {} exists for the login", _0)]
+    NoSuchRevision(i64),
 }
 
 error_support::define_error! {
@@ -67,12 +91,14 @@ error_support::define_error! {
         (InvalidLogin, InvalidLogin),
         (Interrupted, interrupt::Interrupted),
         (ProtobufDecodeError, prost::DecodeError),
+        (CryptoError, rc_crypto::Error),
+        (IoError, std::io::Error),
     }
 }
 
 #[derive(Debug, Fail)]
 pub enum InvalidLogin {
-    // EmptyOrigin error occurs when the login's hostname field is empty.
+    // EmptyOrigin error occurs when the login's origin field is empty.
     #[fail(display = "Origin is empty")]
     EmptyOrigin,
     #[fail(display = "Password is empty")]
@@ -111,6 +137,14 @@ impl Error {
                 InvalidLogin::IllegalFieldValue { .. } => "InvalidLogin::IllegalFieldValue",
             },
             ErrorKind::ProtobufDecodeError(_) => "BufDecodeError",
+            ErrorKind::InvalidCsv(_) => "InvalidCsv",
+            ErrorKind::InvalidBackup(_) => "InvalidBackup",
+            ErrorKind::BackupDecryptionFailed => "BackupDecryptionFailed",
+            ErrorKind::CryptoError(_) => "CryptoError",
+            ErrorKind::IoError(_) => "IoError",
+            ErrorKind::InvalidPasswordRules(_) => "InvalidPasswordRules",
+            ErrorKind::BadRevisionSource(_) => "BadRevisionSource",
+            ErrorKind::NoSuchRevision(_) => "NoSuchRevision",
         }
     }
 }
@@ -0,0 +1,463 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Password generation.
+//!
+//! Sites can describe what they accept using the `passwordrules` attribute
+//! (https://github.com/whatwg/html/issues/3518), for example
+//! `required: upper; required: digit; allowed: lower, [-_]; maxlength: 20`.
+//! `PasswordRules::parse` understands that syntax, and `generate_password`
+//! produces a password which satisfies it.
+//!
+//! Characters which don't appear in any `required` or `allowed` set are never
+//! used, which is how a site forbids characters. Parsing is lenient, as in
+//! browsers: unknown or malformed rules are ignored. The exception is custom
+//! sets with non-ASCII characters, which we can't generate, and so are an
+//! error rather than silently narrowing what the site asked for. Rules which
+//! can't be satisfied (e.g. `minlength` greater than `maxlength`) are an
+//! error too.
+//!
+//! We draw each character uniformly from every allowed character, except
+//! that a character whose run has reached the `max-consecutive` limit is
+//! skipped, and throw away whole passwords which are missing a required
+//! character, rather than e.g. inserting one character from each required
+//! set. This makes every password which satisfies the rules (and has the
+//! chosen length) equally likely, unless `max-consecutive` is more than 1, in
+//! which case passwords with runs at the limit are slightly favored.
+
+use crate::error::*;
+use rc_crypto::rand;
+use std::collections::BTreeSet;
+
+/// The length we aim for, if the rules allow it.
+pub const DEFAULT_LENGTH: usize = 15;
+
+// Generous, as candidates are only rejected for missing a required
+// character, which is rare unless a required set is a tiny part of a large
+// alphabet, and each attempt is cheap.
+const MAX_ATTEMPTS: usize = 10_000;
+
+const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+const DIGIT: &str = "0123456789";
+// The `special` class from the spec, less the space, which we never generate.
+const SPECIAL: &str = "-~!@#$%^&*_+=`|(){}[:;\"'<>,.?]";
+
+type CharSet = BTreeSet<char>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordRules {
+    /// The password must contain at least one character from each of these.
+    required: Vec<CharSet>,
+    /// Characters which may be used, in addition to the required ones.
+    allowed: CharSet,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    /// The longest run of a single repeated character.
+    max_consecutive: Option<usize>,
+}
+
+impl Default for PasswordRules {
+    /// The rules used when a site doesn't specify any.
+    fn default() -> Self {
+        Self {
+            required: vec![class(UPPER), class(LOWER), class(DIGIT)],
+            allowed: CharSet::new(),
+            min_length: None,
+            max_length: None,
+            max_consecutive: None,
+        }
+    }
+}
+
+fn class(chars: &str) -> CharSet {
+    chars.chars().collect()
+}
+
+/// Parses a comma-separated list of character classes and custom sets, e.g.
+/// `upper, [-_.]`, into a single set. Returns `None` if `value` is malformed,
+/// and an error if a custom set has characters we can't generate.
+fn parse_classes(value: &str) -> Result<Option<CharSet>> {
+    let mut set = CharSet::new();
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        if rest.starts_with('[') {
+            let body = &rest[1..];
+            // A `]` immediately after the `[` is part of the set.
+            let search_from = if body.starts_with(']') { 1 } else { 0 };
+            let end = match body[search_from..].find(']') {
+                Some(i) => search_from + i,
+                None => return Ok(None),
+            };
+            if let Some(c) = body[..end].chars().find(|c| !c.is_ascii()) {
+                throw!(ErrorKind::InvalidPasswordRules(format!(
+                    "Custom character sets can only have ASCII characters, not {:?}",
+                    c
+                )));
+            }
+            set.extend(body[..end].chars().filter(char::is_ascii_graphic));
+            rest = &body[end + 1..];
+        } else {
+            let (name, tail) = match rest.find(',') {
+                Some(i) => (&rest[..i], &rest[i..]),
+                None => (rest, ""),
+            };
+            match name.trim().to_ascii_lowercase().as_str() {
+                "upper" => set.extend(UPPER.chars()),
+                "lower" => set.extend(LOWER.chars()),
+                "digit" => set.extend(DIGIT.chars()),
+                "special" => set.extend(SPECIAL.chars()),
+                // We only ever generate ASCII, so `unicode` is the same as
+                // `ascii-printable` for our purposes.
+                "ascii-printable" | "unicode" => {
+                    set.extend((b'!'..=b'~').map(char::from));
+                }
+                _ => return Ok(None),
+            }
+            rest = tail;
+        }
+        rest = rest.trim_start();
+        if rest.starts_with(',') {
+            rest = rest[1..].trim_start();
+        } else if !rest.is_empty() {
+            return Ok(None);
+        }
+    }
+    Ok(Some(set))
+}
+
+fn parse_number(value: &str) -> Option<usize> {
+    value.trim().parse().ok().filter(|n| *n > 0)
+}
+
+impl PasswordRules {
+    /// Parses rules in the `passwordrules` attribute syntax. Unknown or
+    /// malformed rules are ignored (and logged), but custom sets with
+    /// non-ASCII characters are an `InvalidPasswordRules` error. An empty
+    /// string (or one without any `required` or `allowed` rules) gives the
+    /// default character classes.
+    pub fn parse(rules: &str) -> Result<Self> {
+        let mut required = Vec::new();
+        let mut allowed = CharSet::new();
+        let mut min_length: Option<usize> = None;
+        let mut max_length: Option<usize> = None;
+        let mut max_consecutive: Option<usize> = None;
+        for rule in rules.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let (name, value) = match rule.find(':') {
+                Some(i) => (rule[..i].trim().to_ascii_lowercase(), &rule[i + 1..]),
+                None => {
+                    log::warn!("Ignoring password rule without a value: {:?}", rule);
+                    continue;
+                }
+            };
+            let ok = match name.as_str() {
+                "required" => parse_classes(value)?.map(|set| required.push(set)),
+                "allowed" => parse_classes(value)?.map(|set| allowed.extend(set)),
+                // If a rule is repeated, the most restrictive value wins.
+                "minlength" => parse_number(value)
+                    .map(|n| min_length = Some(min_length.map_or(n, |m| m.max(n)))),
+                "maxlength" => parse_number(value)
+                    .map(|n| max_length = Some(max_length.map_or(n, |m| m.min(n)))),
+                "max-consecutive" => parse_number(value)
+                    .map(|n| max_consecutive = Some(max_consecutive.map_or(n, |m| m.min(n)))),
+                _ => None,
+            };
+            if ok.is_none() {
+                log::warn!("Ignoring unknown or malformed password rule: {:?}", rule);
+            }
+        }
+        let defaults = Self::default();
+        Ok(Self {
+            required: if required.is_empty() && allowed.is_empty() {
+                defaults.required
+            } else {
+                required
+            },
+            allowed,
+            min_length,
+            max_length,
+            max_consecutive,
+        })
+    }
+
+    fn length(&self) -> Result<usize> {
+        let min = self.min_length.unwrap_or(0).max(self.required.len());
+        let max = self.max_length.unwrap_or(usize::max_value());
+        if min > max {
+            throw!(ErrorKind::InvalidPasswordRules(format!(
+                "A password needs at least {} characters, but at most {} are allowed",
+                min, max
+            )));
+        }
+        Ok(DEFAULT_LENGTH.max(min).min(max))
+    }
+
+    fn alphabet(&self) -> Result<Vec<char>> {
+        if self.required.iter().any(CharSet::is_empty) {
+            throw!(ErrorKind::InvalidPasswordRules(
+                "A required character set is empty".into()
+            ));
+        }
+        let mut alphabet = self.allowed.clone();
+        for set in &self.required {
+            alphabet.extend(set);
+        }
+        if alphabet.is_empty() {
+            throw!(ErrorKind::InvalidPasswordRules(
+                "No characters are allowed".into()
+            ));
+        }
+        Ok(alphabet.into_iter().collect())
+    }
+
+    fn has_required(&self, password: &[char]) -> bool {
+        self.required
+            .iter()
+            .all(|set| password.iter().any(|c| set.contains(c)))
+    }
+}
+
+/// Produces uniformly distributed indexes into a set of `n` items, using
+/// bytes from `rc_crypto::rand`.
+struct IndexSampler {
+    n: usize,
+    // Bytes at or above this are rejected, so every index is equally likely.
+    limit: usize,
+    buf: [u8; 256],
+    pos: usize,
+}
+
+impl IndexSampler {
+    fn new(n: usize) -> Self {
+        assert!(n > 0 && n <= 256, "alphabet must have 1 to 256 characters");
+        Self {
+            n,
+            limit: 256 - (256 % n),
+            buf: [0; 256],
+            pos: 256,
+        }
+    }
+
+    fn next(&mut self) -> Result<usize> {
+        loop {
+            if self.pos == self.buf.len() {
+                rand::fill(&mut self.buf)?;
+                self.pos = 0;
+            }
+            let b = usize::from(self.buf[self.pos]);
+            self.pos += 1;
+            if b < self.limit {
+                return Ok(b % self.n);
+            }
+        }
+    }
+}
+
+/// Generates a password which satisfies `rules`.
+pub fn generate_password(rules: &PasswordRules) -> Result<String> {
+    let length = rules.length()?;
+    let alphabet = rules.alphabet()?;
+    let max_consecutive = rules.max_consecutive.unwrap_or(usize::max_value());
+    // With two or more characters, we can always break up runs, and fit in
+    // one character from each required set.
+    if alphabet.len() == 1 && length > max_consecutive {
+        throw!(ErrorKind::InvalidPasswordRules(format!(
+            "Only one character is allowed, but it can't be repeated {} times",
+            length
+        )));
+    }
+    let mut sampler = IndexSampler::new(alphabet.len());
+    let mut password = Vec::with_capacity(length);
+    for _ in 0..MAX_ATTEMPTS {
+        password.clear();
+        let mut prev = None;
+        let mut run = 0;
+        for _ in 0..length {
+            let mut index = sampler.next()?;
+            if run == max_consecutive {
+                // Draw uniformly from the other characters instead.
+                while Some(index) == prev {
+                    index = sampler.next()?;
+                }
+            }
+            if Some(index) == prev {
+                run += 1;
+            } else {
+                prev = Some(index);
+                run = 1;
+            }
+            password.push(alphabet[index]);
+        }
+        if rules.has_required(&password) {
+            return Ok(password.into_iter().collect());
+        }
+    }
+    throw!(ErrorKind::InvalidPasswordRules(
+        "Failed to generate a password which satisfies the rules".into()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse() {
+        let rules = PasswordRules::parse(
+            "required: upper; required: digit, [-_]; allowed: [abc]; \
+             max-consecutive: 2; minlength: 8; maxlength: 12",
+        )
+        .unwrap();
+        assert_eq!(
+            rules,
+            PasswordRules {
+                required: vec![class(UPPER), class("0123456789-_")],
+                allowed: class("abc"),
+                min_length: Some(8),
+                max_length: Some(12),
+                max_consecutive: Some(2),
+            }
+        );
+
+        // Repeated rules take the most restrictive value, and junk is ignored.
+        let rules = PasswordRules::parse(
+            "MAXLENGTH: 20; maxlength: 10; minlength: 4; minlength: 6; \
+             bogus: 1; allowed: bogus; required: [unterminated; maxlength: -1",
+        )
+        .unwrap();
+        assert_eq!(
+            rules,
+            PasswordRules {
+                min_length: Some(6),
+                max_length: Some(10),
+                ..PasswordRules::default()
+            }
+        );
+
+        assert_eq!(PasswordRules::parse("").unwrap(), PasswordRules::default());
+        assert_eq!(
+            PasswordRules::parse("allowed: []abc]").unwrap().allowed,
+            class("]abc")
+        );
+        assert_eq!(
+            PasswordRules::parse("allowed: [a b], ascii-printable")
+                .unwrap()
+                .allowed,
+            (b'!'..=b'~').map(char::from).collect::<CharSet>()
+        );
+
+        // We can't generate non-ASCII characters, so a site which asks for
+        // them gets an error, instead of a password without them.
+        for rules in &["required: [äb]", "allowed: lower, [ab€]"] {
+            match PasswordRules::parse(rules).unwrap_err().kind() {
+                ErrorKind::InvalidPasswordRules(_) => {}
+                e => panic!("Unexpected error {:?} for {:?}", e, rules),
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_default() {
+        let password = generate_password(&PasswordRules::default()).unwrap();
+        assert_eq!(password.len(), DEFAULT_LENGTH);
+        assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+        assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+        assert!(password.chars().any(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_generate_with_rules() {
+        let rules = PasswordRules::parse(
+            "required: lower; required: [!#]; allowed: digit; max-consecutive: 1; maxlength: 8",
+        )
+        .unwrap();
+        for _ in 0..100 {
+            let password = generate_password(&rules).unwrap();
+            let chars: Vec<char> = password.chars().collect();
+            assert_eq!(chars.len(), 8);
+            assert!(chars.iter().any(char::is_ascii_lowercase));
+            assert!(chars.iter().any(|c| *c == '!' || *c == '#'));
+            assert!(chars
+                .iter()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '!' || *c == '#'));
+            assert!(chars.windows(2).all(|w| w[0] != w[1]));
+        }
+
+        // `minlength` wins over the default length.
+        let rules = PasswordRules::parse("minlength: 30").unwrap();
+        assert_eq!(generate_password(&rules).unwrap().len(), 30);
+    }
+
+    #[test]
+    fn test_generate_max_consecutive() {
+        // Only "abab..." and "baba..." satisfy these, so we can't find them
+        // by throwing away passwords with long runs.
+        let rules =
+            PasswordRules::parse("required: [a]; required: [b]; max-consecutive: 1").unwrap();
+        let mut seen = BTreeSet::new();
+        for _ in 0..100 {
+            let password = generate_password(&rules).unwrap();
+            assert_eq!(password.len(), DEFAULT_LENGTH);
+            assert!(password.as_bytes().windows(2).all(|w| w[0] != w[1]));
+            seen.insert(password);
+        }
+        assert_eq!(seen.len(), 2);
+
+        // Runs can reach the limit, but not go past it.
+        let rules =
+            PasswordRules::parse("allowed: [ab]; max-consecutive: 3; minlength: 50").unwrap();
+        for _ in 0..100 {
+            let password = generate_password(&rules).unwrap();
+            assert!(!password.contains("aaaa") && !password.contains("bbbb"));
+        }
+
+        // A single character can be repeated up to the limit.
+        let rules =
+            PasswordRules::parse("required: [a]; max-consecutive: 3; maxlength: 3").unwrap();
+        assert_eq!(generate_password(&rules).unwrap(), "aaa");
+    }
+
+    #[test]
+    fn test_generate_impossible() {
+        for rules in &[
+            "minlength: 10; maxlength: 5",
+            "required: upper; required: lower; required: digit; maxlength: 2",
+            "required: [ ]",
+            "required: [a]; max-consecutive: 1",
+        ] {
+            match generate_password(&PasswordRules::parse(rules).unwrap())
+                .unwrap_err()
+                .kind()
+            {
+                ErrorKind::InvalidPasswordRules(_) => {}
+                e => panic!("Unexpected error {:?} for {:?}", e, rules),
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_is_uniform() {
+        // The valid passwords are "ac", "ca", "bc" and "cb" - a generator
+        // which inserted one character from each required set and filled in
+        // the rest wouldn't weight these equally.
+        let rules = PasswordRules::parse("required: [ab]; required: [c]; maxlength: 2").unwrap();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..4000 {
+            *counts
+                .entry(generate_password(&rules).unwrap())
+                .or_default() += 1;
+        }
+        assert_eq!(counts.len(), 4);
+        for (password, count) in &counts {
+            // The standard deviation is ~27, so this won't fail in practice.
+            assert!(
+                *count > 800 && *count < 1200,
+                "{:?} was generated {} times",
+                password,
+                count
+            );
+        }
+    }
+}
@@ -0,0 +1,168 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Password health analysis.
+//!
+//! The analysis is done over `HashedLogin`s, which are built in the DB layer
+//! and only carry a SHA-256 hash of the password, so that plaintext passwords
+//! never make it into the report (or anywhere near it).
+
+use crate::error::*;
+use crate::login::Login;
+use crate::public_suffix;
+use rc_crypto::digest;
+use serde_derive::*;
+use std::collections::{HashMap, HashSet};
+use sync_guid::Guid;
+use url::{Host, Url};
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordHealthReport {
+    /// Groups of logins which share the same password, but which are used
+    /// on more than one base domain. Each group is sorted by GUID.
+    pub reused_passwords: Vec<Vec<Guid>>,
+    /// Logins with an empty username which have the same origin and password
+    /// as another login, and so are probably redundant.
+    pub empty_username_dupes: Vec<Guid>,
+    /// Logins for an `http://` origin where the `https://` version of the
+    /// origin also has a saved login.
+    pub insecure_origins: Vec<Guid>,
+    /// Logins whose password hasn't changed since before the threshold.
+    pub stale_passwords: Vec<Guid>,
+}
+
+/// Just the parts of a login the analysis needs.
+pub(crate) struct HashedLogin {
+    guid: Guid,
+    origin: Option<Url>,
+    username_is_empty: bool,
+    password_hash: Vec<u8>,
+    time_password_changed: i64,
+}
+
+impl HashedLogin {
+    pub(crate) fn new(login: Login) -> Result<Self> {
+        let password_hash = digest::digest(&digest::SHA256, login.password.as_bytes())?
+            .as_ref()
+            .to_vec();
+        Ok(Self {
+            origin: Url::parse(&login.origin).ok(),
+            username_is_empty: login.username.is_empty(),
+            time_password_changed: login.time_password_changed,
+            guid: login.guid,
+            password_hash,
+        })
+    }
+
+    fn base_domain(&self) -> Option<String> {
+        self.origin.as_ref().and_then(|url| match url.host()? {
+            Host::Domain(d) => Some(base_domain(d).to_owned()),
+            Host::Ipv4(ip) => Some(ip.to_string()),
+            Host::Ipv6(ip) => Some(ip.to_string()),
+        })
+    }
+}
+
+/// Returns the registrable domain for `host`, like `example.co.uk` for
+/// `mail.example.co.uk`. Hosts which don't have one, like `localhost` or
+/// `github.io`, are their own base domain.
+pub(crate) fn base_domain(host: &str) -> &str {
+    let host = host.trim_end_matches('.');
+    public_suffix::registrable_domain(host).unwrap_or(host)
+}
+
+pub(crate) fn analyze(logins: Vec<HashedLogin>, stale_before_ms: i64) -> PasswordHealthReport {
+    let mut report = PasswordHealthReport::default();
+
+    let mut by_password: HashMap<&[u8], Vec<&HashedLogin>> = HashMap::new();
+    for login in &logins {
+        by_password
+            .entry(login.password_hash.as_slice())
+            .or_default()
+            .push(login);
+    }
+    for group in by_password.values() {
+        if group.len() < 2 {
+            continue;
+        }
+        let domains: HashSet<Option<String>> =
+            group.iter().map(|login| login.base_domain()).collect();
+        if domains.len() > 1 {
+            let mut guids: Vec<Guid> = group.iter().map(|login| login.guid.clone()).collect();
+            guids.sort();
+            report.reused_passwords.push(guids);
+        }
+
+        for login in group.iter().filter(|login| login.username_is_empty) {
+            // If both logins have an empty username, we only want to report
+            // one of them, so we arbitrarily keep the one with the larger GUID.
+            let has_dupe = group.iter().any(|other| {
+                other.guid != login.guid
+                    && other.origin.is_some()
+                    && other.origin == login.origin
+                    && !(other.username_is_empty && other.guid < login.guid)
+            });
+            if has_dupe {
+                report.empty_username_dupes.push(login.guid.clone());
+            }
+        }
+    }
+
+    let secure_origins: HashSet<(&str, Option<u16>)> = logins
+        .iter()
+        .filter_map(|login| login.origin.as_ref())
+        .filter(|url| url.scheme() == "https")
+        .filter_map(|url| Some((url.host_str()?, url.port())))
+        .collect();
+    for login in &logins {
+        if let Some(url) = &login.origin {
+            if url.scheme() == "http" {
+                if let Some(host) = url.host_str() {
+                    // Default ports aren't stored by `Url`, so `http://a.com`
+                    // matches `https://a.com`, but `http://a.com:8080` only
+                    // matches `https://a.com:8080`.
+                    if secure_origins.contains(&(host, url.port())) {
+                        report.insecure_origins.push(login.guid.clone());
+                    }
+                }
+            }
+        }
+
+        if login.time_password_changed > 0 && login.time_password_changed < stale_before_ms {
+            report.stale_passwords.push(login.guid.clone());
+        }
+    }
+
+    // Keep the output stable, since it comes from a HashMap.
+    report.reused_passwords.sort();
+    report.empty_username_dupes.sort();
+    report.insecure_origins.sort();
+    report.stale_passwords.sort();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_domain() {
+        assert_eq!(base_domain("example.com"), "example.com");
+        assert_eq!(base_domain("www.example.com"), "example.com");
+        assert_eq!(base_domain("a.b.example.com"), "example.com");
+        assert_eq!(base_domain("www.example.co.uk"), "example.co.uk");
+        assert_eq!(base_domain("example.co.uk"), "example.co.uk");
+        assert_eq!(base_domain("localhost"), "localhost");
+        assert_eq!(base_domain("example.com."), "example.com");
+        assert_eq!(base_domain("www.abc.de"), "abc.de");
+        assert_eq!(base_domain("mail.gmx.de"), "gmx.de");
+        assert_eq!(base_domain("www.gmx.de"), "gmx.de");
+        assert_eq!(base_domain("a.web.de"), "web.de");
+        assert_eq!(base_domain("login.example.com.au"), "example.com.au");
+        assert_eq!(base_domain("evil.github.io"), "evil.github.io");
+        assert_eq!(base_domain("www.victim.github.io"), "victim.github.io");
+        assert_eq!(base_domain("github.io"), "github.io");
+    }
+}
@@ -0,0 +1,349 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Password revision history.
+//!
+//! Whenever a login's password is overwritten - by `LoginDb::update`, or by
+//! an incoming record during a sync - the old password is recorded in the
+//! `loginsHistory` table (see the `schema` module), along with where the
+//! change came from. Only the most recent `MAX_REVISIONS` are kept for each
+//! login.
+//!
+//! The history is local only. It's cleared by `wipe` and `wipe_local`, and
+//! for a single login when that login is deleted, but survives a `reset`,
+//! since that doesn't change any logins.
+
+use crate::db::LoginDb;
+use crate::error::*;
+use crate::util;
+use rusqlite::{named_params, Connection, Row};
+use serde_derive::*;
+use sql_support::ConnExt;
+use std::time::SystemTime;
+use sync_guid::Guid;
+
+/// The number of previous passwords we keep for each login.
+pub const MAX_REVISIONS: usize = 10;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+#[repr(u8)]
+pub enum RevisionSource {
+    /// The password was changed locally, e.g. by the user.
+    LocalEdit = 1,
+    /// The password was changed by a record from another device.
+    Sync = 2,
+}
+
+impl RevisionSource {
+    fn from_u8(v: u8) -> Result<Self> {
+        match v {
+            1 => Ok(RevisionSource::LocalEdit),
+            2 => Ok(RevisionSource::Sync),
+            v => throw!(ErrorKind::BadRevisionSource(v)),
+        }
+    }
+}
+
+/// A password which a login used to have.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordRevision {
+    /// Identifies the revision when restoring it.
+    pub id: i64,
+    pub guid: Guid,
+    pub password: String,
+    /// When this password was set, in milliseconds since the unix epoch.
+    pub time_password_changed: i64,
+    /// When this password was overwritten, in milliseconds since the unix
+    /// epoch.
+    pub time_replaced: i64,
+    /// What overwrote it.
+    pub source: RevisionSource,
+}
+
+impl PasswordRevision {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            guid: row.get("guid")?,
+            password: row.get("password")?,
+            time_password_changed: row.get("time_password_changed")?,
+            time_replaced: row.get("time_replaced")?,
+            source: RevisionSource::from_u8(row.get("source")?)?,
+        })
+    }
+}
+
+/// Records the current password of the login with `guid` as a revision of
+/// `new_guid`, if it's about to be replaced by `new_password`. Must be called
+/// before the login is changed.
+///
+/// The guids only differ when an incoming record replaces a local dupe, in
+/// which case the dupe's history moves over to the incoming guid too.
+pub(crate) fn record_overwrite(
+    conn: &Connection,
+    guid: &str,
+    new_guid: &str,
+    new_password: &str,
+    source: RevisionSource,
+) -> Result<()> {
+    let now_ms = util::system_time_ms_i64(SystemTime::now());
+    // Only one of these can match: if there's a live local record, the
+    // mirror is overridden.
+    let changed = conn.execute_named_cached(
+        "INSERT INTO loginsHistory (guid, password, time_password_changed, time_replaced, source)
+         SELECT :new_guid, password, timePasswordChanged, :now_ms, :source
+         FROM loginsL
+         WHERE guid = :guid AND is_deleted = 0 AND password <> :new_password
+         UNION ALL
+         SELECT :new_guid, password, timePasswordChanged, :now_ms, :source
+         FROM loginsM
+         WHERE guid = :guid AND is_overridden = 0 AND password <> :new_password",
+        named_params! {
+            ":guid": guid,
+            ":new_guid": new_guid,
+            ":new_password": new_password,
+            ":now_ms": now_ms,
+            ":source": source as u8,
+        },
+    )?;
+    if guid != new_guid {
+        conn.execute_named_cached(
+            "UPDATE loginsHistory SET guid = :new_guid WHERE guid = :guid",
+            named_params! { ":guid": guid, ":new_guid": new_guid },
+        )?;
+    }
+    if changed > 0 {
+        conn.execute_named_cached(
+            "DELETE FROM loginsHistory
+             WHERE guid = :guid
+               AND id NOT IN (SELECT id FROM loginsHistory
+                              WHERE guid = :guid
+                              ORDER BY id DESC
+                              LIMIT :max_revisions)",
+            named_params! {
+                ":guid": new_guid,
+                ":max_revisions": MAX_REVISIONS as i64,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Removes the history of the login with `guid`, when it's deleted.
+pub(crate) fn forget(conn: &Connection, guid: &str) -> Result<()> {
+    conn.execute_named_cached(
+        "DELETE FROM loginsHistory WHERE guid = :guid",
+        named_params! { ":guid": guid },
+    )?;
+    Ok(())
+}
+
+/// Returns the previous passwords of the login with `guid`, most recently
+/// replaced first.
+pub(crate) fn get_revisions(db: &LoginDb, guid: &str) -> Result<Vec<PasswordRevision>> {
+    let mut stmt = db.prepare_cached(
+        "SELECT id, guid, password, time_password_changed, time_replaced, source
+         FROM loginsHistory
+         WHERE guid = :guid
+         ORDER BY id DESC",
+    )?;
+    let rows =
+        stmt.query_and_then_named(named_params! { ":guid": guid }, PasswordRevision::from_row)?;
+    rows.collect()
+}
+
+/// Sets the password of the login with `guid` back to the one in revision
+/// `id`. This goes through `LoginDb::update`, so the password being replaced
+/// is itself recorded, and the change is synced like any other edit.
+pub(crate) fn restore_revision(db: &LoginDb, guid: &str, id: i64) -> Result<()> {
+    let password: Option<String> = db.try_query_row(
+        "SELECT password FROM loginsHistory WHERE guid = :guid AND id = :id",
+        named_params! { ":guid": guid, ":id": id },
+        |row| row.get(0),
+        false,
+    )?;
+    let password = match password {
+        Some(password) => password,
+        None => throw!(ErrorKind::NoSuchRevision(id)),
+    };
+    let mut login = match db.get_by_id(guid)? {
+        Some(login) => login,
+        None => throw!(ErrorKind::NoSuchRecord(guid.to_owned())),
+    };
+    login.password = password;
+    db.update(login)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::login::Login;
+
+    fn add_login(db: &LoginDb, guid: &str, password: &str) {
+        db.add(Login {
+            guid: guid.into(),
+            origin: "https://www.example.com".into(),
+            form_submit_url: Some("https://www.example.com".into()),
+            username: guid.into(),
+            password: password.into(),
+            ..Login::default()
+        })
+        .unwrap();
+    }
+
+    fn set_password(db: &LoginDb, guid: &str, password: &str) {
+        let mut login = db.get_by_id(guid).unwrap().unwrap();
+        login.password = password.into();
+        db.update(login).unwrap();
+    }
+
+    fn passwords(db: &LoginDb, guid: &str) -> Vec<String> {
+        get_revisions(db, guid)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.password)
+            .collect()
+    }
+
+    // Applies an incoming record for the login with `guid`, returning the
+    // password overwrites that were planned for it.
+    fn sync_password(
+        db: &LoginDb,
+        guid: &str,
+        password: &str,
+        time_password_changed: i64,
+    ) -> Vec<(Guid, Guid, String)> {
+        let scope = db.begin_interrupt_scope();
+        let mut telem = sync15::telemetry::EngineIncoming::new();
+        let payload = sync15::Payload::from_json(serde_json::json!({
+            "id": guid,
+            "hostname": "https://www.example.com",
+            "formSubmitURL": "https://www.example.com",
+            "username": guid,
+            "password": password,
+            "timePasswordChanged": time_password_changed,
+        }))
+        .unwrap();
+        let now = sync15::ServerTimestamp::from_millis(time_password_changed);
+        let data = db
+            .fetch_login_data(&[(payload, now)], &mut telem, &scope)
+            .unwrap();
+        let plan = db.reconcile(data, now, &mut telem, &scope).unwrap();
+        let overwrites = plan.password_overwrites.clone();
+        db.execute_plan(plan, &scope).unwrap();
+        overwrites
+    }
+
+    #[test]
+    fn test_local_edits() {
+        let db = LoginDb::open_in_memory(Some("testing")).unwrap();
+        add_login(&db, "aaaaaaaaaaaa", "p0");
+        assert!(get_revisions(&db, "aaaaaaaaaaaa").unwrap().is_empty());
+
+        set_password(&db, "aaaaaaaaaaaa", "p1");
+        // Updates which don't change the password aren't recorded.
+        set_password(&db, "aaaaaaaaaaaa", "p1");
+        set_password(&db, "aaaaaaaaaaaa", "p2");
+        let revisions = get_revisions(&db, "aaaaaaaaaaaa").unwrap();
+        assert_eq!(
+            revisions
+                .iter()
+                .map(|r| r.password.as_str())
+                .collect::<Vec<_>>(),
+            vec!["p1", "p0"]
+        );
+        assert!(revisions
+            .iter()
+            .all(|r| r.source == RevisionSource::LocalEdit));
+
+        // Restoring records the password it replaces.
+        restore_revision(&db, "aaaaaaaaaaaa", revisions[1].id).unwrap();
+        assert_eq!(
+            db.get_by_id("aaaaaaaaaaaa").unwrap().unwrap().password,
+            "p0"
+        );
+        assert_eq!(passwords(&db, "aaaaaaaaaaaa"), vec!["p2", "p1", "p0"]);
+
+        // Revisions belong to a single login.
+        add_login(&db, "bbbbbbbbbbbb", "other");
+        match restore_revision(&db, "bbbbbbbbbbbb", revisions[1].id)
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::NoSuchRevision(_) => {}
+            e => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_sync_overwrites() {
+        let db = LoginDb::open_in_memory(Some("testing")).unwrap();
+        add_login(&db, "aaaaaaaaaaaa", "p0");
+        let changed = db
+            .get_by_id("aaaaaaaaaaaa")
+            .unwrap()
+            .unwrap()
+            .time_password_changed;
+
+        // A newer incoming record without a shared parent replaces the local
+        // one.
+        let overwrites = sync_password(&db, "aaaaaaaaaaaa", "p1", changed + 1000);
+        assert_eq!(overwrites.len(), 1);
+        assert_eq!(overwrites[0].0, "aaaaaaaaaaaa");
+        assert_eq!(overwrites[0].1, "aaaaaaaaaaaa");
+        assert_eq!(overwrites[0].2, "p1");
+        // Then one which changes the mirror.
+        sync_password(&db, "aaaaaaaaaaaa", "p2", changed + 2000);
+        assert_eq!(
+            db.get_by_id("aaaaaaaaaaaa").unwrap().unwrap().password,
+            "p2"
+        );
+        let revisions = get_revisions(&db, "aaaaaaaaaaaa").unwrap();
+        assert_eq!(
+            revisions
+                .iter()
+                .map(|r| r.password.as_str())
+                .collect::<Vec<_>>(),
+            vec!["p1", "p0"]
+        );
+        assert!(revisions.iter().all(|r| r.source == RevisionSource::Sync));
+        assert_eq!(revisions[1].time_password_changed, changed);
+
+        // Incoming records which keep the password aren't recorded.
+        sync_password(&db, "aaaaaaaaaaaa", "p2", changed + 3000);
+        assert_eq!(passwords(&db, "aaaaaaaaaaaa"), vec!["p1", "p0"]);
+
+        restore_revision(&db, "aaaaaaaaaaaa", revisions[1].id).unwrap();
+        assert_eq!(
+            db.get_by_id("aaaaaaaaaaaa").unwrap().unwrap().password,
+            "p0"
+        );
+        let revisions = get_revisions(&db, "aaaaaaaaaaaa").unwrap();
+        assert_eq!(revisions[0].password, "p2");
+        assert_eq!(revisions[0].source, RevisionSource::LocalEdit);
+    }
+
+    #[test]
+    fn test_bounded_and_cleared() {
+        let db = LoginDb::open_in_memory(Some("testing")).unwrap();
+        add_login(&db, "aaaaaaaaaaaa", "p0");
+        add_login(&db, "bbbbbbbbbbbb", "q0");
+        for i in 1..=MAX_REVISIONS + 5 {
+            set_password(&db, "aaaaaaaaaaaa", &format!("p{}", i));
+        }
+        set_password(&db, "bbbbbbbbbbbb", "q1");
+        let history = passwords(&db, "aaaaaaaaaaaa");
+        assert_eq!(history.len(), MAX_REVISIONS);
+        assert_eq!(history[0], format!("p{}", MAX_REVISIONS + 4));
+
+        db.delete("aaaaaaaaaaaa").unwrap();
+        assert!(passwords(&db, "aaaaaaaaaaaa").is_empty());
+        assert_eq!(passwords(&db, "bbbbbbbbbbbb"), vec!["q0"]);
+
+        db.wipe_local().unwrap();
+        assert!(passwords(&db, "bbbbbbbbbbbb").is_empty());
+    }
+}
@@ -9,8 +9,17 @@
 mod error;
 mod login;
 
+mod backup;
+mod breach;
+mod consistency;
+mod csv;
 mod db;
 mod engine;
+mod generator;
+mod health;
+mod history;
+mod matching;
+mod public_suffix;
 pub mod schema;
 mod update_plan;
 mod util;
@@ -18,10 +27,17 @@ mod util;
 mod ffi;
 
 // Mostly exposed for the sync manager.
+pub use crate::backup::RestoreMetrics;
+pub use crate::breach::{BreachRangeFetcher, BreachStatus};
+pub use crate::consistency::{ConsistencyProblem, ConsistencyProblemKind};
+pub use crate::csv::{CsvImportFailure, CsvImportResult};
 pub use crate::db::LoginDb;
 pub use crate::db::LoginStore;
 pub use crate::engine::*;
 pub use crate::error::*;
+pub use crate::generator::{generate_password, PasswordRules};
+pub use crate::health::PasswordHealthReport;
+pub use crate::history::{PasswordRevision, RevisionSource, MAX_REVISIONS};
 pub use crate::login::*;
 
 pub mod msg_types {
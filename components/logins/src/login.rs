@@ -23,17 +23,18 @@
 //!   treat this it as an opaque identifier. It should be left blank when adding a new record,
 //!   in which case a new id will be automatically generated.
 //!
-//! - `hostname`:  The origin at which this login can be used, as a string.
+//! - `origin`:  The origin at which this login can be used, as a string.
 //!
 //!   The login should only be used on sites that match this origin (for whatever definition
 //!   of "matches" makes sense at the application level, e.g. eTLD+1 matching).
 //!   This field is required, must be a valid origin in punycode format, and must not be
 //!   set to the empty string.
 //!
-//!   **YES, THIS FIELD IS CONFUSINGLY NAMED. IT SHOULD BE A FULL ORIGIN, NOT A HOSTNAME.
-//!   WE INTEND TO RENAME THIS TO `origin` IN A FUTURE RELEASE.**
+//!   This field was previously called `hostname`, and it's still called that in the records
+//!   we upload to the sync server and in serialized JSON, so older clients can read them.
+//!   Either name is accepted when deserializing a login.
 //!
-//!   Examples of valid `hostname` values include:
+//!   Examples of valid `origin` values include:
 //!   - "https://site.com"
 //!   - "http://site.com:1234"
 //!   - "ftp://ftp.site.com"
@@ -47,10 +48,11 @@
 //!   - truncating full URLs to just their origin component, if it is not an opaque origin
 //!   - converting values with non-ascii characters into punycode
 //!
-//!   **XXX TODO:**
-//!   - return a "display" field (exact name TBD) in the serialized
-//!     version, which will be the unicode version of punycode urls.
-//!   - the great renaming
+//! - `displayOrigin`:  A version of `origin` which is suitable for showing to the user.
+//!
+//!   This is derived from `origin` and is never stored; any punycode in the host is decoded
+//!   into unicode, so "http://xn--r28h.com" is displayed as "http://😍.com". It is only
+//!   provided to applications, and is ignored if they pass it back.
 //!
 //! - `password`:  The saved password, as a string.
 //!
@@ -95,11 +97,6 @@
 //!   - converting origins with non-ascii characters into punycode
 //!   - replacing invalid values with null if a valid 'httpRealm' field is present
 //!
-//!   **XXX TODO**:
-//!   - return a "display" field (exact name TBD) in the serialized
-//!     version, which will be the unicode version of punycode urls.
-//!   - the great renaming (maybe we can do the punycode thing at the same time?)
-//!
 //! - `usernameField`:  The name of the form field into which the 'username' should be filled, if any.
 //!
 //!   This value is stored if provided by the application, but does not imply any restrictions on
@@ -242,7 +239,9 @@ pub struct Login {
     #[serde(rename = "id")]
     pub guid: Guid,
 
-    pub hostname: String,
+    // Sync records still call this `hostname`, as do old serialized logins.
+    #[serde(rename = "hostname", alias = "origin")]
+    pub origin: String,
 
     // rename_all = "camelCase" by default will do formSubmitUrl, but we can just
     // override this one field.
@@ -307,6 +306,27 @@ impl Login {
         self.guid.as_str()
     }
 
+    /// Returns `origin` in a form suitable for showing to the user, with any
+    /// punycode in the host decoded into unicode.
+    pub fn display_origin(&self) -> String {
+        let host = match Url::parse(&self.origin)
+            .ok()
+            .and_then(|u| u.domain().map(str::to_owned))
+        {
+            Some(host) => host,
+            None => return self.origin.clone(),
+        };
+        // Only decode actual punycode, since `domain_to_unicode` also
+        // normalizes things like case, which we don't want to change.
+        if !host.split('.').any(|label| label.starts_with("xn--")) {
+            return self.origin.clone();
+        }
+        match idna::domain_to_unicode(&host) {
+            (unicode, Ok(())) if unicode != host => self.origin.replacen(&host, &unicode, 1),
+            _ => self.origin.clone(),
+        }
+    }
+
     /// Checks whether the Login is valid, without attempting to fix any fields.
     /// Returns an error if invalid data is found, even if it could have been fixed.
     pub fn check_valid(&self) -> Result<()> {
@@ -405,7 +425,7 @@ impl Login {
             };
         };
 
-        if self.hostname.is_empty() {
+        if self.origin.is_empty() {
             throw!(InvalidLogin::EmptyOrigin);
         }
 
@@ -432,7 +452,7 @@ impl Login {
         let field_data = [
             ("formSubmitUrl", &form_submit_url),
             ("httpRealm", &http_realm),
-            ("hostname", &self.hostname),
+            ("origin", &self.origin),
             ("usernameField", &self.username_field),
             ("passwordField", &self.password_field),
             ("username", &self.username),
@@ -467,11 +487,11 @@ impl Login {
         }
 
         // Check we can parse the origin, then use the normalized version of it.
-        if let Some(fixed) = Login::validate_and_fixup_origin(&self.hostname)? {
+        if let Some(fixed) = Login::validate_and_fixup_origin(&self.origin)? {
             get_fixed_or_throw!(InvalidLogin::IllegalFieldValue {
                 field_info: "Origin is not normalized".into()
             })?
-            .hostname = fixed;
+            .origin = fixed;
         }
 
         match &maybe_fixed.as_ref().unwrap_or(self).form_submit_url {
@@ -521,7 +541,7 @@ impl Login {
             password: row.get("password")?,
             username: string_or_default(row, "username")?,
 
-            hostname: row.get("hostname")?,
+            origin: row.get("origin")?,
             http_realm: row.get("httpRealm")?,
 
             form_submit_url: row.get("formSubmitURL")?,
@@ -548,7 +568,8 @@ impl From<Login> for PasswordInfo {
     fn from(login: Login) -> Self {
         Self {
             id: login.guid.into_string(),
-            hostname: login.hostname,
+            display_origin: Some(login.display_origin()),
+            origin: login.origin,
             password: login.password,
             username: login.username,
             http_realm: login.http_realm,
@@ -567,7 +588,7 @@ impl From<PasswordInfo> for Login {
     fn from(info: PasswordInfo) -> Self {
         Self {
             guid: Guid::from_string(info.id),
-            hostname: info.hostname,
+            origin: info.origin,
             password: info.password,
             username: info.username,
             http_realm: info.http_realm,
@@ -774,7 +795,7 @@ impl_login_setter!(set_mirror, mirror, MirrorLogin);
 #[derive(Debug, Default, Clone)]
 pub(crate) struct LoginDelta {
     // "non-commutative" fields
-    pub hostname: Option<String>,
+    pub origin: Option<String>,
     pub password: Option<String>,
     pub username: Option<String>,
     pub http_realm: Option<String>,
@@ -792,43 +813,165 @@ pub(crate) struct LoginDelta {
     pub times_used: i64,
 }
 
+/// How a conflict between a local and an incoming change to a login field was
+/// resolved while syncing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConflictResolution {
+    /// Both sides changed the field since the last sync, and the local change
+    /// was more recent, so it was kept.
+    LocalWins,
+    /// Both sides changed the field since the last sync, and the incoming
+    /// change was more recent, so it was taken.
+    RemoteWins,
+    /// There was no shared parent to tell which side changed the field, so
+    /// the whole record with the newer `timePasswordChanged` was kept.
+    Timestamp,
+}
+
+/// A field which had a conflicting value while syncing, and how the conflict
+/// was resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MergeConflict {
+    /// The name of the field, as it appears in the sync record.
+    pub field: &'static str,
+    pub resolution: ConflictResolution,
+}
+
+impl MergeConflict {
+    /// The name this conflict is reported under in validation telemetry, for
+    /// example `passwordConflictRemoteWins`.
+    pub fn problem_name(&self) -> String {
+        let resolution = match self.resolution {
+            ConflictResolution::LocalWins => "LocalWins",
+            ConflictResolution::RemoteWins => "RemoteWins",
+            ConflictResolution::Timestamp => "Timestamp",
+        };
+        format!("{}Conflict{}", self.field, resolution)
+    }
+}
+
 macro_rules! merge_field {
-    ($merged:ident, $b:ident, $prefer_b:expr, $field:ident) => {
+    ($merged:ident, $b:ident, $prefer_b:expr, $conflicts:ident, $field:ident, $name:expr) => {
         if let Some($field) = $b.$field.take() {
-            if $merged.$field.is_some() {
-                log::warn!("Collision merging login field {}", stringify!($field));
-                if $prefer_b {
+            match &$merged.$field {
+                // Both sides making the same change isn't a conflict.
+                Some(ours) if *ours == $field => {}
+                Some(_) => {
+                    log::warn!("Collision merging login field {}", stringify!($field));
+                    $conflicts.push(MergeConflict {
+                        field: $name,
+                        resolution: if $prefer_b {
+                            ConflictResolution::RemoteWins
+                        } else {
+                            ConflictResolution::LocalWins
+                        },
+                    });
+                    if $prefer_b {
+                        $merged.$field = Some($field);
+                    }
+                }
+                None => {
                     $merged.$field = Some($field);
                 }
-            } else {
-                $merged.$field = Some($field);
             }
         }
     };
 }
 
 impl LoginDelta {
+    /// Merges the local delta `self` with the incoming delta `b`. Fields
+    /// changed on both sides take the value from the newer delta, and each
+    /// of these conflicts is added to `conflicts`.
     #[allow(clippy::cognitive_complexity)] // Looks like clippy considers this after macro-expansion...
-    pub fn merge(self, mut b: LoginDelta, b_is_newer: bool) -> LoginDelta {
+    pub fn merge(
+        self,
+        mut b: LoginDelta,
+        b_is_newer: bool,
+        conflicts: &mut Vec<MergeConflict>,
+    ) -> LoginDelta {
         let mut merged = self;
-        merge_field!(merged, b, b_is_newer, hostname);
-        merge_field!(merged, b, b_is_newer, password);
-        merge_field!(merged, b, b_is_newer, username);
-        merge_field!(merged, b, b_is_newer, http_realm);
-        merge_field!(merged, b, b_is_newer, form_submit_url);
-
-        merge_field!(merged, b, b_is_newer, time_created);
-        merge_field!(merged, b, b_is_newer, time_last_used);
-        merge_field!(merged, b, b_is_newer, time_password_changed);
-
-        merge_field!(merged, b, b_is_newer, password_field);
-        merge_field!(merged, b, b_is_newer, username_field);
+        merge_field!(merged, b, b_is_newer, conflicts, origin, "hostname");
+        merge_field!(merged, b, b_is_newer, conflicts, password, "password");
+        merge_field!(merged, b, b_is_newer, conflicts, username, "username");
+        merge_field!(merged, b, b_is_newer, conflicts, http_realm, "httpRealm");
+        merge_field!(
+            merged,
+            b,
+            b_is_newer,
+            conflicts,
+            form_submit_url,
+            "formSubmitURL"
+        );
+
+        merge_field!(
+            merged,
+            b,
+            b_is_newer,
+            conflicts,
+            time_created,
+            "timeCreated"
+        );
+        merge_field!(
+            merged,
+            b,
+            b_is_newer,
+            conflicts,
+            time_last_used,
+            "timeLastUsed"
+        );
+        merge_field!(
+            merged,
+            b,
+            b_is_newer,
+            conflicts,
+            time_password_changed,
+            "timePasswordChanged"
+        );
+
+        merge_field!(
+            merged,
+            b,
+            b_is_newer,
+            conflicts,
+            password_field,
+            "passwordField"
+        );
+        merge_field!(
+            merged,
+            b,
+            b_is_newer,
+            conflicts,
+            username_field,
+            "usernameField"
+        );
 
         // commutative fields
         merged.times_used += b.times_used;
 
         merged
     }
+
+    /// The names of the fields this delta changes, as they appear in the sync
+    /// record. The commutative `timesUsed` isn't included.
+    pub fn changed_fields(&self) -> Vec<&'static str> {
+        let fields = [
+            ("hostname", self.origin.is_some()),
+            ("password", self.password.is_some()),
+            ("username", self.username.is_some()),
+            ("httpRealm", self.http_realm.is_some()),
+            ("formSubmitURL", self.form_submit_url.is_some()),
+            ("timeCreated", self.time_created.is_some()),
+            ("timeLastUsed", self.time_last_used.is_some()),
+            ("timePasswordChanged", self.time_password_changed.is_some()),
+            ("passwordField", self.password_field.is_some()),
+            ("usernameField", self.username_field.is_some()),
+        ];
+        fields
+            .iter()
+            .filter(|(_, changed)| *changed)
+            .map(|(name, _)| *name)
+            .collect()
+    }
 }
 
 macro_rules! apply_field {
@@ -841,7 +984,7 @@ macro_rules! apply_field {
 
 impl Login {
     pub(crate) fn apply_delta(&mut self, mut delta: LoginDelta) {
-        apply_field!(self, delta, hostname);
+        apply_field!(self, delta, origin);
 
         apply_field!(self, delta, password);
         apply_field!(self, delta, username);
@@ -876,8 +1019,8 @@ impl Login {
             delta.http_realm = Some(self.http_realm.clone().unwrap_or_default());
         }
 
-        if self.hostname != older.hostname {
-            delta.hostname = Some(self.hostname.clone());
+        if self.origin != older.origin {
+            delta.origin = Some(self.origin.clone());
         }
         if self.username != older.username {
             delta.username = Some(self.username.clone());
@@ -971,11 +1114,118 @@ mod tests {
         assert_eq!(login.time_password_changed, now64 - 25);
     }
 
+    #[test]
+    fn test_origin_serialization() {
+        // Sync records (and older serialized logins) use `hostname`, but
+        // `origin` is accepted too.
+        for key in &["hostname", "origin"] {
+            let mut json = serde_json::json!({
+                "id": "123412341234",
+                "formSubmitURL": "https://www.example.com/submit",
+                "username": "test",
+                "password": "test",
+            });
+            json[*key] = "https://www.example.com".into();
+            let login: Login = serde_json::from_value(json).unwrap();
+            assert_eq!(login.origin, "https://www.example.com");
+            let out = serde_json::to_value(&login).unwrap();
+            assert_eq!(out["hostname"], "https://www.example.com");
+            assert!(out.get("origin").is_none());
+        }
+    }
+
+    #[test]
+    fn test_display_origin() {
+        for (origin, expected) in &[
+            ("https://www.example.com", "https://www.example.com"),
+            ("http://xn--r28h.com", "http://😍.com"),
+            (
+                "https://xn--bcher-kva.example:8080",
+                "https://bücher.example:8080",
+            ),
+            ("https://[::1]", "https://[::1]"),
+            ("chrome://MyLegacyExtension", "chrome://MyLegacyExtension"),
+            ("file://", "file://"),
+        ] {
+            let login = Login {
+                origin: (*origin).into(),
+                ..Login::default()
+            };
+            assert_eq!(login.display_origin(), *expected);
+        }
+        let info = PasswordInfo::from(Login {
+            origin: "http://xn--r28h.com".into(),
+            ..Login::default()
+        });
+        assert_eq!(info.origin, "http://xn--r28h.com");
+        assert_eq!(info.display_origin, Some("http://😍.com".to_owned()));
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let shared = Login {
+            origin: "https://www.example.com".into(),
+            form_submit_url: Some("https://www.example.com".into()),
+            username: "user".into(),
+            password: "shared".into(),
+            ..Login::default()
+        };
+        let local = Login {
+            password: "local".into(),
+            username: "new user".into(),
+            ..shared.clone()
+        };
+        let remote = Login {
+            password: "remote".into(),
+            username: "new user".into(),
+            username_field: "email".into(),
+            ..shared.clone()
+        };
+        assert_eq!(
+            local.delta(&remote).changed_fields(),
+            vec!["password", "usernameField"]
+        );
+
+        for (remote_is_newer, password, resolution) in &[
+            (true, "remote", ConflictResolution::RemoteWins),
+            (false, "local", ConflictResolution::LocalWins),
+        ] {
+            let mut conflicts = vec![];
+            let merged =
+                local
+                    .delta(&shared)
+                    .merge(remote.delta(&shared), *remote_is_newer, &mut conflicts);
+            // The same change on both sides isn't a conflict, and neither is
+            // a change on only one side.
+            assert_eq!(
+                conflicts,
+                vec![MergeConflict {
+                    field: "password",
+                    resolution: *resolution,
+                }]
+            );
+            let mut login = shared.clone();
+            login.apply_delta(merged);
+            assert_eq!(login.password, *password);
+            assert_eq!(login.username, "new user");
+            assert_eq!(login.username_field, "email");
+        }
+
+        assert_eq!(
+            MergeConflict {
+                field: "password",
+                resolution: ConflictResolution::RemoteWins
+            }
+            .problem_name(),
+            "passwordConflictRemoteWins"
+        );
+    }
+
     #[test]
     fn test_url_fixups() -> Result<()> {
         // Start with URLs which are all valid and already normalized.
         for input in &[
-            // The list of valid hostnames documented at the top of this file.
+            // The list of valid origins documented at the top of this file.
             "https://site.com",
             "http://site.com:1234",
             "ftp://ftp.site.com",
@@ -1032,15 +1282,15 @@ mod tests {
         }
 
         let valid_login = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test".into(),
             ..Login::default()
         };
 
-        let login_with_empty_hostname = Login {
-            hostname: "".into(),
+        let login_with_empty_origin = Login {
+            origin: "".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1048,7 +1298,7 @@ mod tests {
         };
 
         let login_with_empty_password = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "".into(),
@@ -1056,7 +1306,7 @@ mod tests {
         };
 
         let login_with_form_submit_and_http_realm = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: Some("https://www.example.com".into()),
             form_submit_url: Some("https://www.example.com".into()),
             password: "test".into(),
@@ -1064,13 +1314,13 @@ mod tests {
         };
 
         let login_without_form_submit_or_http_realm = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             password: "test".into(),
             ..Login::default()
         };
 
         let login_with_null_http_realm = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: Some("https://www.example.\0com".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1078,7 +1328,7 @@ mod tests {
         };
 
         let login_with_null_username = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "\0".into(),
             password: "test".into(),
@@ -1086,15 +1336,15 @@ mod tests {
         };
 
         let login_with_null_password = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "username".into(),
             password: "test\0".into(),
             ..Login::default()
         };
 
-        let login_with_newline_hostname = Login {
-            hostname: "\rhttps://www.example.com".into(),
+        let login_with_newline_origin = Login {
+            origin: "\rhttps://www.example.com".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1102,7 +1352,7 @@ mod tests {
         };
 
         let login_with_newline_username_field = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1111,7 +1361,7 @@ mod tests {
         };
 
         let login_with_newline_realm = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: Some("foo\nbar".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1119,7 +1369,7 @@ mod tests {
         };
 
         let login_with_newline_password = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test\n".into(),
@@ -1127,7 +1377,7 @@ mod tests {
         };
 
         let login_with_period_username_field = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1137,7 +1387,7 @@ mod tests {
 
         let login_with_period_form_submit_url = Login {
             form_submit_url: Some(".".into()),
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             username: "test".into(),
             password: "test".into(),
             ..Login::default()
@@ -1145,14 +1395,14 @@ mod tests {
 
         let login_with_javascript_form_submit_url = Login {
             form_submit_url: Some("javascript:".into()),
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             username: "test".into(),
             password: "test".into(),
             ..Login::default()
         };
 
         let login_with_malformed_origin_parens = Login {
-            hostname: " (".into(),
+            origin: " (".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1160,23 +1410,23 @@ mod tests {
         };
 
         let login_with_host_unicode = Login {
-            hostname: "http://💖.com".into(),
+            origin: "http://💖.com".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test".into(),
             ..Login::default()
         };
 
-        let login_with_hostname_trailing_slash = Login {
-            hostname: "https://www.example.com/".into(),
+        let login_with_origin_trailing_slash = Login {
+            origin: "https://www.example.com/".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test".into(),
             ..Login::default()
         };
 
-        let login_with_hostname_expanded_ipv6 = Login {
-            hostname: "https://[0:0:0:0:0:0:1:1]".into(),
+        let login_with_origin_expanded_ipv6 = Login {
+            origin: "https://[0:0:0:0:0:0:1:1]".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1184,7 +1434,7 @@ mod tests {
         };
 
         let login_with_unknown_protocol = Login {
-            hostname: "moz-proxy://127.0.0.1:8888".into(),
+            origin: "moz-proxy://127.0.0.1:8888".into(),
             http_realm: Some("https://www.example.com".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1198,7 +1448,7 @@ mod tests {
                 expected_err: "",
             },
             TestCase {
-                login: login_with_empty_hostname,
+                login: login_with_empty_origin,
                 should_err: true,
                 expected_err: "Invalid login: Origin is empty",
             },
@@ -1233,9 +1483,9 @@ mod tests {
                 expected_err: "Invalid login: Login has illegal field: `password` contains Nul",
             },
             TestCase {
-                login: login_with_newline_hostname,
+                login: login_with_newline_origin,
                 should_err: true,
-                expected_err: "Invalid login: Login has illegal field: `hostname` contains newline",
+                expected_err: "Invalid login: Login has illegal field: `origin` contains newline",
             },
             TestCase {
                 login: login_with_newline_realm,
@@ -1280,12 +1530,12 @@ mod tests {
                 expected_err: "Invalid login: Login has illegal field: Origin is not normalized",
             },
             TestCase {
-                login: login_with_hostname_trailing_slash,
+                login: login_with_origin_trailing_slash,
                 should_err: true,
                 expected_err: "Invalid login: Login has illegal field: Origin is not normalized",
             },
             TestCase {
-                login: login_with_hostname_expanded_ipv6,
+                login: login_with_origin_expanded_ipv6,
                 should_err: true,
                 expected_err: "Invalid login: Login has illegal field: Origin is not normalized",
             },
@@ -1319,7 +1569,7 @@ mod tests {
 
         // Note that most URL fixups are tested above, but we have one or 2 here.
         let login_with_full_url = Login {
-            hostname: "http://example.com/foo?query=wtf#bar".into(),
+            origin: "http://example.com/foo?query=wtf#bar".into(),
             form_submit_url: Some("http://example.com/foo?query=wtf#bar".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1327,7 +1577,7 @@ mod tests {
         };
 
         let login_with_host_unicode = Login {
-            hostname: "http://😍.com".into(),
+            origin: "http://😍.com".into(),
             form_submit_url: Some("http://😍.com".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1335,7 +1585,7 @@ mod tests {
         };
 
         let login_with_period_fsu = Login {
-            hostname: "https://example.com".into(),
+            origin: "https://example.com".into(),
             form_submit_url: Some(".".into()),
             username: "test".into(),
             password: "test".into(),
@@ -1343,7 +1593,7 @@ mod tests {
         };
 
         let login_with_form_submit_and_http_realm = Login {
-            hostname: "https://www.example.com".into(),
+            origin: "https://www.example.com".into(),
             form_submit_url: Some("https://www.example.com".into()),
             // If both http_realm and form_submit_url are specified, we drop
             // the former when fixing up. So for this test we must have an
@@ -1380,7 +1630,7 @@ mod tests {
         for tc in &test_cases {
             let login = tc.login.clone().fixup().expect("should work");
             if let Some(expected) = tc.fixedup_host {
-                assert_eq!(login.hostname, expected);
+                assert_eq!(login.origin, expected);
             }
             assert_eq!(login.form_submit_url, tc.fixedup_form_submit_url);
         }
@@ -0,0 +1,380 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Matching saved logins against a page, for autofill.
+//!
+//! This follows the rules used by the desktop LoginManager:
+//!
+//! - A login for `http://` can be used on the `https://` version of the same
+//!   origin ("scheme upgrades"), but never the other way around.
+//! - For forms, logins saved on other subdomains of the same site are
+//!   included, but ranked below logins for the exact host. Sites are
+//!   registrable domains from the public suffix list, so `a.github.io` and
+//!   `b.github.io` are different sites. The ports must always match.
+//! - For forms, a login with an empty `formSubmitURL` matches any form action.
+//!   Otherwise the action origins must match (again allowing scheme
+//!   upgrades).
+//! - For HTTP auth, the realm must match exactly, and subdomains are not
+//!   included.
+//!
+//! Results are ranked best-first by host, then by form action, then by
+//! scheme, and finally by the most recently changed password. Logins which
+//! would autofill the same thing (the same username and password for forms,
+//! or the same username for HTTP auth) are deduped, keeping the best ranked.
+
+use crate::login::Login;
+use crate::public_suffix::registrable_domain;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use url::{Host, Url};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum HostMatch {
+    Exact,
+    Subdomain,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum SchemeMatch {
+    Exact,
+    Upgrade,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum ActionMatch {
+    Exact,
+    Wildcard,
+}
+
+#[derive(Debug, PartialEq)]
+struct Origin {
+    scheme: String,
+    host: Host<String>,
+    // Only explicit ports, so `http://a.com` and `https://a.com` are
+    // considered to be on the same port, as on desktop.
+    port: Option<u16>,
+}
+
+impl Origin {
+    fn parse(s: &str) -> Option<Self> {
+        let url = Url::parse(s).ok()?;
+        Some(Self {
+            scheme: url.scheme().to_owned(),
+            host: url.host()?.to_owned(),
+            port: url.port(),
+        })
+    }
+
+    fn match_scheme(&self, search: &Origin) -> Option<SchemeMatch> {
+        if self.scheme == search.scheme {
+            Some(SchemeMatch::Exact)
+        } else if self.scheme == "http" && search.scheme == "https" {
+            Some(SchemeMatch::Upgrade)
+        } else {
+            None
+        }
+    }
+
+    /// Checks if a login saved for `self` can be used on `search`.
+    fn matches(&self, search: &Origin, allow_subdomains: bool) -> Option<(HostMatch, SchemeMatch)> {
+        let scheme_match = self.match_scheme(search)?;
+        if self.port != search.port {
+            return None;
+        }
+        if self.host == search.host {
+            return Some((HostMatch::Exact, scheme_match));
+        }
+        match (&self.host, &search.host) {
+            (Host::Domain(saved), Host::Domain(wanted)) if allow_subdomains => {
+                // Hosts which are public suffixes themselves, like
+                // `github.io`, don't match any other host.
+                let site = registrable_domain(saved)?;
+                if registrable_domain(wanted) == Some(site) {
+                    Some((HostMatch::Subdomain, scheme_match))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn match_action(saved: &str, search: &str, search_origin: Option<&Origin>) -> Option<ActionMatch> {
+    if saved.is_empty() || search.is_empty() {
+        return Some(ActionMatch::Wildcard);
+    }
+    if saved == search {
+        return Some(ActionMatch::Exact);
+    }
+    let saved = Origin::parse(saved)?;
+    saved
+        .matches(search_origin?, false)
+        .map(|_| ActionMatch::Exact)
+}
+
+/// Sorts `matches` best-first by `key`, and drops any which have the same
+/// `dedupe_key` as a better match.
+fn rank_and_dedupe<K: Ord, D: Eq + std::hash::Hash>(
+    mut matches: Vec<(K, Login)>,
+    dedupe_key: impl Fn(&Login) -> D,
+) -> Vec<Login> {
+    matches.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut seen = HashSet::new();
+    matches
+        .into_iter()
+        .map(|(_, login)| login)
+        .filter(|login| seen.insert(dedupe_key(login)))
+        .collect()
+}
+
+/// Returns the form logins in `logins` which can be filled into a form on
+/// `origin` which submits to `form_action_origin`, ranked best-first. An
+/// empty `form_action_origin` means the action isn't known, and matches any
+/// login.
+pub(crate) fn find_for_form(
+    logins: Vec<Login>,
+    origin: &str,
+    form_action_origin: &str,
+) -> Vec<Login> {
+    let search = match Origin::parse(origin) {
+        Some(search) => search,
+        None => {
+            // don't log the input string as it's PII.
+            log::warn!("find_logins_for_form was passed an invalid origin");
+            return vec![];
+        }
+    };
+    let search_action = Origin::parse(form_action_origin);
+    let matches = logins
+        .into_iter()
+        .filter_map(|login| {
+            let form_submit_url = login.form_submit_url.as_ref()?;
+            let (host_match, scheme_match) =
+                Origin::parse(&login.origin)?.matches(&search, true)?;
+            let action_match =
+                match_action(form_submit_url, form_action_origin, search_action.as_ref())?;
+            let key = (
+                host_match,
+                action_match,
+                scheme_match,
+                Reverse(login.time_password_changed),
+            );
+            Some((key, login))
+        })
+        .collect();
+    rank_and_dedupe(matches, |login| {
+        (login.username.clone(), login.password.clone())
+    })
+}
+
+/// Returns the HTTP auth logins in `logins` which can be used for `realm` on
+/// `origin`, ranked best-first.
+pub(crate) fn find_for_http_auth(logins: Vec<Login>, origin: &str, realm: &str) -> Vec<Login> {
+    let search = match Origin::parse(origin) {
+        Some(search) => search,
+        None => {
+            log::warn!("find_logins_for_http_auth was passed an invalid origin");
+            return vec![];
+        }
+    };
+    let matches = logins
+        .into_iter()
+        .filter_map(|login| {
+            if login.http_realm.as_ref()? != realm {
+                return None;
+            }
+            let (_, scheme_match) = Origin::parse(&login.origin)?.matches(&search, false)?;
+            Some(((scheme_match, Reverse(login.time_password_changed)), login))
+        })
+        .collect();
+    rank_and_dedupe(matches, |login| login.username.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form_login(guid: &str, origin: &str, action: &str, username: &str, changed: i64) -> Login {
+        Login {
+            guid: guid.into(),
+            origin: origin.into(),
+            form_submit_url: Some(action.into()),
+            username: username.into(),
+            password: "password".into(),
+            time_password_changed: changed,
+            ..Login::default()
+        }
+    }
+
+    fn auth_login(guid: &str, origin: &str, realm: &str, username: &str, changed: i64) -> Login {
+        Login {
+            guid: guid.into(),
+            origin: origin.into(),
+            http_realm: Some(realm.into()),
+            username: username.into(),
+            password: "password".into(),
+            time_password_changed: changed,
+            ..Login::default()
+        }
+    }
+
+    fn guids(logins: Vec<Login>) -> Vec<String> {
+        logins.into_iter().map(|l| l.guid.into_string()).collect()
+    }
+
+    #[test]
+    fn test_origin_matching() {
+        let search = Origin::parse("https://www.example.com").unwrap();
+        let check = |saved: &str, allow_subdomains| {
+            Origin::parse(saved)
+                .unwrap()
+                .matches(&search, allow_subdomains)
+        };
+        use HostMatch::*;
+        use SchemeMatch::*;
+        assert_eq!(
+            check("https://www.example.com", false),
+            Some((Exact, Exact))
+        );
+        assert_eq!(
+            check("https://www.example.com:443", false),
+            Some((Exact, Exact))
+        );
+        assert_eq!(
+            check("http://www.example.com", false),
+            Some((Exact, Upgrade))
+        );
+        assert_eq!(check("http://www.example.com:8080", false), None);
+        assert_eq!(check("https://login.example.com", false), None);
+        assert_eq!(
+            check("https://login.example.com", true),
+            Some((Subdomain, Exact))
+        );
+        assert_eq!(
+            check("http://example.com", true),
+            Some((Subdomain, Upgrade))
+        );
+        assert_eq!(check("https://www.example.org", true), None);
+        assert_eq!(check("ftp://www.example.com", true), None);
+
+        // Subdomains must be on the same port too.
+        assert_eq!(check("https://login.example.com:8443", true), None);
+        assert_eq!(check("http://example.com:8080", true), None);
+        let search = Origin::parse("https://www.example.com:8443").unwrap();
+        assert_eq!(
+            Origin::parse("https://login.example.com:8443")
+                .unwrap()
+                .matches(&search, true),
+            Some((Subdomain, Exact))
+        );
+        assert_eq!(
+            Origin::parse("https://login.example.com")
+                .unwrap()
+                .matches(&search, true),
+            None
+        );
+
+        // Subdomains of private suffixes, like `github.io`, are different
+        // sites.
+        let search = Origin::parse("https://victim.github.io").unwrap();
+        let check = |saved: &str| Origin::parse(saved).unwrap().matches(&search, true);
+        assert_eq!(check("https://evil.github.io"), None);
+        assert_eq!(check("https://github.io"), None);
+        assert_eq!(
+            check("https://www.victim.github.io"),
+            Some((Subdomain, Exact))
+        );
+        let search = Origin::parse("https://app.herokuapp.com").unwrap();
+        assert_eq!(
+            Origin::parse("https://other.herokuapp.com")
+                .unwrap()
+                .matches(&search, true),
+            None
+        );
+
+        // Never downgrade.
+        let search = Origin::parse("http://www.example.com").unwrap();
+        assert_eq!(
+            Origin::parse("https://www.example.com")
+                .unwrap()
+                .matches(&search, true),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_for_form() {
+        let logins = vec![
+            form_login("upgraded", "http://www.example.com", "", "u1", 1),
+            form_login("wildcard", "https://www.example.com", "", "u2", 1),
+            form_login(
+                "exact",
+                "https://www.example.com",
+                "https://www.example.com",
+                "u3",
+                1,
+            ),
+            form_login("subdomain", "https://m.example.com", "", "u4", 1),
+            form_login(
+                "other_action",
+                "https://www.example.com",
+                "https://evil.com",
+                "u5",
+                1,
+            ),
+            form_login("newer", "https://www.example.com", "", "u6", 10),
+            form_login("other_site", "https://example.org", "", "u7", 1),
+            auth_login("auth", "https://www.example.com", "realm", "u8", 1),
+        ];
+        assert_eq!(
+            guids(find_for_form(
+                logins.clone(),
+                "https://www.example.com",
+                "https://www.example.com"
+            )),
+            vec!["exact", "newer", "wildcard", "upgraded", "subdomain"]
+        );
+        // An unknown action matches every form login.
+        assert_eq!(
+            guids(find_for_form(logins, "https://www.example.com", "")).len(),
+            6
+        );
+        assert!(find_for_form(vec![], "not a url", "").is_empty());
+    }
+
+    #[test]
+    fn test_find_for_form_dedupes() {
+        // The same credentials saved for both http and https only show once,
+        // and the https one wins.
+        let logins = vec![
+            form_login("http", "http://www.example.com", "", "user", 10),
+            form_login("https", "https://www.example.com", "", "user", 1),
+        ];
+        assert_eq!(
+            guids(find_for_form(logins, "https://www.example.com", "")),
+            vec!["https"]
+        );
+    }
+
+    #[test]
+    fn test_find_for_http_auth() {
+        let logins = vec![
+            auth_login("upgraded", "http://www.example.com", "realm", "u1", 10),
+            auth_login("exact", "https://www.example.com", "realm", "u2", 1),
+            auth_login("dupe", "https://www.example.com", "realm", "u2", 0),
+            auth_login("other_realm", "https://www.example.com", "other", "u3", 1),
+            auth_login("subdomain", "https://m.example.com", "realm", "u4", 1),
+            form_login("form", "https://www.example.com", "", "u5", 1),
+        ];
+        assert_eq!(
+            guids(find_for_http_auth(
+                logins,
+                "https://www.example.com",
+                "realm"
+            )),
+            vec!["exact", "upgraded"]
+        );
+    }
+}
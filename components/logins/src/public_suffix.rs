@@ -0,0 +1,147 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Registrable domains ("eTLD+1"), from the public suffix list.
+//!
+//! `public_suffix_list.dat` is a copy of
+//! https://publicsuffix.org/list/public_suffix_list.dat, including the
+//! private domains section, so that sites like `github.io` and
+//! `herokuapp.com` count as suffixes, and each of their subdomains as a
+//! separate site. To update it, replace the file with a new copy.
+
+use lazy_static::lazy_static;
+use std::collections::HashSet;
+
+#[derive(Default)]
+struct Rules {
+    exact: HashSet<String>,
+    // `*.ck` is stored as `ck`.
+    wildcards: HashSet<String>,
+    // `!www.ck` is stored as `www.ck`.
+    exceptions: HashSet<String>,
+}
+
+impl Rules {
+    fn parse(list: &str) -> Self {
+        let mut rules = Rules::default();
+        for line in list.lines() {
+            let rule = match line.split_whitespace().next() {
+                Some(rule) if !rule.starts_with("//") => rule,
+                _ => continue,
+            };
+            let (set, name) = if rule.starts_with('!') {
+                (&mut rules.exceptions, &rule[1..])
+            } else if rule.starts_with("*.") {
+                (&mut rules.wildcards, &rule[2..])
+            } else {
+                (&mut rules.exact, rule)
+            };
+            // The list has internationalized names in Unicode, but the hosts
+            // we look up are punycoded.
+            if let Ok(name) = idna::domain_to_ascii(name) {
+                set.insert(name);
+            }
+        }
+        rules
+    }
+}
+
+lazy_static! {
+    static ref RULES: Rules = Rules::parse(include_str!("public_suffix_list.dat"));
+}
+
+/// Returns the registrable domain of `host`: its public suffix plus one more
+/// label, like `example.co.uk` for `www.example.co.uk`. Returns `None` if
+/// `host` is a public suffix itself, like `co.uk` or `github.io`. Hosts
+/// under suffixes which aren't on the list are treated as if their last label
+/// is the suffix, so `localhost` doesn't have a registrable domain either.
+///
+/// `host` must be a lowercased, punycoded domain, like `Url::host_str`
+/// returns, and not an IP address.
+pub(crate) fn registrable_domain(host: &str) -> Option<&str> {
+    let host = host.trim_end_matches('.');
+    // Where each label starts, so that `&host[starts[i]..]` is the host
+    // without its first `i` labels.
+    let starts: Vec<usize> = std::iter::once(0)
+        .chain(host.match_indices('.').map(|(i, _)| i + 1))
+        .collect();
+    match public_suffix_label(host, &starts) {
+        0 => None,
+        label => Some(&host[starts[label - 1]..]),
+    }
+}
+
+/// Returns the index of the first label of `host`'s public suffix. The
+/// longest matching rule wins, and exceptions win over the wildcards they're
+/// exceptions to.
+fn public_suffix_label(host: &str, starts: &[usize]) -> usize {
+    for (label, &start) in starts.iter().enumerate() {
+        let name = &host[start..];
+        if RULES.exceptions.contains(name) {
+            return label + 1;
+        }
+        if RULES.exact.contains(name) {
+            return label;
+        }
+        if let Some(&parent) = starts.get(label + 1) {
+            if RULES.wildcards.contains(&host[parent..]) {
+                return label;
+            }
+        }
+    }
+    starts.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registrable_domain() {
+        // Some of the test cases from
+        // https://raw.githubusercontent.com/publicsuffix/list/master/tests/test_psl.txt
+        let cases = [
+            ("com", None),
+            ("example.com", Some("example.com")),
+            ("b.example.com", Some("example.com")),
+            ("a.b.example.com", Some("example.com")),
+            ("uk.com", None),
+            ("example.uk.com", Some("example.uk.com")),
+            ("b.example.uk.com", Some("example.uk.com")),
+            ("c.com.ac", Some("c.com.ac")),
+            // Wildcards and exceptions.
+            ("test.ck", None),
+            ("b.test.ck", Some("b.test.ck")),
+            ("a.b.test.ck", Some("b.test.ck")),
+            ("www.ck", Some("www.ck")),
+            ("www.www.ck", Some("www.ck")),
+            ("city.kawasaki.jp", Some("city.kawasaki.jp")),
+            ("www.city.kawasaki.jp", Some("city.kawasaki.jp")),
+            ("test.kawasaki.jp", None),
+            ("b.test.kawasaki.jp", Some("b.test.kawasaki.jp")),
+            // Unlisted TLDs.
+            ("example", None),
+            ("b.example.example", Some("example.example")),
+            // Private domains.
+            ("github.io", None),
+            ("victim.github.io", Some("victim.github.io")),
+            ("www.victim.github.io", Some("victim.github.io")),
+            // Internationalized names.
+            (
+                "xn--85x722f.xn--55qx5d.cn",
+                Some("xn--85x722f.xn--55qx5d.cn"),
+            ),
+            (
+                "www.xn--85x722f.xn--55qx5d.cn",
+                Some("xn--85x722f.xn--55qx5d.cn"),
+            ),
+            ("xn--55qx5d.cn", None),
+            // Trailing dots.
+            ("www.example.com.", Some("example.com")),
+        ];
+        for (host, expected) in &cases {
+            assert_eq!(registrable_domain(host), *expected, "{}", host);
+        }
+    }
+}
@@ -2,15 +2,18 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-//! Logins Schema v4
+//! Logins Schema v7
 //! ================
 //!
 //! The schema we use is a evolution of the firefox-ios logins database format.
-//! There are three tables:
+//! There are five tables:
 //!
 //! - `loginsL`: The local table.
 //! - `loginsM`: The mirror table.
 //! - `loginsSyncMeta`: The table used to to store various sync metadata.
+//! - `loginsBreaches`: The results of checking passwords against a breach
+//!   corpus.
+//! - `loginsHistory`: Previous passwords of each login.
 //!
 //! ## `loginsL`
 //!
@@ -18,7 +21,8 @@
 //!
 //! `loginsL` is essentially unchanged from firefox-ios, however note the
 //! semantic change v4 makes to timestamp fields (which is explained in more
-//! detail in the [COMMON_COLS] documentation).
+//! detail in the [COMMON_COLS] documentation), and that v7 renamed the
+//! `hostname` column to `origin`.
 //!
 //! It is important to note that `loginsL` is not guaranteed to be present for
 //! all records. Synced records may only exist in `loginsM` (although this is
@@ -51,7 +55,8 @@
 //! This stores server-side login information, also known as the "mirror".
 //!
 //! Like `loginsL`, `loginM` has not changed from firefox-ios, beyond the
-//! change to store timestamps as milliseconds explained in [COMMON_COLS].
+//! change to store timestamps as milliseconds explained in [COMMON_COLS], and
+//! the rename of `hostname` to `origin` in v7.
 //!
 //! Also like `loginsL`, `loginsM` is not guaranteed to have rows for all
 //! records. It should not have rows for records which were not synced!
@@ -87,18 +92,68 @@
 //!    [GLOBAL_STATE_META_KEY]. This is a `sync15::GlobalState` stored as
 //!    JSON.
 //!
+//! ## `loginsBreaches`
+//!
+//! This stores the result of the most recent breach check for each login,
+//! keyed by `guid`, so that the results can be shown without re-querying. It
+//! was added in version 5, and is never synced.
+//!
+//! ### `loginsBreaches` Columns
+//!
+//! - `guid`: The guid of the login which was checked.
+//!
+//! - `breach_count`: The number of times the password appears in the breach
+//!   corpus, or 0 if it doesn't.
+//!
+//! - `time_checked`: A millisecond local timestamp of when the check ran.
+//!
+//! - `time_password_changed`: The login's `timePasswordChanged` at the time
+//!   of the check. If it no longer matches, the password has since changed,
+//!   and the result is stale.
+//!
+//! ## `loginsHistory`
+//!
+//! This stores passwords which were overwritten, either by a local edit or by
+//! applying incoming sync records, so that they can be restored. It was added
+//! in version 6, and is never synced. Like every other table, it's encrypted
+//! at rest by SQLCipher. Only the most recent `history::MAX_REVISIONS` rows
+//! are kept for each login, and the rows for a login are removed when it is
+//! deleted.
+//!
+//! ### `loginsHistory` Columns
+//!
+//! - `id`: An autoincrementing ID, which also orders the revisions.
+//!
+//! - `guid`: The guid of the login the password belonged to.
+//!
+//! - `password`: The password which was overwritten.
+//!
+//! - `time_password_changed`: When that password was set, as the login's
+//!   `timePasswordChanged` in milliseconds.
+//!
+//! - `time_replaced`: A millisecond local timestamp of when it was
+//!   overwritten.
+//!
+//! - `source`: A `RevisionSource` enum value - `1` for a local edit, or `2`
+//!   for a sync merge.
+//!
 
 use crate::error::*;
 use lazy_static::lazy_static;
 use rusqlite::Connection;
 use sql_support::ConnExt;
 
-/// Note that firefox-ios is currently on version 3. Version 4 is this version,
-/// which adds a metadata table and changes timestamps to be in milliseconds
-pub const VERSION: i64 = 4;
+/// Note that firefox-ios is currently on version 3. Version 4 adds a metadata
+/// table and changes timestamps to be in milliseconds, version 5 adds the
+/// breaches table, version 6 adds the history table, and version 7 renames
+/// `hostname` to `origin`.
+pub const VERSION: i64 = 7;
 
 /// Every column shared by both tables except for `id`
 ///
+/// Note: `origin` was called `hostname` before v7, and still is in sync
+/// records.
+///
 /// Note: `timeCreated`, `timeLastUsed`, and `timePasswordChanged` are in
 /// milliseconds. This is in line with how the server and Desktop handle it, but
 /// counter to how firefox-ios handles it (hence needing to fix them up
@@ -118,7 +173,7 @@ pub const COMMON_COLS: &str = "
     guid,
     username,
     password,
-    hostname,
+    origin,
     httpRealm,
     formSubmitURL,
     usernameField,
@@ -131,7 +186,7 @@ pub const COMMON_COLS: &str = "
 
 const COMMON_SQL: &str = "
     id                  INTEGER PRIMARY KEY AUTOINCREMENT,
-    hostname            TEXT NOT NULL,
+    origin              TEXT NOT NULL,
     -- Exactly one of httpRealm or formSubmitURL should be set
     httpRealm           TEXT,
     formSubmitURL       TEXT,
@@ -179,14 +234,39 @@ const CREATE_META_TABLE_SQL: &str = "
     )
 ";
 
-const CREATE_OVERRIDE_HOSTNAME_INDEX_SQL: &str = "
-    CREATE INDEX IF NOT EXISTS idx_loginsM_is_overridden_hostname
-    ON loginsM (is_overridden, hostname)
+const CREATE_BREACHES_TABLE_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS loginsBreaches (
+        guid                  TEXT PRIMARY KEY,
+        breach_count          INTEGER NOT NULL,
+        time_checked          INTEGER NOT NULL,
+        time_password_changed INTEGER NOT NULL
+    )
+";
+
+const CREATE_HISTORY_TABLE_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS loginsHistory (
+        id                    INTEGER PRIMARY KEY AUTOINCREMENT,
+        guid                  TEXT NOT NULL,
+        password              TEXT NOT NULL,
+        time_password_changed INTEGER NOT NULL,
+        time_replaced         INTEGER NOT NULL,
+        source                TINYINT NOT NULL
+    )
 ";
 
-const CREATE_DELETED_HOSTNAME_INDEX_SQL: &str = "
-    CREATE INDEX IF NOT EXISTS idx_loginsL_is_deleted_hostname
-    ON loginsL (is_deleted, hostname)
+const CREATE_HISTORY_GUID_INDEX_SQL: &str = "
+    CREATE INDEX IF NOT EXISTS idx_loginsHistory_guid
+    ON loginsHistory (guid)
+";
+
+const CREATE_OVERRIDE_ORIGIN_INDEX_SQL: &str = "
+    CREATE INDEX IF NOT EXISTS idx_loginsM_is_overridden_origin
+    ON loginsM (is_overridden, origin)
+";
+
+const CREATE_DELETED_ORIGIN_INDEX_SQL: &str = "
+    CREATE INDEX IF NOT EXISTS idx_loginsL_is_deleted_origin
+    ON loginsL (is_deleted, origin)
 ";
 
 // As noted above, we use these when updating from schema v3 (firefox-ios's
@@ -261,13 +341,6 @@ fn upgrade(db: &Connection, from: i64) -> Result<()> {
         from, 0,
         "Upgrading from user_version = 0 should already be handled (in `init`)"
     );
-    if from < 3 {
-        // These indices were added in v3 (apparently)
-        db.execute_all(&[
-            CREATE_OVERRIDE_HOSTNAME_INDEX_SQL,
-            CREATE_DELETED_HOSTNAME_INDEX_SQL,
-        ])?;
-    }
     if from < 4 {
         // This is the update from the firefox-ios schema to our schema.
         // The `loginsSyncMeta` table was added in v4, and we moved
@@ -277,9 +350,29 @@ fn upgrade(db: &Connection, from: i64) -> Result<()> {
             CREATE_META_TABLE_SQL,
             UPDATE_LOCAL_TIMESTAMPS_TO_MILLIS_SQL,
             UPDATE_MIRROR_TIMESTAMPS_TO_MILLIS_SQL,
-            &*SET_VERSION_SQL,
         ])?;
     }
+    if from < 5 {
+        db.execute_all(&[CREATE_BREACHES_TABLE_SQL])?;
+    }
+    if from < 6 {
+        db.execute_all(&[CREATE_HISTORY_TABLE_SQL, CREATE_HISTORY_GUID_INDEX_SQL])?;
+    }
+    if from < 7 {
+        // The `hostname` columns were renamed to `origin`. The indices on
+        // them were added in v3 (apparently), under names which mentioned
+        // `hostname`, so we replace them here too - which also creates them
+        // for databases older than v3.
+        db.execute_all(&[
+            "ALTER TABLE loginsL RENAME COLUMN hostname TO origin",
+            "ALTER TABLE loginsM RENAME COLUMN hostname TO origin",
+            "DROP INDEX IF EXISTS idx_loginsM_is_overridden_hostname",
+            "DROP INDEX IF EXISTS idx_loginsL_is_deleted_hostname",
+            CREATE_OVERRIDE_ORIGIN_INDEX_SQL,
+            CREATE_DELETED_ORIGIN_INDEX_SQL,
+        ])?;
+    }
+    db.execute_all(&[&*SET_VERSION_SQL])?;
     Ok(())
 }
 
@@ -288,9 +381,12 @@ pub(crate) fn create(db: &Connection) -> Result<()> {
     db.execute_all(&[
         &*CREATE_LOCAL_TABLE_SQL,
         &*CREATE_MIRROR_TABLE_SQL,
-        CREATE_OVERRIDE_HOSTNAME_INDEX_SQL,
-        CREATE_DELETED_HOSTNAME_INDEX_SQL,
+        CREATE_OVERRIDE_ORIGIN_INDEX_SQL,
+        CREATE_DELETED_ORIGIN_INDEX_SQL,
         CREATE_META_TABLE_SQL,
+        CREATE_BREACHES_TABLE_SQL,
+        CREATE_HISTORY_TABLE_SQL,
+        CREATE_HISTORY_GUID_INDEX_SQL,
         &*SET_VERSION_SQL,
     ])?;
     Ok(())
@@ -302,7 +398,184 @@ pub(crate) fn drop(db: &Connection) -> Result<()> {
         "DROP TABLE IF EXISTS loginsM",
         "DROP TABLE IF EXISTS loginsL",
         "DROP TABLE IF EXISTS loginsSyncMeta",
+        "DROP TABLE IF EXISTS loginsBreaches",
+        "DROP TABLE IF EXISTS loginsHistory",
         "PRAGMA user_version = 0",
     ])?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::LoginDb;
+    use rusqlite::NO_PARAMS;
+
+    // The tables as of version 4, which is the oldest version this library
+    // has created.
+    const V4_SCHEMA_SQL: &str = "
+        CREATE TABLE loginsL (
+            id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+            hostname            TEXT NOT NULL,
+            httpRealm           TEXT,
+            formSubmitURL       TEXT,
+            usernameField       TEXT,
+            passwordField       TEXT,
+            timesUsed           INTEGER NOT NULL DEFAULT 0,
+            timeCreated         INTEGER NOT NULL,
+            timeLastUsed        INTEGER,
+            timePasswordChanged INTEGER NOT NULL,
+            username            TEXT,
+            password            TEXT NOT NULL,
+            guid                TEXT NOT NULL UNIQUE,
+            local_modified      INTEGER,
+            is_deleted          TINYINT NOT NULL DEFAULT 0,
+            sync_status         TINYINT NOT NULL DEFAULT 0
+        );
+        CREATE TABLE loginsM (
+            id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+            hostname            TEXT NOT NULL,
+            httpRealm           TEXT,
+            formSubmitURL       TEXT,
+            usernameField       TEXT,
+            passwordField       TEXT,
+            timesUsed           INTEGER NOT NULL DEFAULT 0,
+            timeCreated         INTEGER NOT NULL,
+            timeLastUsed        INTEGER,
+            timePasswordChanged INTEGER NOT NULL,
+            username            TEXT,
+            password            TEXT NOT NULL,
+            guid                TEXT NOT NULL UNIQUE,
+            server_modified     INTEGER NOT NULL,
+            is_overridden       TINYINT NOT NULL DEFAULT 0
+        );
+        CREATE INDEX idx_loginsM_is_overridden_hostname
+        ON loginsM (is_overridden, hostname);
+        CREATE INDEX idx_loginsL_is_deleted_hostname
+        ON loginsL (is_deleted, hostname);
+        CREATE TABLE loginsSyncMeta (
+            key TEXT PRIMARY KEY,
+            value NOT NULL
+        );
+
+        INSERT INTO loginsL(guid, hostname, formSubmitURL, username, password,
+                            timeCreated, timePasswordChanged, local_modified,
+                            sync_status)
+        VALUES('local0000001', 'https://www.example.com', '', 'local',
+               'password1', 1000, 1000, 1000, 2);
+        INSERT INTO loginsM(guid, hostname, httpRealm, username, password,
+                            timeCreated, timePasswordChanged, server_modified)
+        VALUES('mirror000001', 'https://www.example.org', 'realm', 'mirror',
+               'password2', 1000, 1000, 1000);
+
+        PRAGMA user_version = 4;
+    ";
+
+    const V5_SCHEMA_SQL: &str = "
+        CREATE TABLE loginsBreaches (
+            guid                  TEXT PRIMARY KEY,
+            breach_count          INTEGER NOT NULL,
+            time_checked          INTEGER NOT NULL,
+            time_password_changed INTEGER NOT NULL
+        );
+        INSERT INTO loginsBreaches(guid, breach_count, time_checked,
+                                   time_password_changed)
+        VALUES('local0000001', 3, 2000, 1000);
+
+        PRAGMA user_version = 5;
+    ";
+
+    const V6_SCHEMA_SQL: &str = "
+        CREATE TABLE loginsHistory (
+            id                    INTEGER PRIMARY KEY AUTOINCREMENT,
+            guid                  TEXT NOT NULL,
+            password              TEXT NOT NULL,
+            time_password_changed INTEGER NOT NULL,
+            time_replaced         INTEGER NOT NULL,
+            source                TINYINT NOT NULL
+        );
+        CREATE INDEX idx_loginsHistory_guid
+        ON loginsHistory (guid);
+
+        PRAGMA user_version = 6;
+    ";
+
+    fn check_upgrade(fixture: &[&str]) {
+        let dir = tempdir::TempDir::new("logins_upgrade").unwrap();
+        let path = dir.path().join("logins.sqlite");
+        let conn = Connection::open(&path).unwrap();
+        for sql in fixture {
+            conn.execute_batch(sql).unwrap();
+        }
+        drop(conn);
+
+        let db = LoginDb::open(&path, None).unwrap();
+        assert_eq!(db.query_one::<i64>("PRAGMA user_version").unwrap(), VERSION);
+        let mut origins = db
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .map(|login| (login.guid.into_string(), login.origin))
+            .collect::<Vec<_>>();
+        origins.sort();
+        assert_eq!(
+            origins,
+            vec![
+                (
+                    "local0000001".to_owned(),
+                    "https://www.example.com".to_owned()
+                ),
+                (
+                    "mirror000001".to_owned(),
+                    "https://www.example.org".to_owned()
+                ),
+            ]
+        );
+        // The indices were renamed along with the columns.
+        let mut indices = db
+            .query_rows_and_then_named(
+                "SELECT name FROM sqlite_master
+                 WHERE type = 'index' AND tbl_name IN ('loginsL', 'loginsM')
+                   AND sql IS NOT NULL",
+                &[],
+                |row| row.get::<_, String>(0),
+            )
+            .unwrap();
+        indices.sort();
+        assert_eq!(
+            indices,
+            vec![
+                "idx_loginsL_is_deleted_origin".to_owned(),
+                "idx_loginsM_is_overridden_origin".to_owned(),
+            ]
+        );
+        // Tables added since then are usable.
+        db.execute(
+            "INSERT INTO loginsHistory(guid, password, time_password_changed,
+                                       time_replaced, source)
+             VALUES('local0000001', 'password0', 500, 1000, 1)",
+            NO_PARAMS,
+        )
+        .unwrap();
+        assert_eq!(
+            db.query_one::<i64>("SELECT COUNT(*) FROM loginsBreaches")
+                .unwrap(),
+            if fixture.len() > 1 { 1 } else { 0 }
+        );
+    }
+
+    #[test]
+    fn test_upgrade_from_v4() {
+        check_upgrade(&[V4_SCHEMA_SQL]);
+    }
+
+    #[test]
+    fn test_upgrade_from_v5() {
+        check_upgrade(&[V4_SCHEMA_SQL, V5_SCHEMA_SQL]);
+    }
+
+    #[test]
+    fn test_upgrade_from_v6() {
+        check_upgrade(&[V4_SCHEMA_SQL, V5_SCHEMA_SQL, V6_SCHEMA_SQL]);
+    }
+}
@@ -3,7 +3,8 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use crate::error::*;
-use crate::login::{LocalLogin, Login, MirrorLogin, SyncStatus};
+use crate::history::{self, RevisionSource};
+use crate::login::{ConflictResolution, LocalLogin, Login, MergeConflict, MirrorLogin, SyncStatus};
 use crate::util;
 use rusqlite::{named_params, Connection};
 use sql_support::SqlInterruptScope;
@@ -19,16 +20,35 @@ pub(crate) struct UpdatePlan {
     // the bool is the `is_overridden` flag, the i64 is ServerTimestamp in millis
     pub mirror_inserts: Vec<(Login, i64, bool)>,
     pub mirror_updates: Vec<(Login, i64)>,
+    // Passwords which the plan may overwrite, so we can keep a history. Each
+    // is (the guid being overwritten, the guid it becomes, the new password).
+    pub password_overwrites: Vec<(Guid, Guid, String)>,
+    // Fields which conflicted while planning, for telemetry.
+    pub conflicts: Vec<MergeConflict>,
 }
 
 impl UpdatePlan {
     pub fn plan_two_way_merge(&mut self, local: &Login, upstream: (Login, ServerTimestamp)) {
         let is_override = local.time_password_changed > upstream.0.time_password_changed;
-        self.mirror_inserts
-            .push((upstream.0, upstream.1.as_millis() as i64, is_override));
+        // Without a shared parent we can't tell which side changed what, so
+        // every field which differs is a conflict, resolved by which whole
+        // record is newer.
+        for field in local.delta(&upstream.0).changed_fields() {
+            self.conflicts.push(MergeConflict {
+                field,
+                resolution: ConflictResolution::Timestamp,
+            });
+        }
         if !is_override {
+            self.password_overwrites.push((
+                local.guid.clone(),
+                upstream.0.guid.clone(),
+                upstream.0.password.clone(),
+            ));
             self.delete_local.push(local.guid.clone());
         }
+        self.mirror_inserts
+            .push((upstream.0, upstream.1.as_millis() as i64, is_override));
     }
 
     pub fn plan_three_way_merge(
@@ -47,7 +67,8 @@ impl UpdatePlan {
         let local_delta = local.login.delta(&shared.login);
         let upstream_delta = upstream.delta(&shared.login);
 
-        let merged_delta = local_delta.merge(upstream_delta, remote_age < local_age);
+        let merged_delta =
+            local_delta.merge(upstream_delta, remote_age < local_age, &mut self.conflicts);
 
         // Update mirror to upstream
         self.mirror_updates
@@ -56,6 +77,11 @@ impl UpdatePlan {
 
         new.login.apply_delta(merged_delta);
         new.server_modified = upstream_time;
+        self.password_overwrites.push((
+            new.login.guid.clone(),
+            new.login.guid.clone(),
+            new.login.password.clone(),
+        ));
         self.local_updates.push(new);
     }
 
@@ -65,6 +91,11 @@ impl UpdatePlan {
     }
 
     pub fn plan_mirror_update(&mut self, login: Login, time: ServerTimestamp) {
+        self.password_overwrites.push((
+            login.guid.clone(),
+            login.guid.clone(),
+            login.password.clone(),
+        ));
         self.mirror_updates.push((login, time.as_millis() as i64));
     }
 
@@ -73,7 +104,20 @@ impl UpdatePlan {
             .push((login, time.as_millis() as i64, is_override));
     }
 
+    // This must run before anything else changes the tables.
+    fn perform_history(&self, conn: &Connection, scope: &SqlInterruptScope) -> Result<()> {
+        for (guid, new_guid, new_password) in &self.password_overwrites {
+            history::record_overwrite(conn, guid, new_guid, new_password, RevisionSource::Sync)?;
+            scope.err_if_interrupted()?;
+        }
+        Ok(())
+    }
+
     fn perform_deletes(&self, conn: &Connection, scope: &SqlInterruptScope) -> Result<()> {
+        // Incoming deletions take the password history with them.
+        for guid in &self.delete_mirror {
+            history::forget(conn, guid)?;
+        }
         sql_support::each_chunk(&self.delete_local, |chunk, _| -> Result<()> {
             conn.execute(
                 &format!(
@@ -108,7 +152,7 @@ impl UpdatePlan {
                 usernameField   = :username_field,
                 passwordField   = :password_field,
                 password        = :password,
-                hostname        = :hostname,
+                origin          = :origin,
                 username        = :username,
                 -- Avoid zeroes if the remote has been overwritten by an older client.
                 timesUsed           = coalesce(nullif(:times_used,            0), timesUsed),
@@ -127,7 +171,7 @@ impl UpdatePlan {
                 ":username_field": login.username_field,
                 ":password_field": login.password_field,
                 ":password": login.password,
-                ":hostname": login.hostname,
+                ":origin": login.origin,
                 ":username": login.username,
                 ":times_used": login.times_used,
                 ":time_last_used": login.time_last_used,
@@ -151,7 +195,7 @@ impl UpdatePlan {
                 usernameField,
                 passwordField,
                 password,
-                hostname,
+                origin,
                 username,
 
                 timesUsed,
@@ -169,7 +213,7 @@ impl UpdatePlan {
                 :username_field,
                 :password_field,
                 :password,
-                :hostname,
+                :origin,
                 :username,
 
                 :times_used,
@@ -191,7 +235,7 @@ impl UpdatePlan {
                 ":username_field": login.username_field,
                 ":password_field": login.password_field,
                 ":password": login.password,
-                ":hostname": login.hostname,
+                ":origin": login.origin,
                 ":username": login.username,
                 ":times_used": login.times_used,
                 ":time_last_used": login.time_last_used,
@@ -216,7 +260,7 @@ impl UpdatePlan {
                  timePasswordChanged = :time_password_changed,
                  timesUsed           = :times_used,
                  password            = :password,
-                 hostname            = :hostname,
+                 origin              = :origin,
                  username            = :username,
                  sync_status         = {changed}
              WHERE guid = :guid",
@@ -234,7 +278,7 @@ impl UpdatePlan {
                 ":username_field": l.login.username_field,
                 ":password_field": l.login.password_field,
                 ":password": l.login.password,
-                ":hostname": l.login.hostname,
+                ":origin": l.login.origin,
                 ":username": l.login.username,
                 ":time_last_used": l.login.time_last_used,
                 ":time_password_changed": l.login.time_password_changed,
@@ -247,6 +291,8 @@ impl UpdatePlan {
     }
 
     pub fn execute(&self, conn: &Connection, scope: &SqlInterruptScope) -> Result<()> {
+        log::debug!("UpdatePlan: recording overwritten passwords...");
+        self.perform_history(conn, scope)?;
         log::debug!("UpdatePlan: deleting records...");
         self.perform_deletes(conn, scope)?;
         log::debug!("UpdatePlan: Updating existing mirror records...");
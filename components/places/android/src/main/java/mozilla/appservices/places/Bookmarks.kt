@@ -225,6 +225,59 @@ interface ReadableBookmarksConnection : InterruptibleConnection {
      */
     fun getBookmarkUrlForKeyword(keyword: String): String?
 
+    /**
+     * Returns the search keyword for the provided URL, if one exists.
+     *
+     * @param url The bookmarked URL.
+     * @return The keyword for the URL, if set.
+     *
+     * @throws UrlParseFailed If `url` does not refer to a valid URL.
+     * @throws OperationInterrupted if this database implements [InterruptibleConnection] and
+     * has its `interrupt()` method called on another thread.
+     */
+    fun getBookmarkKeywordForUrl(url: String): String?
+
+    /**
+     * Returns all search keywords, and the URLs they belong to, ordered by keyword.
+     *
+     * @throws OperationInterrupted if this database implements [InterruptibleConnection] and
+     * has its `interrupt()` method called on another thread.
+     */
+    fun getAllBookmarkKeywords(): List<BookmarkKeyword>
+
+    /**
+     * Returns the URLs which have the provided tag.
+     *
+     * @param tag The tag.
+     * @return A list of URLs, ordered by frecency.
+     *
+     * @throws InvalidTag If `tag` is empty or too long.
+     * @throws OperationInterrupted if this database implements [InterruptibleConnection] and
+     * has its `interrupt()` method called on another thread.
+     */
+    fun getUrlsWithTag(tag: String): List<String>
+
+    /**
+     * Returns the tags for the provided URL.
+     *
+     * @param url The tagged URL.
+     * @return A list of tags, most recently modified first.
+     *
+     * @throws UrlParseFailed If `url` does not refer to a valid URL.
+     * @throws OperationInterrupted if this database implements [InterruptibleConnection] and
+     * has its `interrupt()` method called on another thread.
+     */
+    fun getTagsForUrl(url: String): List<String>
+
+    /**
+     * Returns all tags which are used by at least one URL, with the number of
+     * URLs which have each tag, ordered by that number, then by tag.
+     *
+     * @throws OperationInterrupted if this database implements [InterruptibleConnection] and
+     * has its `interrupt()` method called on another thread.
+     */
+    fun getAllTags(): List<TagInfo>
+
     /**
      * Returns the list of bookmarks that match the provided search string.
      *
@@ -360,8 +413,86 @@ interface WritableBookmarksConnection : ReadableBookmarksConnection {
      * folder node.
      */
     fun updateBookmark(guid: String, info: BookmarkUpdateInfo)
+
+    /**
+     * Set the search keyword for a bookmarked URL, replacing its existing
+     * keyword. Keywords are trimmed and lowercased, and a keyword belongs
+     * to at most one URL, so it is removed from any other URL first.
+     *
+     * @param url The bookmarked URL.
+     * @param keyword The new keyword.
+     *
+     * @throws InvalidKeyword If `keyword` is empty or contains whitespace.
+     * @throws UrlParseFailed If `url` does not refer to a valid URL.
+     * @throws PlacesException If `url` is not bookmarked.
+     */
+    fun setBookmarkKeyword(url: String, keyword: String)
+
+    /**
+     * Remove a search keyword.
+     *
+     * @param keyword The keyword to remove.
+     * @return Whether or not the keyword existed.
+     */
+    fun removeBookmarkKeyword(keyword: String): Boolean
+
+    /**
+     * Tag a URL. The URL must already be known, for example, because it was
+     * visited or bookmarked. Tags are trimmed.
+     *
+     * @param url The URL to tag.
+     * @param tag The tag to add.
+     *
+     * @throws InvalidTag If `tag` is empty or too long.
+     * @throws UrlParseFailed If `url` does not refer to a valid URL.
+     * @throws PlacesException If `url` is not known.
+     */
+    fun tagUrl(url: String, tag: String)
+
+    /**
+     * Remove a tag from a URL. Does nothing if the URL doesn't have the tag.
+     *
+     * @param url The tagged URL.
+     * @param tag The tag to remove.
+     *
+     * @throws InvalidTag If `tag` is empty or too long.
+     * @throws UrlParseFailed If `url` does not refer to a valid URL.
+     */
+    fun untagUrl(url: String, tag: String)
+
+    /**
+     * Remove all tags from a URL.
+     *
+     * @param url The tagged URL.
+     *
+     * @throws UrlParseFailed If `url` does not refer to a valid URL.
+     */
+    fun removeAllTagsFromUrl(url: String)
+
+    /**
+     * Remove a tag from all URLs.
+     *
+     * @param tag The tag to remove.
+     */
+    fun removeTag(tag: String)
 }
 
+/**
+ * A search keyword, and the bookmarked URL it belongs to.
+ */
+data class BookmarkKeyword(
+    val keyword: String,
+    val url: String
+)
+
+/**
+ * A tag, and the number of URLs which have it.
+ */
+data class TagInfo(
+    val tag: String,
+    val urlCount: Int
+)
+
 /**
  * Information describing the changes to make in order to update a bookmark.
  */
@@ -464,6 +595,98 @@ open class CannotUpdateRoot(msg: String) : PlacesException(msg)
  */
 open class InvalidParent(msg: String) : PlacesException(msg)
 
+/**
+ * Thrown when setting a keyword which is empty or contains whitespace.
+ */
+open class InvalidKeyword(msg: String) : PlacesException(msg)
+
+/**
+ * Thrown when tagging with a tag which is empty or too long.
+ */
+open class InvalidTag(msg: String) : PlacesException(msg)
+
+/**
+ * A change to the bookmarks tree, passed to a [BookmarkObserver].
+ */
+sealed class BookmarkEvent {
+    data class ItemAdded(
+        val guid: String,
+        val type: BookmarkType,
+        val parentGUID: String,
+        val position: Int
+    ) : BookmarkEvent()
+
+    /**
+     * An item was removed. Its descendants are removed along with it, but there are no events for
+     * them.
+     */
+    data class ItemRemoved(
+        val guid: String,
+        val type: BookmarkType,
+        val parentGUID: String,
+        val position: Int
+    ) : BookmarkEvent()
+
+    data class ItemMoved(
+        val guid: String,
+        val type: BookmarkType,
+        val oldParentGUID: String,
+        val oldPosition: Int,
+        val newParentGUID: String,
+        val newPosition: Int
+    ) : BookmarkEvent()
+
+    /**
+     * The title, URL or keyword of an item changed.
+     */
+    data class ItemChanged(
+        val guid: String,
+        val type: BookmarkType
+    ) : BookmarkEvent()
+
+    /**
+     * A sync changed [numChanges] items. There are no events for each item, so observers should
+     * re-fetch the parts of the tree they're interested in.
+     */
+    data class SyncApplied(val numChanges: Int) : BookmarkEvent()
+
+    companion object {
+        @Suppress("TooGenericExceptionThrown")
+        internal fun fromMessage(msg: MsgTypes.BookmarkEvent): BookmarkEvent {
+            val type = BookmarkType.values().find { it.value == msg.nodeType }
+            return when (msg.eventType) {
+                MsgTypes.BookmarkEventType.ITEM_ADDED ->
+                    ItemAdded(msg.guid, type!!, msg.parentGuid, msg.position)
+                MsgTypes.BookmarkEventType.ITEM_REMOVED ->
+                    ItemRemoved(msg.guid, type!!, msg.parentGuid, msg.position)
+                MsgTypes.BookmarkEventType.ITEM_MOVED -> ItemMoved(
+                    msg.guid,
+                    type!!,
+                    msg.oldParentGuid,
+                    msg.oldPosition,
+                    msg.parentGuid,
+                    msg.position
+                )
+                MsgTypes.BookmarkEventType.ITEM_CHANGED -> ItemChanged(msg.guid, type!!)
+                MsgTypes.BookmarkEventType.SYNC_APPLIED -> SyncApplied(msg.numChanges)
+                // Should never happen
+                else -> throw RuntimeException("Rust passed in an illegal event type ${msg.eventType}")
+            }
+        }
+    }
+}
+
+/**
+ * An observer for changes to bookmarks, registered with [PlacesApi.registerBookmarkObserver].
+ */
+interface BookmarkObserver {
+    /**
+     * Called after a change is committed, on the thread which made the change. This must not throw,
+     * and shouldn't call back into Places; post that work to another thread instead.
+     */
+    fun onBookmarkEvent(event: BookmarkEvent)
+}
+
 /**
  * Turn the protobuf rust passes us into a BookmarkTreeNode.
  *
@@ -5,6 +5,7 @@
 
 package mozilla.appservices.places
 
+import com.sun.jna.Callback
 import com.sun.jna.Library
 import com.sun.jna.Pointer
 import com.sun.jna.PointerType
@@ -30,6 +31,19 @@ internal interface LibPlacesFFI : Library {
         out_err: RustError.ByReference
     ): PlacesApiHandle
 
+    /** Returns an ID which can be passed to `places_api_unregister_bookmark_observer` */
+    fun places_api_register_bookmark_observer(
+        handle: PlacesApiHandle,
+        callback: RawBookmarkObserverCallback,
+        out_err: RustError.ByReference
+    ): Long
+
+    fun places_api_unregister_bookmark_observer(
+        handle: PlacesApiHandle,
+        id: Long,
+        out_err: RustError.ByReference
+    ): Byte
+
     /** Create a new places connection */
     fun places_connection_new(
         handle: PlacesApiHandle,
@@ -126,6 +140,12 @@ internal interface LibPlacesFFI : Library {
         out_err: RustError.ByReference
     )
 
+    fun places_delete_visits_for_host(
+        handle: PlacesConnectionHandle,
+        host: String,
+        out_err: RustError.ByReference
+    )
+
     fun places_delete_visit(
         handle: PlacesConnectionHandle,
         visit_url: String,
@@ -221,6 +241,74 @@ internal interface LibPlacesFFI : Library {
         error: RustError.ByReference
     ): Pointer?
 
+    fun bookmarks_get_keyword_for_url(
+        handle: PlacesConnectionHandle,
+        url: String,
+        error: RustError.ByReference
+    ): Pointer?
+
+    fun bookmarks_get_all_keywords(
+        handle: PlacesConnectionHandle,
+        error: RustError.ByReference
+    ): RustBuffer.ByValue
+
+    fun bookmarks_set_keyword(
+        handle: PlacesConnectionHandle,
+        url: String,
+        keyword: String,
+        error: RustError.ByReference
+    )
+
+    // Returns 1 if the keyword existed and was removed.
+    fun bookmarks_remove_keyword(
+        handle: PlacesConnectionHandle,
+        keyword: String,
+        error: RustError.ByReference
+    ): Byte
+
+    fun places_tag_url(
+        handle: PlacesConnectionHandle,
+        url: String,
+        tag: String,
+        error: RustError.ByReference
+    )
+
+    fun places_untag_url(
+        handle: PlacesConnectionHandle,
+        url: String,
+        tag: String,
+        error: RustError.ByReference
+    )
+
+    fun places_remove_all_tags_from_url(
+        handle: PlacesConnectionHandle,
+        url: String,
+        error: RustError.ByReference
+    )
+
+    fun places_remove_tag(
+        handle: PlacesConnectionHandle,
+        tag: String,
+        error: RustError.ByReference
+    )
+
+    fun places_get_urls_with_tag(
+        handle: PlacesConnectionHandle,
+        tag: String,
+        error: RustError.ByReference
+    ): RustBuffer.ByValue
+
+    fun places_get_tags_for_url(
+        handle: PlacesConnectionHandle,
+        url: String,
+        error: RustError.ByReference
+    ): RustBuffer.ByValue
+
+    fun places_get_all_tags(
+        handle: PlacesConnectionHandle,
+        error: RustError.ByReference
+    ): RustBuffer.ByValue
+
     fun bookmarks_get_tree(
         handle: PlacesConnectionHandle,
         optRootId: String?,
@@ -307,6 +395,11 @@ internal interface LibPlacesFFI : Library {
     )
 }
 
+internal interface RawBookmarkObserverCallback : Callback {
+    /** Called with a `BookmarkEvent` protobuf message, which must be freed. */
+    fun invoke(event: RustBuffer.ByValue)
+}
+
 internal typealias PlacesConnectionHandle = Long
 internal typealias PlacesApiHandle = Long
 
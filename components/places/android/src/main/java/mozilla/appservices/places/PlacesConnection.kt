@@ -7,6 +7,7 @@ package mozilla.appservices.places
 import com.sun.jna.Native
 import com.sun.jna.Pointer
 import com.sun.jna.StringArray
+import mozilla.appservices.support.native.RustBuffer
 import mozilla.appservices.support.native.toNioDirectBuffer
 import mozilla.appservices.sync15.SyncTelemetryPing
 import org.json.JSONArray
@@ -14,6 +15,7 @@ import org.json.JSONObject
 import org.json.JSONException
 import java.nio.ByteBuffer
 import java.nio.ByteOrder
+import java.util.concurrent.ConcurrentHashMap
 import java.util.concurrent.atomic.AtomicLong
 import java.util.concurrent.atomic.AtomicReference
 import java.lang.ref.WeakReference
@@ -41,6 +43,8 @@ import mozilla.components.service.glean.private.LabeledMetricType
 class PlacesApi(path: String) : PlacesManager, AutoCloseable {
     private var handle: AtomicLong = AtomicLong(0)
     private var writeConn: PlacesWriterConnection
+    // Keeps the callbacks we pass to Rust alive until they're unregistered.
+    private val bookmarkObserverCallbacks = ConcurrentHashMap<Long, RawBookmarkObserverCallback>()
 
     init {
         handle.set(rustCall(this) { error ->
@@ -77,6 +81,33 @@ class PlacesApi(path: String) : PlacesManager, AutoCloseable {
         return writeConn
     }
 
+    /**
+     * Registers an observer for changes to bookmarks made by any connection, including syncs.
+     *
+     * @return An ID which can be passed to [unregisterBookmarkObserver].
+     */
+    fun registerBookmarkObserver(observer: BookmarkObserver): Long {
+        val callback = BookmarkObserverCallbackImpl(observer)
+        val id = rustCall(this) { error ->
+            LibPlacesFFI.INSTANCE.places_api_register_bookmark_observer(handle.get(), callback, error)
+        }
+        bookmarkObserverCallbacks[id] = callback
+        return id
+    }
+
+    /**
+     * Unregisters a bookmark observer.
+     *
+     * @return Whether or not an observer had the ID.
+     */
+    fun unregisterBookmarkObserver(id: Long): Boolean {
+        val removed = rustCall(this) { error ->
+            LibPlacesFFI.INSTANCE.places_api_unregister_bookmark_observer(handle.get(), id, error)
+        }
+        bookmarkObserverCallbacks.remove(id)
+        return removed.toInt() != 0
+    }
+
     @Synchronized
     override fun close() {
         // Take the write connection's handle and clear its reference to us.
@@ -96,6 +127,7 @@ class PlacesApi(path: String) : PlacesManager, AutoCloseable {
             rustCall(this) { error ->
                 LibPlacesFFI.INSTANCE.places_api_destroy(handle, error)
             }
+            bookmarkObserverCallbacks.clear()
         }
     }
 
@@ -441,6 +473,80 @@ open class PlacesReaderConnection internal constructor(connHandle: Long) :
         }
     }
 
+    override fun getBookmarkKeywordForUrl(url: String): String? {
+        return rustCallForOptString { error ->
+            LibPlacesFFI.INSTANCE.bookmarks_get_keyword_for_url(this.handle.get(), url, error)
+        }
+    }
+
+    override fun getAllBookmarkKeywords(): List<BookmarkKeyword> {
+        readQueryCounters.measure {
+            val rustBuf = rustCall { err ->
+                PlacesManagerMetrics.readQueryTime.measure {
+                    LibPlacesFFI.INSTANCE.bookmarks_get_all_keywords(this.handle.get(), err)
+                }
+            }
+
+            try {
+                val message = MsgTypes.BookmarkKeywordList.parseFrom(rustBuf.asCodedInputStream()!!)
+                return message.keywordsList.map { BookmarkKeyword(it.keyword, it.url) }
+            } finally {
+                LibPlacesFFI.INSTANCE.places_destroy_bytebuffer(rustBuf)
+            }
+        }
+    }
+
+    override fun getUrlsWithTag(tag: String): List<String> {
+        readQueryCounters.measure {
+            val rustBuf = rustCall { err ->
+                PlacesManagerMetrics.readQueryTime.measure {
+                    LibPlacesFFI.INSTANCE.places_get_urls_with_tag(this.handle.get(), tag, err)
+                }
+            }
+
+            try {
+                val message = MsgTypes.StringList.parseFrom(rustBuf.asCodedInputStream()!!)
+                return message.valuesList
+            } finally {
+                LibPlacesFFI.INSTANCE.places_destroy_bytebuffer(rustBuf)
+            }
+        }
+    }
+
+    override fun getTagsForUrl(url: String): List<String> {
+        readQueryCounters.measure {
+            val rustBuf = rustCall { err ->
+                PlacesManagerMetrics.readQueryTime.measure {
+                    LibPlacesFFI.INSTANCE.places_get_tags_for_url(this.handle.get(), url, err)
+                }
+            }
+
+            try {
+                val message = MsgTypes.StringList.parseFrom(rustBuf.asCodedInputStream()!!)
+                return message.valuesList
+            } finally {
+                LibPlacesFFI.INSTANCE.places_destroy_bytebuffer(rustBuf)
+            }
+        }
+    }
+
+    override fun getAllTags(): List<TagInfo> {
+        readQueryCounters.measure {
+            val rustBuf = rustCall { err ->
+                PlacesManagerMetrics.readQueryTime.measure {
+                    LibPlacesFFI.INSTANCE.places_get_all_tags(this.handle.get(), err)
+                }
+            }
+
+            try {
+                val message = MsgTypes.TagInfoList.parseFrom(rustBuf.asCodedInputStream()!!)
+                return message.tagsList.map { TagInfo(it.tag, it.urlCount) }
+            } finally {
+                LibPlacesFFI.INSTANCE.places_destroy_bytebuffer(rustBuf)
+            }
+        }
+    }
+
     override fun searchBookmarks(query: String, limit: Int): List<BookmarkItem> {
         readQueryCounters.measure {
             val rustBuf = rustCall { err ->
@@ -551,6 +657,17 @@ class PlacesWriterConnection internal constructor(connHandle: Long, api: PlacesA
         }
     }
 
+    override fun deleteVisitsForHost(host: String) {
+        return writeQueryCounters.measure {
+            rustCall { error ->
+                PlacesManagerMetrics.writeQueryTime.measure {
+                    LibPlacesFFI.INSTANCE.places_delete_visits_for_host(
+                        this.handle.get(), host, error)
+                }
+            }
+        }
+    }
+
     override fun wipeLocal() {
         rustCall { error ->
             LibPlacesFFI.INSTANCE.places_wipe_local(this.handle.get(), error)
@@ -653,6 +770,67 @@ class PlacesWriterConnection internal constructor(connHandle: Long, api: PlacesA
         }
     }
 
+    override fun setBookmarkKeyword(url: String, keyword: String) {
+        return writeQueryCounters.measure {
+            rustCall { error ->
+                PlacesManagerMetrics.writeQueryTime.measure {
+                    LibPlacesFFI.INSTANCE.bookmarks_set_keyword(this.handle.get(), url, keyword, error)
+                }
+            }
+        }
+    }
+
+    override fun removeBookmarkKeyword(keyword: String): Boolean {
+        return writeQueryCounters.measure {
+            rustCall { error ->
+                val existedByte = PlacesManagerMetrics.writeQueryTime.measure {
+                    LibPlacesFFI.INSTANCE.bookmarks_remove_keyword(this.handle.get(), keyword, error)
+                }
+                existedByte.toInt() != 0
+            }
+        }
+    }
+
+    override fun tagUrl(url: String, tag: String) {
+        return writeQueryCounters.measure {
+            rustCall { error ->
+                PlacesManagerMetrics.writeQueryTime.measure {
+                    LibPlacesFFI.INSTANCE.places_tag_url(this.handle.get(), url, tag, error)
+                }
+            }
+        }
+    }
+
+    override fun untagUrl(url: String, tag: String) {
+        return writeQueryCounters.measure {
+            rustCall { error ->
+                PlacesManagerMetrics.writeQueryTime.measure {
+                    LibPlacesFFI.INSTANCE.places_untag_url(this.handle.get(), url, tag, error)
+                }
+            }
+        }
+    }
+
+    override fun removeAllTagsFromUrl(url: String) {
+        return writeQueryCounters.measure {
+            rustCall { error ->
+                PlacesManagerMetrics.writeQueryTime.measure {
+                    LibPlacesFFI.INSTANCE.places_remove_all_tags_from_url(this.handle.get(), url, error)
+                }
+            }
+        }
+    }
+
+    override fun removeTag(tag: String) {
+        return writeQueryCounters.measure {
+            rustCall { error ->
+                PlacesManagerMetrics.writeQueryTime.measure {
+                    LibPlacesFFI.INSTANCE.places_remove_tag(this.handle.get(), tag, error)
+                }
+            }
+        }
+    }
+
     override fun acceptResult(searchString: String, url: String) {
         rustCall { error ->
             LibPlacesFFI.INSTANCE.places_accept_result(
@@ -806,9 +984,17 @@ interface ReadableHistoryConnection : InterruptibleConnection {
     /**
      * A way to search the internal database tailored for autocompletion purposes.
      *
+     * The query is split into words, and every word must match the title, URL or
+     * tags of a result. Words which are restriction characters, like `*` for
+     * bookmarks or `^` for history, restrict the results instead.
+     *
      * @param query a string to match results against.
      * @param limit a maximum number of results to retrieve.
      * @return a list of [SearchResult] matching the [query], in arbitrary order.
+     *
+     * @throws OperationInterrupted if this database implements [InterruptibleConnection] and
+     * has its `interrupt()` method called on another thread, for example, because
+     * the user typed another character.
      */
     fun queryAutocomplete(query: String, limit: Int): List<SearchResult>
 
@@ -932,9 +1118,10 @@ interface WritableHistoryConnection : ReadableHistoryConnection {
     fun runMaintenance()
 
     /**
-     * Aggressively prune history visits. These deletions are not intended
-     * to be synced, however due to the way history sync works, this can
-     * still cause data loss.
+     * Aggressively prune history visits. Old visits to the least frecent
+     * pages are removed first, until the database is smaller than 60 MiB.
+     * Visits from the last 7 days, and visits to bookmarked pages, are
+     * kept. Like other deletions, these are synced.
      *
      * As a result, this should only be called if a low disk space
      * notification is received from the OS, and things like the network
@@ -997,6 +1184,17 @@ interface WritableHistoryConnection : ReadableHistoryConnection {
      */
     fun deleteVisitsBetween(startTime: Long, endTime: Long)
 
+    /**
+     * Deletes all history for pages on a host, or any of its subdomains, with
+     * any scheme or port. This removes their visits and input history. Like
+     * deleteVisitsFor, pages which are bookmarked, or have a keyword or tags,
+     * keep the page without its visits; everything else is removed, and the
+     * deletions will be synced.
+     *
+     * @param host The host to forget about, like "example.com".
+     */
+    fun deleteVisitsForHost(host: String)
+
     /**
      * Delete the single visit that occurred at the provided timestamp.
      *
@@ -1139,7 +1337,12 @@ data class SearchResult(
     val url: String,
     val title: String,
     val frecency: Long,
-    val reasons: List<SearchResultReason>
+    val reasons: List<SearchResultReason>,
+    /**
+     * The tags for this result, separated by commas, if it matched because of
+     * its tags.
+     */
+    val tags: String? = null
 ) {
     companion object {
         internal fun fromMessage(msg: MsgTypes.SearchResultMessage): SearchResult {
@@ -1149,7 +1352,8 @@ data class SearchResult(
                 frecency = msg.frecency,
                 reasons = msg.reasonsList.map {
                     SearchResultReason.fromMessage(it)
-                }
+                },
+                tags = if (msg.hasTags()) { msg.tags } else { null }
             )
         }
         internal fun fromCollectionMessage(msg: MsgTypes.SearchResultList): List<SearchResult> {
@@ -1293,3 +1497,22 @@ class PlacesManagerCounterMetrics(
         }
     }
 }
+
+internal class BookmarkObserverCallbackImpl(private val observer: BookmarkObserver) : RawBookmarkObserverCallback {
+    @Suppress("TooGenericExceptionCaught")
+    override fun invoke(event: RustBuffer.ByValue) {
+        // We can't safely throw here!
+        try {
+            val msg = MsgTypes.BookmarkEvent.parseFrom(event.asCodedInputStream()!!)
+            observer.onBookmarkEvent(BookmarkEvent.fromMessage(msg))
+        } catch (e: Throwable) {
+            try {
+                println("Exception when notifying bookmark observer: $e")
+            } catch (e: Throwable) {
+                // :(
+            }
+        } finally {
+            LibPlacesFFI.INSTANCE.places_destroy_bytebuffer(event)
+        }
+    }
+}
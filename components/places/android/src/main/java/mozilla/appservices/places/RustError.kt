@@ -51,6 +51,8 @@ internal open class RustError : Structure() {
             66 -> return UrlTooLong(message)
             67 -> return InvalidBookmarkUpdate(message)
             68 -> return CannotUpdateRoot(message)
+            69 -> return InvalidKeyword(message)
+            70 -> return InvalidTag(message)
 
             -1 -> return InternalPanic(message)
             // Note: `1` is used as a generic catch all, but we
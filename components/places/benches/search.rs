@@ -54,6 +54,7 @@ impl TestDb {
             ConnectionType::ReadWrite,
             0,
             Arc::new(Mutex::new(())),
+            Default::default(),
         )
         .unwrap();
         println!("Populating test database...");
@@ -82,6 +83,7 @@ fn bench_search_frecent(c: &mut Criterion) {
                 search_string: "mozilla".into(),
                 limit: 10,
             },
+            &db.begin_interrupt_scope(),
         )
         .unwrap()
     });
@@ -92,6 +94,7 @@ fn bench_search_frecent(c: &mut Criterion) {
                 search_string: "blog.mozilla.org".into(),
                 limit: 10,
             },
+            &db.begin_interrupt_scope(),
         )
         .unwrap()
     });
@@ -102,6 +105,7 @@ fn bench_search_frecent(c: &mut Criterion) {
                 search_string: "https://hg.mozilla.org/mozilla-central".into(),
                 limit: 10,
             },
+            &db.begin_interrupt_scope(),
         )
         .unwrap()
     });
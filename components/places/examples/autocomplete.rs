@@ -322,6 +322,10 @@ mod autocomplete {
                 thread::spawn(move || {
                     // Note: unwraps/panics here won't bring down the main thread.
                     for AutocompleteRequest { id, search } in recv_query.iter() {
+                        // Begin the scope before checking `last_id`, so that a
+                        // newer query interrupts this one even if it arrives
+                        // before we start searching.
+                        let scope = conn.begin_interrupt_scope();
                         // Check if this query is worth processing. Note that we check that the id
                         // isn't known to be stale. The id can be ahead of `last_id`, since
                         // we push the item on before incrementing `last_id`.
@@ -329,7 +333,7 @@ mod autocomplete {
                             continue;
                         }
                         let start = Instant::now();
-                        match search_frecent(&conn, search.clone()) {
+                        match search_frecent(&conn, search.clone(), &scope) {
                             Ok(results) => {
                                 // Should we skip sending results if `last_id` indicates we
                                 // don't care anymore?
@@ -155,6 +155,24 @@ fn run_native_import(db: &PlacesDb, filename: String) -> Result<()> {
     do_import(db, root)
 }
 
+fn run_html_import(db: &PlacesDb, filename: String) -> Result<()> {
+    println!("html import from {}", filename);
+    let html = std::fs::read_to_string(filename)?;
+    let result = places::import::import_bookmarks_html(db, &html)?;
+    println!(
+        "Import finished! {} of {} items imported",
+        result.num_succeeded, result.num_total
+    );
+    Ok(())
+}
+
+fn run_html_export(db: &PlacesDb, filename: String) -> Result<()> {
+    println!("html export to {}", filename);
+    let html = places::storage::bookmarks::export_bookmarks_html(db)?;
+    std::fs::write(filename, html)?;
+    Ok(())
+}
+
 fn run_native_export(db: &PlacesDb, filename: String) -> Result<()> {
     println!("export to {}", filename);
 
@@ -347,7 +365,8 @@ enum Command {
     },
 
     #[structopt(name = "export-bookmarks")]
-    /// Exports bookmarks (but not in a way Desktop can import it!)
+    /// Exports bookmarks (but not in a way Desktop can import it - see
+    /// export-html-bookmarks for that!)
     ExportBookmarks {
         #[structopt(name = "output-file", long, short = "o")]
         /// The name of the output file where the json will be written.
@@ -377,6 +396,23 @@ enum Command {
         /// Imports bookmarks from a desktop export
         input_file: String,
     },
+
+    #[structopt(name = "export-html-bookmarks")]
+    /// Exports bookmarks as a bookmarks.html file, which Desktop can import
+    ExportHtmlBookmarks {
+        #[structopt(name = "output-file", long, short = "o")]
+        /// The name of the output file where the html will be written.
+        output_file: String,
+    },
+
+    #[structopt(name = "import-html-bookmarks")]
+    /// Import bookmarks from a bookmarks.html file, as exported by Desktop
+    /// and other browsers
+    ImportHtmlBookmarks {
+        #[structopt(name = "input-file", long, short = "i")]
+        /// The name of the file to read.
+        input_file: String,
+    },
 }
 
 fn main() -> Result<()> {
@@ -412,5 +448,7 @@ fn main() -> Result<()> {
         Command::ImportBookmarks { input_file } => run_native_import(&db, input_file),
         Command::ImportIosBookmarks { input_file } => run_ios_import(&api, input_file),
         Command::ImportDesktopBookmarks { input_file } => run_desktop_import(&db, input_file),
+        Command::ExportHtmlBookmarks { output_file } => run_html_export(&db, output_file),
+        Command::ImportHtmlBookmarks { input_file } => run_html_import(&db, input_file),
     }
 }
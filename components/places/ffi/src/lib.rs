@@ -10,10 +10,13 @@
 
 use ffi_support::{
     define_box_destructor, define_bytebuffer_destructor, define_handle_map_deleter,
-    define_string_destructor, ByteBuffer, ConcurrentHandleMap, ExternError, FfiStr,
+    define_string_destructor, ByteBuffer, ConcurrentHandleMap, ExternError, FfiStr, IntoFfi,
 };
+use places::api::observer::{BookmarkEvent, BookmarkObserver};
 use places::error::*;
-use places::msg_types::{BookmarkNodeList, SearchResultList};
+use places::msg_types::{
+    self, BookmarkKeywordList, BookmarkNodeList, SearchResultList, StringList, TagInfoList,
+};
 use places::storage::bookmarks;
 use places::types::VisitTransitionSet;
 use places::{storage, ConnectionType, PlacesApi, PlacesDb};
@@ -57,6 +60,49 @@ pub extern "C" fn places_new_sync_conn_interrupt_handle(
     })
 }
 
+/// The type of the callback passed to `places_api_register_bookmark_observer`.
+/// It's called on the thread that made the change, with a `BookmarkEvent`
+/// protobuf message, which the callback must free with
+/// `places_destroy_bytebuffer`.
+pub type BookmarkObserverCallback = unsafe extern "C" fn(event: ByteBuffer);
+
+struct FfiBookmarkObserver(BookmarkObserverCallback);
+
+impl BookmarkObserver for FfiBookmarkObserver {
+    fn on_bookmark_event(&self, event: &BookmarkEvent) {
+        let buf = msg_types::BookmarkEvent::from(event).into_ffi_value();
+        unsafe { (self.0)(buf) }
+    }
+}
+
+/// Registers a callback for changes to bookmarks made by any of this API's
+/// connections. Returns an ID which can be passed to
+/// `places_api_unregister_bookmark_observer`. The callback must remain
+/// valid until it's unregistered, or the API is destroyed.
+#[no_mangle]
+pub extern "C" fn places_api_register_bookmark_observer(
+    handle: u64,
+    callback: BookmarkObserverCallback,
+    error: &mut ExternError,
+) -> u64 {
+    log::debug!("places_api_register_bookmark_observer");
+    APIS.call_with_output(error, handle, |api| {
+        api.register_bookmark_observer(Arc::new(FfiBookmarkObserver(callback))) as u64
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn places_api_unregister_bookmark_observer(
+    handle: u64,
+    id: u64,
+    error: &mut ExternError,
+) -> u8 {
+    log::debug!("places_api_unregister_bookmark_observer");
+    APIS.call_with_output(error, handle, |api| {
+        api.unregister_bookmark_observer(id as usize)
+    })
+}
+
 #[no_mangle]
 pub extern "C" fn places_connection_new(
     handle: u64,
@@ -195,12 +241,14 @@ pub extern "C" fn places_query_autocomplete(
 ) -> ByteBuffer {
     log::debug!("places_query_autocomplete");
     CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        let scope = conn.begin_interrupt_scope();
         let results = search_frecent(
             conn,
             SearchParams {
                 search_string: search.into_string(),
                 limit,
             },
+            &scope,
         )?
         .into_iter()
         .map(|r| r.into())
@@ -316,6 +364,19 @@ pub extern "C" fn places_delete_visits_between(
     })
 }
 
+#[no_mangle]
+pub extern "C" fn places_delete_visits_for_host(
+    handle: u64,
+    host: FfiStr<'_>,
+    error: &mut ExternError,
+) {
+    log::debug!("places_delete_visits_for_host");
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        storage::history::delete_visits_for_host(conn, host.as_str())?;
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub extern "C" fn places_delete_visit(
     handle: u64,
@@ -361,8 +422,9 @@ pub extern "C" fn places_run_maintenance(handle: u64, error: &mut ExternError) {
 #[no_mangle]
 pub extern "C" fn places_prune_destructively(handle: u64, error: &mut ExternError) {
     log::debug!("places_prune_destructively");
-    CONNECTIONS.call_with_result(error, handle, |conn| {
-        storage::history::prune_destructively(conn)
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        storage::history::prune_destructively(conn, &Default::default())?;
+        Ok(())
     })
 }
 
@@ -676,6 +738,142 @@ pub unsafe extern "C" fn bookmarks_get_url_for_keyword(
     })
 }
 
+#[no_mangle]
+pub extern "C" fn bookmarks_get_keyword_for_url(
+    handle: u64,
+    url: FfiStr<'_>,
+    error: &mut ExternError,
+) -> *mut c_char {
+    log::debug!("bookmarks_get_keyword_for_url");
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        let url = parse_url(url.as_str())?;
+        bookmarks::bookmarks_get_keyword_for_url(conn, &url)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn bookmarks_get_all_keywords(handle: u64, error: &mut ExternError) -> ByteBuffer {
+    log::debug!("bookmarks_get_all_keywords");
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        Ok(BookmarkKeywordList::from(
+            bookmarks::bookmarks_get_all_keywords(conn)?,
+        ))
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn bookmarks_set_keyword(
+    handle: u64,
+    url: FfiStr<'_>,
+    keyword: FfiStr<'_>,
+    error: &mut ExternError,
+) {
+    log::debug!("bookmarks_set_keyword");
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        let url = parse_url(url.as_str())?;
+        bookmarks::bookmarks_set_keyword(conn, &url, keyword.as_str())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn bookmarks_remove_keyword(
+    handle: u64,
+    keyword: FfiStr<'_>,
+    error: &mut ExternError,
+) -> u8 {
+    log::debug!("bookmarks_remove_keyword");
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        bookmarks::bookmarks_remove_keyword(conn, keyword.as_str())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn places_tag_url(
+    handle: u64,
+    url: FfiStr<'_>,
+    tag: FfiStr<'_>,
+    error: &mut ExternError,
+) {
+    log::debug!("places_tag_url");
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        let url = parse_url(url.as_str())?;
+        storage::tags::tag_url(conn, &url, tag.as_str())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn places_untag_url(
+    handle: u64,
+    url: FfiStr<'_>,
+    tag: FfiStr<'_>,
+    error: &mut ExternError,
+) {
+    log::debug!("places_untag_url");
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        let url = parse_url(url.as_str())?;
+        storage::tags::untag_url(conn, &url, tag.as_str())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn places_remove_all_tags_from_url(
+    handle: u64,
+    url: FfiStr<'_>,
+    error: &mut ExternError,
+) {
+    log::debug!("places_remove_all_tags_from_url");
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        let url = parse_url(url.as_str())?;
+        storage::tags::remove_all_tags_from_url(conn, &url)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn places_remove_tag(handle: u64, tag: FfiStr<'_>, error: &mut ExternError) {
+    log::debug!("places_remove_tag");
+    CONNECTIONS.call_with_result(error, handle, |conn| {
+        storage::tags::remove_tag(conn, tag.as_str())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn places_get_urls_with_tag(
+    handle: u64,
+    tag: FfiStr<'_>,
+    error: &mut ExternError,
+) -> ByteBuffer {
+    log::debug!("places_get_urls_with_tag");
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        let urls = storage::tags::get_urls_with_tag(conn, tag.as_str())?;
+        Ok(StringList {
+            values: urls.into_iter().map(|url| url.into_string()).collect(),
+        })
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn places_get_tags_for_url(
+    handle: u64,
+    url: FfiStr<'_>,
+    error: &mut ExternError,
+) -> ByteBuffer {
+    log::debug!("places_get_tags_for_url");
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        let url = parse_url(url.as_str())?;
+        Ok(StringList {
+            values: storage::tags::get_tags_for_url(conn, &url)?,
+        })
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn places_get_all_tags(handle: u64, error: &mut ExternError) -> ByteBuffer {
+    log::debug!("places_get_all_tags");
+    CONNECTIONS.call_with_result(error, handle, |conn| -> places::Result<_> {
+        Ok(TagInfoList::from(storage::tags::get_all_tags(conn)?))
+    })
+}
+
 #[no_mangle]
 pub extern "C" fn bookmarks_search(
     handle: u64,
@@ -213,6 +213,20 @@ public class BookmarkFolder: BookmarkNode {
     }
 }
 
+/**
+ * A search keyword, and the bookmarked URL it belongs to.
+ */
+public struct BookmarkKeyword {
+    public let keyword: String
+    public let url: String
+}
+
+/// A tag, and the number of URLs which have it.
+public struct TagInfo {
+    public let tag: String
+    public let urlCount: UInt32
+}
+
 // We pass in whether or not we expect children, because we don't have a way
 // of distinguishing 'empty folder' from 'this API does not return children'.
 internal func unpackProtobuf(msg: MsgTypes_BookmarkNode) -> BookmarkNode {
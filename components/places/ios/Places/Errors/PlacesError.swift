@@ -56,6 +56,14 @@ public enum PlacesError: LocalizedError {
     /// insert a new item as a child of root________.
     case cannotUpdateRoot(message: String)
 
+    /// Thrown when attempting to set a keyword which is empty or
+    /// contains whitespace.
+    case invalidKeyword(message: String)
+
+    /// Thrown when attempting to tag a URL with a tag which is empty or
+    /// too long.
+    case invalidTag(message: String)
+
     /// Our implementation of the localizedError protocol -- (This shows up in Sentry)
     public var errorDescription: String? {
         switch self {
@@ -85,6 +93,10 @@ public enum PlacesError: LocalizedError {
             return "PlacesError.illegalChange: \(message)"
         case let .cannotUpdateRoot(message):
             return "PlacesError.cannotUpdateRoot: \(message)"
+        case let .invalidKeyword(message):
+            return "PlacesError.invalidKeyword: \(message)"
+        case let .invalidTag(message):
+            return "PlacesError.invalidTag: \(message)"
         }
     }
 
@@ -128,6 +140,12 @@ public enum PlacesError: LocalizedError {
         case Places_InvalidPlace_CannotUpdateRoot:
             return .cannotUpdateRoot(message: String(freeingPlacesString: message!))
 
+        case Places_InvalidPlace_InvalidKeyword:
+            return .invalidKeyword(message: String(freeingPlacesString: message!))
+
+        case Places_InvalidPlace_InvalidTag:
+            return .invalidTag(message: String(freeingPlacesString: message!))
+
         case Places_Corrupt:
             return .databaseCorrupt(message: String(freeingPlacesString: message!))
 
@@ -442,6 +442,150 @@ public class PlacesReadConnection {
         }
     }
 
+    /**
+     * Returns the search keyword for the provided URL, if one exists.
+     *
+     * - Parameter url: The bookmarked URL.
+     * - Returns: The keyword for the URL, if set.
+     * - Throws:
+     *     - `PlacesError.urlParseError`: If `url` is not a valid URL.
+     *     - `PlacesError.databaseInterrupted`: If a call is made to `interrupt()` on this
+     *                                          object from another thread.
+     *     - `PlacesError.connUseAfterAPIClosed`: If the PlacesAPI that returned this connection
+     *                                            object has been closed. This indicates API
+     *                                            misuse.
+     *     - `PlacesError.unexpected`: When an error that has not specifically been exposed
+     *                                 to Swift is encountered (for example IO errors from
+     *                                 the database code, etc).
+     *     - `PlacesError.panic`: If the rust code panics while completing this
+     *                            operation. (If this occurs, please let us know).
+     */
+    open func getBookmarkKeywordForURL(url: String) throws -> String? {
+        return try queue.sync {
+            try self.checkApi()
+            let maybeKeyword = try PlacesError.tryUnwrap { error in
+                bookmarks_get_keyword_for_url(self.handle, url, error)
+            }
+            guard let keyword = maybeKeyword else {
+                return nil
+            }
+            return String(freeingPlacesString: keyword)
+        }
+    }
+
+    /**
+     * Returns all search keywords, and the URLs they belong to, ordered by keyword.
+     *
+     * - Throws:
+     *     - `PlacesError.databaseInterrupted`: If a call is made to `interrupt()` on this
+     *                                          object from another thread.
+     *     - `PlacesError.connUseAfterAPIClosed`: If the PlacesAPI that returned this connection
+     *                                            object has been closed. This indicates API
+     *                                            misuse.
+     *     - `PlacesError.unexpected`: When an error that has not specifically been exposed
+     *                                 to Swift is encountered (for example IO errors from
+     *                                 the database code, etc).
+     *     - `PlacesError.panic`: If the rust code panics while completing this
+     *                            operation. (If this occurs, please let us know).
+     */
+    open func getAllBookmarkKeywords() throws -> [BookmarkKeyword] {
+        return try queue.sync {
+            try self.checkApi()
+            let buffer = try PlacesError.unwrap { error in
+                bookmarks_get_all_keywords(self.handle, error)
+            }
+            defer { places_destroy_bytebuffer(buffer) }
+            // This could probably be try!
+            let msg = try MsgTypes_BookmarkKeywordList(serializedData: Data(placesRustBuffer: buffer))
+            return msg.keywords.map { BookmarkKeyword(keyword: $0.keyword, url: $0.url) }
+        }
+    }
+
+    /**
+     * Returns the URLs which have the provided tag, ordered by frecency.
+     *
+     * - Parameter tag: The tag.
+     * - Throws:
+     *     - `PlacesError.databaseInterrupted`: If a call is made to `interrupt()` on this
+     *                                          object from another thread.
+     *     - `PlacesError.connUseAfterAPIClosed`: If the PlacesAPI that returned this connection
+     *                                            object has been closed. This indicates API
+     *                                            misuse.
+     *     - `PlacesError.unexpected`: When an error that has not specifically been exposed
+     *                                 to Swift is encountered (for example IO errors from
+     *                                 the database code, etc).
+     *     - `PlacesError.panic`: If the rust code panics while completing this
+     *                            operation. (If this occurs, please let us know).
+     */
+    open func getURLsWithTag(tag: String) throws -> [String] {
+        return try queue.sync {
+            try self.checkApi()
+            let buffer = try PlacesError.unwrap { error in
+                places_get_urls_with_tag(self.handle, tag, error)
+            }
+            defer { places_destroy_bytebuffer(buffer) }
+            let msg = try MsgTypes_StringList(serializedData: Data(placesRustBuffer: buffer))
+            return msg.values
+        }
+    }
+
+    /**
+     * Returns the tags for the provided URL, most recently modified first.
+     *
+     * - Parameter url: The tagged URL.
+     * - Throws:
+     *     - `PlacesError.urlParseError`: If `url` is not a valid URL.
+     *     - `PlacesError.databaseInterrupted`: If a call is made to `interrupt()` on this
+     *                                          object from another thread.
+     *     - `PlacesError.connUseAfterAPIClosed`: If the PlacesAPI that returned this connection
+     *                                            object has been closed. This indicates API
+     *                                            misuse.
+     *     - `PlacesError.unexpected`: When an error that has not specifically been exposed
+     *                                 to Swift is encountered (for example IO errors from
+     *                                 the database code, etc).
+     *     - `PlacesError.panic`: If the rust code panics while completing this
+     *                            operation. (If this occurs, please let us know).
+     */
+    open func getTagsForURL(url: String) throws -> [String] {
+        return try queue.sync {
+            try self.checkApi()
+            let buffer = try PlacesError.unwrap { error in
+                places_get_tags_for_url(self.handle, url, error)
+            }
+            defer { places_destroy_bytebuffer(buffer) }
+            let msg = try MsgTypes_StringList(serializedData: Data(placesRustBuffer: buffer))
+            return msg.values
+        }
+    }
+
+    /**
+     * Returns all tags which are used by at least one URL, with the number
+     * of URLs which have each tag, ordered by that number, then by tag.
+     *
+     * - Throws:
+     *     - `PlacesError.databaseInterrupted`: If a call is made to `interrupt()` on this
+     *                                          object from another thread.
+     *     - `PlacesError.connUseAfterAPIClosed`: If the PlacesAPI that returned this connection
+     *                                            object has been closed. This indicates API
+     *                                            misuse.
+     *     - `PlacesError.unexpected`: When an error that has not specifically been exposed
+     *                                 to Swift is encountered (for example IO errors from
+     *                                 the database code, etc).
+     *     - `PlacesError.panic`: If the rust code panics while completing this
+     *                            operation. (If this occurs, please let us know).
+     */
+    open func getAllTags() throws -> [TagInfo] {
+        return try queue.sync {
+            try self.checkApi()
+            let buffer = try PlacesError.unwrap { error in
+                places_get_all_tags(self.handle, error)
+            }
+            defer { places_destroy_bytebuffer(buffer) }
+            let msg = try MsgTypes_TagInfoList(serializedData: Data(placesRustBuffer: buffer))
+            return msg.tags.map { TagInfo(tag: $0.tag, urlCount: $0.urlCount) }
+        }
+    }
+
     /**
      * Returns the list of bookmarks that match the provided search string.
      *
@@ -801,6 +945,169 @@ public class PlacesWriteConnection: PlacesReadConnection {
         }
     }
 
+    /**
+     * Set the search keyword for a bookmarked URL, replacing its existing
+     * keyword. Keywords are trimmed and lowercased, and a keyword belongs
+     * to at most one URL, so it is removed from any other URL first.
+     *
+     * - Parameter url: The bookmarked URL.
+     * - Parameter keyword: The new keyword.
+     *
+     * - Throws:
+     *     - `PlacesError.invalidKeyword`: If `keyword` is empty or contains whitespace.
+     *     - `PlacesError.urlParseError`: If `url` is not a valid URL.
+     *     - `PlacesError.connUseAfterAPIClosed`: If the PlacesAPI that returned this connection
+     *                                            object has been closed. This indicates API
+     *                                            misuse.
+     *     - `PlacesError.unexpected`: If `url` is not bookmarked, or when an error that
+     *                                 has not specifically been exposed to Swift is
+     *                                 encountered (for example IO errors from the
+     *                                 database code, etc).
+     *     - `PlacesError.panic`: If the rust code panics while completing this
+     *                            operation. (If this occurs, please let us know).
+     */
+    open func setBookmarkKeyword(url: String, keyword: String) throws {
+        try queue.sync {
+            try self.checkApi()
+            try PlacesError.unwrap { error in
+                bookmarks_set_keyword(self.handle, url, keyword, error)
+            }
+        }
+    }
+
+    /**
+     * Remove a search keyword.
+     *
+     * - Parameter keyword: The keyword to remove.
+     *
+     * - Returns: Whether or not the keyword existed.
+     *
+     * - Throws:
+     *     - `PlacesError.connUseAfterAPIClosed`: If the PlacesAPI that returned this connection
+     *                                            object has been closed. This indicates API
+     *                                            misuse.
+     *     - `PlacesError.unexpected`: When an error that has not specifically been exposed
+     *                                 to Swift is encountered (for example IO errors from
+     *                                 the database code, etc).
+     *     - `PlacesError.panic`: If the rust code panics while completing this
+     *                            operation. (If this occurs, please let us know).
+     */
+    @discardableResult
+    open func removeBookmarkKeyword(keyword: String) throws -> Bool {
+        return try queue.sync {
+            try self.checkApi()
+            let resByte = try PlacesError.unwrap { error in
+                bookmarks_remove_keyword(self.handle, keyword, error)
+            }
+            return resByte != 0
+        }
+    }
+
+    /**
+     * Tag a URL. The URL must already be known, for example, because it
+     * was visited or bookmarked. Tags are trimmed.
+     *
+     * - Parameter url: The URL to tag.
+     * - Parameter tag: The tag to add.
+     *
+     * - Throws:
+     *     - `PlacesError.invalidTag`: If `tag` is empty or too long.
+     *     - `PlacesError.urlParseError`: If `url` is not a valid URL.
+     *     - `PlacesError.connUseAfterAPIClosed`: If the PlacesAPI that returned this connection
+     *                                            object has been closed. This indicates API
+     *                                            misuse.
+     *     - `PlacesError.unexpected`: If `url` is not known, or when an error that
+     *                                 has not specifically been exposed to Swift is
+     *                                 encountered (for example IO errors from the
+     *                                 database code, etc).
+     *     - `PlacesError.panic`: If the rust code panics while completing this
+     *                            operation. (If this occurs, please let us know).
+     */
+    open func tagURL(url: String, tag: String) throws {
+        try queue.sync {
+            try self.checkApi()
+            try PlacesError.unwrap { error in
+                places_tag_url(self.handle, url, tag, error)
+            }
+        }
+    }
+
+    /**
+     * Remove a tag from a URL. Does nothing if the URL doesn't have the tag.
+     *
+     * - Parameter url: The tagged URL.
+     * - Parameter tag: The tag to remove.
+     *
+     * - Throws:
+     *     - `PlacesError.invalidTag`: If `tag` is empty or too long.
+     *     - `PlacesError.urlParseError`: If `url` is not a valid URL.
+     *     - `PlacesError.connUseAfterAPIClosed`: If the PlacesAPI that returned this connection
+     *                                            object has been closed. This indicates API
+     *                                            misuse.
+     *     - `PlacesError.unexpected`: When an error that has not specifically been exposed
+     *                                 to Swift is encountered (for example IO errors from
+     *                                 the database code, etc).
+     *     - `PlacesError.panic`: If the rust code panics while completing this
+     *                            operation. (If this occurs, please let us know).
+     */
+    open func untagURL(url: String, tag: String) throws {
+        try queue.sync {
+            try self.checkApi()
+            try PlacesError.unwrap { error in
+                places_untag_url(self.handle, url, tag, error)
+            }
+        }
+    }
+
+    /**
+     * Remove all tags from a URL.
+     *
+     * - Parameter url: The tagged URL.
+     *
+     * - Throws:
+     *     - `PlacesError.urlParseError`: If `url` is not a valid URL.
+     *     - `PlacesError.connUseAfterAPIClosed`: If the PlacesAPI that returned this connection
+     *                                            object has been closed. This indicates API
+     *                                            misuse.
+     *     - `PlacesError.unexpected`: When an error that has not specifically been exposed
+     *                                 to Swift is encountered (for example IO errors from
+     *                                 the database code, etc).
+     *     - `PlacesError.panic`: If the rust code panics while completing this
+     *                            operation. (If this occurs, please let us know).
+     */
+    open func removeAllTagsFromURL(url: String) throws {
+        try queue.sync {
+            try self.checkApi()
+            try PlacesError.unwrap { error in
+                places_remove_all_tags_from_url(self.handle, url, error)
+            }
+        }
+    }
+
+    /**
+     * Remove a tag from all URLs.
+     *
+     * - Parameter tag: The tag to remove.
+     *
+     * - Throws:
+     *     - `PlacesError.connUseAfterAPIClosed`: If the PlacesAPI that returned this connection
+     *                                            object has been closed. This indicates API
+     *                                            misuse.
+     *     - `PlacesError.unexpected`: When an error that has not specifically been exposed
+     *                                 to Swift is encountered (for example IO errors from
+     *                                 the database code, etc).
+     *     - `PlacesError.panic`: If the rust code panics while completing this
+     *                            operation. (If this occurs, please let us know).
+     */
+    open func removeTag(tag: String) throws {
+        try queue.sync {
+            try self.checkApi()
+            try PlacesError.unwrap { error in
+                places_remove_tag(self.handle, tag, error)
+            }
+        }
+    }
+
     // Helper for the various creation functions.
     // Note: Caller synchronizes
     private func doInsert(msg: MsgTypes_BookmarkNode) throws -> String {
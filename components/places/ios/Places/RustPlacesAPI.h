@@ -22,6 +22,8 @@ typedef enum PlacesErrorCode {
     Places_InvalidPlace_UrlTooLong = 64 + 2,
     Places_InvalidPlace_IllegalChange = 64 + 3,
     Places_InvalidPlace_CannotUpdateRoot = 64 + 4,
+    Places_InvalidPlace_InvalidKeyword = 64 + 5,
+    Places_InvalidPlace_InvalidTag = 64 + 6,
 } PlacesErrorCode;
 
 typedef struct PlacesRustError {
@@ -107,6 +109,10 @@ void places_delete_visits_between(PlacesConnectionHandle handle,
                                   int64_t end,
                                   PlacesRustError *_Nonnull out_err);
 
+void places_delete_visits_for_host(PlacesConnectionHandle handle,
+                                   const char *_Nonnull host,
+                                   PlacesRustError *_Nonnull out_err);
+
 void places_wipe_local(PlacesConnectionHandle handle,
                        PlacesRustError *_Nonnull out_err);
 
@@ -161,6 +167,51 @@ char *_Nullable bookmarks_get_url_for_keyword(PlacesConnectionHandle handle,
                                               char const *_Nonnull keyword,
                                               PlacesRustError *_Nonnull out_err);
 
+char *_Nullable bookmarks_get_keyword_for_url(PlacesConnectionHandle handle,
+                                              char const *_Nonnull url,
+                                              PlacesRustError *_Nonnull out_err);
+
+PlacesRustBuffer bookmarks_get_all_keywords(PlacesConnectionHandle handle,
+                                            PlacesRustError *_Nonnull out_err);
+
+void bookmarks_set_keyword(PlacesConnectionHandle handle,
+                           char const *_Nonnull url,
+                           char const *_Nonnull keyword,
+                           PlacesRustError *_Nonnull out_err);
+
+uint8_t bookmarks_remove_keyword(PlacesConnectionHandle handle,
+                                 char const *_Nonnull keyword,
+                                 PlacesRustError *_Nonnull out_err);
+
+void places_tag_url(PlacesConnectionHandle handle,
+                    char const *_Nonnull url,
+                    char const *_Nonnull tag,
+                    PlacesRustError *_Nonnull out_err);
+
+void places_untag_url(PlacesConnectionHandle handle,
+                      char const *_Nonnull url,
+                      char const *_Nonnull tag,
+                      PlacesRustError *_Nonnull out_err);
+
+void places_remove_all_tags_from_url(PlacesConnectionHandle handle,
+                                     char const *_Nonnull url,
+                                     PlacesRustError *_Nonnull out_err);
+
+void places_remove_tag(PlacesConnectionHandle handle,
+                       char const *_Nonnull tag,
+                       PlacesRustError *_Nonnull out_err);
+
+PlacesRustBuffer places_get_urls_with_tag(PlacesConnectionHandle handle,
+                                          char const *_Nonnull tag,
+                                          PlacesRustError *_Nonnull out_err);
+
+PlacesRustBuffer places_get_tags_for_url(PlacesConnectionHandle handle,
+                                         char const *_Nonnull url,
+                                         PlacesRustError *_Nonnull out_err);
+
+PlacesRustBuffer places_get_all_tags(PlacesConnectionHandle handle,
+                                     PlacesRustError *_Nonnull out_err);
+
 PlacesRustBuffer bookmarks_search(PlacesConnectionHandle handle,
                                   char const *_Nonnull query,
                                   int32_t limit,
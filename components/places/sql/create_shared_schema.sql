@@ -235,3 +235,94 @@ CREATE TABLE IF NOT EXISTS moz_keywords(
                      ON DELETE RESTRICT,
     keyword TEXT NOT NULL UNIQUE
 );
+
+-- This table holds favicons. Like desktop, each size of an icon is stored
+-- as a separate row, so a single `.ico` or a `sizes` attribute with several
+-- sizes can result in several rows for the same URL.
+CREATE TABLE IF NOT EXISTS moz_icons(
+    id INTEGER PRIMARY KEY,
+    icon_url TEXT NOT NULL,
+    icon_url_hash INTEGER NOT NULL,
+    -- Icons are square, so we only store the width. 0 means the icon is
+    -- scalable (for example, an SVG), and can be used at any size.
+    width INTEGER NOT NULL DEFAULT 0,
+    mime_type TEXT,
+    data BLOB NOT NULL,
+    -- In milliseconds.
+    last_updated INTEGER NOT NULL,
+    UNIQUE(icon_url, width)
+);
+
+CREATE INDEX IF NOT EXISTS moz_icons_iconurlhashindex ON moz_icons(icon_url_hash);
+
+-- This table maps pages to the icons they use. Mappings are removed along
+-- with the page, and icons without any pages are removed when history is
+-- deleted.
+CREATE TABLE IF NOT EXISTS moz_icons_to_pages(
+    page_id INTEGER NOT NULL REFERENCES moz_places(id) ON DELETE CASCADE,
+    icon_id INTEGER NOT NULL REFERENCES moz_icons(id) ON DELETE CASCADE,
+    PRIMARY KEY(page_id, icon_id)
+) WITHOUT ROWID;
+
+CREATE INDEX IF NOT EXISTS moz_icons_to_pages_iconindex ON moz_icons_to_pages(icon_id);
+
+-- This table holds how the user engaged with pages, beyond their visits.
+-- Observations for the same page, referrer and search term are grouped into
+-- a single row. Unlike visits, these aren't synced.
+CREATE TABLE IF NOT EXISTS moz_places_metadata(
+    id INTEGER PRIMARY KEY,
+    place_id INTEGER NOT NULL REFERENCES moz_places(id) ON DELETE CASCADE,
+    referrer_place_id INTEGER REFERENCES moz_places(id) ON DELETE CASCADE,
+    search_query_id INTEGER REFERENCES moz_places_metadata_search_queries(id)
+                            ON DELETE CASCADE,
+    document_type INTEGER NOT NULL DEFAULT 0,
+    -- In milliseconds.
+    total_view_time INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS moz_places_metadata_placeindex ON moz_places_metadata(place_id);
+CREATE INDEX IF NOT EXISTS moz_places_metadata_referrerindex ON moz_places_metadata(referrer_place_id);
+CREATE INDEX IF NOT EXISTS moz_places_metadata_updatedindex ON moz_places_metadata(updated_at);
+
+-- Search terms which led to pages in `moz_places_metadata`. Terms are
+-- normalized to lowercase.
+CREATE TABLE IF NOT EXISTS moz_places_metadata_search_queries(
+    id INTEGER PRIMARY KEY,
+    term TEXT NOT NULL UNIQUE
+);
+
+-- Sites which the app has pinned to, or blocked from, its top sites, so that
+-- `top_frecent_site_infos` can leave them out. `kind` is a
+-- `TopSiteExclusionKind`. A pinned site leaves out its whole host, since the
+-- app already shows it; a blocked site only leaves out its own URL.
+CREATE TABLE IF NOT EXISTS moz_top_sites_exclusions(
+    url TEXT NOT NULL PRIMARY KEY,
+    host TEXT NOT NULL,
+    kind INTEGER NOT NULL,
+    date_added INTEGER NOT NULL
+) WITHOUT ROWID;
+
+CREATE INDEX IF NOT EXISTS moz_top_sites_exclusions_hostindex ON moz_top_sites_exclusions(host);
+
+-- Deleted bookmarks and history, which `storage::undo::undo` can restore
+-- until they expire. `kind` is an `UndoKind`, and `data` is a JSON
+-- description of the deleted items.
+CREATE TABLE IF NOT EXISTS moz_undo_journal(
+    id INTEGER PRIMARY KEY,
+    kind INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    data TEXT NOT NULL
+);
+
+-- An optional full-text index over page titles, URLs and tags, used by
+-- `storage::history_search`. The `rowid` is the page's `moz_places.id`, and
+-- `tags` are separated by spaces. It's only filled in, and kept up to date by
+-- triggers, while the `history_search_index` meta key is set.
+CREATE VIRTUAL TABLE IF NOT EXISTS moz_places_fts USING fts5(
+    title,
+    url,
+    tags,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
@@ -338,3 +338,66 @@ BEGIN
         foreign_count = foreign_count - 1
     WHERE id = OLD.place_id;
 END;
+
+-- Search terms are private, so we remove them as soon as they're no longer
+-- used. Metadata is removed along with pages in many places, so it's easiest
+-- to do this in a trigger.
+CREATE TEMP TRIGGER moz_places_metadata_afterdelete_trigger
+AFTER DELETE ON moz_places_metadata FOR EACH ROW
+WHEN OLD.search_query_id NOT NULL
+BEGIN
+    DELETE FROM moz_places_metadata_search_queries
+    WHERE id = OLD.search_query_id AND
+          NOT EXISTS(SELECT 1 FROM moz_places_metadata
+                     WHERE search_query_id = OLD.search_query_id);
+END;
+
+-- These triggers keep the full-text search index up to date, if it's enabled.
+CREATE TEMP TRIGGER moz_places_fts_afterinsert_trigger
+AFTER INSERT ON moz_places
+WHEN EXISTS(SELECT 1 FROM moz_meta WHERE key = 'history_search_index')
+BEGIN
+    INSERT INTO moz_places_fts(rowid, title, url, tags)
+    VALUES(NEW.id, NEW.title, NEW.url, '');
+END;
+
+CREATE TEMP TRIGGER moz_places_fts_afterupdate_trigger
+AFTER UPDATE OF title, url ON moz_places
+WHEN EXISTS(SELECT 1 FROM moz_meta WHERE key = 'history_search_index')
+BEGIN
+    UPDATE moz_places_fts SET
+        title = NEW.title,
+        url = NEW.url
+    WHERE rowid = NEW.id;
+END;
+
+CREATE TEMP TRIGGER moz_places_fts_afterdelete_trigger
+AFTER DELETE ON moz_places
+WHEN EXISTS(SELECT 1 FROM moz_meta WHERE key = 'history_search_index')
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid = OLD.id;
+END;
+
+CREATE TEMP TRIGGER moz_tags_relation_fts_afterinsert_trigger
+AFTER INSERT ON moz_tags_relation
+WHEN EXISTS(SELECT 1 FROM moz_meta WHERE key = 'history_search_index')
+BEGIN
+    UPDATE moz_places_fts SET
+        tags = (SELECT IFNULL(group_concat(t.tag, ' '), '')
+                FROM moz_tags_relation r
+                JOIN moz_tags t ON t.id = r.tag_id
+                WHERE r.place_id = NEW.place_id)
+    WHERE rowid = NEW.place_id;
+END;
+
+CREATE TEMP TRIGGER moz_tags_relation_fts_afterdelete_trigger
+AFTER DELETE ON moz_tags_relation
+WHEN EXISTS(SELECT 1 FROM moz_meta WHERE key = 'history_search_index')
+BEGIN
+    UPDATE moz_places_fts SET
+        tags = (SELECT IFNULL(group_concat(t.tag, ' '), '')
+                FROM moz_tags_relation r
+                JOIN moz_tags t ON t.id = r.tag_id
+                WHERE r.place_id = OLD.place_id)
+    WHERE rowid = OLD.place_id;
+END;
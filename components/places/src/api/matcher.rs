@@ -4,16 +4,18 @@
 
 use crate::db::PlacesDb;
 use crate::error::Result;
+use crate::match_impl::find_in_string;
 pub use crate::match_impl::{MatchBehavior, SearchBehavior};
 use crate::msg_types::{SearchResultMessage, SearchResultReason};
 use rusqlite::{types::ToSql, Row};
 use serde_derive::*;
-use sql_support::{maybe_log_plan, ConnExt};
+use sql_support::{maybe_log_plan, ConnExt, SqlInterruptScope};
 use url::Url;
 
 // A helper to log, cache and execute a query, returning a vector of flattened rows.
 fn query_flat_rows_and_then_named<T, F>(
     conn: &PlacesDb,
+    scope: &SqlInterruptScope,
     sql: &str,
     params: &[(&str, &dyn ToSql)],
     mapper: F,
@@ -23,18 +25,22 @@ where
 {
     maybe_log_plan(conn, sql, params);
     let mut stmt = conn.prepare_maybe_cached(sql, true)?;
-    let iter = stmt.query_and_then_named(params, mapper)?;
-    Ok(iter
-        .inspect(|r| {
-            if let Err(ref e) = *r {
+    let mut results = Vec::new();
+    for result in stmt.query_and_then_named(params, mapper)? {
+        match result {
+            Ok(row) => results.push(row),
+            Err(e) => {
+                // Interrupting the search fails the rest of its rows, so we
+                // bail out instead of skipping them.
+                scope.err_if_interrupted()?;
                 log::warn!("Failed to perform a search: {}", e);
                 if cfg!(debug_assertions) {
                     panic!("Failed to perform a search: {}", e);
                 }
             }
-        })
-        .flatten()
-        .collect::<Vec<_>>())
+        }
+    }
+    Ok(results)
 }
 
 #[derive(Debug, Clone)]
@@ -44,40 +50,44 @@ pub struct SearchParams {
 }
 
 /// Synchronously queries all providers for autocomplete matches, then filters
-/// the matches. This isn't cancelable yet; once a search is started, it can't
-/// be interrupted, even if the user moves on (see
-/// https://github.com/mozilla/application-services/issues/265).
+/// the matches. The search fails with an interrupted error if the connection
+/// is interrupted after `scope` begins, so that a new keystroke can abort the
+/// previous search.
+///
+/// Like desktop's UnifiedComplete, the search string is split into tokens on
+/// whitespace, and every token must match the title, URL or tags of a page.
+/// Tokens that are restriction characters, like `*` for bookmarks or `^` for
+/// history, are removed, and restrict the search instead.
 ///
 /// A provider can be anything that returns URL suggestions: Places history
 /// and bookmarks, synced tabs, search engine suggestions, and search keywords.
-pub fn search_frecent(conn: &PlacesDb, params: SearchParams) -> Result<Vec<SearchResult>> {
-    // TODO: Tokenize the query.
+pub fn search_frecent(
+    conn: &PlacesDb,
+    params: SearchParams,
+    scope: &SqlInterruptScope,
+) -> Result<Vec<SearchResult>> {
+    let tokens = Tokens::new(&params.search_string);
+    let query = tokens.query();
 
     // Try to find the first heuristic result. Desktop tries extensions,
     // search engine aliases, origins, URLs, search engine domains, and
     // preloaded sites, before trying to fall back to fixing up the URL,
     // and a search if all else fails. We only try origins and URLs for
-    // heuristic matches, since that's all we support.
+    // heuristic matches, since that's all we support. Like desktop, we
+    // only autofill single-token, unrestricted searches.
+    let origin_or_url = OriginOrUrl::new(&query);
+    let adaptive = Adaptive::with_behavior(&query, MatchBehavior::Anywhere, tokens.behavior);
+    let suggestions = Suggestions::with_behavior(&query, MatchBehavior::Anywhere, tokens.behavior);
+    let mut matchers: Vec<&dyn Matcher> = Vec::with_capacity(3);
+    if tokens.words.len() == 1 && !tokens.is_restricted() {
+        // Try to match on the origin, or the full URL.
+        matchers.push(&origin_or_url);
+    }
+    // query adaptive matches and suggestions, matching Anywhere.
+    matchers.push(&adaptive);
+    matchers.push(&suggestions);
 
-    let mut matches = match_with_limit(
-        conn,
-        &[
-            // Try to match on the origin, or the full URL.
-            &OriginOrUrl::new(&params.search_string),
-            // query adaptive matches and suggestions, matching Anywhere.
-            &Adaptive::with_behavior(
-                &params.search_string,
-                MatchBehavior::Anywhere,
-                SearchBehavior::default(),
-            ),
-            &Suggestions::with_behavior(
-                &params.search_string,
-                MatchBehavior::Anywhere,
-                SearchBehavior::default(),
-            ),
-        ],
-        params.limit,
-    )?;
+    let mut matches = match_with_limit(conn, scope, &matchers, params.limit)?;
 
     matches.sort_unstable_by(|a, b| a.url.cmp(&b.url));
     matches.dedup_by(|a, b| a.url == b.url);
@@ -89,7 +99,7 @@ pub fn match_url(conn: &PlacesDb, query: impl AsRef<str>) -> Result<Option<Strin
     let scope = conn.begin_interrupt_scope();
     let matcher = OriginOrUrl::new(query.as_ref());
     // Note: The matcher ignores the limit argument (it's a trait method)
-    let results = matcher.search(conn, 1)?;
+    let results = matcher.search(conn, &scope, 1)?;
     scope.err_if_interrupted()?;
     // Doing it like this lets us move the result, avoiding a copy (which almost
     // certainly doesn't matter but whatever)
@@ -102,24 +112,77 @@ pub fn match_url(conn: &PlacesDb, query: impl AsRef<str>) -> Result<Option<Strin
 
 fn match_with_limit(
     conn: &PlacesDb,
+    scope: &SqlInterruptScope,
     matchers: &[&dyn Matcher],
     max_results: u32,
 ) -> Result<Vec<SearchResult>> {
     let mut results = Vec::new();
     let mut rem_results = max_results;
-    let scope = conn.begin_interrupt_scope();
     for m in matchers {
         if rem_results == 0 {
             break;
         }
         scope.err_if_interrupted()?;
-        let matches = m.search(conn, rem_results)?;
+        let matches = m.search(conn, scope, rem_results)?;
         results.extend(matches);
         rem_results = rem_results.saturating_sub(results.len() as u32);
     }
+    scope.err_if_interrupted()?;
     Ok(results)
 }
 
+/// The restriction characters that desktop's UnifiedComplete recognizes, and
+/// the behavior they restrict the search to.
+const RESTRICTION_TOKENS: &[(&str, SearchBehavior)] = &[
+    ("^", SearchBehavior::HISTORY),
+    ("*", SearchBehavior::BOOKMARK),
+    ("+", SearchBehavior::TAG),
+    ("%", SearchBehavior::OPENPAGE),
+    ("~", SearchBehavior::TYPED),
+    ("$", SearchBehavior::SEARCHES),
+    ("#", SearchBehavior::TITLE),
+    ("@", SearchBehavior::URL),
+];
+
+/// A search string, split into the words to match, and the behavior from any
+/// restriction tokens.
+#[derive(Debug, Clone, PartialEq)]
+struct Tokens<'query> {
+    words: Vec<&'query str>,
+    behavior: SearchBehavior,
+}
+
+impl<'query> Tokens<'query> {
+    fn new(search_string: &'query str) -> Tokens<'query> {
+        let mut words = Vec::new();
+        let mut restrictions = SearchBehavior::empty();
+        for token in search_string.split_whitespace() {
+            match RESTRICTION_TOKENS.iter().find(|(t, _)| *t == token) {
+                Some((_, behavior)) => restrictions |= *behavior,
+                None => words.push(token),
+            }
+        }
+        // Restricting the search replaces the default behavior, since
+        // `RESTRICT` requires matches to have all of the other behaviors.
+        let behavior = if restrictions.is_empty() {
+            SearchBehavior::default()
+        } else {
+            SearchBehavior::RESTRICT | restrictions
+        };
+        Tokens { words, behavior }
+    }
+
+    fn is_restricted(&self) -> bool {
+        self.behavior.contains(SearchBehavior::RESTRICT)
+    }
+
+    /// Returns the words to match, separated by spaces, as `AUTOCOMPLETE_MATCH`
+    /// expects.
+    fn query(&self) -> String {
+        self.words.join(" ")
+    }
+}
+
 /// Records an accepted autocomplete match, recording the query string,
 /// and chosen URL for subsequent matches.
 pub fn accept_result(conn: &PlacesDb, search_string: &str, url: &Url) -> Result<()> {
@@ -227,7 +290,7 @@ impl SearchResult {
         let title = bookmark_title.or_else(|| history_title).unwrap_or_default();
 
         let tags = row.get::<_, Option<String>>("tags")?;
-        if let Some(tags) = tags {
+        if let Some(tags) = matched_tags(&search_string, tags) {
             reasons.push(MatchReason::Tags(tags));
         }
         if bookmarked {
@@ -256,7 +319,7 @@ impl SearchResult {
         let title = bookmark_title.or_else(|| history_title).unwrap_or_default();
 
         let tags = row.get::<_, Option<String>>("tags")?;
-        if let Some(tags) = tags {
+        if let Some(tags) = matched_tags(&search_string, tags) {
             reasons.push(MatchReason::Tags(tags));
         }
         let url = Url::parse(&url)?;
@@ -333,8 +396,26 @@ impl SearchResult {
     }
 }
 
+/// Returns a result's tags if any of the words in the search string match
+/// them, so that we only report tags as a match reason if they matched.
+fn matched_tags(search_string: &str, tags: Option<String>) -> Option<String> {
+    let tags = tags?;
+    if search_string
+        .split_whitespace()
+        .any(|token| find_in_string(token, &tags, false))
+    {
+        Some(tags)
+    } else {
+        None
+    }
+}
+
 impl From<SearchResult> for SearchResultMessage {
     fn from(res: SearchResult) -> Self {
+        let tags = res.reasons.iter().find_map(|r| match r {
+            MatchReason::Tags(tags) => Some(tags.clone()),
+            _ => None,
+        });
         Self {
             url: res.url.into_string(),
             title: res.title,
@@ -344,6 +425,7 @@ impl From<SearchResult> for SearchResultMessage {
                 .into_iter()
                 .map(|r| Into::<SearchResultReason>::into(r) as i32)
                 .collect::<Vec<i32>>(),
+            tags,
         }
     }
 }
@@ -362,7 +444,12 @@ impl From<MatchReason> for SearchResultReason {
 }
 
 trait Matcher {
-    fn search(&self, conn: &PlacesDb, max_results: u32) -> Result<Vec<SearchResult>>;
+    fn search(
+        &self,
+        conn: &PlacesDb,
+        scope: &SqlInterruptScope,
+        max_results: u32,
+    ) -> Result<Vec<SearchResult>>;
 }
 
 struct OriginOrUrl<'query> {
@@ -436,10 +523,16 @@ const ORIGIN_SQL: &str = "
 ";
 
 impl<'query> Matcher for OriginOrUrl<'query> {
-    fn search(&self, conn: &PlacesDb, _: u32) -> Result<Vec<SearchResult>> {
+    fn search(
+        &self,
+        conn: &PlacesDb,
+        scope: &SqlInterruptScope,
+        _: u32,
+    ) -> Result<Vec<SearchResult>> {
         Ok(if looks_like_origin(self.query) {
             query_flat_rows_and_then_named(
                 conn,
+                scope,
                 ORIGIN_SQL,
                 &[
                     (":prefix", &rusqlite::types::Null),
@@ -463,6 +556,7 @@ impl<'query> Matcher for OriginOrUrl<'query> {
             };
             query_flat_rows_and_then_named(
                 conn,
+                scope,
                 URL_SQL,
                 &[
                     (":searchString", &self.query),
@@ -499,9 +593,15 @@ impl<'query> Adaptive<'query> {
 }
 
 impl<'query> Matcher for Adaptive<'query> {
-    fn search(&self, conn: &PlacesDb, max_results: u32) -> Result<Vec<SearchResult>> {
+    fn search(
+        &self,
+        conn: &PlacesDb,
+        scope: &SqlInterruptScope,
+        max_results: u32,
+    ) -> Result<Vec<SearchResult>> {
         Ok(query_flat_rows_and_then_named(
             conn,
+            scope,
             "
             SELECT h.url as url,
                    h.title as title,
@@ -512,7 +612,10 @@ impl<'query> Matcher for Adaptive<'query> {
                           title NOT NULL
                     ORDER BY lastModified DESC
                     LIMIT 1) AS btitle,
-                   NULL AS tags,
+                   (SELECT GROUP_CONCAT(t.tag, ',')
+                    FROM moz_tags t
+                    JOIN moz_tags_relation r ON r.tag_id = t.id
+                    WHERE r.place_id = h.id) AS tags,
                    h.visit_count_local + h.visit_count_remote AS visit_count,
                    h.typed as typed,
                    h.id as id,
@@ -565,9 +668,15 @@ impl<'query> Suggestions<'query> {
 }
 
 impl<'query> Matcher for Suggestions<'query> {
-    fn search(&self, conn: &PlacesDb, max_results: u32) -> Result<Vec<SearchResult>> {
+    fn search(
+        &self,
+        conn: &PlacesDb,
+        scope: &SqlInterruptScope,
+        max_results: u32,
+    ) -> Result<Vec<SearchResult>> {
         Ok(query_flat_rows_and_then_named(
             conn,
+            scope,
             "
             SELECT h.url, h.title,
                    EXISTS(SELECT 1 FROM moz_bookmarks
@@ -577,7 +686,10 @@ impl<'query> Matcher for Suggestions<'query> {
                           title NOT NULL
                     ORDER BY lastModified DESC
                     LIMIT 1) AS btitle,
-                   NULL AS tags,
+                   (SELECT GROUP_CONCAT(t.tag, ',')
+                    FROM moz_tags t
+                    JOIN moz_tags_relation r ON r.tag_id = t.id
+                    WHERE r.place_id = h.id) AS tags,
                    h.visit_count_local + h.visit_count_remote AS visit_count,
                    h.typed as typed,
                    h.id as id,
@@ -656,6 +768,7 @@ mod tests {
                 search_string: "example.com".into(),
                 limit: 10,
             },
+            &conn.begin_interrupt_scope(),
         )
         .expect("Should search by origin");
         assert!(by_origin
@@ -671,6 +784,7 @@ mod tests {
                 search_string: "http://example.com".into(),
                 limit: 10,
             },
+            &conn.begin_interrupt_scope(),
         )
         .expect("Should search by URL without path");
         assert!(by_url_without_path
@@ -685,6 +799,7 @@ mod tests {
                 search_string: "http://example.com/1".into(),
                 limit: 10,
             },
+            &conn.begin_interrupt_scope(),
         )
         .expect("Should search by URL with path");
         assert!(by_url_with_path
@@ -701,6 +816,7 @@ mod tests {
                 search_string: "ample".into(),
                 limit: 10,
             },
+            &conn.begin_interrupt_scope(),
         )
         .expect("Should search by adaptive input history");
         assert!(by_adaptive
@@ -715,6 +831,7 @@ mod tests {
                 search_string: "example".into(),
                 limit: 1,
             },
+            &conn.begin_interrupt_scope(),
         )
         .expect("Should search until reaching limit");
         assert_eq!(
@@ -747,6 +864,7 @@ mod tests {
                 search_string: "http://exämple.com".into(),
                 limit: 10,
             },
+            &conn.begin_interrupt_scope(),
         )
         .expect("Should search by URL without path");
         assert!(by_url_without_path
@@ -762,6 +880,7 @@ mod tests {
                 search_string: "http://exämple.com/1".into(),
                 limit: 10,
             },
+            &conn.begin_interrupt_scope(),
         )
         .expect("Should search by URL with path");
         assert!(
@@ -786,6 +905,7 @@ mod tests {
                 search_string: ball_of_yarn_about_blank.into(),
                 limit: 10,
             },
+            &conn.begin_interrupt_scope(),
         )
         .unwrap();
     }
@@ -814,6 +934,167 @@ mod tests {
                 search_string: "not-a-url".into(),
                 limit: 10,
             },
+            &conn.begin_interrupt_scope(),
+        );
+    }
+
+    #[test]
+    fn test_tokens() {
+        let tokens = Tokens::new("  rust  lang ");
+        assert_eq!(tokens.words, &["rust", "lang"]);
+        assert_eq!(tokens.behavior, SearchBehavior::default());
+        assert!(!tokens.is_restricted());
+        assert_eq!(tokens.query(), "rust lang");
+
+        let tokens = Tokens::new("* rust ^ lang*");
+        assert_eq!(tokens.words, &["rust", "lang*"]);
+        assert_eq!(
+            tokens.behavior,
+            SearchBehavior::RESTRICT | SearchBehavior::BOOKMARK | SearchBehavior::HISTORY
+        );
+
+        let tokens = Tokens::new("+");
+        assert!(tokens.words.is_empty());
+        assert_eq!(
+            tokens.behavior,
+            SearchBehavior::RESTRICT | SearchBehavior::TAG
+        );
+    }
+
+    #[test]
+    fn search_tokenized() {
+        use crate::storage::bookmarks::BookmarkRootGuid;
+        use crate::storage::tags::tag_url;
+        use crate::tests::insert_json_tree;
+        use serde_json::json;
+
+        let conn = new_mem_connection();
+        for (url, title) in &[
+            ("http://example.com/rust", "Learning Rust"),
+            ("http://example.com/go", "Learning Go"),
+            ("http://example.org/crab", "Crabs"),
+        ] {
+            let visit = VisitObservation::new(Url::parse(url).unwrap())
+                .with_title(title.to_string())
+                .with_visit_type(VisitTransition::Typed)
+                .with_at(Timestamp::now());
+            apply_observation(&conn, visit).expect("Should apply visit");
+        }
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    { "title": "Learning Go", "url": "http://example.com/go" },
+                ],
+            }),
+        );
+        tag_url(
+            &conn,
+            &Url::parse("http://example.org/crab").unwrap(),
+            "rustacean",
+        )
+        .unwrap();
+
+        let search = |search_string: &str| {
+            let mut urls = search_frecent(
+                &conn,
+                SearchParams {
+                    search_string: search_string.into(),
+                    limit: 10,
+                },
+                &conn.begin_interrupt_scope(),
+            )
+            .expect("Should search")
+            .into_iter()
+            .map(|result| result.url.into_string())
+            .collect::<Vec<_>>();
+            urls.sort();
+            urls
+        };
+
+        // Every token must match, in any order.
+        assert_eq!(search("rust learning"), &["http://example.com/rust"]);
+        assert_eq!(
+            search("learning"),
+            &["http://example.com/go", "http://example.com/rust"]
         );
+        assert!(search("learning crabs").is_empty());
+
+        // Tokens can match tags, too, and we report the tags as the reason.
+        assert_eq!(
+            search("rust"),
+            &["http://example.com/rust", "http://example.org/crab"]
+        );
+        let results = search_frecent(
+            &conn,
+            SearchParams {
+                search_string: "crab rust".into(),
+                limit: 10,
+            },
+            &conn.begin_interrupt_scope(),
+        )
+        .expect("Should search by tag");
+        assert_eq!(results.len(), 1);
+        assert!(results[0]
+            .reasons
+            .contains(&MatchReason::Tags("rustacean".into())));
+        let message = SearchResultMessage::from(results[0].clone());
+        assert_eq!(message.tags, Some("rustacean".to_string()));
+        assert!(message.reasons.contains(&(SearchResultReason::Tag as i32)));
+        let results = search_frecent(
+            &conn,
+            SearchParams {
+                search_string: "crab".into(),
+                limit: 10,
+            },
+            &conn.begin_interrupt_scope(),
+        )
+        .expect("Should search by title");
+        assert!(results
+            .iter()
+            .all(|r| r.reasons.iter().all(|reason| match reason {
+                MatchReason::Tags(_) => false,
+                _ => true,
+            })));
+
+        // Restriction tokens aren't matched, but filter the results.
+        assert_eq!(search("learning *"), &["http://example.com/go"]);
+        assert_eq!(search("+ rust"), &["http://example.org/crab"]);
+        assert_eq!(search("* +"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn search_interrupted() {
+        use crate::error::ErrorKind;
+
+        let conn = new_mem_connection();
+        let scope = conn.begin_interrupt_scope();
+        conn.new_interrupt_handle().interrupt();
+        match search_frecent(
+            &conn,
+            SearchParams {
+                search_string: "example".into(),
+                limit: 10,
+            },
+            &scope,
+        )
+        .expect_err("Should fail an interrupted search")
+        .kind()
+        {
+            ErrorKind::InterruptedError(_) => {}
+            e => panic!("Wanted interrupted error; got {:?}", e),
+        }
+
+        // A new scope isn't affected by the earlier interruption.
+        search_frecent(
+            &conn,
+            SearchParams {
+                search_string: "example".into(),
+                limit: 10,
+            },
+            &conn.begin_interrupt_scope(),
+        )
+        .expect("Should search in a new scope");
     }
 }
@@ -4,6 +4,7 @@
 
 pub mod history;
 pub mod matcher;
+pub mod observer;
 pub mod places_api;
 use crate::db::PlacesDb;
 use crate::error::Result;
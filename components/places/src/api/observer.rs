@@ -0,0 +1,172 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Bookmark observers, which are told about changes to the bookmarks tree so
+//! that apps can update their UI without re-fetching the whole tree.
+//!
+//! Observers are registered with a `PlacesApi`, and shared by all of its
+//! connections. Events are queued while a change is being made, and only
+//! delivered once its transaction commits, on the thread that made the
+//! change.
+
+use crate::msg_types;
+use crate::types::BookmarkType;
+use std::fmt;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use sync_guid::Guid as SyncGuid;
+
+/// A change to the bookmarks tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookmarkEvent {
+    ItemAdded {
+        guid: SyncGuid,
+        item_type: BookmarkType,
+        parent_guid: SyncGuid,
+        position: u32,
+    },
+    /// An item was removed. Its descendants are removed along with it, but
+    /// we don't send events for them.
+    ItemRemoved {
+        guid: SyncGuid,
+        item_type: BookmarkType,
+        parent_guid: SyncGuid,
+        position: u32,
+    },
+    ItemMoved {
+        guid: SyncGuid,
+        item_type: BookmarkType,
+        old_parent_guid: SyncGuid,
+        old_position: u32,
+        new_parent_guid: SyncGuid,
+        new_position: u32,
+    },
+    /// The title, URL or keyword of an item changed.
+    ItemChanged {
+        guid: SyncGuid,
+        item_type: BookmarkType,
+    },
+    /// A sync changed `num_changes` local items. Syncs can change many items
+    /// at once, so we don't send events for each one; observers should
+    /// re-fetch the parts of the tree they're interested in instead.
+    SyncApplied { num_changes: u32 },
+}
+
+pub trait BookmarkObserver: Send + Sync {
+    fn on_bookmark_event(&self, event: &BookmarkEvent);
+}
+
+/// The observers for a `PlacesApi`.
+#[derive(Default)]
+pub struct BookmarkObservers {
+    next_id: AtomicUsize,
+    observers: Mutex<Vec<(usize, Arc<dyn BookmarkObserver>)>>,
+}
+
+impl BookmarkObservers {
+    /// Registers an observer, returning an ID which can be passed to
+    /// `unregister`.
+    pub fn register(&self, observer: Arc<dyn BookmarkObserver>) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.observers.lock().unwrap().push((id, observer));
+        id
+    }
+
+    /// Unregisters an observer. Returns false if no observer had the ID.
+    pub fn unregister(&self, id: usize) -> bool {
+        let mut observers = self.observers.lock().unwrap();
+        let len = observers.len();
+        observers.retain(|(observer_id, _)| *observer_id != id);
+        observers.len() != len
+    }
+
+    pub(crate) fn notify(&self, events: &[BookmarkEvent]) {
+        if events.is_empty() {
+            return;
+        }
+        // Don't hold the lock while calling observers, so that they can
+        // unregister themselves.
+        let observers: Vec<Arc<dyn BookmarkObserver>> = self
+            .observers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, observer)| observer.clone())
+            .collect();
+        for event in events {
+            for observer in &observers {
+                observer.on_bookmark_event(event);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for BookmarkObservers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BookmarkObservers")
+            .field("count", &self.observers.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl From<&BookmarkEvent> for msg_types::BookmarkEvent {
+    fn from(event: &BookmarkEvent) -> Self {
+        use msg_types::BookmarkEventType;
+        let mut msg = msg_types::BookmarkEvent::default();
+        match event {
+            BookmarkEvent::ItemAdded {
+                guid,
+                item_type,
+                parent_guid,
+                position,
+            } => {
+                msg.event_type = BookmarkEventType::ItemAdded as i32;
+                msg.guid = Some(guid.to_string());
+                msg.node_type = Some(*item_type as i32);
+                msg.parent_guid = Some(parent_guid.to_string());
+                msg.position = Some(*position);
+            }
+            BookmarkEvent::ItemRemoved {
+                guid,
+                item_type,
+                parent_guid,
+                position,
+            } => {
+                msg.event_type = BookmarkEventType::ItemRemoved as i32;
+                msg.guid = Some(guid.to_string());
+                msg.node_type = Some(*item_type as i32);
+                msg.parent_guid = Some(parent_guid.to_string());
+                msg.position = Some(*position);
+            }
+            BookmarkEvent::ItemMoved {
+                guid,
+                item_type,
+                old_parent_guid,
+                old_position,
+                new_parent_guid,
+                new_position,
+            } => {
+                msg.event_type = BookmarkEventType::ItemMoved as i32;
+                msg.guid = Some(guid.to_string());
+                msg.node_type = Some(*item_type as i32);
+                msg.parent_guid = Some(new_parent_guid.to_string());
+                msg.position = Some(*new_position);
+                msg.old_parent_guid = Some(old_parent_guid.to_string());
+                msg.old_position = Some(*old_position);
+            }
+            BookmarkEvent::ItemChanged { guid, item_type } => {
+                msg.event_type = BookmarkEventType::ItemChanged as i32;
+                msg.guid = Some(guid.to_string());
+                msg.node_type = Some(*item_type as i32);
+            }
+            BookmarkEvent::SyncApplied { num_changes } => {
+                msg.event_type = BookmarkEventType::SyncApplied as i32;
+                msg.num_changes = Some(*num_changes);
+            }
+        }
+        msg
+    }
+}
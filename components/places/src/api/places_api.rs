@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use crate::api::observer::{BookmarkObserver, BookmarkObservers};
 use crate::bookmark_sync::store::BookmarksStore;
 use crate::db::db::PlacesDb;
 use crate::error::*;
@@ -81,6 +82,7 @@ pub struct PlacesApi {
     write_connection: Mutex<Option<PlacesDb>>,
     sync_state: Mutex<Option<SyncState>>,
     coop_tx_lock: Arc<Mutex<()>>,
+    bookmark_observers: Arc<BookmarkObservers>,
     sync_conn_active: AtomicBool,
     id: usize,
 }
@@ -110,11 +112,13 @@ impl PlacesApi {
                 // We always create a new read-write connection for an initial open so
                 // we can create the schema and/or do version upgrades.
                 let coop_tx_lock = Arc::new(Mutex::new(()));
+                let bookmark_observers = Arc::new(BookmarkObservers::default());
                 match PlacesDb::open(
                     &db_name,
                     ConnectionType::ReadWrite,
                     id,
                     coop_tx_lock.clone(),
+                    bookmark_observers.clone(),
                 ) {
                     Ok(connection) => {
                         let new = PlacesApi {
@@ -124,6 +128,7 @@ impl PlacesApi {
                             sync_conn_active: AtomicBool::new(false),
                             id,
                             coop_tx_lock,
+                            bookmark_observers,
                         };
                         let arc = Arc::new(new);
                         target.insert(db_name, Arc::downgrade(&arc));
@@ -160,6 +165,7 @@ impl PlacesApi {
                     ConnectionType::ReadOnly,
                     self.id,
                     self.coop_tx_lock.clone(),
+                    self.bookmark_observers.clone(),
                 )
             }
             ConnectionType::ReadWrite => {
@@ -188,6 +194,7 @@ impl PlacesApi {
                 ConnectionType::Sync,
                 self.id,
                 self.coop_tx_lock.clone(),
+                self.bookmark_observers.clone(),
             )?;
             Ok(SyncConn {
                 db,
@@ -196,6 +203,19 @@ impl PlacesApi {
         }
     }
 
+    /// Registers an observer for changes to bookmarks made by any of this
+    /// API's connections, including syncs. Returns an ID which can be passed
+    /// to `unregister_bookmark_observer`.
+    pub fn register_bookmark_observer(&self, observer: Arc<dyn BookmarkObserver>) -> usize {
+        self.bookmark_observers.register(observer)
+    }
+
+    /// Unregisters a bookmark observer. Returns false if no observer had the
+    /// ID.
+    pub fn unregister_bookmark_observer(&self, id: usize) -> bool {
+        self.bookmark_observers.unregister(id)
+    }
+
     /// Close a connection to the database. If the connection is the write
     /// connection, you can re-fetch it using open_connection.
     pub fn close_connection(&self, connection: PlacesDb) -> Result<()> {
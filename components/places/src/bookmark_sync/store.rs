@@ -8,6 +8,7 @@ use super::record::{
     SeparatorRecord,
 };
 use super::{SyncedBookmarkKind, SyncedBookmarkValidity};
+use crate::api::observer::BookmarkEvent;
 use crate::api::places_api::ConnectionType;
 use crate::db::PlacesDb;
 use crate::error::*;
@@ -1526,8 +1527,20 @@ impl<'a> dogear::Store for Merger<'a> {
             .stage_items_to_upload(&ops.upload_items, &ops.upload_tombstones)?;
 
         self.store.db.execute_batch("DELETE FROM itemsToApply;")?;
+
+        let num_changes = ops.apply_remote_items.len()
+            + ops.apply_new_local_structure.len()
+            + ops.delete_local_items.len();
+        if num_changes > 0 {
+            self.store
+                .db
+                .queue_bookmark_event(BookmarkEvent::SyncApplied {
+                    num_changes: num_changes as u32,
+                });
+        }
         if let Some(tx) = tx {
             tx.commit()?;
+            self.store.db.notify_bookmark_observers();
         }
         Ok(())
     }
@@ -2925,7 +2938,9 @@ mod tests {
 
     #[test]
     fn test_keywords() -> Result<()> {
-        use crate::storage::bookmarks::bookmarks_get_url_for_keyword;
+        use crate::storage::bookmarks::{
+            bookmarks_get_url_for_keyword, bookmarks_remove_keyword, bookmarks_set_keyword,
+        };
 
         let api = new_mem_api();
         let writer = api.open_connection(ConnectionType::ReadWrite)?;
@@ -3039,6 +3054,45 @@ mod tests {
             _ => panic!("Wanted constraint violation error; got {:?}", err),
         }
 
+        store
+            .sync_finished(ServerTimestamp(1000), vec!["bookmarkAAAA".into()])
+            .expect("Should push synced changes back to the store");
+
+        // Changing the keyword locally should reupload the bookmark with the
+        // new keyword...
+        bookmarks_set_keyword(&writer, &Url::parse("http://example.com/a/%s")?, " B ")?;
+        let outgoing = store
+            .apply_incoming(
+                vec![IncomingChangeset::new(
+                    store.collection_name(),
+                    ServerTimestamp(2000),
+                )],
+                &mut telemetry::Engine::new("bookmarks"),
+            )
+            .expect("Should fetch outgoing records after changing keyword");
+        assert_eq!(outgoing.changes.len(), 1);
+        assert_eq!(outgoing.changes[0].id, "bookmarkAAAA");
+        assert_eq!(outgoing.changes[0].data["keyword"], "b");
+
+        store
+            .sync_finished(ServerTimestamp(2000), vec!["bookmarkAAAA".into()])
+            .expect("Should push synced changes back to the store");
+
+        // ...and removing it should reupload the bookmark without one.
+        assert!(bookmarks_remove_keyword(&writer, "b")?);
+        let outgoing = store
+            .apply_incoming(
+                vec![IncomingChangeset::new(
+                    store.collection_name(),
+                    ServerTimestamp(3000),
+                )],
+                &mut telemetry::Engine::new("bookmarks"),
+            )
+            .expect("Should fetch outgoing records after removing keyword");
+        assert_eq!(outgoing.changes.len(), 1);
+        assert_eq!(outgoing.changes[0].id, "bookmarkAAAA");
+        assert!(outgoing.changes[0].data.get("keyword").is_none());
+
         Ok(())
     }
 
@@ -3046,8 +3100,8 @@ mod tests {
     fn test_apply_complex_bookmark_keywords() -> Result<()> {
         use crate::storage::bookmarks::bookmarks_get_url_for_keyword;
 
-        // We don't provide an API for setting keywords locally, but we'll
-        // still round-trip and fix up keywords on the server.
+        // We'll round-trip and fix up keywords on the server, even if they
+        // weren't set locally.
 
         let api = new_mem_api();
         let writer = api.open_connection(ConnectionType::ReadWrite)?;
@@ -3,10 +3,12 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use super::schema;
+use crate::api::observer::{BookmarkEvent, BookmarkObservers};
 use crate::api::places_api::ConnectionType;
 use crate::error::*;
 use rusqlite::Connection;
 use sql_support::{ConnExt, SqlInterruptHandle, SqlInterruptScope};
+use std::cell::RefCell;
 use std::ops::Deref;
 use std::path::Path;
 
@@ -21,6 +23,10 @@ pub struct PlacesDb {
     interrupt_counter: Arc<AtomicUsize>,
     api_id: usize,
     pub(super) coop_tx_lock: Arc<Mutex<()>>,
+    bookmark_observers: Arc<BookmarkObservers>,
+    // Events for changes in the current transaction, which are delivered
+    // once it commits.
+    pending_bookmark_events: RefCell<Vec<BookmarkEvent>>,
 }
 
 impl PlacesDb {
@@ -29,6 +35,7 @@ impl PlacesDb {
         conn_type: ConnectionType,
         api_id: usize,
         coop_tx_lock: Arc<Mutex<()>>,
+        bookmark_observers: Arc<BookmarkObservers>,
     ) -> Result<Self> {
         let initial_pragmas = "
             -- The value we use was taken from Desktop Firefox, and seems necessary to
@@ -76,6 +83,8 @@ impl PlacesDb {
             api_id,
             interrupt_counter: Arc::new(AtomicUsize::new(0)),
             coop_tx_lock,
+            bookmark_observers,
+            pending_bookmark_events: RefCell::new(Vec::new()),
         };
         match res.conn_type() {
             // For read-only connections, we can avoid opening a transaction,
@@ -98,12 +107,14 @@ impl PlacesDb {
         conn_type: ConnectionType,
         api_id: usize,
         coop_tx_lock: Arc<Mutex<()>>,
+        bookmark_observers: Arc<BookmarkObservers>,
     ) -> Result<Self> {
         Ok(Self::with_connection(
             Connection::open_with_flags(path, conn_type.rusqlite_flags())?,
             conn_type,
             api_id,
             coop_tx_lock,
+            bookmark_observers,
         )?)
     }
 
@@ -116,6 +127,7 @@ impl PlacesDb {
             conn_ty,
             0,
             Arc::new(Mutex::new(())),
+            Arc::new(BookmarkObservers::default()),
         )?)
     }
 
@@ -140,6 +152,29 @@ impl PlacesDb {
     pub fn api_id(&self) -> usize {
         self.api_id
     }
+
+    #[inline]
+    pub fn bookmark_observers(&self) -> &BookmarkObservers {
+        &self.bookmark_observers
+    }
+
+    /// Queues an event for a change made in the current transaction. Queued
+    /// events are delivered by `notify_bookmark_observers`, and discarded
+    /// when a new transaction begins.
+    pub(crate) fn queue_bookmark_event(&self, event: BookmarkEvent) {
+        self.pending_bookmark_events.borrow_mut().push(event);
+    }
+
+    /// Delivers queued events to observers. Must only be called after the
+    /// transaction which queued them commits.
+    pub(crate) fn notify_bookmark_observers(&self) {
+        let events = self.pending_bookmark_events.replace(Vec::new());
+        self.bookmark_observers.notify(&events);
+    }
+
+    pub(crate) fn discard_bookmark_events(&self) {
+        self.pending_bookmark_events.borrow_mut().clear();
+    }
 }
 
 impl Drop for PlacesDb {
@@ -18,7 +18,7 @@ use crate::types::SyncStatus;
 use rusqlite::NO_PARAMS;
 use sql_support::ConnExt;
 
-const VERSION: i64 = 11;
+const VERSION: i64 = 16;
 
 // Shared schema and temp tables for the read-write and Sync connections.
 const CREATE_SHARED_SCHEMA_SQL: &str = include_str!("../../sql/create_shared_schema.sql");
@@ -255,6 +255,16 @@ fn upgrade(db: &PlacesDb, from: i64) -> Result<()> {
         ],
         || Ok(()),
     )?;
+    // New favicon tables.
+    migration(db, 11, 12, &[CREATE_SHARED_SCHEMA_SQL], || Ok(()))?;
+    // New history metadata tables.
+    migration(db, 12, 13, &[CREATE_SHARED_SCHEMA_SQL], || Ok(()))?;
+    // New top sites exclusions table.
+    migration(db, 13, 14, &[CREATE_SHARED_SCHEMA_SQL], || Ok(()))?;
+    // New undo journal table.
+    migration(db, 14, 15, &[CREATE_SHARED_SCHEMA_SQL], || Ok(()))?;
+    // New full-text search index.
+    migration(db, 15, 16, &[CREATE_SHARED_SCHEMA_SQL], || Ok(()))?;
     // Add more migrations here...
 
     if get_current_schema_version(db)? == VERSION {
@@ -747,8 +757,14 @@ mod tests {
         // first one that we support for migrations. We don't actually roll
         // back any of the schema changes; we just want to make sure that
         // running through all our migration routines doesn't trigger errors.
-        let downgrade = PlacesDb::open(path, ConnectionType::ReadWrite, 0, Default::default())
-            .expect("Should open first in-memory database with shared cache");
+        let downgrade = PlacesDb::open(
+            path,
+            ConnectionType::ReadWrite,
+            0,
+            Default::default(),
+            Default::default(),
+        )
+        .expect("Should open first in-memory database with shared cache");
         downgrade.execute_batch("PRAGMA user_version = 2")?;
         assert_eq!(
             get_current_schema_version(&downgrade)?,
@@ -758,8 +774,14 @@ mod tests {
 
         // Now open a second connection to the same named in-memory database.
         // This should run through all our migrations.
-        let upgrade = PlacesDb::open(path, ConnectionType::ReadWrite, 0, Default::default())
-            .expect("Should open second in-memory database with shared cache");
+        let upgrade = PlacesDb::open(
+            path,
+            ConnectionType::ReadWrite,
+            0,
+            Default::default(),
+            Default::default(),
+        )
+        .expect("Should open second in-memory database with shared cache");
         assert_eq!(
             get_current_schema_version(&upgrade)?,
             VERSION,
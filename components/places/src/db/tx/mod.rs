@@ -88,6 +88,9 @@ impl super::PlacesDb {
     /// - for ReadWrite connections, begins a normal coop transaction
     /// - for ReadOnly connections, begins an unchecked transaction.
     pub fn begin_transaction(&self) -> Result<PlacesTransaction<'_>> {
+        // Events from a transaction that was rolled back, or which were
+        // queued outside of a transaction, must not be delivered.
+        self.discard_bookmark_events();
         Ok(PlacesTransaction(match self.conn_type() {
             ConnectionType::Sync => {
                 PlacesTransactionRepr::ChunkedWrite(self.chunked_coop_trransaction()?)
@@ -78,6 +78,9 @@ pub enum ErrorKind {
 
     #[fail(display = "Database version {} is not supported", _0)]
     UnsupportedDatabaseVersion(i64),
+
+    #[fail(display = "The history search index isn't enabled")]
+    HistorySearchIndexDisabled,
 }
 
 error_support::define_error! {
@@ -131,6 +134,10 @@ pub enum InvalidPlaceInfo {
     // Like Urls, a tag is considered private info, so the value isn't in the error.
     #[fail(display = "The tag value is invalid")]
     InvalidTag,
+
+    // Keywords are also private, for the same reason.
+    #[fail(display = "The keyword is invalid")]
+    InvalidKeyword,
     #[fail(
         display = "Cannot change the '{}' property of a bookmark of type {:?}",
         _0, _1
@@ -139,6 +146,13 @@ pub enum InvalidPlaceInfo {
 
     #[fail(display = "Cannot update the bookmark root {:?}", _0)]
     CannotUpdateRoot(BookmarkRootGuid),
+
+    #[fail(display = "No such undo entry: {}", _0)]
+    NoSuchUndoEntry(i64),
+
+    // Undoing a bookmark deletion would restore an item whose GUID is in use.
+    #[fail(display = "Can't undo: an item with guid {} already exists", _0)]
+    UndoConflict(String),
 }
 
 // Error types used when we can't continue due to corruption.
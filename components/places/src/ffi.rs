@@ -53,6 +53,12 @@ pub mod error_codes {
     /// `CannotUpdateRoot`: Attempt to modify a root in a way that is illegal, e.g. adding a child
     /// to root________, updating properties of a root, deleting a root, etc.
     pub const INVALID_PLACE_INFO_CANNOT_UPDATE_ROOT: i32 = 64 + 4;
+
+    /// `InvalidKeyword`: The provided keyword is empty, or contains whitespace.
+    pub const INVALID_PLACE_INFO_INVALID_KEYWORD: i32 = 64 + 5;
+
+    /// `InvalidTag`: The provided tag is empty, or too long.
+    pub const INVALID_PLACE_INFO_INVALID_TAG: i32 = 64 + 6;
 }
 
 fn get_code(err: &Error) -> ErrorCode {
@@ -71,6 +77,8 @@ fn get_code(err: &Error) -> ErrorCode {
                 InvalidPlaceInfo::CannotUpdateRoot(..) => {
                     error_codes::INVALID_PLACE_INFO_CANNOT_UPDATE_ROOT
                 }
+                InvalidPlaceInfo::InvalidKeyword => error_codes::INVALID_PLACE_INFO_INVALID_KEYWORD,
+                InvalidPlaceInfo::InvalidTag => error_codes::INVALID_PLACE_INFO_INVALID_TAG,
                 _ => error_codes::UNEXPECTED,
             };
             ErrorCode::new(code)
@@ -142,6 +150,10 @@ implement_into_ffi_by_protobuf!(msg_types::HistoryVisitInfos);
 implement_into_ffi_by_protobuf!(msg_types::HistoryVisitInfosWithBound);
 implement_into_ffi_by_protobuf!(msg_types::BookmarkNode);
 implement_into_ffi_by_protobuf!(msg_types::BookmarkNodeList);
+implement_into_ffi_by_protobuf!(msg_types::BookmarkKeywordList);
+implement_into_ffi_by_protobuf!(msg_types::TagInfoList);
+implement_into_ffi_by_protobuf!(msg_types::StringList);
+implement_into_ffi_by_protobuf!(msg_types::BookmarkEvent);
 implement_into_ffi_by_delegation!(
     crate::storage::bookmarks::PublicNode,
     msg_types::BookmarkNode
@@ -792,6 +792,7 @@ mod tests {
                 search_string: "http://example.com".into(),
                 limit: 2,
             },
+            &db.begin_interrupt_scope(),
         )?;
         assert_eq!(found.len(), 1);
         let result = found.into_iter().next().unwrap();
@@ -0,0 +1,607 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Imports bookmarks from the Netscape `bookmarks.html` format, as exported by
+//! desktop Firefox, other browsers, and `export_bookmarks_html`.
+//!
+//! The format is "HTML", in the sense that every browser writes something
+//! slightly different and nobody closes their `<DT>` and `<p>` tags, so we
+//! don't use a real HTML parser. Instead, we tokenize the document and only
+//! look at the tags we care about:
+//!
+//! - `<A>` is a bookmark, with `HREF`, `ADD_DATE`, `LAST_MODIFIED`,
+//!   `SHORTCUTURL` (the keyword) and `TAGS` attributes.
+//! - `<H3>` is a folder, whose children are in the `<DL>` that follows it.
+//!   Folders marked with one of the `SPECIAL_FOLDERS` attributes are merged
+//!   into that root.
+//! - `<HR>` is a separator.
+//!
+//! Everything at the top level goes into the menu, like on desktop.
+//! Livemarks and bookmarks with URLs we can't store are skipped.
+
+use crate::db::PlacesDb;
+use crate::error::*;
+use crate::storage::bookmarks::{
+    html::SPECIAL_FOLDERS, insert_tree_in_tx, set_keyword_in_tx, validate_keyword, BookmarkNode,
+    BookmarkRootGuid, BookmarkTreeNode, FolderNode, SeparatorNode, USER_CONTENT_ROOTS,
+};
+use crate::storage::tags::{tag_url_in_tx, validate_tag};
+use crate::storage::{delete_pending_temp_tables, URL_LENGTH_MAX};
+use crate::types::Timestamp;
+use serde_derive::*;
+use std::collections::HashMap;
+use url::Url;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct BookmarksHtmlImportResult {
+    pub num_total: u32,
+    pub num_succeeded: u32,
+    pub num_failed: u32,
+}
+
+/// Imports the bookmarks in `html`, appending them to the existing roots.
+/// Everything is inserted in a single transaction, with `insert_tree`, so
+/// the new items are uploaded on the next sync.
+pub fn import_bookmarks_html(db: &PlacesDb, html: &str) -> Result<BookmarksHtmlImportResult> {
+    let parsed = parse(html);
+    let tx = db.begin_transaction()?;
+    for (root, children) in parsed.roots {
+        if children.is_empty() {
+            continue;
+        }
+        let tree = FolderNode {
+            guid: Some(root.as_guid()),
+            children,
+            ..Default::default()
+        };
+        insert_tree_in_tx(db, &tree)?;
+    }
+    for (url, keyword) in &parsed.keywords {
+        // This replaces any existing keyword for the URL, and removes the
+        // keyword from any other URL.
+        set_keyword_in_tx(db, url, keyword)?;
+    }
+    for (url, tag) in &parsed.tags {
+        tag_url_in_tx(db, url, tag)?;
+    }
+    delete_pending_temp_tables(db)?;
+    tx.commit()?;
+    db.notify_bookmark_observers();
+    Ok(BookmarksHtmlImportResult {
+        num_total: parsed.num_succeeded + parsed.num_failed,
+        num_succeeded: parsed.num_succeeded,
+        num_failed: parsed.num_failed,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Open(&'a str, HashMap<String, String>),
+    Close(&'a str),
+    Text(&'a str),
+}
+
+/// Splits a document into tags and the text between them. Attribute names are
+/// lowercased and their values unescaped, but text is left as-is.
+struct Tokenizer<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+            if self.rest.starts_with("<!--") {
+                self.rest = self.rest.find("-->").map_or("", |i| &self.rest[i + 3..]);
+                continue;
+            }
+            if !self.rest.starts_with('<') {
+                let end = self.rest.find('<').unwrap_or_else(|| self.rest.len());
+                let text = &self.rest[..end];
+                self.rest = &self.rest[end..];
+                return Some(Token::Text(text));
+            }
+            let end = tag_end(self.rest);
+            let inner = &self.rest[1..end];
+            let token = parse_tag(inner);
+            if token.is_none() && !inner.starts_with('!') && !inner.starts_with('?') {
+                // Not a tag at all, just a stray `<`.
+                self.rest = &self.rest[1..];
+                return Some(Token::Text("<"));
+            }
+            self.rest = self.rest.get(end + 1..).unwrap_or("");
+            if token.is_some() {
+                return token;
+            }
+        }
+    }
+}
+
+/// Returns the index of the `>` which ends the tag at the start of `s`,
+/// skipping any inside quoted attribute values, or the length of `s` if it's
+/// unterminated.
+fn tag_end(s: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return i,
+            _ => {}
+        }
+    }
+    // An unbalanced quote; fall back to the first `>`.
+    s.find('>').unwrap_or_else(|| s.len())
+}
+
+fn parse_tag(inner: &str) -> Option<Token<'_>> {
+    let (closing, inner) = if inner.starts_with('/') {
+        (true, &inner[1..])
+    } else {
+        (false, inner)
+    };
+    let name_len = inner
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or_else(|| inner.len());
+    if name_len == 0 || !inner.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name = &inner[..name_len];
+    if closing {
+        return Some(Token::Close(name));
+    }
+    let mut attrs = HashMap::new();
+    let mut rest = &inner[name_len..];
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            break;
+        }
+        let attr_len = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or_else(|| rest.len());
+        let attr_name = rest[..attr_len].to_ascii_lowercase();
+        rest = rest[attr_len..].trim_start();
+        let value = if rest.starts_with('=') {
+            rest = rest[1..].trim_start();
+            let (value, after) = match rest.chars().next() {
+                Some(q) if q == '"' || q == '\'' => {
+                    let value_end = rest[1..].find(q).map_or(rest.len(), |i| i + 1);
+                    (&rest[1..value_end], rest.get(value_end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let value_end = rest.find(char::is_whitespace).unwrap_or_else(|| rest.len());
+                    (&rest[..value_end], &rest[value_end..])
+                }
+            };
+            rest = after;
+            unescape(value)
+        } else {
+            String::new()
+        };
+        if attr_len == 0 {
+            // Something like a stray `=`, which we already skipped.
+            continue;
+        }
+        attrs.entry(attr_name).or_insert(value);
+    }
+    Some(Token::Open(name, attrs))
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                e if e.starts_with("#x") || e.starts_with("#X") => u32::from_str_radix(&e[2..], 16)
+                    .ok()
+                    .and_then(std::char::from_u32),
+                e if e.starts_with('#') => e[1..].parse().ok().and_then(std::char::from_u32),
+                _ => None,
+            };
+            c.map(|c| (c, end))
+        });
+        match entity {
+            Some((c, end)) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Parses an `ADD_DATE` or `LAST_MODIFIED` attribute. These should be in
+/// seconds, but some exporters use milliseconds or microseconds, so we take
+/// the first interpretation that's a sane date.
+fn parse_date(value: Option<&String>) -> Option<Timestamp> {
+    let value = value?.trim().parse::<u64>().ok()?;
+    let now = Timestamp::now();
+    [value.saturating_mul(1000), value, value / 1000]
+        .iter()
+        .map(|ms| Timestamp(*ms))
+        .find(|ts| Timestamp::EARLIEST <= *ts && *ts <= now)
+}
+
+enum FrameKind {
+    /// The children are appended to a root.
+    Root(BookmarkRootGuid),
+    /// The children belong to this folder.
+    Folder(FolderNode),
+    /// A `<DL>` without a heading, whose children belong to the parent.
+    Parent,
+}
+
+struct Frame {
+    kind: FrameKind,
+    children: Vec<BookmarkTreeNode>,
+}
+
+enum OpenItem {
+    Bookmark(HashMap<String, String>),
+    Folder(HashMap<String, String>),
+}
+
+#[derive(Default)]
+struct Parser {
+    roots: Vec<(BookmarkRootGuid, Vec<BookmarkTreeNode>)>,
+    keywords: Vec<(Url, String)>,
+    tags: Vec<(Url, String)>,
+    num_succeeded: u32,
+    num_failed: u32,
+    stack: Vec<Frame>,
+    // A folder heading we've seen, but not its `<DL>` yet.
+    pending: Option<FrameKind>,
+    // The `<A>` or `<H3>` we're in, and its text so far.
+    open: Option<OpenItem>,
+    text: String,
+}
+
+fn parse(html: &str) -> Parser {
+    let mut parser = Parser {
+        roots: USER_CONTENT_ROOTS.iter().map(|r| (*r, vec![])).collect(),
+        ..Parser::default()
+    };
+    for token in (Tokenizer { rest: html }) {
+        match token {
+            Token::Text(text) => {
+                if parser.open.is_some() {
+                    parser.text.push_str(text);
+                }
+            }
+            Token::Open(name, attrs) => match name.to_ascii_lowercase().as_str() {
+                "a" => {
+                    parser.finish_item();
+                    parser.flush_pending();
+                    parser.open = Some(OpenItem::Bookmark(attrs));
+                }
+                "h3" => {
+                    parser.finish_item();
+                    parser.flush_pending();
+                    parser.open = Some(OpenItem::Folder(attrs));
+                }
+                "hr" => {
+                    parser.finish_item();
+                    parser.flush_pending();
+                    parser.num_succeeded += 1;
+                    parser.append(SeparatorNode::default().into());
+                }
+                "dl" => {
+                    parser.finish_item();
+                    let kind = match parser.pending.take() {
+                        Some(kind) => kind,
+                        None if parser.stack.is_empty() => FrameKind::Root(BookmarkRootGuid::Menu),
+                        None => FrameKind::Parent,
+                    };
+                    parser.stack.push(Frame {
+                        kind,
+                        children: vec![],
+                    });
+                }
+                // Folders can have a `<DD>` description between their
+                // heading and `<DL>`, so these don't flush a pending folder.
+                "dt" | "dd" => parser.finish_item(),
+                _ => {}
+            },
+            Token::Close(name) => match name.to_ascii_lowercase().as_str() {
+                "a" | "h3" => parser.finish_item(),
+                "dl" => {
+                    parser.finish_item();
+                    parser.pop_frame();
+                }
+                _ => {}
+            },
+        }
+    }
+    parser.finish_item();
+    parser.flush_pending();
+    while !parser.stack.is_empty() {
+        parser.pop_frame();
+    }
+    parser
+}
+
+impl Parser {
+    fn append(&mut self, node: BookmarkTreeNode) {
+        match self.stack.last_mut() {
+            Some(frame) => frame.children.push(node),
+            None => self.append_to_root(BookmarkRootGuid::Menu, vec![node]),
+        }
+    }
+
+    fn append_to_root(&mut self, root: BookmarkRootGuid, nodes: Vec<BookmarkTreeNode>) {
+        if let Some((_, children)) = self.roots.iter_mut().find(|(r, _)| *r == root) {
+            children.extend(nodes);
+        }
+    }
+
+    /// Adds a folder whose heading wasn't followed by a `<DL>`. This is
+    /// called when the next item starts, or its parent ends.
+    fn flush_pending(&mut self) {
+        if let Some(FrameKind::Folder(folder)) = self.pending.take() {
+            self.append(folder.into());
+        }
+    }
+
+    fn pop_frame(&mut self) {
+        self.flush_pending();
+        let frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        match frame.kind {
+            FrameKind::Root(root) => self.append_to_root(root, frame.children),
+            FrameKind::Folder(mut folder) => {
+                folder.children = frame.children;
+                self.append(folder.into());
+            }
+            FrameKind::Parent => {
+                for child in frame.children {
+                    self.append(child);
+                }
+            }
+        }
+    }
+
+    /// Finishes the `<A>` or `<H3>` we're in, if any. This is called when
+    /// it's closed, and when anything else starts, since not every exporter
+    /// closes them.
+    fn finish_item(&mut self) {
+        let text = std::mem::take(&mut self.text);
+        let title = Some(unescape(text.trim())).filter(|t| !t.is_empty());
+        match self.open.take() {
+            Some(OpenItem::Bookmark(attrs)) => self.finish_bookmark(attrs, title),
+            Some(OpenItem::Folder(attrs)) => {
+                self.flush_pending();
+                let special = SPECIAL_FOLDERS
+                    .iter()
+                    .find(|(_, attr, _)| attrs.contains_key(&attr.to_ascii_lowercase()));
+                self.pending = Some(match special {
+                    Some((root, _, _)) => FrameKind::Root(*root),
+                    None => {
+                        self.num_succeeded += 1;
+                        FrameKind::Folder(FolderNode {
+                            date_added: parse_date(attrs.get("add_date")),
+                            last_modified: parse_date(attrs.get("last_modified")),
+                            title,
+                            ..Default::default()
+                        })
+                    }
+                });
+            }
+            None => {}
+        }
+    }
+
+    fn finish_bookmark(&mut self, attrs: HashMap<String, String>, title: Option<String>) {
+        if attrs.contains_key("feedurl") {
+            log::warn!("Skipping livemark");
+            self.num_failed += 1;
+            return;
+        }
+        let url = match attrs.get("href").map(|href| Url::parse(href.trim())) {
+            Some(Ok(url)) if url.as_str().len() <= URL_LENGTH_MAX => url,
+            _ => {
+                log::warn!("Skipping bookmark with invalid URL");
+                self.num_failed += 1;
+                return;
+            }
+        };
+        if let Some(Ok(keyword)) = attrs.get("shortcuturl").map(|k| validate_keyword(k)) {
+            self.keywords.push((url.clone(), keyword));
+        }
+        if let Some(tags) = attrs.get("tags") {
+            for tag in tags.split(',') {
+                if let Ok(tag) = validate_tag(tag).ensure_valid() {
+                    self.tags.push((url.clone(), tag.to_string()));
+                }
+            }
+        }
+        self.num_succeeded += 1;
+        self.append(
+            BookmarkNode {
+                guid: None,
+                date_added: parse_date(attrs.get("add_date")),
+                last_modified: parse_date(attrs.get("last_modified")),
+                title,
+                url,
+            }
+            .into(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::storage::bookmarks::export_bookmarks_html;
+    use crate::storage::tags::get_tags_for_url;
+    use crate::tests::assert_json_tree;
+    use serde_json::json;
+    use sql_support::ConnExt;
+
+    #[test]
+    fn test_tokenizer() {
+        let tokens: Vec<_> = Tokenizer {
+            rest: r#"<!-- <A> --><DT><a HREF="x>y" add_date=1 Empty>a &amp; b < c</A"#,
+        }
+        .collect();
+        let mut attrs = HashMap::new();
+        attrs.insert("href".to_string(), "x>y".to_string());
+        attrs.insert("add_date".to_string(), "1".to_string());
+        attrs.insert("empty".to_string(), "".to_string());
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Open("DT", HashMap::new()),
+                Token::Open("a", attrs),
+                Token::Text("a &amp; b "),
+                Token::Text("<"),
+                Token::Text(" c"),
+                Token::Close("A"),
+            ]
+        );
+        assert_eq!(unescape("&lt;&#65;&#x42;&bogus;&"), "<AB&bogus;&");
+    }
+
+    #[test]
+    fn test_import() {
+        let conn = new_mem_connection();
+        // Roughly what desktop writes, with a few of the quirks other
+        // browsers have.
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+            <META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+            <TITLE>Bookmarks</TITLE>
+            <H1>Bookmarks Menu</H1>
+            <DL><p>
+                <DT><A HREF="https://www.example.com/" ADD_DATE="1500000000" LAST_MODIFIED="1500000001" SHORTCUTURL=" Ex " TAGS="a, b,,">Example &amp; co</A>
+                <DD>A description, which we ignore.
+                <HR>
+                <DT><H3 ADD_DATE="1500000000000">Folder</H3>
+                <DD>A folder description.
+                <DL><p>
+                    <DT><A HREF="https://www.mozilla.org/">Mozilla
+                    <DT><H3>Empty</H3>
+                </DL><p>
+                <DT><A HREF="not a url">Invalid</A>
+                <DT><A HREF="https://www.example.com/feed" FEEDURL="https://www.example.com/rss">Livemark</A>
+                <DT><H3 PERSONAL_TOOLBAR_FOLDER="true">Toolbar</H3>
+                <DL><p>
+                    <DT><A HREF="https://www.example.org/">Toolbar bookmark</A>
+                </DL><p>
+            </DL>
+            <DT><A HREF="https://www.example.net/">Stray</A>"#;
+        let result = import_bookmarks_html(&conn, html).unwrap();
+        assert_eq!(
+            result,
+            BookmarksHtmlImportResult {
+                num_total: 9,
+                num_succeeded: 7,
+                num_failed: 2,
+            }
+        );
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Menu.into(),
+            json!({
+                "guid": BookmarkRootGuid::Menu.as_guid(),
+                "children": [
+                    {
+                        "title": "Example & co",
+                        "url": "https://www.example.com/",
+                        "date_added": 1_500_000_000_000u64,
+                        "last_modified": 1_500_000_001_000u64,
+                    },
+                    { "type": 3 },
+                    {
+                        "title": "Folder",
+                        "date_added": 1_500_000_000_000u64,
+                        "children": [
+                            { "title": "Mozilla", "url": "https://www.mozilla.org/" },
+                            { "title": "Empty", "children": [] },
+                        ],
+                    },
+                    { "title": "Stray", "url": "https://www.example.net/" },
+                ],
+            }),
+        );
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Toolbar.into(),
+            json!({
+                "guid": BookmarkRootGuid::Toolbar.as_guid(),
+                "children": [
+                    { "title": "Toolbar bookmark", "url": "https://www.example.org/" },
+                ],
+            }),
+        );
+        let url = Url::parse("https://www.example.com/").unwrap();
+        let mut tags = get_tags_for_url(&conn, &url).unwrap();
+        tags.sort();
+        assert_eq!(tags, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(
+            crate::storage::bookmarks::bookmarks_get_url_for_keyword(&conn, "ex").unwrap(),
+            Some(url)
+        );
+        // New items are uploaded on the next sync.
+        let unsynced: u32 = conn
+            .query_one("SELECT COUNT(*) FROM moz_bookmarks WHERE syncChangeCounter = 0")
+            .unwrap();
+        assert_eq!(unsynced, 0);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let conn = new_mem_connection();
+        let html = r#"<DL><p>
+            <DT><A HREF="https://www.example.com/" ADD_DATE="1500000000" LAST_MODIFIED="1500000000" SHORTCUTURL="ex" TAGS="a,b">&quot;Example&quot;</A>
+            <DT><H3 ADD_DATE="1500000000" LAST_MODIFIED="1500000000">Folder</H3>
+            <DL><p>
+                <HR>
+            </DL><p>
+            <DT><H3 MOBILE_BOOKMARKS_FOLDER="true">Mobile Bookmarks</H3>
+            <DL><p>
+                <DT><A HREF="https://www.mozilla.org/" ADD_DATE="1500000000" LAST_MODIFIED="1500000000">Mozilla</A>
+            </DL><p>
+        </DL>"#;
+        import_bookmarks_html(&conn, html).unwrap();
+        let exported = export_bookmarks_html(&conn).unwrap();
+
+        let other = new_mem_connection();
+        import_bookmarks_html(&other, &exported).unwrap();
+        // Separators don't have dates in the format, and the dates of the
+        // roots are ignored on import, so those will differ.
+        let without_dateless = |html: &str| {
+            html.lines()
+                .filter(|line| !line.contains("<HR>") && !line.contains("_FOLDER="))
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            without_dateless(&export_bookmarks_html(&other).unwrap()),
+            without_dateless(&exported)
+        );
+        assert!(exported.contains(r#"SHORTCUTURL="ex" TAGS="a,b">&quot;Example&quot;</A>"#));
+        assert!(exported.contains(r#"MOBILE_BOOKMARKS_FOLDER="true">Mobile Bookmarks</H3>"#));
+    }
+}
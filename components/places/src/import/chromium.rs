@@ -0,0 +1,27 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Imports history and bookmarks from a Chrome or Chromium profile, using its
+//! `History` SQLite database and `Bookmarks` JSON file.
+
+pub mod bookmarks;
+pub mod history;
+pub use bookmarks::import as import_bookmarks;
+pub use history::import as import_history;
+
+use crate::types::Timestamp;
+use std::convert::TryFrom;
+
+// Chromium stores times as microseconds since 1601-01-01 UTC (the "WebKit"
+// or Windows epoch), which is this many milliseconds before the Unix epoch.
+const WEBKIT_EPOCH_OFFSET_MS: u64 = 11_644_473_600_000;
+
+/// Converts a Chromium timestamp to one of ours. Returns None for times before
+/// the Unix epoch, which Chromium uses (as 0) for "no time".
+pub fn webkit_to_timestamp(webkit_us: i64) -> Option<Timestamp> {
+    let ms = u64::try_from(webkit_us).ok()? / 1000;
+    ms.checked_sub(WEBKIT_EPOCH_OFFSET_MS)
+        .filter(|ms| *ms > 0)
+        .map(Timestamp)
+}
@@ -0,0 +1,191 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Chromium keeps bookmarks in a JSON file, with a tree under each of its
+//! three roots:
+//!
+//! - `bookmark_bar`, which we import into the toolbar.
+//! - `other`, which we import into "Other Bookmarks" (unfiled).
+//! - `synced`, the mobile bookmarks, which we import into the mobile root.
+//!
+//! Chromium's GUIDs are UUIDs, which we can't use, so imported items get new
+//! ones. Chromium doesn't have separators, keywords or tags.
+
+use crate::api::places_api::PlacesApi;
+use crate::error::*;
+use crate::import::chromium::webkit_to_timestamp;
+use crate::import::fennec::bookmarks::BookmarksMigrationResult;
+use crate::storage::bookmarks::{
+    insert_tree_in_tx, BookmarkNode, BookmarkRootGuid, BookmarkTreeNode, FolderNode,
+};
+use crate::storage::{delete_pending_temp_tables, URL_LENGTH_MAX};
+use crate::types::Timestamp;
+use serde_derive::*;
+use std::time::Instant;
+use url::Url;
+
+#[derive(Debug, Deserialize)]
+struct ChromiumBookmarks {
+    roots: ChromiumRoots,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChromiumRoots {
+    bookmark_bar: Option<ChromiumNode>,
+    other: Option<ChromiumNode>,
+    synced: Option<ChromiumNode>,
+}
+
+// Times are strings of WebKit timestamps.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ChromiumNode {
+    Url {
+        #[serde(default)]
+        name: String,
+        url: String,
+        date_added: Option<String>,
+    },
+    Folder {
+        #[serde(default)]
+        name: String,
+        date_added: Option<String>,
+        date_modified: Option<String>,
+        #[serde(default)]
+        children: Vec<ChromiumNode>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+fn parse_time(time: &Option<String>) -> Option<Timestamp> {
+    time.as_ref()
+        .and_then(|t| t.parse::<i64>().ok())
+        .and_then(webkit_to_timestamp)
+}
+
+fn non_empty(name: &str) -> Option<String> {
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_owned())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Converter {
+    num_succeeded: u32,
+    num_failed: u32,
+}
+
+impl Converter {
+    fn convert_children(&mut self, children: &[ChromiumNode]) -> Vec<BookmarkTreeNode> {
+        children
+            .iter()
+            .filter_map(|child| self.convert(child))
+            .collect()
+    }
+
+    fn convert(&mut self, node: &ChromiumNode) -> Option<BookmarkTreeNode> {
+        match node {
+            ChromiumNode::Url {
+                name,
+                url,
+                date_added,
+            } => match Url::parse(url) {
+                Ok(url) if url.as_str().len() <= URL_LENGTH_MAX => {
+                    self.num_succeeded += 1;
+                    let date_added = parse_time(date_added);
+                    Some(
+                        BookmarkNode {
+                            guid: None,
+                            date_added,
+                            last_modified: date_added,
+                            title: non_empty(name),
+                            url,
+                        }
+                        .into(),
+                    )
+                }
+                _ => {
+                    self.num_failed += 1;
+                    None
+                }
+            },
+            ChromiumNode::Folder {
+                name,
+                date_added,
+                date_modified,
+                children,
+            } => {
+                self.num_succeeded += 1;
+                Some(
+                    FolderNode {
+                        guid: None,
+                        date_added: parse_time(date_added),
+                        last_modified: parse_time(date_modified),
+                        title: non_empty(name),
+                        children: self.convert_children(children),
+                    }
+                    .into(),
+                )
+            }
+            ChromiumNode::Unknown => {
+                self.num_failed += 1;
+                None
+            }
+        }
+    }
+}
+
+pub fn import(
+    places_api: &PlacesApi,
+    path: impl AsRef<std::path::Path>,
+) -> Result<BookmarksMigrationResult> {
+    let json = std::fs::read_to_string(path)?;
+    do_import(places_api, &json)
+}
+
+fn do_import(places_api: &PlacesApi, json: &str) -> Result<BookmarksMigrationResult> {
+    let import_start = Instant::now();
+    let bookmarks: ChromiumBookmarks = serde_json::from_str(json)?;
+    let mut converter = Converter::default();
+    let roots = [
+        (BookmarkRootGuid::Toolbar, &bookmarks.roots.bookmark_bar),
+        (BookmarkRootGuid::Unfiled, &bookmarks.roots.other),
+        (BookmarkRootGuid::Mobile, &bookmarks.roots.synced),
+    ];
+
+    let conn = places_api.open_sync_connection()?;
+    let tx = conn.begin_transaction()?;
+    for (root, node) in &roots {
+        // Chromium's roots are merged into ours, so only their children are
+        // imported.
+        let children = match node {
+            Some(ChromiumNode::Folder { children, .. }) => converter.convert_children(children),
+            _ => continue,
+        };
+        if children.is_empty() {
+            continue;
+        }
+        let tree = FolderNode {
+            guid: Some(root.as_guid()),
+            children,
+            ..Default::default()
+        };
+        insert_tree_in_tx(&conn, &tree)?;
+    }
+    delete_pending_temp_tables(&conn)?;
+    tx.commit()?;
+    conn.notify_bookmark_observers();
+
+    log::info!("Successfully imported bookmarks!");
+
+    Ok(BookmarksMigrationResult {
+        num_total: converter.num_succeeded + converter.num_failed,
+        num_succeeded: converter.num_succeeded,
+        num_failed: converter.num_failed,
+        total_duration: import_start.elapsed().as_millis(),
+    })
+}
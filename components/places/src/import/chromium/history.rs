@@ -0,0 +1,236 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::api::places_api::PlacesApi;
+use crate::bookmark_sync::store::BookmarksStore;
+use crate::error::*;
+use crate::import::common::attached_database;
+use crate::import::fennec::history::{select_count, HistoryMigrationResult};
+use crate::types::VisitTransition;
+use rusqlite::{Connection, NO_PARAMS};
+use sql_support::ConnExt;
+use std::time::Instant;
+use url::Url;
+
+// We only read columns of the `urls` and `visits` tables which have been
+// around since well before this version of Chromium's history database.
+const MIN_CHROMIUM_DB_VERSION: i64 = 20;
+
+// Chromium's `ui::PageTransition` values. The low byte is the "core" type,
+// and the high bits are qualifiers.
+const CHROMIUM_CORE_MASK: i64 = 0xFF;
+const CHROMIUM_CLIENT_REDIRECT: i64 = 0x4000_0000;
+const CHROMIUM_SERVER_REDIRECT: i64 = 0x8000_0000;
+
+/// Maps a Chromium page transition onto our visit types.
+pub fn visit_transition_from_chromium(transition: i64) -> VisitTransition {
+    // Chromium doesn't record whether a redirect was permanent, so we treat
+    // them all as temporary.
+    if transition & (CHROMIUM_CLIENT_REDIRECT | CHROMIUM_SERVER_REDIRECT) != 0 {
+        return VisitTransition::RedirectTemporary;
+    }
+    match transition & CHROMIUM_CORE_MASK {
+        // TYPED, and GENERATED, KEYWORD and KEYWORD_GENERATED, which are
+        // suggestions the user picked in the address bar.
+        1 | 5 | 9 | 10 => VisitTransition::Typed,
+        // AUTO_BOOKMARK
+        2 => VisitTransition::Bookmark,
+        // AUTO_SUBFRAME
+        3 => VisitTransition::Embed,
+        // MANUAL_SUBFRAME
+        4 => VisitTransition::FramedLink,
+        // RELOAD
+        8 => VisitTransition::Reload,
+        // LINK, AUTO_TOPLEVEL, FORM_SUBMIT, and anything newer than us.
+        _ => VisitTransition::Link,
+    }
+}
+
+pub fn import(
+    places_api: &PlacesApi,
+    path: impl AsRef<std::path::Path>,
+) -> Result<HistoryMigrationResult> {
+    let url = crate::util::ensure_url_path(path)?;
+    do_import(places_api, url)
+}
+
+fn do_import(places_api: &PlacesApi, chromium_db_file_url: Url) -> Result<HistoryMigrationResult> {
+    let conn = places_api.open_sync_connection()?;
+
+    let scope = conn.begin_interrupt_scope();
+
+    define_sql_functions(&conn)?;
+
+    let import_start = Instant::now();
+    log::trace!("Attaching database {}", chromium_db_file_url);
+    let auto_detach = attached_database(&conn, &chromium_db_file_url, "chromium")?;
+
+    let db_version = conn
+        .db
+        .try_query_one::<i64>(
+            "SELECT CAST(value AS INTEGER) FROM chromium.meta WHERE key = 'version'",
+            &[],
+            false,
+        )?
+        .unwrap_or_default();
+    if db_version < MIN_CHROMIUM_DB_VERSION {
+        return Err(ErrorKind::UnsupportedDatabaseVersion(db_version).into());
+    }
+
+    let tx = conn.begin_transaction()?;
+
+    log::debug!("Counting Chromium history visits");
+    let num_total = select_count(&conn, &COUNT_CHROMIUM_HISTORY_VISITS);
+
+    log::debug!("Creating and populating staging table");
+    conn.execute_batch(&CREATE_STAGING_TABLE)?;
+    conn.execute_batch(&FILL_STAGING)?;
+
+    log::debug!("Populating missing entries in moz_places");
+    conn.execute_batch(&FILL_MOZ_PLACES)?;
+    scope.err_if_interrupted()?;
+
+    log::debug!("Inserting the history visits");
+    let num_succeeded = conn.execute(&INSERT_HISTORY_VISITS, NO_PARAMS)? as u32;
+    scope.err_if_interrupted()?;
+
+    log::debug!("Committing...");
+    tx.commit()?;
+
+    // Note: update_frecencies manages its own transaction, which is fine,
+    // since nothing that bad will happen if it is aborted.
+    log::debug!("Updating frecencies");
+    let store = BookmarksStore::new(&conn, &scope);
+    store.update_frecencies()?;
+
+    log::info!("Successfully imported history visits!");
+
+    auto_detach.execute_now()?;
+
+    Ok(HistoryMigrationResult {
+        num_total,
+        num_succeeded,
+        // Visits we already had count as failures, too.
+        num_failed: num_total.saturating_sub(num_succeeded),
+        total_duration: import_start.elapsed().as_millis(),
+    })
+}
+
+lazy_static::lazy_static! {
+    // Maps Chromium's URL IDs to normalized URLs. Chromium's internal pages,
+    // like `chrome://newtab/`, aren't useful to us, so we skip them.
+    static ref CREATE_STAGING_TABLE: &'static str = "
+        CREATE TEMP TABLE temp.chromiumHistoryStaging(
+            id INTEGER PRIMARY KEY,
+            url TEXT NOT NULL,
+            url_hash INTEGER NOT NULL,
+            title TEXT
+        );"
+    ;
+
+    static ref FILL_STAGING: &'static str = "
+        INSERT INTO temp.chromiumHistoryStaging(id, url, url_hash, title)
+            SELECT id, url, hash(url), title
+            FROM (SELECT
+                      u.id,
+                      validate_url(u.url) AS url,
+                      sanitize_utf8(u.title) AS title
+                  FROM chromium.urls u)
+            WHERE url IS NOT NULL AND
+                  substr(url, 1, 6) <> 'chrome'"
+    ;
+
+    // Several Chromium URLs may normalize to the same one, and moz_places
+    // doesn't stop us inserting duplicates, so group them.
+    static ref FILL_MOZ_PLACES: &'static str =
+        "INSERT OR IGNORE INTO main.moz_places(guid, url, url_hash, title, frecency, sync_change_counter)
+            SELECT
+                IFNULL(
+                    (SELECT p.guid FROM main.moz_places p WHERE p.url_hash = t.url_hash AND p.url = t.url),
+                    generate_guid()
+                ),
+                t.url,
+                t.url_hash,
+                MAX(t.title),
+                -1,
+                1
+            FROM temp.chromiumHistoryStaging t
+            GROUP BY t.url_hash, t.url"
+    ;
+
+    // Insert history visits, skipping any we already have, so that importing
+    // the same profile twice doesn't duplicate them. All the visits are local,
+    // since Chromium doesn't keep the visits it syncs.
+    static ref INSERT_HISTORY_VISITS: &'static str =
+        "INSERT OR IGNORE INTO main.moz_historyvisits(from_visit, place_id, visit_date, visit_type, is_local)
+            SELECT NULL, place_id, visit_date, visit_type, 1
+            FROM (SELECT
+                      (SELECT p.id FROM main.moz_places p
+                       WHERE p.url_hash = t.url_hash AND p.url = t.url) AS place_id,
+                      sanitize_timestamp(webkit_to_millis(v.visit_time)) AS visit_date,
+                      chromium_visit_type(v.transition) AS visit_type
+                  FROM chromium.visits v
+                  JOIN temp.chromiumHistoryStaging t ON t.id = v.url) n
+            WHERE NOT EXISTS(SELECT 1 FROM main.moz_historyvisits e
+                             WHERE e.place_id = n.place_id AND
+                                   e.visit_date = n.visit_date)"
+    ;
+
+    static ref COUNT_CHROMIUM_HISTORY_VISITS: &'static str =
+        "SELECT COUNT(*) FROM chromium.visits"
+    ;
+}
+
+mod sql_fns {
+    use crate::import::chromium::webkit_to_timestamp;
+    use rusqlite::{functions::Context, Result};
+
+    // Returns NULL for invalid times, which `sanitize_timestamp` replaces
+    // with the time of the import.
+    #[inline(never)]
+    pub fn webkit_to_millis(ctx: &Context<'_>) -> Result<Option<i64>> {
+        Ok(ctx
+            .get::<i64>(0)
+            .ok()
+            .and_then(webkit_to_timestamp)
+            .map(|ts| ts.as_millis() as i64))
+    }
+
+    #[inline(never)]
+    pub fn chromium_visit_type(ctx: &Context<'_>) -> Result<u8> {
+        let transition = ctx.get::<i64>(0).unwrap_or_default();
+        Ok(super::visit_transition_from_chromium(transition) as u8)
+    }
+}
+
+fn define_sql_functions(c: &Connection) -> Result<()> {
+    c.create_scalar_function(
+        "validate_url",
+        1,
+        true,
+        crate::import::common::sql_fns::validate_url,
+    )?;
+    c.create_scalar_function(
+        "sanitize_timestamp",
+        1,
+        true,
+        crate::import::common::sql_fns::sanitize_timestamp,
+    )?;
+    c.create_scalar_function(
+        "sanitize_utf8",
+        1,
+        true,
+        crate::import::common::sql_fns::sanitize_utf8,
+    )?;
+    c.create_scalar_function("hash", -1, true, crate::db::db::sql_fns::hash)?;
+    c.create_scalar_function(
+        "generate_guid",
+        0,
+        false,
+        crate::db::db::sql_fns::generate_guid,
+    )?;
+    c.create_scalar_function("webkit_to_millis", 1, true, sql_fns::webkit_to_millis)?;
+    c.create_scalar_function("chromium_visit_type", 1, true, sql_fns::chromium_visit_type)?;
+    Ok(())
+}
@@ -2,6 +2,11 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+pub mod bookmarks_html;
+pub use bookmarks_html::import_bookmarks_html;
+pub mod chromium;
+pub use chromium::import_bookmarks as import_chromium_bookmarks;
+pub use chromium::import_history as import_chromium_history;
 pub mod common;
 pub mod fennec;
 pub use fennec::import_bookmarks as import_fennec_bookmarks;
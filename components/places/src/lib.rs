@@ -38,7 +38,7 @@ pub use crate::api::places_api::{ConnectionType, PlacesApi};
 
 pub use crate::db::PlacesDb;
 pub use crate::error::*;
-pub use crate::observation::VisitObservation;
+pub use crate::observation::{HistoryMetadataObservation, VisitObservation};
 pub use crate::storage::PageInfo;
 pub use crate::storage::RowId;
 pub use crate::types::*;
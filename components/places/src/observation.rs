@@ -139,3 +139,74 @@ impl VisitObservation {
         }
     }
 }
+
+/// An observation of how the user engaged with a page, beyond the visit
+/// itself: how long they viewed it, what kind of document it was, and the
+/// search term that led to it. Observations for the same page, referrer and
+/// search term are grouped together, and their view times are added up.
+///
+/// Like `VisitObservation`, this exposes a "builder" API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryMetadataObservation {
+    /// See the comment about `VisitObservation::url`.
+    pub url: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub referrer_url: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub search_term: Option<String>,
+
+    /// In milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub view_time: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub document_type: Option<DocumentType>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub at: Option<Timestamp>,
+}
+
+impl HistoryMetadataObservation {
+    pub fn new(url: Url) -> Self {
+        HistoryMetadataObservation {
+            url: url.into_string(),
+            referrer_url: None,
+            search_term: None,
+            view_time: None,
+            document_type: None,
+            at: None,
+        }
+    }
+
+    pub fn with_referrer(mut self, v: impl Into<Option<Url>>) -> Self {
+        self.referrer_url = v.into().map(Url::into_string);
+        self
+    }
+
+    pub fn with_search_term(mut self, v: impl Into<Option<String>>) -> Self {
+        self.search_term = v.into();
+        self
+    }
+
+    pub fn with_view_time(mut self, v: impl Into<Option<u64>>) -> Self {
+        self.view_time = v.into();
+        self
+    }
+
+    pub fn with_document_type(mut self, v: impl Into<Option<DocumentType>>) -> Self {
+        self.document_type = v.into();
+        self
+    }
+
+    pub fn with_at(mut self, v: impl Into<Option<Timestamp>>) -> Self {
+        self.at = v.into();
+        self
+    }
+}
@@ -2,9 +2,11 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use super::undo::{self, UndoKind};
 use super::RowId;
 use super::{delete_meta, put_meta};
 use super::{fetch_page_info, new_page_info};
+use crate::api::observer::BookmarkEvent;
 use crate::bookmark_sync::store::{
     COLLECTION_SYNCID_META_KEY, GLOBAL_SYNCID_META_KEY, LAST_SYNC_META_KEY,
 };
@@ -26,10 +28,13 @@ use std::collections::HashMap;
 use sync_guid::Guid as SyncGuid;
 use url::Url;
 
+pub use html::export_bookmarks_html;
 pub use public_node::PublicNode;
 pub use root_guid::{BookmarkRootGuid, USER_CONTENT_ROOTS};
 
 mod conversions;
+pub mod html;
+pub mod maintenance;
 pub mod public_node;
 mod root_guid;
 
@@ -253,6 +258,7 @@ pub fn insert_bookmark(db: &PlacesDb, bm: &InsertableItem) -> Result<SyncGuid> {
         Ok(_) => tx.commit()?,
         Err(_) => tx.rollback()?,
     }
+    db.notify_bookmark_observers();
     result
 }
 
@@ -262,7 +268,7 @@ pub fn maybe_truncate_title<'a>(t: &Option<&'a str>) -> Option<&'a str> {
     t.map(|title| slice_up_to(title, TITLE_LENGTH_MAX))
 }
 
-fn insert_bookmark_in_tx(db: &PlacesDb, bm: &InsertableItem) -> Result<SyncGuid> {
+pub(crate) fn insert_bookmark_in_tx(db: &PlacesDb, bm: &InsertableItem) -> Result<SyncGuid> {
     // find the row ID of the parent.
     if bm.parent_guid() == BookmarkRootGuid::Root {
         return Err(InvalidPlaceInfo::CannotUpdateRoot(BookmarkRootGuid::Root).into());
@@ -363,6 +369,12 @@ fn insert_bookmark_in_tx(db: &PlacesDb, bm: &InsertableItem) -> Result<SyncGuid>
         WHERE id = :parent_id";
     db.execute_named_cached(sql_counter, &[(":parent_id", &parent.row_id)])?;
 
+    db.queue_bookmark_event(BookmarkEvent::ItemAdded {
+        guid: guid.clone(),
+        item_type: bookmark_type,
+        parent_guid: parent.guid.clone(),
+        position,
+    });
     Ok(guid)
 }
 
@@ -370,11 +382,13 @@ fn insert_bookmark_in_tx(db: &PlacesDb, bm: &InsertableItem) -> Result<SyncGuid>
 /// existed and was deleted, false otherwise.
 pub fn delete_bookmark(db: &PlacesDb, guid: &SyncGuid) -> Result<bool> {
     let tx = db.begin_transaction()?;
-    let result = delete_bookmark_in_tx(db, guid);
+    let result =
+        undo::journal_bookmark_deletion(db, guid).and_then(|()| delete_bookmark_in_tx(db, guid));
     match result {
         Ok(_) => tx.commit()?,
         Err(_) => tx.rollback()?,
     }
+    db.notify_bookmark_observers();
     result
 }
 
@@ -403,7 +417,23 @@ fn delete_bookmark_in_tx(db: &PlacesDb, guid: &SyncGuid) -> Result<bool> {
         "DELETE from moz_bookmarks WHERE id = :id",
         &[(":id", &record.row_id)],
     )?;
+    // Like desktop, keywords are removed along with the last bookmark for
+    // their URL. We don't know which URLs that might be if we deleted a
+    // folder, but there aren't many keywords.
+    db.execute_batch(
+        "DELETE FROM moz_keywords
+         WHERE NOT EXISTS(SELECT 1 FROM moz_bookmarks b
+                          WHERE b.fk = moz_keywords.place_id)",
+    )?;
     super::delete_pending_temp_tables(db)?;
+    if let Some(parent_guid) = record.parent_guid {
+        db.queue_bookmark_event(BookmarkEvent::ItemRemoved {
+            guid: guid.clone(),
+            item_type: record.bookmark_type,
+            parent_guid,
+            position: record.position,
+        });
+    }
     Ok(true)
 }
 
@@ -433,6 +463,10 @@ pub struct UpdatableBookmark {
     pub location: UpdateTreeLocation,
     pub url: Option<Url>,
     pub title: Option<String>,
+    /// The keyword for the bookmark's URL. Like the title, an empty string
+    /// removes it. Keywords belong to URLs rather than bookmarks, so this
+    /// changes the keyword for every bookmark with the URL.
+    pub keyword: Option<String>,
 }
 
 impl From<UpdatableBookmark> for UpdatableItem {
@@ -497,6 +531,9 @@ pub fn update_bookmark(db: &PlacesDb, guid: &SyncGuid, item: &UpdatableItem) ->
     super::delete_pending_temp_tables(db)?;
     // Note: `tx` automatically rolls back on drop if we don't commit
     tx.commit()?;
+    if result.is_ok() {
+        db.notify_bookmark_observers();
+    }
     result
 }
 
@@ -527,6 +564,20 @@ fn update_bookmark_in_tx(
         .into());
     }
 
+    // Validate the keyword before we change anything. `None` means "do not
+    // change", and `Some(None)` means "remove".
+    let new_keyword = match item {
+        UpdatableItem::Bookmark(UpdatableBookmark {
+            keyword: Some(keyword),
+            ..
+        }) => Some(if keyword.is_empty() {
+            None
+        } else {
+            Some(validate_keyword(keyword)?)
+        }),
+        _ => None,
+    };
+
     let update_old_parent_status;
     let update_new_parent_status;
     // to make our life easier we update every field, using existing when
@@ -651,9 +702,62 @@ fn update_bookmark_in_tx(
         set_ancestors_last_modified(db, parent_id, now)?;
         db.execute_named_cached(sql_counter, &[(":parent_id", &parent_id)])?;
     }
+
+    let keyword_changed = new_keyword.is_some();
+    if let Some(place_id) = place_id {
+        update_keyword_for_bookmark(db, raw.place_id, place_id, new_keyword)?;
+    }
+
+    if parent_id != existing_parent_id || position != raw.position {
+        let new_parent_guid = match item.location() {
+            UpdateTreeLocation::Parent(new_parent_guid, _) => new_parent_guid,
+            _ => existing_parent_guid,
+        };
+        db.queue_bookmark_event(BookmarkEvent::ItemMoved {
+            guid: guid.clone(),
+            item_type: raw.bookmark_type,
+            old_parent_guid: existing_parent_guid.clone(),
+            old_position: raw.position,
+            new_parent_guid: new_parent_guid.clone(),
+            new_position: position,
+        });
+    }
+    if change_incr || keyword_changed {
+        db.queue_bookmark_event(BookmarkEvent::ItemChanged {
+            guid: guid.clone(),
+            item_type: raw.bookmark_type,
+        });
+    }
     Ok(())
 }
 
+fn update_keyword_for_bookmark(
+    db: &PlacesDb,
+    old_place_id: Option<RowId>,
+    place_id: RowId,
+    new_keyword: Option<Option<String>>,
+) -> Result<()> {
+    // If the URL changed and no other bookmark has the old one, its keyword
+    // follows the bookmark to the new URL, unless it already has one.
+    let mut moved_keyword = None;
+    if let Some(old_place_id) = old_place_id.filter(|id| *id != place_id) {
+        if !is_place_bookmarked(db, old_place_id)? {
+            moved_keyword = get_keyword_for_place(db, old_place_id)?;
+            remove_keyword_for_place(db, old_place_id)?;
+        }
+    }
+    match new_keyword {
+        Some(Some(keyword)) => set_keyword_for_place(db, place_id, &keyword),
+        Some(None) => remove_keyword_for_place(db, place_id),
+        None => match moved_keyword {
+            Some(keyword) if get_keyword_for_place(db, place_id)?.is_none() => {
+                set_keyword_for_place(db, place_id, &keyword)
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
 fn set_ancestors_last_modified(db: &PlacesDb, parent_id: RowId, time: Timestamp) -> Result<()> {
     let sql = "
         WITH RECURSIVE
@@ -906,6 +1010,164 @@ impl<'de> Deserialize<'de> for BookmarkTreeNode {
     }
 }
 
+/// A keyword, and the URL it belongs to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookmarkKeyword {
+    pub keyword: String,
+    pub url: Url,
+}
+
+/// Checks and normalizes a keyword. Like desktop, keywords are trimmed and
+/// lowercased, and can't be empty. They also can't contain whitespace, since
+/// only the first word typed into the address bar is matched against them.
+pub fn validate_keyword(keyword: &str) -> Result<String> {
+    let keyword = keyword.trim();
+    if keyword.is_empty() || keyword.contains(char::is_whitespace) {
+        return Err(InvalidPlaceInfo::InvalidKeyword.into());
+    }
+    Ok(keyword.to_lowercase())
+}
+
+/// Sets the keyword for a bookmarked URL. A URL has at most one keyword, and
+/// a keyword belongs to at most one URL, so this replaces the URL's existing
+/// keyword, and removes the keyword from any other URL.
+pub fn bookmarks_set_keyword(db: &PlacesDb, url: &Url, keyword: &str) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    let result = set_keyword_in_tx(db, url, keyword);
+    match result {
+        Ok(_) => tx.commit()?,
+        Err(_) => tx.rollback()?,
+    }
+    result
+}
+
+pub(crate) fn set_keyword_in_tx(db: &PlacesDb, url: &Url, keyword: &str) -> Result<()> {
+    let keyword = validate_keyword(keyword)?;
+    // Keywords are synced as part of bookmark records, so a URL needs to be
+    // bookmarked to have one.
+    let place_id = db
+        .try_query_row(
+            "SELECT h.id FROM moz_places h
+             WHERE url_hash = hash(:url) AND url = :url
+               AND EXISTS(SELECT 1 FROM moz_bookmarks b WHERE b.fk = h.id)",
+            &[(":url", &url.as_str())],
+            |row| row.get::<_, RowId>(0),
+            true,
+        )?
+        .ok_or(InvalidPlaceInfo::NoSuchUrl)?;
+    set_keyword_for_place(db, place_id, &keyword)
+}
+
+/// Returns the keyword for a URL, if it has one.
+pub fn bookmarks_get_keyword_for_url(db: &PlacesDb, url: &Url) -> Result<Option<String>> {
+    Ok(db.try_query_row(
+        "SELECT k.keyword FROM moz_keywords k
+         JOIN moz_places h ON h.id = k.place_id
+         WHERE h.url_hash = hash(:url) AND h.url = :url",
+        &[(":url", &url.as_str())],
+        |row| row.get::<_, String>(0),
+        true,
+    )?)
+}
+
+/// Returns all keywords, ordered by keyword.
+pub fn bookmarks_get_all_keywords(db: &PlacesDb) -> Result<Vec<BookmarkKeyword>> {
+    db.query_rows_and_then_named_cached(
+        "SELECT k.keyword, h.url FROM moz_keywords k
+         JOIN moz_places h ON h.id = k.place_id
+         ORDER BY k.keyword",
+        &[],
+        |row| -> Result<_> {
+            Ok(BookmarkKeyword {
+                keyword: row.get("keyword")?,
+                url: Url::parse(&row.get::<_, String>("url")?)?,
+            })
+        },
+    )
+}
+
+/// Removes a keyword. Returns true if it existed.
+pub fn bookmarks_remove_keyword(db: &PlacesDb, keyword: &str) -> Result<bool> {
+    let keyword = keyword.trim().to_lowercase();
+    let tx = db.begin_transaction()?;
+    let place_id = db.try_query_row(
+        "SELECT place_id FROM moz_keywords WHERE keyword = :keyword",
+        &[(":keyword", &keyword)],
+        |row| row.get::<_, RowId>(0),
+        true,
+    )?;
+    if let Some(place_id) = place_id {
+        remove_keyword_for_place(db, place_id)?;
+    }
+    tx.commit()?;
+    Ok(place_id.is_some())
+}
+
+fn is_place_bookmarked(db: &PlacesDb, place_id: RowId) -> Result<bool> {
+    Ok(db.query_row_named(
+        "SELECT EXISTS(SELECT 1 FROM moz_bookmarks WHERE fk = :place_id)",
+        &[(":place_id", &place_id)],
+        |row| row.get(0),
+    )?)
+}
+
+fn get_keyword_for_place(db: &PlacesDb, place_id: RowId) -> Result<Option<String>> {
+    Ok(db.try_query_row(
+        "SELECT keyword FROM moz_keywords WHERE place_id = :place_id",
+        &[(":place_id", &place_id)],
+        |row| row.get::<_, String>(0),
+        true,
+    )?)
+}
+
+fn set_keyword_for_place(db: &PlacesDb, place_id: RowId, keyword: &str) -> Result<()> {
+    let existing_place_id = db.try_query_row(
+        "SELECT place_id FROM moz_keywords WHERE keyword = :keyword",
+        &[(":keyword", &keyword)],
+        |row| row.get::<_, RowId>(0),
+        true,
+    )?;
+    if existing_place_id == Some(place_id) {
+        return Ok(());
+    }
+    if let Some(existing_place_id) = existing_place_id {
+        remove_keyword_for_place(db, existing_place_id)?;
+    }
+    // Use an explicit `DELETE` instead of `INSERT OR REPLACE`, since the
+    // latter doesn't run the triggers which maintain `foreign_count`.
+    db.execute_named_cached(
+        "DELETE FROM moz_keywords WHERE place_id = :place_id",
+        &[(":place_id", &place_id)],
+    )?;
+    db.execute_named_cached(
+        "INSERT INTO moz_keywords(keyword, place_id) VALUES(:keyword, :place_id)",
+        &[(":keyword", &keyword), (":place_id", &place_id)],
+    )?;
+    bump_change_counters_for_place(db, place_id)
+}
+
+fn remove_keyword_for_place(db: &PlacesDb, place_id: RowId) -> Result<()> {
+    let removed = db.execute_named_cached(
+        "DELETE FROM moz_keywords WHERE place_id = :place_id",
+        &[(":place_id", &place_id)],
+    )?;
+    if removed > 0 {
+        bump_change_counters_for_place(db, place_id)?;
+    }
+    Ok(())
+}
+
+// Sync records carry the keyword, so every bookmark for the URL needs to be
+// reuploaded when it changes.
+fn bump_change_counters_for_place(db: &PlacesDb, place_id: RowId) -> Result<()> {
+    db.execute_named_cached(
+        "UPDATE moz_bookmarks SET syncChangeCounter = syncChangeCounter + 1
+         WHERE fk = :place_id",
+        &[(":place_id", &place_id)],
+    )?;
+    Ok(())
+}
+
 /// Get the URL of the bookmark matching a keyword
 pub fn bookmarks_get_url_for_keyword(db: &PlacesDb, keyword: &str) -> Result<Option<Url>> {
     let bookmark_url = db.try_query_row(
@@ -1082,6 +1344,7 @@ pub fn delete_everything(db: &PlacesDb) -> Result<()> {
 }
 
 fn delete_everything_in_tx(db: &PlacesDb) -> Result<()> {
+    undo::delete_undo_entries(db, UndoKind::Bookmarks)?;
     db.execute_batch(&format!(
         "DELETE FROM moz_bookmarks_synced;
 
@@ -1108,6 +1371,15 @@ fn delete_everything_in_tx(db: &PlacesDb) -> Result<()> {
 }
 
 pub fn insert_tree(db: &PlacesDb, tree: &FolderNode) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    insert_tree_in_tx(db, tree)?;
+    super::delete_pending_temp_tables(db)?;
+    tx.commit()?;
+    db.notify_bookmark_observers();
+    Ok(())
+}
+
+pub(crate) fn insert_tree_in_tx(db: &PlacesDb, tree: &FolderNode) -> Result<()> {
     let parent_guid = match &tree.guid {
         Some(guid) => guid,
         None => return Err(InvalidPlaceInfo::InvalidParent("<no guid>".into()).into()),
@@ -1116,13 +1388,10 @@ pub fn insert_tree(db: &PlacesDb, tree: &FolderNode) -> Result<()> {
     let mut insert_infos: Vec<InsertableItem> = Vec::new();
     add_subtree_infos(&parent_guid, tree, &mut insert_infos);
     log::info!("insert_tree inserting {} records", insert_infos.len());
-    let tx = db.begin_transaction()?;
 
     for insertable in insert_infos {
         insert_bookmark_in_tx(db, &insertable)?;
     }
-    super::delete_pending_temp_tables(db)?;
-    tx.commit()?;
     Ok(())
 }
 
@@ -1596,6 +1865,149 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_bookmark_keywords() -> Result<()> {
+        let _ = env_logger::try_init();
+        let conn = new_mem_connection();
+
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": &BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    {
+                        "guid": "bookmark1___",
+                        "title": "first",
+                        "url": "https://www.example1.com/"
+                    },
+                    {
+                        "guid": "bookmark2___",
+                        "title": "second",
+                        "url": "https://www.example2.com/"
+                    },
+                    {
+                        "guid": "bookmark3___",
+                        "title": "also second",
+                        "url": "https://www.example2.com/"
+                    },
+                ],
+            }),
+        );
+        conn.execute("UPDATE moz_bookmarks SET syncChangeCounter = 0", NO_PARAMS)
+            .expect("should work");
+
+        let url1 = Url::parse("https://www.example1.com/")?;
+        let url2 = Url::parse("https://www.example2.com/")?;
+        let counter = |guid: &str| {
+            get_raw_bookmark(&conn, &guid.into())
+                .expect("should work")
+                .expect("should exist")
+                .sync_change_counter
+        };
+
+        // Keywords are normalized, and must be a single word.
+        assert_eq!(validate_keyword(" Ex ")?, "ex");
+        for invalid in &["", "   ", "two words"] {
+            match bookmarks_set_keyword(&conn, &url1, invalid)
+                .expect_err("should fail")
+                .kind()
+            {
+                ErrorKind::InvalidPlaceInfo(InvalidPlaceInfo::InvalidKeyword) => {}
+                e => panic!("Wanted invalid keyword error; got {:?}", e),
+            }
+        }
+        // Only bookmarked URLs can have keywords.
+        match bookmarks_set_keyword(&conn, &Url::parse("https://www.example3.com/")?, "ex")
+            .expect_err("should fail")
+            .kind()
+        {
+            ErrorKind::InvalidPlaceInfo(InvalidPlaceInfo::NoSuchUrl) => {}
+            e => panic!("Wanted no such URL error; got {:?}", e),
+        }
+
+        bookmarks_set_keyword(&conn, &url2, "Ex")?;
+        assert_eq!(
+            bookmarks_get_keyword_for_url(&conn, &url2)?,
+            Some("ex".to_string())
+        );
+        assert_eq!(
+            bookmarks_get_url_for_keyword(&conn, "ex")?,
+            Some(url2.clone())
+        );
+        // Both bookmarks for the URL need to be reuploaded.
+        assert_eq!(counter("bookmark1___"), 0);
+        assert_eq!(counter("bookmark2___"), 1);
+        assert_eq!(counter("bookmark3___"), 1);
+
+        // Setting the keyword on another URL moves it.
+        bookmarks_set_keyword(&conn, &url1, "ex")?;
+        assert_eq!(bookmarks_get_keyword_for_url(&conn, &url2)?, None);
+        assert_eq!(
+            bookmarks_get_all_keywords(&conn)?,
+            vec![BookmarkKeyword {
+                keyword: "ex".to_string(),
+                url: url1.clone(),
+            }]
+        );
+        assert_eq!(counter("bookmark1___"), 1);
+        assert_eq!(counter("bookmark2___"), 2);
+
+        // Keywords can be set and removed as part of an update.
+        update_bookmark(
+            &conn,
+            &"bookmark2___".into(),
+            &UpdatableBookmark {
+                keyword: Some("two".to_string()),
+                ..Default::default()
+            }
+            .into(),
+        )?;
+        assert_eq!(
+            bookmarks_get_keyword_for_url(&conn, &url2)?,
+            Some("two".to_string())
+        );
+        update_bookmark(
+            &conn,
+            &"bookmark3___".into(),
+            &UpdatableBookmark {
+                keyword: Some("".to_string()),
+                ..Default::default()
+            }
+            .into(),
+        )?;
+        assert_eq!(bookmarks_get_keyword_for_url(&conn, &url2)?, None);
+
+        // Changing the URL of the only bookmark for a URL moves its keyword.
+        let url4 = Url::parse("https://www.example4.com/")?;
+        update_bookmark(
+            &conn,
+            &"bookmark1___".into(),
+            &UpdatableBookmark {
+                url: Some(url4.clone()),
+                ..Default::default()
+            }
+            .into(),
+        )?;
+        assert_eq!(bookmarks_get_keyword_for_url(&conn, &url1)?, None);
+        assert_eq!(bookmarks_get_url_for_keyword(&conn, "ex")?, Some(url4));
+
+        // Removing the last bookmark for a URL removes its keyword.
+        bookmarks_set_keyword(&conn, &url2, "two")?;
+        delete_bookmark(&conn, &"bookmark2___".into())?;
+        assert_eq!(
+            bookmarks_get_keyword_for_url(&conn, &url2)?,
+            Some("two".to_string())
+        );
+        delete_bookmark(&conn, &"bookmark3___".into())?;
+        assert_eq!(bookmarks_get_url_for_keyword(&conn, "two")?, None);
+
+        assert!(bookmarks_remove_keyword(&conn, "EX")?);
+        assert!(!bookmarks_remove_keyword(&conn, "ex")?);
+        assert!(bookmarks_get_all_keywords(&conn)?.is_empty());
+
+        Ok(())
+    }
+
     #[test]
     fn test_insert() -> Result<()> {
         let _ = env_logger::try_init();
@@ -2285,6 +2697,141 @@ mod tests {
         Ok(())
     }
 
+    #[derive(Default)]
+    struct RecordingObserver(std::sync::Mutex<Vec<BookmarkEvent>>);
+
+    impl crate::api::observer::BookmarkObserver for RecordingObserver {
+        fn on_bookmark_event(&self, event: &BookmarkEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    impl RecordingObserver {
+        fn take(&self) -> Vec<BookmarkEvent> {
+            std::mem::replace(&mut *self.0.lock().unwrap(), Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_bookmark_observers() -> Result<()> {
+        let _ = env_logger::try_init();
+        let conn = new_mem_connection();
+        let observer = std::sync::Arc::new(RecordingObserver::default());
+        let id = conn.bookmark_observers().register(observer.clone());
+        let unfiled: SyncGuid = BookmarkRootGuid::Unfiled.into();
+
+        let folder_guid = insert_bookmark(
+            &conn,
+            &InsertableItem::Folder(InsertableFolder {
+                parent_guid: unfiled.clone(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                title: Some("folder".into()),
+            }),
+        )?;
+        let bookmark_guid = insert_bookmark(
+            &conn,
+            &InsertableItem::Bookmark(InsertableBookmark {
+                parent_guid: folder_guid.clone(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                url: Url::parse("https://www.example.com")?,
+                title: None,
+            }),
+        )?;
+        assert_eq!(
+            observer.take(),
+            vec![
+                BookmarkEvent::ItemAdded {
+                    guid: folder_guid.clone(),
+                    item_type: BookmarkType::Folder,
+                    parent_guid: unfiled.clone(),
+                    position: 0,
+                },
+                BookmarkEvent::ItemAdded {
+                    guid: bookmark_guid.clone(),
+                    item_type: BookmarkType::Bookmark,
+                    parent_guid: folder_guid.clone(),
+                    position: 0,
+                },
+            ]
+        );
+
+        // Changes which fail don't send events.
+        insert_bookmark(
+            &conn,
+            &InsertableItem::Separator(InsertableSeparator {
+                parent_guid: bookmark_guid.clone(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+            }),
+        )
+        .expect_err("should fail to insert into a bookmark");
+        assert_eq!(observer.take(), vec![]);
+
+        update_bookmark(
+            &conn,
+            &bookmark_guid,
+            &UpdatableBookmark {
+                location: UpdateTreeLocation::Parent(unfiled.clone(), BookmarkPosition::Append),
+                title: Some("the title".into()),
+                ..Default::default()
+            }
+            .into(),
+        )?;
+        assert_eq!(
+            observer.take(),
+            vec![
+                BookmarkEvent::ItemMoved {
+                    guid: bookmark_guid.clone(),
+                    item_type: BookmarkType::Bookmark,
+                    old_parent_guid: folder_guid.clone(),
+                    old_position: 0,
+                    new_parent_guid: unfiled.clone(),
+                    new_position: 1,
+                },
+                BookmarkEvent::ItemChanged {
+                    guid: bookmark_guid.clone(),
+                    item_type: BookmarkType::Bookmark,
+                },
+            ]
+        );
+
+        // Updates which don't change anything don't send events.
+        update_bookmark(
+            &conn,
+            &bookmark_guid,
+            &UpdatableBookmark {
+                title: Some("the title".into()),
+                ..Default::default()
+            }
+            .into(),
+        )?;
+        assert_eq!(observer.take(), vec![]);
+
+        assert!(delete_bookmark(&conn, &folder_guid)?);
+        assert_eq!(
+            observer.take(),
+            vec![BookmarkEvent::ItemRemoved {
+                guid: folder_guid,
+                item_type: BookmarkType::Folder,
+                parent_guid: unfiled,
+                position: 0,
+            }]
+        );
+
+        assert!(conn.bookmark_observers().unregister(id));
+        delete_bookmark(&conn, &bookmark_guid)?;
+        assert_eq!(observer.take(), vec![]);
+        Ok(())
+    }
+
     #[test]
     fn test_fetch_root() -> Result<()> {
         let _ = env_logger::try_init();
@@ -3,9 +3,9 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use super::{
-    BookmarkPosition, BookmarkRootGuid, BookmarkTreeNode, InsertableBookmark, InsertableFolder,
-    InsertableItem, InsertableSeparator, PublicNode, RawBookmark, UpdatableBookmark,
-    UpdatableFolder, UpdatableItem, UpdatableSeparator, UpdateTreeLocation,
+    BookmarkKeyword, BookmarkPosition, BookmarkRootGuid, BookmarkTreeNode, InsertableBookmark,
+    InsertableFolder, InsertableItem, InsertableSeparator, PublicNode, RawBookmark,
+    UpdatableBookmark, UpdatableFolder, UpdatableItem, UpdatableSeparator, UpdateTreeLocation,
 };
 
 use crate::error::{InvalidPlaceInfo, Result};
@@ -227,6 +227,7 @@ impl BookmarkUpdateInfo {
                 location,
                 title: self.title,
                 url: self.url.map(|u| Url::parse(&u)).transpose()?,
+                keyword: None,
             }),
             BookmarkType::Separator => UpdatableItem::Separator(UpdatableSeparator { location }),
             BookmarkType::Folder => UpdatableItem::Folder(UpdatableFolder {
@@ -258,3 +259,17 @@ impl From<Vec<msg_types::BookmarkNode>> for msg_types::BookmarkNodeList {
         Self { nodes }
     }
 }
+
+impl From<Vec<BookmarkKeyword>> for msg_types::BookmarkKeywordList {
+    fn from(keywords: Vec<BookmarkKeyword>) -> Self {
+        Self {
+            keywords: keywords
+                .into_iter()
+                .map(|k| msg_types::BookmarkKeyword {
+                    keyword: k.keyword,
+                    url: k.url.into_string(),
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,292 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Exports bookmarks in the Netscape `bookmarks.html` format, which desktop
+//! Firefox and every other browser can import. See `import::bookmarks_html`
+//! for the other direction.
+//!
+//! The format has no notion of multiple roots, so, like desktop, we write the
+//! menu's children at the top level, and the other roots as folders marked
+//! with a special attribute.
+
+use super::{fetch_tree, BookmarkRootGuid, BookmarkTreeNode, FetchDepth, FolderNode};
+use crate::db::PlacesDb;
+use crate::error::*;
+use crate::types::Timestamp;
+use sql_support::ConnExt;
+use std::collections::HashMap;
+
+/// The roots written as folders inside the menu, the attribute which marks
+/// each of them, and the title we give them.
+pub(crate) const SPECIAL_FOLDERS: &[(BookmarkRootGuid, &str, &str)] = &[
+    (
+        BookmarkRootGuid::Toolbar,
+        "PERSONAL_TOOLBAR_FOLDER",
+        "Bookmarks Toolbar",
+    ),
+    (
+        BookmarkRootGuid::Unfiled,
+        "UNFILED_BOOKMARKS_FOLDER",
+        "Other Bookmarks",
+    ),
+    (
+        BookmarkRootGuid::Mobile,
+        "MOBILE_BOOKMARKS_FOLDER",
+        "Mobile Bookmarks",
+    ),
+];
+
+const HEADER: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file.
+     It will be read and overwritten.
+     DO NOT EDIT! -->
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+
+"#;
+
+/// Exports all bookmarks as a `bookmarks.html` document, including their
+/// keywords and tags.
+pub fn export_bookmarks_html(db: &PlacesDb) -> Result<String> {
+    let mut writer = HtmlWriter {
+        out: HEADER.to_string(),
+        keywords: fetch_keywords(db)?,
+        tags: fetch_tags(db)?,
+    };
+    writer.out.push_str("<DL><p>\n");
+    let menu = fetch_root(db, BookmarkRootGuid::Menu)?;
+    writer.write_children(&menu.children, 1);
+    for (root, attr, title) in SPECIAL_FOLDERS {
+        let folder = fetch_root(db, *root)?;
+        // Leave out empty roots, so we don't litter other browsers with
+        // empty folders.
+        if !folder.children.is_empty() {
+            writer.write_folder(&folder, Some(*attr), title, 1);
+        }
+    }
+    writer.out.push_str("</DL>\n");
+    Ok(writer.out)
+}
+
+fn fetch_root(db: &PlacesDb, root: BookmarkRootGuid) -> Result<FolderNode> {
+    match fetch_tree(db, root.guid(), &FetchDepth::Deepest)? {
+        Some((BookmarkTreeNode::Folder(folder), _, _)) => Ok(folder),
+        _ => Err(InvalidPlaceInfo::NoSuchGuid(root.as_str().into()).into()),
+    }
+}
+
+fn fetch_keywords(db: &PlacesDb) -> Result<HashMap<String, String>> {
+    let rows = db.query_rows_and_then_named(
+        "SELECT h.url, k.keyword
+         FROM moz_keywords k
+         JOIN moz_places h ON h.id = k.place_id",
+        &[],
+        |row| -> Result<_> { Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)) },
+    )?;
+    Ok(rows.into_iter().collect())
+}
+
+fn fetch_tags(db: &PlacesDb) -> Result<HashMap<String, Vec<String>>> {
+    let rows = db.query_rows_and_then_named(
+        "SELECT h.url, t.tag
+         FROM moz_tags_relation r
+         JOIN moz_tags t ON t.id = r.tag_id
+         JOIN moz_places h ON h.id = r.place_id
+         ORDER BY t.tag",
+        &[],
+        |row| -> Result<_> { Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)) },
+    )?;
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for (url, tag) in rows {
+        tags.entry(url).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+struct HtmlWriter {
+    out: String,
+    keywords: HashMap<String, String>,
+    tags: HashMap<String, Vec<String>>,
+}
+
+impl HtmlWriter {
+    fn indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn write_children(&mut self, children: &[BookmarkTreeNode], depth: usize) {
+        for child in children {
+            match child {
+                BookmarkTreeNode::Bookmark(b) => {
+                    self.indent(depth);
+                    self.out.push_str("<DT><A HREF=\"");
+                    self.out.push_str(&escape(b.url.as_str()));
+                    self.out.push('"');
+                    self.write_dates(b.date_added, b.last_modified);
+                    if let Some(keyword) = self.keywords.get(b.url.as_str()) {
+                        let attr = format!(" SHORTCUTURL=\"{}\"", escape(keyword));
+                        self.out.push_str(&attr);
+                    }
+                    if let Some(tags) = self.tags.get(b.url.as_str()) {
+                        let attr = format!(" TAGS=\"{}\"", escape(&tags.join(",")));
+                        self.out.push_str(&attr);
+                    }
+                    self.out.push('>');
+                    self.out
+                        .push_str(&escape(b.title.as_ref().map_or("", String::as_str)));
+                    self.out.push_str("</A>\n");
+                }
+                BookmarkTreeNode::Separator(_) => {
+                    self.indent(depth);
+                    self.out.push_str("<HR>\n");
+                }
+                BookmarkTreeNode::Folder(f) => {
+                    let title = f.title.as_ref().map_or("", String::as_str);
+                    self.write_folder(f, None, title, depth);
+                }
+            }
+        }
+    }
+
+    fn write_folder(
+        &mut self,
+        folder: &FolderNode,
+        special_attr: Option<&str>,
+        title: &str,
+        depth: usize,
+    ) {
+        self.indent(depth);
+        self.out.push_str("<DT><H3");
+        self.write_dates(folder.date_added, folder.last_modified);
+        if let Some(attr) = special_attr {
+            self.out.push_str(&format!(" {}=\"true\"", attr));
+        }
+        self.out.push('>');
+        self.out.push_str(&escape(title));
+        self.out.push_str("</H3>\n");
+        self.indent(depth);
+        self.out.push_str("<DL><p>\n");
+        self.write_children(&folder.children, depth + 1);
+        self.indent(depth);
+        self.out.push_str("</DL><p>\n");
+    }
+
+    fn write_dates(&mut self, date_added: Option<Timestamp>, last_modified: Option<Timestamp>) {
+        // The format uses seconds, not milliseconds.
+        if let Some(date_added) = date_added {
+            let attr = format!(" ADD_DATE=\"{}\"", date_added.as_millis() / 1000);
+            self.out.push_str(&attr);
+        }
+        if let Some(last_modified) = last_modified {
+            let attr = format!(" LAST_MODIFIED=\"{}\"", last_modified.as_millis() / 1000);
+            self.out.push_str(&attr);
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::storage::tags::tag_url;
+    use crate::tests::insert_json_tree;
+    use serde_json::json;
+    use url::Url;
+
+    #[test]
+    fn test_export() {
+        let conn = new_mem_connection();
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": BookmarkRootGuid::Menu.as_guid(),
+                "children": [
+                    {
+                        "title": "Q&A <1>",
+                        "url": "https://www.example.com/",
+                        "date_added": 1_500_000_000_000u64,
+                        "last_modified": 1_500_000_001_000u64,
+                    },
+                    { "type": 3 },
+                    {
+                        "title": "folder",
+                        "date_added": 1_500_000_000_000u64,
+                        "last_modified": 1_500_000_000_000u64,
+                        "children": [],
+                    },
+                ],
+            }),
+        );
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": BookmarkRootGuid::Toolbar.as_guid(),
+                "children": [
+                    {
+                        "title": "\"quoted\"",
+                        "url": "https://www.mozilla.org/",
+                        "date_added": 1_500_000_000_000u64,
+                        "last_modified": 1_500_000_000_000u64,
+                    },
+                ],
+            }),
+        );
+        let url = Url::parse("https://www.mozilla.org/").unwrap();
+        tag_url(&conn, &url, "mozilla").unwrap();
+        tag_url(&conn, &url, "firefox").unwrap();
+        conn.execute_named_cached(
+            "INSERT INTO moz_keywords(keyword, place_id)
+             SELECT 'moz', id FROM moz_places WHERE url = :url",
+            &[(":url", &url.as_str())],
+        )
+        .unwrap();
+
+        let html = export_bookmarks_html(&conn).unwrap();
+        let dates = r#"ADD_DATE="1500000000" LAST_MODIFIED="1500000000""#;
+        let expected = vec![
+            "<DL><p>".to_string(),
+            r#"    <DT><A HREF="https://www.example.com/" ADD_DATE="1500000000" LAST_MODIFIED="1500000001">Q&amp;A &lt;1&gt;</A>"#.to_string(),
+            "    <HR>".to_string(),
+            format!("    <DT><H3 {}>folder</H3>", dates),
+            "    <DL><p>".to_string(),
+            "    </DL><p>".to_string(),
+        ];
+        let lines = html.lines().skip_while(|l| *l != "<DL><p>");
+        assert_eq!(
+            lines.clone().take(expected.len()).collect::<Vec<_>>(),
+            expected
+        );
+        // The toolbar is exported as a special folder, and the (empty)
+        // unfiled and mobile roots aren't exported at all.
+        let toolbar = lines.skip(expected.len()).collect::<Vec<_>>();
+        assert_eq!(toolbar.len(), 5);
+        assert!(toolbar[0].ends_with(r#"PERSONAL_TOOLBAR_FOLDER="true">Bookmarks Toolbar</H3>"#));
+        assert_eq!(
+            toolbar[2],
+            format!(
+                r#"        <DT><A HREF="https://www.mozilla.org/" {} SHORTCUTURL="moz" TAGS="firefox,mozilla">&quot;quoted&quot;</A>"#,
+                dates
+            )
+        );
+        assert_eq!(toolbar[4], "</DL>");
+    }
+}
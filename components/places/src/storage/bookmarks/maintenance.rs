@@ -0,0 +1,335 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Cleans up the clutter that long-lived profiles collect, mostly from
+//! importing the same bookmarks more than once:
+//!
+//! - Duplicate bookmarks, with the same URL as an earlier sibling.
+//! - Stray separators, at the start or end of a folder, or right after
+//!   another separator.
+//! - Empty folders, including ones which are only empty because of the above.
+//!
+//! Roots are never removed. Removing an item writes a tombstone if it was
+//! synced, and bumps its parent's change counter, so the cleanup is uploaded
+//! on the next sync.
+
+use super::{delete_bookmark_in_tx, BookmarkRootGuid, RowId};
+use crate::db::PlacesDb;
+use crate::error::*;
+use crate::types::{BookmarkType, Timestamp};
+use rusqlite::Row;
+use sql_support::ConnExt;
+use std::collections::{HashMap, HashSet};
+use sync_guid::Guid as SyncGuid;
+
+/// The items a cleanup removes, or would remove for a dry run. Items in empty
+/// folders are listed before the folders themselves.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CleanupReport {
+    pub duplicate_bookmarks: Vec<SyncGuid>,
+    pub stray_separators: Vec<SyncGuid>,
+    pub empty_folders: Vec<SyncGuid>,
+}
+
+impl CleanupReport {
+    pub fn is_empty(&self) -> bool {
+        self.duplicate_bookmarks.is_empty()
+            && self.stray_separators.is_empty()
+            && self.empty_folders.is_empty()
+    }
+}
+
+/// Finds duplicate bookmarks, stray separators and empty folders, and removes
+/// them unless `dry_run` is set.
+pub fn clean_up_bookmarks(db: &PlacesDb, dry_run: bool) -> Result<CleanupReport> {
+    let tx = db.begin_transaction()?;
+    let result = find_clutter(db).and_then(|report| {
+        if !dry_run {
+            remove_clutter(db, &report)?;
+        }
+        Ok(report)
+    });
+    match result {
+        Ok(_) => tx.commit()?,
+        Err(_) => tx.rollback()?,
+    }
+    db.notify_bookmark_observers();
+    result
+}
+
+#[derive(Debug)]
+struct Item {
+    id: RowId,
+    guid: SyncGuid,
+    bookmark_type: BookmarkType,
+    url: Option<String>,
+}
+
+impl Item {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            guid: row.get("guid")?,
+            bookmark_type: row.get("type")?,
+            url: row.get("url")?,
+        })
+    }
+}
+
+fn find_clutter(db: &PlacesDb) -> Result<CleanupReport> {
+    let mut children: HashMap<RowId, Vec<Item>> = HashMap::new();
+    let rows = db.query_rows_and_then_named(
+        "SELECT b.id, b.guid, b.type, b.parent, h.url
+         FROM moz_bookmarks b
+         LEFT JOIN moz_places h ON h.id = b.fk
+         WHERE b.parent NOT NULL
+         ORDER BY b.parent, b.position",
+        &[],
+        |row| -> Result<_> { Ok((row.get::<_, RowId>("parent")?, Item::from_row(row)?)) },
+    )?;
+    for (parent_id, item) in rows {
+        children.entry(parent_id).or_default().push(item);
+    }
+    let root_id = db.query_row_and_then_named(
+        "SELECT id FROM moz_bookmarks WHERE guid = :guid",
+        &[(":guid", &BookmarkRootGuid::Root.as_guid())],
+        |row| row.get::<_, RowId>(0),
+        true,
+    )?;
+    let mut report = CleanupReport::default();
+    find_clutter_in_folder(&children, root_id, &mut report);
+    Ok(report)
+}
+
+/// Adds the clutter in a folder and its descendants to `report`, and returns
+/// whether the folder would be empty once it's removed.
+fn find_clutter_in_folder(
+    children: &HashMap<RowId, Vec<Item>>,
+    folder_id: RowId,
+    report: &mut CleanupReport,
+) -> bool {
+    let items = match children.get(&folder_id) {
+        Some(items) => items,
+        None => return true,
+    };
+    let mut kept: Vec<&Item> = Vec::with_capacity(items.len());
+    let mut seen_urls = HashSet::new();
+    for item in items {
+        match item.bookmark_type {
+            BookmarkType::Bookmark => {
+                if let Some(url) = &item.url {
+                    if !seen_urls.insert(url.as_str()) {
+                        report.duplicate_bookmarks.push(item.guid.clone());
+                        continue;
+                    }
+                }
+                kept.push(item);
+            }
+            BookmarkType::Folder => {
+                let is_empty = find_clutter_in_folder(children, item.id, report);
+                if is_empty && BookmarkRootGuid::well_known(item.guid.as_str()).is_none() {
+                    report.empty_folders.push(item.guid.clone());
+                } else {
+                    kept.push(item);
+                }
+            }
+            BookmarkType::Separator => kept.push(item),
+        }
+    }
+    // Now that we know which items are staying, look for separators which
+    // would be next to each other, or at either end of the folder.
+    let mut remaining: Vec<&Item> = Vec::with_capacity(kept.len());
+    for item in kept {
+        let is_stray = item.bookmark_type == BookmarkType::Separator
+            && remaining
+                .last()
+                .map_or(true, |prev| prev.bookmark_type == BookmarkType::Separator);
+        if is_stray {
+            report.stray_separators.push(item.guid.clone());
+        } else {
+            remaining.push(item);
+        }
+    }
+    while let Some(last) = remaining.last() {
+        if last.bookmark_type != BookmarkType::Separator {
+            break;
+        }
+        report.stray_separators.push(last.guid.clone());
+        remaining.pop();
+    }
+    remaining.is_empty()
+}
+
+fn remove_clutter(db: &PlacesDb, report: &CleanupReport) -> Result<()> {
+    // Removed items are tombstoned by `delete_bookmark_in_tx`, but the parents
+    // which lost children need uploading, too.
+    let mut parents = HashSet::new();
+    for guid in report
+        .duplicate_bookmarks
+        .iter()
+        .chain(&report.stray_separators)
+        .chain(&report.empty_folders)
+    {
+        if let Some(parent_id) = db.try_query_row(
+            "SELECT parent FROM moz_bookmarks WHERE guid = :guid",
+            &[(":guid", guid)],
+            |row| row.get::<_, RowId>(0),
+            true,
+        )? {
+            parents.insert(parent_id);
+        }
+        delete_bookmark_in_tx(db, guid)?;
+    }
+    let now = Timestamp::now();
+    for parent_id in parents {
+        // Parents which were removed themselves won't match.
+        db.execute_named_cached(
+            "UPDATE moz_bookmarks SET
+                 syncChangeCounter = syncChangeCounter + 1,
+                 lastModified = MAX(lastModified, :now)
+             WHERE id = :parent_id",
+            &[(":now", &now), (":parent_id", &parent_id)],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::storage::bookmarks::get_raw_bookmark;
+    use crate::tests::{assert_json_tree, insert_json_tree};
+    use crate::types::SyncStatus;
+    use rusqlite::NO_PARAMS;
+    use serde_json::json;
+
+    #[test]
+    fn test_clean_up_bookmarks() -> Result<()> {
+        let conn = new_mem_connection();
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": &BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    {
+                        "guid": "separator1__",
+                        "type": BookmarkType::Separator,
+                    },
+                    {
+                        "guid": "bookmark1___",
+                        "url": "https://www.example1.com/",
+                    },
+                    {
+                        "guid": "separator2__",
+                        "type": BookmarkType::Separator,
+                    },
+                    {
+                        "guid": "folder1_____",
+                        "children": [
+                            {
+                                "guid": "folder2_____",
+                                "children": [],
+                            },
+                            {
+                                "guid": "separator3__",
+                                "type": BookmarkType::Separator,
+                            },
+                        ],
+                    },
+                    {
+                        "guid": "separator4__",
+                        "type": BookmarkType::Separator,
+                    },
+                    {
+                        "guid": "bookmark2___",
+                        "url": "https://www.example1.com/",
+                    },
+                    {
+                        "guid": "bookmark3___",
+                        "url": "https://www.example2.com/",
+                    },
+                    {
+                        "guid": "separator5__",
+                        "type": BookmarkType::Separator,
+                    },
+                ]
+            }),
+        );
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": &BookmarkRootGuid::Menu.as_guid(),
+                "children": [
+                    {
+                        // Not a duplicate, since it's in another folder.
+                        "guid": "bookmark4___",
+                        "url": "https://www.example1.com/",
+                    },
+                ]
+            }),
+        );
+        conn.execute(
+            &format!(
+                "UPDATE moz_bookmarks SET syncStatus = {}, syncChangeCounter = 0",
+                SyncStatus::Normal as u8
+            ),
+            NO_PARAMS,
+        )?;
+
+        let expected = CleanupReport {
+            duplicate_bookmarks: vec!["bookmark2___".into()],
+            stray_separators: vec![
+                "separator3__".into(),
+                "separator1__".into(),
+                "separator4__".into(),
+                "separator5__".into(),
+            ],
+            empty_folders: vec!["folder2_____".into(), "folder1_____".into()],
+        };
+
+        // A dry run doesn't change anything.
+        assert_eq!(clean_up_bookmarks(&conn, true)?, expected);
+        assert!(get_raw_bookmark(&conn, &"folder1_____".into())?.is_some());
+
+        assert_eq!(clean_up_bookmarks(&conn, false)?, expected);
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Unfiled.as_guid(),
+            json!({
+                "guid": &BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    {
+                        "guid": "bookmark1___",
+                        "url": "https://www.example1.com/",
+                    },
+                    {
+                        "guid": "separator2__",
+                        "type": BookmarkType::Separator,
+                    },
+                    {
+                        "guid": "bookmark3___",
+                        "url": "https://www.example2.com/",
+                    },
+                ]
+            }),
+        );
+        assert!(get_raw_bookmark(&conn, &"bookmark4___".into())?.is_some());
+
+        // Everything we removed was synced, so needs tombstones, and the
+        // folder we removed them from needs uploading.
+        let tombstones = conn.query_one::<u32>("SELECT COUNT(*) FROM moz_bookmarks_deleted")?;
+        assert_eq!(tombstones, 7);
+        let unfiled =
+            get_raw_bookmark(&conn, &BookmarkRootGuid::Unfiled.as_guid())?.expect("should exist");
+        assert_eq!(unfiled.sync_change_counter, 1);
+        let menu =
+            get_raw_bookmark(&conn, &BookmarkRootGuid::Menu.as_guid())?.expect("should exist");
+        assert_eq!(menu.sync_change_counter, 0);
+
+        // And there's nothing left to clean up.
+        assert!(clean_up_bookmarks(&conn, true)?.is_empty());
+        Ok(())
+    }
+}
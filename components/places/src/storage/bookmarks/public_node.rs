@@ -129,6 +129,7 @@ pub fn update_bookmark_from_message(db: &PlacesDb, msg: ProtoBookmark) -> Result
 
     update_bookmark_in_tx(db, &guid, &updatable, existing)?;
     tx.commit()?;
+    db.notify_bookmark_observers();
     Ok(())
 }
 
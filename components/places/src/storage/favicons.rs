@@ -0,0 +1,280 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Favicon storage. Icons are stored per size, and mapped to the pages that
+//! use them, so that they're removed along with history.
+
+use super::fetch_page_info;
+use crate::db::PlacesDb;
+use crate::error::{InvalidPlaceInfo, Result};
+use crate::types::Timestamp;
+use rusqlite::Row;
+use sql_support::ConnExt;
+use url::Url;
+
+/// Like desktop, we treat scalable icons as larger than any fixed size.
+const SCALABLE_ICON_WIDTH: u32 = 65535;
+
+/// A single size of a favicon.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Icon {
+    /// The URL the icon was loaded from.
+    pub url: Url,
+    /// The width of the icon, in pixels. Icons are square, so this is also
+    /// the height. 0 means the icon is scalable, and can be used at any size.
+    pub width: u32,
+    pub mime_type: Option<String>,
+    pub data: Vec<u8>,
+}
+
+impl Icon {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        Ok(Self {
+            url: Url::parse(&row.get::<_, String>("icon_url")?)?,
+            width: row.get("width")?,
+            mime_type: row.get("mime_type")?,
+            data: row.get("data")?,
+        })
+    }
+
+    fn effective_width(&self) -> u32 {
+        if self.width == 0 {
+            SCALABLE_ICON_WIDTH
+        } else {
+            self.width
+        }
+    }
+}
+
+/// Stores an icon, and maps it to a page. The page must already exist, for
+/// example, because it was visited or bookmarked. Storing an icon with the
+/// same URL and width as an existing one replaces its data.
+pub fn store_icon_for_page(db: &PlacesDb, page_url: &Url, icon: &Icon) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    let result = store_icon_for_page_in_tx(db, page_url, icon);
+    match result {
+        Ok(_) => tx.commit()?,
+        Err(_) => tx.rollback()?,
+    }
+    result
+}
+
+fn store_icon_for_page_in_tx(db: &PlacesDb, page_url: &Url, icon: &Icon) -> Result<()> {
+    let page_id = match fetch_page_info(db, page_url)? {
+        Some(info) => info.page.row_id,
+        None => return Err(InvalidPlaceInfo::NoSuchUrl.into()),
+    };
+    db.execute_named_cached(
+        "INSERT INTO moz_icons(icon_url, icon_url_hash, width, mime_type, data, last_updated)
+         VALUES(:icon_url, hash(:icon_url), :width, :mime_type, :data, :now)
+         ON CONFLICT(icon_url, width) DO UPDATE SET
+             mime_type = excluded.mime_type,
+             data = excluded.data,
+             last_updated = excluded.last_updated",
+        &[
+            (":icon_url", &icon.url.as_str()),
+            (":width", &icon.width),
+            (":mime_type", &icon.mime_type),
+            (":data", &icon.data),
+            (":now", &Timestamp::now()),
+        ],
+    )?;
+    db.execute_named_cached(
+        "INSERT OR IGNORE INTO moz_icons_to_pages(page_id, icon_id)
+         SELECT :page_id, id FROM moz_icons
+         WHERE icon_url_hash = hash(:icon_url) AND icon_url = :icon_url
+           AND width = :width",
+        &[
+            (":page_id", &page_id),
+            (":icon_url", &icon.url.as_str()),
+            (":width", &icon.width),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Returns all the icons for a page, in no particular order.
+pub fn get_icons_for_page(db: &PlacesDb, page_url: &Url) -> Result<Vec<Icon>> {
+    db.query_rows_and_then_named_cached(
+        "SELECT i.icon_url, i.width, i.mime_type, i.data
+         FROM moz_icons i
+         JOIN moz_icons_to_pages p ON p.icon_id = i.id
+         JOIN moz_places h ON h.id = p.page_id
+         WHERE h.url_hash = hash(:page_url) AND h.url = :page_url",
+        &[(":page_url", &page_url.as_str())],
+        Icon::from_row,
+    )
+}
+
+/// Returns the icon for a page which best fits the `preferred_width`: the
+/// smallest icon at least as wide, or the largest icon if they're all
+/// narrower. A `preferred_width` of 0 returns the largest icon.
+pub fn get_best_icon_for_page(
+    db: &PlacesDb,
+    page_url: &Url,
+    preferred_width: u32,
+) -> Result<Option<Icon>> {
+    let icons = get_icons_for_page(db, page_url)?;
+    Ok(choose_best_icon(icons, preferred_width))
+}
+
+fn choose_best_icon(icons: Vec<Icon>, preferred_width: u32) -> Option<Icon> {
+    let preferred_width = if preferred_width == 0 {
+        SCALABLE_ICON_WIDTH
+    } else {
+        preferred_width
+    };
+    let mut best: Option<Icon> = None;
+    for icon in icons {
+        let is_better = match &best {
+            None => true,
+            Some(best) => {
+                let (width, best_width) = (icon.effective_width(), best.effective_width());
+                if best_width >= preferred_width {
+                    // Prefer a smaller icon that's still wide enough.
+                    width >= preferred_width && width < best_width
+                } else {
+                    // Nothing is wide enough yet, so prefer a wider icon.
+                    width > best_width
+                }
+            }
+        };
+        if is_better {
+            best = Some(icon);
+        }
+    }
+    best
+}
+
+/// Removes all icons for a page.
+pub fn delete_icons_for_page(db: &PlacesDb, page_url: &Url) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    db.execute_named_cached(
+        "DELETE FROM moz_icons_to_pages
+         WHERE page_id = (SELECT id FROM moz_places
+                          WHERE url_hash = hash(:page_url) AND url = :page_url)",
+        &[(":page_url", &page_url.as_str())],
+    )?;
+    delete_orphaned_icons(db)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Removes icons which aren't used by any pages. Mappings are removed along
+/// with their pages, so this should be called after deleting pages.
+pub(crate) fn delete_orphaned_icons(db: &PlacesDb) -> Result<()> {
+    db.execute_cached(
+        "DELETE FROM moz_icons
+         WHERE NOT EXISTS(SELECT 1 FROM moz_icons_to_pages
+                          WHERE icon_id = moz_icons.id)",
+        rusqlite::NO_PARAMS,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::error::ErrorKind;
+    use crate::observation::VisitObservation;
+    use crate::storage::history::{apply_observation, delete_visits_for, url_to_guid};
+    use crate::types::VisitTransition;
+
+    fn icon(url: &str, width: u32) -> Icon {
+        Icon {
+            url: Url::parse(url).unwrap(),
+            width,
+            mime_type: Some("image/png".to_string()),
+            data: vec![width as u8],
+        }
+    }
+
+    fn visit(conn: &PlacesDb, url: &Url) {
+        apply_observation(
+            conn,
+            VisitObservation::new(url.clone())
+                .with_at(Timestamp::now())
+                .with_visit_type(VisitTransition::Link),
+        )
+        .expect("should apply visit");
+    }
+
+    fn count_icons(conn: &PlacesDb) -> u32 {
+        conn.query_one("SELECT COUNT(*) FROM moz_icons").unwrap()
+    }
+
+    #[test]
+    fn test_choose_best_icon() {
+        let icons = vec![
+            icon("https://example.com/16.png", 16),
+            icon("https://example.com/32.png", 32),
+            icon("https://example.com/64.png", 64),
+        ];
+        let best_width = |icons: &[Icon], width| {
+            choose_best_icon(icons.to_vec(), width)
+                .map(|icon| icon.width)
+                .unwrap()
+        };
+        assert_eq!(best_width(&icons, 16), 16);
+        assert_eq!(best_width(&icons, 20), 32);
+        assert_eq!(best_width(&icons, 128), 64);
+        assert_eq!(best_width(&icons, 0), 64);
+
+        let mut with_svg = icons.clone();
+        with_svg.push(icon("https://example.com/icon.svg", 0));
+        assert_eq!(best_width(&with_svg, 20), 32);
+        assert_eq!(best_width(&with_svg, 128), 0);
+        assert_eq!(best_width(&with_svg, 0), 0);
+
+        assert_eq!(choose_best_icon(Vec::new(), 16), None);
+    }
+
+    #[test]
+    fn test_store_icons() {
+        let conn = new_mem_connection();
+        let page = Url::parse("https://example.com/page").unwrap();
+
+        match store_icon_for_page(&conn, &page, &icon("https://example.com/16.png", 16))
+            .expect_err("should fail for unknown page")
+            .kind()
+        {
+            ErrorKind::InvalidPlaceInfo(InvalidPlaceInfo::NoSuchUrl) => {}
+            e => panic!("Wanted no such URL error; got {:?}", e),
+        }
+
+        visit(&conn, &page);
+        store_icon_for_page(&conn, &page, &icon("https://example.com/16.png", 16)).unwrap();
+        store_icon_for_page(&conn, &page, &icon("https://example.com/32.png", 32)).unwrap();
+        assert_eq!(get_icons_for_page(&conn, &page).unwrap().len(), 2);
+        assert_eq!(
+            get_best_icon_for_page(&conn, &page, 24).unwrap(),
+            Some(icon("https://example.com/32.png", 32))
+        );
+
+        // Storing the same URL and width again replaces the data, and the
+        // icon can be shared with other pages.
+        let mut replaced = icon("https://example.com/32.png", 32);
+        replaced.data = vec![1, 2, 3];
+        let other_page = Url::parse("https://example.com/other").unwrap();
+        visit(&conn, &other_page);
+        store_icon_for_page(&conn, &other_page, &replaced).unwrap();
+        assert_eq!(count_icons(&conn), 2);
+        assert_eq!(
+            get_best_icon_for_page(&conn, &page, 32).unwrap(),
+            Some(replaced)
+        );
+
+        // Removing the icons for one page keeps icons used by other pages.
+        delete_icons_for_page(&conn, &page).unwrap();
+        assert!(get_icons_for_page(&conn, &page).unwrap().is_empty());
+        assert_eq!(count_icons(&conn), 1);
+        assert_eq!(get_icons_for_page(&conn, &other_page).unwrap().len(), 1);
+
+        // Deleting history for a page removes its icons.
+        let guid = url_to_guid(&conn, &other_page).unwrap().unwrap();
+        delete_visits_for(&conn, &guid).unwrap();
+        assert_eq!(count_icons(&conn), 0);
+    }
+}
@@ -2,6 +2,9 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use super::favicons::delete_orphaned_icons;
+use super::history_metadata::{delete_metadata_between, delete_metadata_for_page, escape_like};
+use super::undo::{self, UndoKind};
 use super::{fetch_page_info, new_page_info, PageInfo, RowId};
 use crate::db::PlacesDb;
 use crate::error::Result;
@@ -13,7 +16,9 @@ use crate::history_sync::store::{
 use crate::msg_types::{HistoryVisitInfo, HistoryVisitInfos, HistoryVisitInfosWithBound};
 use crate::observation::VisitObservation;
 use crate::storage::{delete_meta, delete_pending_temp_tables, get_meta, put_meta};
-use crate::types::{SyncStatus, Timestamp, VisitTransition, VisitTransitionSet};
+use crate::types::{
+    SyncStatus, Timestamp, TopSiteExclusionKind, VisitTransition, VisitTransitionSet,
+};
 use rusqlite::types::ToSql;
 use rusqlite::Result as RusqliteResult;
 use rusqlite::{Row, NO_PARAMS};
@@ -21,6 +26,12 @@ use sql_support::{self, ConnExt};
 use sync_guid::Guid as SyncGuid;
 use url::Url;
 
+pub use query::{
+    HistoryCursor, HistoryGrouping, HistoryPage, HistoryQuery, HistoryVisit, VisitSource,
+};
+
+pub mod query;
+
 /// When `delete_everything` is called (to perform a permanent local deletion), in
 /// addition to performing the deletion as requested, we make a note of the time
 /// when it occurred, and refuse to sync incoming visits from before this time.
@@ -47,9 +58,9 @@ pub fn apply_observation_direct(
     if url.as_str().len() > super::URL_LENGTH_MAX {
         return Ok(None);
     }
-    let mut page_info = match fetch_page_info(db, &url)? {
-        Some(info) => info.page,
-        None => new_page_info(db, &url, None)?,
+    let (mut page_info, is_new_page) = match fetch_page_info(db, &url)? {
+        Some(info) => (info.page, false),
+        None => (new_page_info(db, &url, None)?, true),
     };
     let mut update_change_counter = false;
     let mut update_frec = false;
@@ -64,9 +75,13 @@ pub fn apply_observation_direct(
     // testing we return the rowid of the visit we added.
     let visit_row_id = match visit_ob.visit_type {
         Some(visit_type) => {
-            // A single non-hidden visit makes the place non-hidden.
+            // A single non-hidden visit makes the place non-hidden, and
+            // places first seen with a hidden visit, like redirect sources,
+            // start out hidden.
             if !visit_ob.get_is_hidden() {
                 updates.push(("hidden", ":hidden", &false));
+            } else if is_new_page {
+                updates.push(("hidden", ":hidden", &true));
             }
             if visit_type == VisitTransition::Typed {
                 page_info.typed += 1;
@@ -213,6 +228,17 @@ pub fn href_to_guid(db: &PlacesDb, url: &str) -> Result<Option<SyncGuid>> {
 /// Internal function for deleting a page, creating a tombstone if necessary.
 /// Assumes a transaction is already set up by the caller.
 fn delete_visits_for_in_tx(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
+    delete_page_history(db, guid)?;
+    delete_orphaned_icons(db)?;
+    delete_pending_temp_tables(db)?;
+    Ok(())
+}
+
+/// Deletes a page's visits and metadata, and the page itself unless it's
+/// bookmarked or has a keyword or tags, creating tombstones if necessary.
+/// Leaves removing orphaned icons and flushing the temp tables to the caller,
+/// so that deleting many pages only does those once.
+fn delete_page_history(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
     // We only create tombstones for history which exists and with sync_status
     // == SyncStatus::Normal
     let to_clean = db.conn().try_query_row(
@@ -226,6 +252,11 @@ fn delete_visits_for_in_tx(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
         PageToClean::from_row,
         true,
     )?;
+    if let Some(page) = &to_clean {
+        // Metadata for the page, and for pages it referred to, is part of its
+        // history, so it's removed even if we keep the page.
+        delete_metadata_for_page(db, page.id)?;
+    }
     match to_clean {
         Some(PageToClean {
             id,
@@ -275,7 +306,6 @@ fn delete_visits_for_in_tx(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
         }
         None => {}
     }
-    delete_pending_temp_tables(db)?;
     Ok(())
 }
 
@@ -326,7 +356,8 @@ fn delete_page(db: &PlacesDb, page_id: RowId) -> Result<()> {
 /// necessary.
 pub fn delete_visits_for(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
     let tx = db.begin_transaction()?;
-    let result = delete_visits_for_in_tx(db, guid);
+    let result =
+        undo::journal_history_for_page(db, guid).and_then(|()| delete_visits_for_in_tx(db, guid));
     tx.commit()?;
     result
 }
@@ -334,11 +365,94 @@ pub fn delete_visits_for(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
 /// Delete all visits in a date range.
 pub fn delete_visits_between(db: &PlacesDb, start: Timestamp, end: Timestamp) -> Result<()> {
     let tx = db.begin_transaction()?;
+    undo::journal_history_between(db, start, end)?;
     delete_visits_between_in_tx(db, start, end)?;
     tx.commit()?;
     Ok(())
 }
 
+/// Deletes all history for pages on `host`, or any of its subdomains, with
+/// any scheme or port. This removes their visits, metadata and input history,
+/// and writes tombstones like `delete_visits_for`. Pages which are
+/// bookmarked, or have a keyword or tags, are kept without their visits, and
+/// so are their origins; everything else is removed. Unlike
+/// `delete_visits_for`, this can't be undone, and it also removes the site's
+/// pages from any earlier history deletions which could still be undone.
+pub fn delete_visits_for_host(db: &PlacesDb, host: &str) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    let result = delete_visits_for_host_in_tx(db, host);
+    match result {
+        Ok(_) => tx.commit()?,
+        Err(_) => tx.rollback()?,
+    }
+    result
+}
+
+// Matches the origin `o` against `:host` and its subdomains, with or
+// without a port, using the patterns from `host_patterns`.
+// `moz_origins.host` includes the port, if there is one.
+const HOST_MATCHES_SQL: &str = "(o.host = :host OR
+      o.host LIKE :host_with_port ESCAPE '\\' OR
+      o.host LIKE :subdomain ESCAPE '\\' OR
+      o.host LIKE :subdomain_with_port ESCAPE '\\')";
+
+/// Returns the `LIKE` patterns for `HOST_MATCHES_SQL`'s `:host_with_port`,
+/// `:subdomain` and `:subdomain_with_port`, in that order.
+fn host_patterns(host: &str) -> [String; 3] {
+    let escaped_host = escape_like(host);
+    [
+        format!("{}:%", escaped_host),
+        format!("%.{}", escaped_host),
+        format!("%.{}:%", escaped_host),
+    ]
+}
+
+fn delete_visits_for_host_in_tx(db: &PlacesDb, host: &str) -> Result<()> {
+    let host = host.trim().trim_end_matches('.').to_ascii_lowercase();
+    if host.is_empty() {
+        return Ok(());
+    }
+    let [host_with_port, subdomain, subdomain_with_port] = host_patterns(&host);
+    let pages = db.query_rows_and_then_named(
+        &format!(
+            "SELECT h.id, h.guid, (h.foreign_count != 0) AS has_foreign
+             FROM moz_places h
+             JOIN moz_origins o ON o.id = h.origin_id
+             WHERE {host_matches}",
+            host_matches = HOST_MATCHES_SQL
+        ),
+        &[
+            (":host", &host),
+            (":host_with_port", &host_with_port),
+            (":subdomain", &subdomain),
+            (":subdomain_with_port", &subdomain_with_port),
+        ],
+        |row| -> rusqlite::Result<_> {
+            Ok((
+                row.get::<_, RowId>("id")?,
+                row.get::<_, SyncGuid>("guid")?,
+                row.get::<_, bool>("has_foreign")?,
+            ))
+        },
+    )?;
+    for (page_id, guid, _) in &pages {
+        db.execute_named_cached(
+            "DELETE FROM moz_inputhistory WHERE place_id = :page_id",
+            &[(":page_id", page_id)],
+        )?;
+        delete_page_history(db, guid)?;
+    }
+    // The pages we kept don't have any visits now, so their frecencies need
+    // recalculating.
+    for (page_id, _, _) in pages.iter().filter(|(_, _, has_foreign)| *has_foreign) {
+        update_frecency(db, *page_id, None)?;
+    }
+    undo::delete_undo_history_for_host(db, &host)?;
+    delete_orphaned_icons(db)?;
+    delete_pending_temp_tables(db)?;
+    Ok(())
+}
+
 pub fn delete_place_visit_at_time(db: &PlacesDb, place: &Url, visit: Timestamp) -> Result<()> {
     delete_place_visit_at_time_by_href(db, place.as_str(), visit)
 }
@@ -354,9 +468,145 @@ pub fn delete_place_visit_at_time_by_href(
     Ok(())
 }
 
-pub fn prune_destructively(db: &PlacesDb) -> Result<()> {
-    // For now, just fall back to wipe_local until we decide how this should work.
-    wipe_local(db)
+/// The default size `prune_destructively` shrinks the database to. This
+/// matches desktop's `DATABASE_MAX_SIZE`.
+pub const DEFAULT_PRUNE_TARGET_SIZE_BYTES: u64 = 60 * 1024 * 1024;
+
+/// Visits more recent than this many days are never pruned.
+pub const DEFAULT_PRUNE_KEEP_DAYS: u64 = 7;
+
+const PRUNE_BATCH_SIZE: u64 = 1000;
+
+/// Limits for `prune_destructively`. Pruning stops once the database is
+/// within all of the limits, or there's nothing left to prune.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PruneOptions {
+    /// The maximum size of the database, not counting free pages.
+    pub target_size_bytes: Option<u64>,
+    /// The maximum number of visits to keep.
+    pub target_visit_count: Option<u64>,
+    /// Visits on or after this date are never pruned.
+    pub keep_visits_since: Timestamp,
+}
+
+impl Default for PruneOptions {
+    fn default() -> Self {
+        PruneOptions {
+            target_size_bytes: Some(DEFAULT_PRUNE_TARGET_SIZE_BYTES),
+            target_visit_count: None,
+            keep_visits_since: Timestamp(
+                Timestamp::now()
+                    .as_millis()
+                    .saturating_sub(DEFAULT_PRUNE_KEEP_DAYS * 24 * 60 * 60 * 1000),
+            ),
+        }
+    }
+}
+
+/// What `prune_destructively` removed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PruneMetrics {
+    pub visits_removed: u64,
+    pub places_removed: u64,
+    pub size_before_bytes: u64,
+    pub size_after_bytes: u64,
+}
+
+/// Removes old visits to infrequently visited pages, and then the pages
+/// themselves once they have no visits left, until the database is within the
+/// limits in `options`. Like desktop's expiration, the least frecent pages go
+/// first, and visits to bookmarked pages (and pages with keywords or tags) are
+/// never removed. Removed visits and pages get tombstones, like any other
+/// deletion, so that Sync doesn't bring them back.
+///
+/// This is intended for low disk space situations, so it also vacuums the
+/// database afterward.
+pub fn prune_destructively(db: &PlacesDb, options: &PruneOptions) -> Result<PruneMetrics> {
+    let mut metrics = PruneMetrics {
+        size_before_bytes: get_used_db_size(db)?,
+        ..PruneMetrics::default()
+    };
+    let places_before = db.query_one::<i64>("SELECT COUNT(*) FROM moz_places")?;
+    // Removing visits adds tombstones, and only frees pages once they're
+    // completely empty, so a batch might not shrink the database at all. We
+    // stop pruning for size once that happens, instead of removing everything
+    // old without getting any closer to the target.
+    let mut last_size = None;
+    loop {
+        let excess_visits = match options.target_visit_count {
+            Some(target) => {
+                let visit_count = db.query_one::<i64>("SELECT COUNT(*) FROM moz_historyvisits")?;
+                (visit_count.max(0) as u64).saturating_sub(target)
+            }
+            None => 0,
+        };
+        let over_size = match options.target_size_bytes {
+            Some(target) => {
+                let size = get_used_db_size(db)?;
+                let shrinking = last_size.map_or(true, |last| size < last);
+                if !shrinking {
+                    log::info!("Pruning isn't shrinking the database; stopping");
+                }
+                last_size = Some(size);
+                size > target && shrinking
+            }
+            None => false,
+        };
+        let limit = if over_size {
+            PRUNE_BATCH_SIZE
+        } else {
+            excess_visits.min(PRUNE_BATCH_SIZE)
+        };
+        if limit == 0 {
+            break;
+        }
+        let tx = db.begin_transaction()?;
+        let visits = db.query_rows_and_then_named(
+            "SELECT v.id, v.place_id, v.visit_date
+             FROM moz_historyvisits v
+             JOIN moz_places h ON h.id = v.place_id
+             WHERE v.visit_date < :keep_since
+               AND h.foreign_count = 0
+             ORDER BY h.frecency ASC, v.visit_date ASC
+             LIMIT :limit",
+            &[
+                (":keep_since", &options.keep_visits_since),
+                (":limit", &(limit as i64)),
+            ],
+            |row| -> rusqlite::Result<_> {
+                Ok((
+                    row.get::<_, RowId>(0)?,
+                    row.get::<_, RowId>(1)?,
+                    row.get::<_, Timestamp>(2)?,
+                ))
+            },
+        )?;
+        if visits.is_empty() {
+            tx.commit()?;
+            break;
+        }
+        delete_visits_in_tx(db, &visits)?;
+        tx.commit()?;
+        metrics.visits_removed += visits.len() as u64;
+    }
+    let places_after = db.query_one::<i64>("SELECT COUNT(*) FROM moz_places")?;
+    metrics.places_removed = (places_before - places_after).max(0) as u64;
+    if metrics.visits_removed > 0 {
+        // Note: SQLite cannot VACUUM within a transaction.
+        db.execute_batch("VACUUM")?;
+    }
+    metrics.size_after_bytes = get_used_db_size(db)?;
+    log::info!("Pruned history: {:?}", metrics);
+    Ok(metrics)
+}
+
+/// Returns the size of the database, not counting pages on the freelist,
+/// which are reused before the file grows.
+fn get_used_db_size(db: &PlacesDb) -> Result<u64> {
+    let page_count = db.query_one::<i64>("PRAGMA page_count")?;
+    let freelist_count = db.query_one::<i64>("PRAGMA freelist_count")?;
+    let page_size = db.query_one::<i64>("PRAGMA page_size")?;
+    Ok(((page_count - freelist_count) * page_size).max(0) as u64)
 }
 
 pub fn wipe_local(db: &PlacesDb) -> Result<()> {
@@ -370,7 +620,15 @@ pub fn wipe_local(db: &PlacesDb) -> Result<()> {
 
 fn wipe_local_in_tx(db: &PlacesDb) -> Result<()> {
     use crate::frecency::DEFAULT_FRECENCY_SETTINGS;
+    undo::delete_undo_entries(db, UndoKind::History)?;
     db.execute_all(&[
+        "DELETE FROM moz_places_metadata",
+        "DELETE FROM moz_places_metadata_search_queries",
+        // Blocked sites reveal history, but pinned sites are the user's choice.
+        &format!(
+            "DELETE FROM moz_top_sites_exclusions WHERE kind = {}",
+            TopSiteExclusionKind::Blocked as u8
+        ),
         "DELETE FROM moz_places WHERE foreign_count == 0",
         "DELETE FROM moz_historyvisits",
         "DELETE FROM moz_places_tombstones",
@@ -391,6 +649,7 @@ fn wipe_local_in_tx(db: &PlacesDb) -> Result<()> {
             unvisited_bookmark_frec = DEFAULT_FRECENCY_SETTINGS.unvisited_bookmark_bonus
         ),
     ])?;
+    delete_orphaned_icons(db)?;
 
     let need_frecency_update =
         db.query_rows_and_then_named("SELECT id FROM moz_places", &[], |r| r.get::<_, RowId>(0))?;
@@ -509,9 +768,15 @@ pub fn delete_visits_between_in_tx(db: &PlacesDb, start: Timestamp, end: Timesta
             ))
         },
     )?;
+    delete_metadata_between(db, start, end)?;
+    delete_visits_in_tx(db, &visits)
+}
 
+/// Deletes visits given their ids, place ids and dates, writes tombstones for
+/// them, and cleans up the affected pages.
+fn delete_visits_in_tx(db: &PlacesDb, visits: &[(RowId, RowId, Timestamp)]) -> Result<()> {
     sql_support::each_chunk_mapped(
-        &visits,
+        visits,
         |(visit_id, _, _)| visit_id,
         |chunk, _| -> Result<()> {
             db.conn().execute(
@@ -539,7 +804,7 @@ pub fn delete_visits_between_in_tx(db: &PlacesDb, start: Timestamp, end: Timesta
 
     // Find out which pages have been possibly orphaned and clean them up.
     sql_support::each_chunk_mapped(
-        &visits,
+        visits,
         |(_, place_id, _)| place_id.0,
         |chunk, _| -> Result<()> {
             let query = format!(
@@ -638,6 +903,7 @@ fn cleanup_pages(db: &PlacesDb, pages: &[PageToClean]) -> Result<()> {
         )?;
         Ok(())
     })?;
+    delete_orphaned_icons(db)?;
 
     Ok(())
 }
@@ -1309,6 +1575,120 @@ pub fn get_visit_page_with_bound(
     }
 }
 
+/// A site to show in the top sites grid: the most frecent page for its host.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopFrecentSiteInfo {
+    pub url: Url,
+    pub title: Option<String>,
+    pub frecency: i64,
+}
+
+impl TopFrecentSiteInfo {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        Ok(Self {
+            url: Url::parse(&row.get::<_, String>("url")?)?,
+            title: row.get("title")?,
+            frecency: row.get("frecency")?,
+        })
+    }
+}
+
+/// Returns up to `num_items` of the most frecent sites, with a frecency of
+/// at least `frecency_threshold`, for the top sites grid. Only the most
+/// frecent page for each host is returned, and hidden pages (which include
+/// redirect sources), non-HTTP(S) pages, and pages only visited via
+/// downloads, embeds, frames or reloads are left out. Redirect targets are
+/// kept, since they're where the user ended up. Sites pinned or blocked with
+/// `add_top_site_exclusion` are also left out if their kind is in
+/// `excluded_kinds`.
+pub fn top_frecent_site_infos(
+    db: &PlacesDb,
+    num_items: u32,
+    frecency_threshold: i64,
+    excluded_kinds: &[TopSiteExclusionKind],
+) -> Result<Vec<TopFrecentSiteInfo>> {
+    let allowed_types = VisitTransitionSet::for_specific(&[
+        VisitTransition::Download,
+        VisitTransition::Embed,
+        VisitTransition::FramedLink,
+        VisitTransition::Reload,
+    ])
+    .complement();
+    let excluded_kinds = excluded_kinds
+        .iter()
+        .fold(0u32, |kinds, kind| kinds | (1 << *kind as u8));
+    // SQLite returns the other columns from the row with the `MAX` frecency
+    // in each group, which is how we pick the best page for each host.
+    let sql = format!(
+        "SELECT h.url, h.title, MAX(h.frecency) AS frecency
+         FROM moz_places h
+         JOIN moz_origins o ON o.id = h.origin_id
+         WHERE h.frecency >= :frecency_threshold
+           AND NOT h.hidden
+           AND o.prefix IN ('http://', 'https://')
+           AND EXISTS(SELECT 1 FROM moz_historyvisits v
+                      WHERE v.place_id = h.id
+                        AND ((1 << v.visit_type) & :allowed_types) != 0)
+           AND NOT EXISTS(SELECT 1 FROM moz_top_sites_exclusions e
+                          WHERE ((1 << e.kind) & :excluded_kinds) != 0
+                            AND (e.url = h.url OR
+                                 (e.kind = {pinned} AND e.host = o.host)))
+         GROUP BY o.host
+         ORDER BY frecency DESC, h.url
+         LIMIT :num_items",
+        pinned = TopSiteExclusionKind::Pinned as u8
+    );
+    db.query_rows_and_then_named_cached(
+        &sql,
+        rusqlite::named_params! {
+            ":frecency_threshold": frecency_threshold,
+            ":allowed_types": allowed_types,
+            ":excluded_kinds": excluded_kinds,
+            ":num_items": num_items,
+        },
+        TopFrecentSiteInfo::from_row,
+    )
+}
+
+/// Pins or blocks a site from the top sites. Adding an exclusion for a URL
+/// that's already excluded replaces its kind.
+pub fn add_top_site_exclusion(db: &PlacesDb, url: &Url, kind: TopSiteExclusionKind) -> Result<()> {
+    db.execute_named_cached(
+        "INSERT INTO moz_top_sites_exclusions(url, host, kind, date_added)
+         VALUES(:url, get_host_and_port(:url), :kind, :now)
+         ON CONFLICT(url) DO UPDATE SET
+             kind = excluded.kind,
+             date_added = excluded.date_added",
+        &[
+            (":url", &url.as_str()),
+            (":kind", &kind),
+            (":now", &Timestamp::now()),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Unpins or unblocks a site. Returns true if the site was excluded.
+pub fn remove_top_site_exclusion(db: &PlacesDb, url: &Url) -> Result<bool> {
+    let changed = db.execute_named_cached(
+        "DELETE FROM moz_top_sites_exclusions WHERE url = :url",
+        &[(":url", &url.as_str())],
+    )?;
+    Ok(changed != 0)
+}
+
+/// Returns the URLs of all sites pinned or blocked from the top sites, most
+/// recently excluded first.
+pub fn get_top_site_exclusions(db: &PlacesDb, kind: TopSiteExclusionKind) -> Result<Vec<Url>> {
+    db.query_rows_and_then_named_cached(
+        "SELECT url FROM moz_top_sites_exclusions
+         WHERE kind = :kind
+         ORDER BY date_added DESC, url",
+        &[(":kind", &kind)],
+        |row| -> Result<_> { Ok(Url::parse(&row.get::<_, String>(0)?)?) },
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::history_sync::*;
@@ -1951,8 +2331,8 @@ mod tests {
             }
 
             if let Some(keyword) = page.keyword {
-                // We don't have a public API for inserting keywords, so just
-                // write to the database directly.
+                // Our keyword API only allows keywords for bookmarked URLs,
+                // so just write to the database directly.
                 db.execute_named_cached(
                     "INSERT INTO moz_keywords(place_id, keyword)
                      SELECT id, :keyword
@@ -2035,6 +2415,117 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_delete_visits_for_host() -> Result<()> {
+        use crate::api::matcher::accept_result;
+        use crate::storage::bookmarks::{
+            self, BookmarkPosition, BookmarkRootGuid, InsertableBookmark,
+        };
+
+        let db = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let now: Timestamp = SystemTime::now().into();
+        for href in &[
+            "https://example.com/a",
+            "https://www.example.com:8080/b",
+            "http://sub.example.com/c",
+            "https://example.org/",
+            "https://notexample.com/",
+        ] {
+            let url = Url::parse(href)?;
+            apply_observation(
+                &db,
+                VisitObservation::new(url.clone())
+                    .with_visit_type(VisitTransition::Typed)
+                    .with_at(now),
+            )?;
+            accept_result(&db, "example", &url)?;
+        }
+        db.execute_batch(&format!(
+            "UPDATE moz_places SET sync_status = {}
+             WHERE url <> 'http://sub.example.com/c'",
+            SyncStatus::Normal as u8
+        ))?;
+        let bookmarked = Url::parse("https://example.com/a")?;
+        bookmarks::insert_bookmark(
+            &db,
+            &InsertableBookmark {
+                parent_guid: BookmarkRootGuid::Unfiled.into(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                url: bookmarked.clone(),
+                title: Some("A".to_owned()),
+            }
+            .into(),
+        )?;
+        let before = fetch_page_info(&db, &bookmarked)?
+            .expect("should exist")
+            .page;
+        let removed_guid = url_to_guid(&db, &Url::parse("https://www.example.com:8080/b")?)?
+            .expect("should exist");
+
+        delete_visits_for_host(&db, "Example.com")?;
+
+        // The bookmarked page stays, without its visits, and with a new
+        // frecency.
+        let (_, visits) = fetch_visits(&db, &bookmarked, 0)?.expect("should keep page");
+        assert!(visits.is_empty());
+        let after = fetch_page_info(&db, &bookmarked)?
+            .expect("should exist")
+            .page;
+        assert_ne!(after.frecency, before.frecency);
+        let visit_tombstones = db.query_one::<i64>(&format!(
+            "SELECT COUNT(*) FROM moz_historyvisit_tombstones WHERE place_id = {}",
+            after.row_id.0
+        ))?;
+        assert_eq!(visit_tombstones, 1);
+
+        // The other pages on the host and its subdomains are gone. Only the
+        // synced one needs a tombstone.
+        let mut tombstones =
+            db.query_rows_and_then_named("SELECT guid FROM moz_places_tombstones", &[], |row| {
+                row.get::<_, SyncGuid>(0)
+            })?;
+        tombstones.sort();
+        assert_eq!(tombstones, vec![removed_guid]);
+        let mut urls = db.query_rows_and_then_named("SELECT url FROM moz_places", &[], |row| {
+            row.get::<_, String>(0)
+        })?;
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/a",
+                "https://example.org/",
+                "https://notexample.com/",
+            ]
+        );
+
+        // So is their input history, and the origins they were on, except for
+        // the bookmarked page's.
+        let inputs = db.query_one::<i64>(
+            "SELECT COUNT(*) FROM moz_inputhistory i
+             JOIN moz_places h ON h.id = i.place_id
+             WHERE h.url LIKE '%example.com%' AND h.url NOT LIKE '%notexample%'",
+        )?;
+        assert_eq!(inputs, 0);
+        let mut origins =
+            db.query_rows_and_then_named("SELECT prefix || host FROM moz_origins", &[], |row| {
+                row.get::<_, String>(0)
+            })?;
+        origins.sort();
+        assert_eq!(
+            origins,
+            vec![
+                "https://example.com",
+                "https://example.org",
+                "https://notexample.com",
+            ]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_tombstones() -> Result<()> {
         let _ = env_logger::try_init();
@@ -2313,6 +2804,7 @@ mod tests {
         use crate::storage::bookmarks::{
             self, BookmarkPosition, BookmarkRootGuid, InsertableBookmark, InsertableItem,
         };
+        use crate::storage::favicons;
         use url::Url;
         let _ = env_logger::try_init();
         let mut conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).unwrap();
@@ -2390,6 +2882,24 @@ mod tests {
 
         assert!(bookmarks::delete_bookmark(&conn, &b0.0).unwrap());
 
+        // Icons for bookmarked pages should be kept, and the rest removed.
+        for (page, icon_url) in &[
+            (&b1.1, "http://www.example6.com/favicon.ico"),
+            (&b2.1, "http://www.example9.com/favicon.ico"),
+            (
+                &Url::parse("http://www.example1.com/1").unwrap(),
+                "http://www.example1.com/favicon.ico",
+            ),
+        ] {
+            let icon = favicons::Icon {
+                url: Url::parse(icon_url).unwrap(),
+                width: 16,
+                mime_type: None,
+                data: vec![0],
+            };
+            favicons::store_icon_for_page(&conn, page, &icon).unwrap();
+        }
+
         wipe_local(&conn).unwrap();
 
         let places = conn
@@ -2420,6 +2930,8 @@ mod tests {
             (1, "SELECT COUNT(*) FROM moz_bookmarks_deleted"),
             (0, "SELECT COUNT(*) FROM moz_historyvisit_tombstones"),
             (0, "SELECT COUNT(*) FROM moz_places_tombstones"),
+            (2, "SELECT COUNT(*) FROM moz_icons"),
+            (2, "SELECT COUNT(*) FROM moz_icons_to_pages"),
         ];
         for (want, query) in &counts_sql {
             assert_eq!(
@@ -2431,6 +2943,153 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_prune_destructively() {
+        use crate::storage::bookmarks::{
+            self, BookmarkPosition, BookmarkRootGuid, InsertableBookmark, InsertableItem,
+        };
+        let _ = env_logger::try_init();
+        let mut conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).unwrap();
+        let now = Timestamp::now();
+        let old = Timestamp(now.0 - 30 * 24 * 60 * 60 * 1000);
+
+        // Page `i` has `i + 1` old visits, so the earlier pages are less
+        // frecent, and should be pruned first.
+        for i in 0..5 {
+            for j in 0..=i {
+                get_custom_observed_page(&mut conn, &format!("http://example.com/{}", i), |o| {
+                    o.with_at(Timestamp(old.0 + i * 1000 + j))
+                })
+                .unwrap();
+            }
+        }
+        // Old visits to bookmarked pages, and recent visits, are kept.
+        let bookmarked = Url::parse("http://example.com/bookmarked").unwrap();
+        get_custom_observed_page(&mut conn, bookmarked.as_str(), |o| o.with_at(old)).unwrap();
+        bookmarks::insert_bookmark(
+            &conn,
+            &InsertableItem::Bookmark(InsertableBookmark {
+                parent_guid: BookmarkRootGuid::Unfiled.into(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                url: bookmarked.clone(),
+                title: None,
+            }),
+        )
+        .unwrap();
+        get_custom_observed_page(&mut conn, "http://example.com/recent", |o| o.with_at(now))
+            .unwrap();
+        conn.execute_cached(
+            &format!(
+                "UPDATE moz_places SET sync_status = {}",
+                (SyncStatus::Normal as u8)
+            ),
+            NO_PARAMS,
+        )
+        .unwrap();
+
+        let options = PruneOptions {
+            target_size_bytes: None,
+            target_visit_count: Some(12),
+            keep_visits_since: Timestamp(now.0 - 1000),
+        };
+        let metrics = prune_destructively(&conn, &options).unwrap();
+        assert_eq!(metrics.visits_removed, 5);
+        assert_eq!(metrics.places_removed, 2);
+
+        let visit_count = |url: &str| {
+            let url = Url::parse(url).unwrap();
+            fetch_page_info(&conn, &url)
+                .unwrap()
+                .map(|info| info.page.visit_count_local)
+        };
+        assert_eq!(visit_count("http://example.com/0"), None);
+        assert_eq!(visit_count("http://example.com/1"), None);
+        assert_eq!(visit_count("http://example.com/2"), Some(1));
+        assert_eq!(visit_count("http://example.com/4"), Some(5));
+        assert_eq!(visit_count(bookmarked.as_str()), Some(1));
+        assert_eq!(visit_count("http://example.com/recent"), Some(1));
+
+        // Removed pages and visits should have tombstones, so that Sync
+        // doesn't bring them back.
+        assert_eq!(get_tombstone_count(&conn), 2);
+        assert_eq!(
+            conn.query_one::<i64>("SELECT COUNT(*) FROM moz_historyvisit_tombstones")
+                .unwrap(),
+            2
+        );
+
+        // Pruning everything old keeps the bookmarked and recent visits.
+        let options = PruneOptions {
+            target_visit_count: Some(0),
+            ..options
+        };
+        let metrics = prune_destructively(&conn, &options).unwrap();
+        assert_eq!(metrics.visits_removed, 10);
+        assert_eq!(metrics.places_removed, 3);
+        assert_eq!(
+            conn.query_one::<i64>("SELECT COUNT(*) FROM moz_historyvisits")
+                .unwrap(),
+            2
+        );
+
+        // And there's nothing left to prune if we're already under the target.
+        let metrics = prune_destructively(&conn, &PruneOptions::default()).unwrap();
+        assert_eq!(metrics.visits_removed, 0);
+    }
+
+    #[test]
+    fn test_prune_destructively_to_size() {
+        let _ = env_logger::try_init();
+        let mut conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).unwrap();
+        let now = Timestamp::now();
+        let old = Timestamp(now.0 - 30 * 24 * 60 * 60 * 1000);
+        for i in 0..3000 {
+            get_custom_observed_page(
+                &mut conn,
+                &format!("http://example.com/{}/{}", i, "page".repeat(10)),
+                |o| {
+                    o.with_at(Timestamp(old.0 + i))
+                        .with_title(format!("Page {}", i))
+                },
+            )
+            .unwrap();
+        }
+        get_custom_observed_page(&mut conn, "http://example.com/recent", |o| o.with_at(now))
+            .unwrap();
+        conn.execute_batch("VACUUM").unwrap();
+        let size_before = get_used_db_size(&conn).unwrap();
+
+        let options = PruneOptions {
+            target_size_bytes: Some(size_before / 2),
+            target_visit_count: None,
+            keep_visits_since: Timestamp(now.0 - 1000),
+        };
+        let metrics = prune_destructively(&conn, &options).unwrap();
+        assert_eq!(metrics.size_before_bytes, size_before);
+        assert!(metrics.visits_removed > 0);
+        assert_eq!(metrics.places_removed, metrics.visits_removed);
+        assert!(metrics.size_after_bytes <= size_before / 2);
+        assert_eq!(metrics.size_after_bytes, get_used_db_size(&conn).unwrap());
+        let recent = Url::parse("http://example.com/recent").unwrap();
+        assert!(fetch_page_info(&conn, &recent).unwrap().is_some());
+
+        // Recent visits are kept even if we can't reach the target without
+        // removing them.
+        let options = PruneOptions {
+            target_size_bytes: Some(0),
+            ..options
+        };
+        prune_destructively(&conn, &options).unwrap();
+        assert_eq!(
+            conn.query_one::<i64>("SELECT COUNT(*) FROM moz_historyvisits")
+                .unwrap(),
+            1
+        );
+    }
+
     #[test]
     fn test_delete_everything() {
         use crate::storage::bookmarks::{
@@ -2845,4 +3504,135 @@ mod tests {
         assert_eq!(infos_with_bound.bound, now_i64 - 199_000);
         assert_eq!(infos_with_bound.offset, 1);
     }
+
+    #[test]
+    fn test_top_frecent_site_infos() {
+        let _ = env_logger::try_init();
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).unwrap();
+        let pages = [
+            ("https://www.example.com/", VisitTransition::Typed, 500),
+            ("https://www.example.com/page", VisitTransition::Link, 400),
+            ("http://mozilla.org/", VisitTransition::Link, 300),
+            ("https://news.example.org/", VisitTransition::Link, 200),
+            ("https://low.example.net/", VisitTransition::Link, 10),
+            ("ftp://files.example.com/", VisitTransition::Link, 1000),
+            (
+                "https://embedded.example.com/",
+                VisitTransition::Embed,
+                1000,
+            ),
+            (
+                "https://reloaded.example.com/",
+                VisitTransition::Reload,
+                1000,
+            ),
+        ];
+        for &(url, visit_type, frecency) in &pages {
+            let url = Url::parse(url).unwrap();
+            apply_observation(
+                &conn,
+                VisitObservation::new(url.clone()).with_visit_type(visit_type),
+            )
+            .expect("Should apply visit");
+            // Set the frecency directly, so the order doesn't depend on the
+            // frecency algorithm.
+            conn.execute_named_cached(
+                "UPDATE moz_places SET frecency = :frecency WHERE url = :url",
+                &[(":frecency", &frecency), (":url", &url.as_str())],
+            )
+            .unwrap();
+        }
+
+        let top_urls = |excluded_kinds: &[TopSiteExclusionKind]| {
+            top_frecent_site_infos(&conn, 10, 100, excluded_kinds)
+                .unwrap()
+                .into_iter()
+                .map(|info| info.url.into_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            top_urls(&[]),
+            &[
+                "https://www.example.com/",
+                "http://mozilla.org/",
+                "https://news.example.org/",
+            ]
+        );
+        let infos = top_frecent_site_infos(&conn, 1, 100, &[]).unwrap();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].frecency, 500);
+
+        // Pinning any page leaves out its whole host, but blocking a page
+        // only leaves out that page.
+        let pinned = Url::parse("https://www.example.com/other").unwrap();
+        let blocked = Url::parse("https://www.example.com/").unwrap();
+        add_top_site_exclusion(&conn, &pinned, TopSiteExclusionKind::Pinned).unwrap();
+        add_top_site_exclusion(&conn, &blocked, TopSiteExclusionKind::Blocked).unwrap();
+        assert_eq!(
+            top_urls(&[TopSiteExclusionKind::Blocked]),
+            &[
+                "https://www.example.com/page",
+                "http://mozilla.org/",
+                "https://news.example.org/",
+            ]
+        );
+        assert_eq!(
+            top_urls(&[TopSiteExclusionKind::Pinned, TopSiteExclusionKind::Blocked]),
+            &["http://mozilla.org/", "https://news.example.org/"]
+        );
+        assert_eq!(top_urls(&[]).len(), 3);
+        assert_eq!(
+            get_top_site_exclusions(&conn, TopSiteExclusionKind::Pinned).unwrap(),
+            &[pinned.clone()]
+        );
+
+        // Wiping history forgets blocked sites, but keeps pinned ones.
+        wipe_local(&conn).unwrap();
+        assert!(
+            get_top_site_exclusions(&conn, TopSiteExclusionKind::Blocked)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(remove_top_site_exclusion(&conn, &pinned).unwrap());
+        assert!(!remove_top_site_exclusion(&conn, &pinned).unwrap());
+    }
+
+    #[test]
+    fn test_top_frecent_site_infos_redirects() {
+        let _ = env_logger::try_init();
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).unwrap();
+        // The user types `example.com`, which redirects to
+        // `https://www.example.com/`.
+        let source = Url::parse("http://example.com/").unwrap();
+        let target = Url::parse("https://www.example.com/").unwrap();
+        apply_observation(
+            &conn,
+            VisitObservation::new(source.clone())
+                .with_visit_type(VisitTransition::Typed)
+                .with_is_redirect_source(true)
+                .with_is_permanent_redirect_source(true),
+        )
+        .expect("Should apply visit");
+        apply_observation(
+            &conn,
+            VisitObservation::new(target.clone())
+                .with_visit_type(VisitTransition::RedirectPermanent)
+                .with_referrer(source.clone()),
+        )
+        .expect("Should apply visit");
+        for url in &[&source, &target] {
+            conn.execute_named_cached(
+                "UPDATE moz_places SET frecency = 500 WHERE url = :url",
+                &[(":url", &url.as_str())],
+            )
+            .unwrap();
+        }
+
+        // The source is hidden, but the target is kept.
+        let infos = top_frecent_site_infos(&conn, 10, 100, &[]).unwrap();
+        assert_eq!(
+            infos.into_iter().map(|info| info.url).collect::<Vec<_>>(),
+            &[target]
+        );
+    }
 }
@@ -0,0 +1,592 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A builder for querying visits, for history screens which need more than
+//! `get_visit_page` offers: filtering by host, origin, text or where the
+//! visit came from, and grouping visits by day or by domain.
+//!
+//! Results are paginated with a cursor, rather than an offset, so adding
+//! or removing visits between fetching pages doesn't skip or repeat any.
+
+use super::{host_patterns, HOST_MATCHES_SQL};
+use crate::api::matcher::{split_after_host_and_port, split_after_prefix};
+use crate::db::PlacesDb;
+use crate::error::Result;
+use crate::storage::history_metadata::escape_like;
+use crate::storage::RowId;
+use crate::types::{Timestamp, VisitTransition, VisitTransitionSet};
+use rusqlite::types::ToSql;
+use rusqlite::Row;
+use sql_support::ConnExt;
+use url::Url;
+
+const DEFAULT_LIMIT: u32 = 100;
+
+/// Where the visits to include came from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VisitSource {
+    Any,
+    /// Visits made on this device.
+    Local,
+    /// Visits synced from other devices.
+    Remote,
+}
+
+impl Default for VisitSource {
+    fn default() -> Self {
+        VisitSource::Any
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HistoryGrouping {
+    /// The most recent visits first.
+    None,
+    /// The most recent visits first, grouped by the local date they were
+    /// made on.
+    Day,
+    /// Visits grouped by host and port, in alphabetical order, with the
+    /// most recent visits first in each group.
+    Domain,
+}
+
+impl Default for HistoryGrouping {
+    fn default() -> Self {
+        HistoryGrouping::None
+    }
+}
+
+/// Where a page of results ended. Passing it to `HistoryQuery::with_after`
+/// fetches the next page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryCursor {
+    host: String,
+    visit_date: Timestamp,
+    visit_id: RowId,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryVisit {
+    pub url: Url,
+    pub title: Option<String>,
+    pub visit_date: Timestamp,
+    pub visit_type: VisitTransition,
+    pub is_local: bool,
+    /// The page's host and port.
+    pub host: String,
+    /// The group the visit is in: its local date, as `YYYY-MM-DD`, when
+    /// grouping by day, its host when grouping by domain, and `None` when
+    /// not grouping.
+    pub group: Option<String>,
+    visit_id: RowId,
+}
+
+impl HistoryVisit {
+    fn from_row(row: &Row<'_>, grouping: HistoryGrouping) -> Result<Self> {
+        let host: String = row.get("host")?;
+        let group = match grouping {
+            HistoryGrouping::None => None,
+            HistoryGrouping::Day => Some(row.get("day")?),
+            HistoryGrouping::Domain => Some(host.clone()),
+        };
+        Ok(Self {
+            url: Url::parse(&row.get::<_, String>("url")?)?,
+            title: row.get("title")?,
+            visit_date: row.get("visit_date")?,
+            visit_type: VisitTransition::from_primitive(row.get("visit_type")?)
+                .unwrap_or(VisitTransition::Link),
+            is_local: row.get("is_local")?,
+            host,
+            group,
+            visit_id: row.get("id")?,
+        })
+    }
+
+    fn cursor(&self) -> HistoryCursor {
+        HistoryCursor {
+            host: self.host.clone(),
+            visit_date: self.visit_date,
+            visit_id: self.visit_id,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HistoryPage {
+    pub visits: Vec<HistoryVisit>,
+    /// The cursor for the next page, or `None` if this is the last one.
+    pub next: Option<HistoryCursor>,
+}
+
+impl HistoryPage {
+    /// Splits the visits into runs with the same group. The last group on a
+    /// page can continue on the next one.
+    pub fn groups(&self) -> Vec<(Option<&str>, &[HistoryVisit])> {
+        let mut groups = Vec::new();
+        let mut start = 0;
+        for i in 1..=self.visits.len() {
+            if i == self.visits.len() || self.visits[i].group != self.visits[start].group {
+                groups.push((self.visits[start].group.as_deref(), &self.visits[start..i]));
+                start = i;
+            }
+        }
+        groups
+    }
+}
+
+/// Describes which visits to fetch, and how to order them. Hidden pages, like
+/// redirect sources and embedded frames, are never included.
+///
+/// For example, to fetch the most recent local visits to pages on
+/// `example.com` with "recipe" in their title or URL, grouped by day:
+///
+/// ```ignore
+/// let page = HistoryQuery::new()
+///     .with_host("example.com")
+///     .with_text("recipe")
+///     .with_source(VisitSource::Local)
+///     .with_grouping(HistoryGrouping::Day)
+///     .fetch(&db)?;
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryQuery {
+    start: Option<Timestamp>,
+    end: Option<Timestamp>,
+    exclude_types: VisitTransitionSet,
+    host: Option<String>,
+    origin: Option<(String, String)>,
+    text: Option<String>,
+    source: VisitSource,
+    grouping: HistoryGrouping,
+    after: Option<HistoryCursor>,
+    limit: u32,
+}
+
+impl Default for HistoryQuery {
+    fn default() -> Self {
+        Self {
+            start: None,
+            end: None,
+            exclude_types: VisitTransitionSet::empty(),
+            host: None,
+            origin: None,
+            text: None,
+            source: VisitSource::Any,
+            grouping: HistoryGrouping::None,
+            after: None,
+            limit: DEFAULT_LIMIT,
+        }
+    }
+}
+
+impl HistoryQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only includes visits made between `start` and `end`, inclusive.
+    pub fn with_range(mut self, start: Timestamp, end: Timestamp) -> Self {
+        self.start = Some(start);
+        self.end = Some(end);
+        self
+    }
+
+    pub fn with_exclude_types(mut self, exclude_types: VisitTransitionSet) -> Self {
+        self.exclude_types = exclude_types;
+        self
+    }
+
+    /// Only includes visits to pages on `host`, or any of its subdomains, on
+    /// any port.
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.host = Some(host.to_ascii_lowercase());
+        self
+    }
+
+    /// Only includes visits to pages with the same scheme, host and port as
+    /// `url`.
+    pub fn with_origin(mut self, url: &Url) -> Self {
+        let (prefix, _) = split_after_prefix(url.as_str());
+        let (host_and_port, _) = split_after_host_and_port(url.as_str());
+        self.origin = Some((prefix.to_owned(), host_and_port.to_owned()));
+        self
+    }
+
+    /// Only includes visits to pages with `text` in their title or URL,
+    /// ignoring ASCII case.
+    pub fn with_text(mut self, text: &str) -> Self {
+        let text = text.trim();
+        self.text = if text.is_empty() {
+            None
+        } else {
+            Some(text.to_owned())
+        };
+        self
+    }
+
+    pub fn with_source(mut self, source: VisitSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn with_grouping(mut self, grouping: HistoryGrouping) -> Self {
+        self.grouping = grouping;
+        self
+    }
+
+    /// Fetches the page after `cursor`, which must come from a query with the
+    /// same grouping.
+    pub fn with_after(mut self, cursor: Option<HistoryCursor>) -> Self {
+        self.after = cursor;
+        self
+    }
+
+    /// The most visits to return in a page.
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn fetch(&self, db: &PlacesDb) -> Result<HistoryPage> {
+        let allowed_types = self.exclude_types.complement();
+        let mut conditions = vec![
+            "NOT h.hidden",
+            "((1 << v.visit_type) & :allowed_types) != 0",
+        ];
+        let mut params: Vec<(&str, &dyn ToSql)> = vec![(":allowed_types", &allowed_types)];
+
+        if let Some(start) = &self.start {
+            conditions.push("v.visit_date >= :start");
+            params.push((":start", start));
+        }
+        if let Some(end) = &self.end {
+            conditions.push("v.visit_date <= :end");
+            params.push((":end", end));
+        }
+
+        let host_patterns = self.host.as_ref().map(|host| host_patterns(host));
+        if let (Some(host), Some([host_with_port, subdomain, subdomain_with_port])) =
+            (&self.host, &host_patterns)
+        {
+            conditions.push(HOST_MATCHES_SQL);
+            params.push((":host", host));
+            params.push((":host_with_port", host_with_port));
+            params.push((":subdomain", subdomain));
+            params.push((":subdomain_with_port", subdomain_with_port));
+        }
+
+        if let Some((prefix, host_and_port)) = &self.origin {
+            conditions.push("o.prefix = :origin_prefix AND o.host = :origin_host");
+            params.push((":origin_prefix", prefix));
+            params.push((":origin_host", host_and_port));
+        }
+
+        let text_pattern = self
+            .text
+            .as_ref()
+            .map(|text| format!("%{}%", escape_like(text)));
+        if let Some(pattern) = &text_pattern {
+            conditions.push(
+                "(h.title LIKE :text_pattern ESCAPE '\\' OR
+                  h.url LIKE :text_pattern ESCAPE '\\')",
+            );
+            params.push((":text_pattern", pattern));
+        }
+
+        match self.source {
+            VisitSource::Any => {}
+            VisitSource::Local => conditions.push("v.is_local"),
+            VisitSource::Remote => conditions.push("NOT v.is_local"),
+        }
+
+        // Ordering by the visit ID, as well as the date, makes the order
+        // total, so that the cursor can pick up exactly where the last page
+        // left off.
+        let order_by = match self.grouping {
+            HistoryGrouping::None | HistoryGrouping::Day => "v.visit_date DESC, v.id DESC",
+            HistoryGrouping::Domain => "host, v.visit_date DESC, v.id DESC",
+        };
+        if let Some(cursor) = &self.after {
+            conditions.push(match self.grouping {
+                HistoryGrouping::None | HistoryGrouping::Day => {
+                    "(v.visit_date < :cursor_date OR
+                      (v.visit_date = :cursor_date AND v.id < :cursor_id))"
+                }
+                HistoryGrouping::Domain => {
+                    "(IFNULL(o.host, '') > :cursor_host OR
+                      (IFNULL(o.host, '') = :cursor_host AND
+                       (v.visit_date < :cursor_date OR
+                        (v.visit_date = :cursor_date AND v.id < :cursor_id))))"
+                }
+            });
+            params.push((":cursor_host", &cursor.host));
+            params.push((":cursor_date", &cursor.visit_date));
+            params.push((":cursor_id", &cursor.visit_id));
+        }
+
+        // Fetch one more visit than we need, to find out if there's another
+        // page.
+        let limit = i64::from(self.limit) + 1;
+        params.push((":limit", &limit));
+
+        let sql = format!(
+            "SELECT v.id, h.url, h.title, v.visit_date, v.visit_type, v.is_local,
+                    IFNULL(o.host, '') AS host,
+                    date(v.visit_date / 1000, 'unixepoch', 'localtime') AS day
+             FROM moz_historyvisits v
+             JOIN moz_places h ON h.id = v.place_id
+             LEFT JOIN moz_origins o ON o.id = h.origin_id
+             WHERE {conditions}
+             ORDER BY {order_by}
+             LIMIT :limit",
+            conditions = conditions.join(" AND "),
+            order_by = order_by,
+        );
+        let grouping = self.grouping;
+        let mut visits = db.query_rows_and_then_named_cached(&sql, &params, |row| {
+            HistoryVisit::from_row(row, grouping)
+        })?;
+        let next = if visits.len() > self.limit as usize {
+            visits.truncate(self.limit as usize);
+            visits.last().map(HistoryVisit::cursor)
+        } else {
+            None
+        };
+        Ok(HistoryPage { visits, next })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::history::apply_observation;
+
+    // 2020-09-19T12:00:00Z.
+    const NOON: u64 = 1_600_516_800_000;
+    const HOUR: u64 = 60 * 60 * 1000;
+    const DAY: u64 = 24 * HOUR;
+
+    fn visit(conn: &PlacesDb, url: &str, title: &str, at: u64, is_remote: bool) -> Result<()> {
+        apply_observation(
+            conn,
+            VisitObservation::new(Url::parse(url)?)
+                .with_title(title.to_string())
+                .with_at(Timestamp(at))
+                .with_is_remote(is_remote)
+                .with_visit_type(VisitTransition::Link),
+        )?;
+        Ok(())
+    }
+
+    fn urls(page: &HistoryPage) -> Vec<&str> {
+        page.visits.iter().map(|v| v.url.as_str()).collect()
+    }
+
+    fn fill_history(conn: &PlacesDb) -> Result<()> {
+        visit(conn, "https://example.com/1", "Pancake recipe", NOON, false)?;
+        visit(
+            conn,
+            "https://www.example.com/2",
+            "Waffles",
+            NOON + HOUR,
+            true,
+        )?;
+        visit(conn, "http://example.com/3", "Bread", NOON - DAY, false)?;
+        visit(
+            conn,
+            "https://example.org/",
+            "Recipes",
+            NOON - 3 * DAY,
+            false,
+        )?;
+        visit(conn, "https://notexample.com/", "Other", NOON + 2, false)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_filters() -> Result<()> {
+        let conn = new_mem_connection();
+        fill_history(&conn)?;
+
+        assert_eq!(
+            urls(&HistoryQuery::new().fetch(&conn)?),
+            vec![
+                "https://www.example.com/2",
+                "https://notexample.com/",
+                "https://example.com/1",
+                "http://example.com/3",
+                "https://example.org/",
+            ]
+        );
+        // Subdomains match the host, but other domains ending with it don't.
+        assert_eq!(
+            urls(&HistoryQuery::new().with_host("Example.com").fetch(&conn)?),
+            vec![
+                "https://www.example.com/2",
+                "https://example.com/1",
+                "http://example.com/3",
+            ]
+        );
+        assert_eq!(
+            urls(
+                &HistoryQuery::new()
+                    .with_origin(&Url::parse("https://example.com/other")?)
+                    .fetch(&conn)?
+            ),
+            vec!["https://example.com/1"]
+        );
+        assert_eq!(
+            urls(&HistoryQuery::new().with_text("RECIPE").fetch(&conn)?),
+            vec!["https://example.com/1", "https://example.org/"]
+        );
+        assert_eq!(
+            urls(&HistoryQuery::new().with_text("100%").fetch(&conn)?),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            urls(
+                &HistoryQuery::new()
+                    .with_source(VisitSource::Remote)
+                    .fetch(&conn)?
+            ),
+            vec!["https://www.example.com/2"]
+        );
+        assert_eq!(
+            urls(
+                &HistoryQuery::new()
+                    .with_source(VisitSource::Local)
+                    .with_range(Timestamp(NOON - DAY), Timestamp(NOON))
+                    .fetch(&conn)?
+            ),
+            vec!["https://example.com/1", "http://example.com/3"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_host_with_port() -> Result<()> {
+        let conn = new_mem_connection();
+        visit(&conn, "https://example.com:8080/1", "Port", NOON, false)?;
+        visit(
+            &conn,
+            "https://sub.example.com:8443/2",
+            "Subdomain and port",
+            NOON + 1,
+            false,
+        )?;
+        visit(
+            &conn,
+            "https://notexample.com:8080/",
+            "Other",
+            NOON + 2,
+            false,
+        )?;
+        assert_eq!(
+            urls(&HistoryQuery::new().with_host("example.com").fetch(&conn)?),
+            vec![
+                "https://sub.example.com:8443/2",
+                "https://example.com:8080/1",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_grouping() -> Result<()> {
+        let conn = new_mem_connection();
+        fill_history(&conn)?;
+
+        let page = HistoryQuery::new()
+            .with_grouping(HistoryGrouping::Day)
+            .fetch(&conn)?;
+        // Each visit is grouped by its local date, in whatever time zone
+        // the tests run in...
+        for visit in &page.visits {
+            let day = conn.query_one::<String>(&format!(
+                "SELECT date({} / 1000, 'unixepoch', 'localtime')",
+                visit.visit_date.0
+            ))?;
+            assert_eq!(visit.group.as_deref(), Some(day.as_str()));
+        }
+        // ...so the visits around noon UTC can be split over two days (at
+        // UTC+11, 12:00 is 23:00, and 13:00 is the next day). The older
+        // visits are a day or more apart, and always get a day to themselves.
+        let groups = page.groups();
+        assert!(groups.len() == 3 || groups.len() == 4, "{:?}", groups);
+        assert_eq!(
+            groups[groups.len() - 2..]
+                .iter()
+                .map(|(_, visits)| visits.iter().map(|v| v.url.as_str()).collect())
+                .collect::<Vec<Vec<_>>>(),
+            vec![vec!["http://example.com/3"], vec!["https://example.org/"]]
+        );
+
+        let page = HistoryQuery::new()
+            .with_grouping(HistoryGrouping::Domain)
+            .fetch(&conn)?;
+        assert_eq!(
+            page.groups()
+                .into_iter()
+                .map(|(host, visits)| (host.unwrap(), visits.len()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("example.com", 2),
+                ("example.org", 1),
+                ("notexample.com", 1),
+                ("www.example.com", 1),
+            ]
+        );
+
+        let page = HistoryQuery::new().fetch(&conn)?;
+        let groups = page.groups();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].0, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pagination() -> Result<()> {
+        let conn = new_mem_connection();
+        fill_history(&conn)?;
+
+        for grouping in &[HistoryGrouping::None, HistoryGrouping::Domain] {
+            let query = HistoryQuery::new().with_grouping(*grouping);
+            let all = urls(&query.fetch(&conn)?)
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>();
+
+            let first = query.clone().with_limit(2).fetch(&conn)?;
+            assert_eq!(urls(&first), &all[0..2]);
+            assert!(first.next.is_some());
+
+            // Visits added between pages don't shift the next one, unless
+            // they belong on it.
+            visit(&conn, "https://zzz.example/", "New", NOON + 2 * DAY, false)?;
+
+            let second = query
+                .clone()
+                .with_after(first.next.clone())
+                .with_limit(2)
+                .fetch(&conn)?;
+            assert_eq!(urls(&second), &all[2..4]);
+            let third = query
+                .clone()
+                .with_after(second.next.clone())
+                .with_limit(2)
+                .fetch(&conn)?;
+            let rest = urls(&third);
+            assert_eq!(rest[0], all[4]);
+            assert!(third.next.is_none());
+
+            conn.execute_batch(
+                "DELETE FROM moz_historyvisits
+                 WHERE place_id = (SELECT id FROM moz_places WHERE url = 'https://zzz.example/')",
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,419 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! History metadata records how the user engaged with pages, beyond their
+//! visits. It's local-only, and is removed along with history.
+
+use super::{fetch_page_info, RowId};
+use crate::db::PlacesDb;
+use crate::error::{InvalidPlaceInfo, Result};
+use crate::observation::HistoryMetadataObservation;
+use crate::types::{DocumentType, Timestamp};
+use rusqlite::Row;
+use sql_support::ConnExt;
+use url::Url;
+
+/// Engagement with a page, from a referrer and search term.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryMetadata {
+    pub url: Url,
+    pub title: Option<String>,
+    pub referrer_url: Option<Url>,
+    pub search_term: Option<String>,
+    pub document_type: DocumentType,
+    /// In milliseconds.
+    pub total_view_time: u64,
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+}
+
+impl HistoryMetadata {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        let referrer_url = match row.get::<_, Option<String>>("referrer_url")? {
+            Some(url) => Some(Url::parse(&url)?),
+            None => None,
+        };
+        Ok(Self {
+            url: Url::parse(&row.get::<_, String>("url")?)?,
+            title: row.get("title")?,
+            referrer_url,
+            search_term: row.get("search_term")?,
+            document_type: row.get("document_type")?,
+            total_view_time: row.get::<_, i64>("total_view_time")?.max(0) as u64,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+const SELECT_METADATA_SQL: &str = "
+    SELECT h.url, h.title, r.url AS referrer_url, q.term AS search_term,
+           m.document_type, m.total_view_time, m.created_at, m.updated_at
+    FROM moz_places_metadata m
+    JOIN moz_places h ON h.id = m.place_id
+    LEFT JOIN moz_places r ON r.id = m.referrer_place_id
+    LEFT JOIN moz_places_metadata_search_queries q ON q.id = m.search_query_id";
+
+/// Records a history metadata observation. If there's already metadata for
+/// the page, referrer and search term, the view time is added to it. The page
+/// must already be in history, or this fails with
+/// `InvalidPlaceInfo::NoSuchUrl`; a referrer which isn't in history is left
+/// out, rather than adding a page for it.
+pub fn apply_metadata_observation(
+    db: &PlacesDb,
+    observation: HistoryMetadataObservation,
+) -> Result<()> {
+    let url = Url::parse(&observation.url)?;
+    let referrer_url = match &observation.referrer_url {
+        Some(url) => Some(Url::parse(url)?),
+        None => None,
+    };
+    let tx = db.begin_transaction()?;
+    let result = apply_metadata_observation_in_tx(db, &url, referrer_url.as_ref(), &observation);
+    match result {
+        Ok(_) => tx.commit()?,
+        Err(_) => tx.rollback()?,
+    }
+    result
+}
+
+fn apply_metadata_observation_in_tx(
+    db: &PlacesDb,
+    url: &Url,
+    referrer_url: Option<&Url>,
+    observation: &HistoryMetadataObservation,
+) -> Result<()> {
+    let place_id = match fetch_page_info(db, url)? {
+        Some(info) => info.page.row_id,
+        None => return Err(InvalidPlaceInfo::NoSuchUrl.into()),
+    };
+    let referrer_place_id = match referrer_url {
+        Some(url) => fetch_page_info(db, url)?.map(|info| info.page.row_id),
+        None => None,
+    };
+    let search_query_id = match normalize_search_term(observation.search_term.as_deref()) {
+        Some(term) => {
+            db.execute_named_cached(
+                "INSERT OR IGNORE INTO moz_places_metadata_search_queries(term)
+                 VALUES(:term)",
+                &[(":term", &term)],
+            )?;
+            Some(db.query_row_named(
+                "SELECT id FROM moz_places_metadata_search_queries WHERE term = :term",
+                &[(":term", &term)],
+                |row| row.get::<_, RowId>(0),
+            )?)
+        }
+        None => None,
+    };
+    let now = observation.at.unwrap_or_else(Timestamp::now);
+    let view_time = observation.view_time.unwrap_or(0) as i64;
+
+    // `IS` instead of `=`, since the referrer and search term can be NULL.
+    let existing_id = db.try_query_row(
+        "SELECT id FROM moz_places_metadata
+         WHERE place_id = :place_id
+           AND referrer_place_id IS :referrer_place_id
+           AND search_query_id IS :search_query_id",
+        &[
+            (":place_id", &place_id),
+            (":referrer_place_id", &referrer_place_id),
+            (":search_query_id", &search_query_id),
+        ],
+        |row| row.get::<_, RowId>(0),
+        true,
+    )?;
+    match existing_id {
+        Some(id) => {
+            db.execute_named_cached(
+                "UPDATE moz_places_metadata SET
+                     total_view_time = total_view_time + :view_time,
+                     document_type = IFNULL(:document_type, document_type),
+                     updated_at = MAX(updated_at, :now)
+                 WHERE id = :id",
+                &[
+                    (":view_time", &view_time),
+                    (":document_type", &observation.document_type),
+                    (":now", &now),
+                    (":id", &id),
+                ],
+            )?;
+        }
+        None => {
+            db.execute_named_cached(
+                "INSERT INTO moz_places_metadata(place_id, referrer_place_id, search_query_id,
+                                                 document_type, total_view_time,
+                                                 created_at, updated_at)
+                 VALUES(:place_id, :referrer_place_id, :search_query_id,
+                        :document_type, :view_time, :now, :now)",
+                &[
+                    (":place_id", &place_id),
+                    (":referrer_place_id", &referrer_place_id),
+                    (":search_query_id", &search_query_id),
+                    (
+                        ":document_type",
+                        &observation.document_type.unwrap_or_default(),
+                    ),
+                    (":view_time", &view_time),
+                    (":now", &now),
+                ],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn normalize_search_term(term: Option<&str>) -> Option<String> {
+    let term = term?.trim();
+    if term.is_empty() {
+        None
+    } else {
+        Some(term.to_lowercase())
+    }
+}
+
+/// Returns metadata for pages the user engaged with since `since`, most
+/// recent first.
+pub fn get_recently_engaged_pages(
+    db: &PlacesDb,
+    since: Timestamp,
+    limit: u32,
+) -> Result<Vec<HistoryMetadata>> {
+    db.query_rows_and_then_named_cached(
+        &format!(
+            "{}
+             WHERE m.updated_at >= :since
+             ORDER BY m.updated_at DESC
+             LIMIT :limit",
+            SELECT_METADATA_SQL
+        ),
+        &[(":since", &since), (":limit", &limit)],
+        HistoryMetadata::from_row,
+    )
+}
+
+/// Returns metadata for pages reached from searches containing `term`, the
+/// most viewed first.
+pub fn get_pages_for_search_term(
+    db: &PlacesDb,
+    term: &str,
+    limit: u32,
+) -> Result<Vec<HistoryMetadata>> {
+    let term = match normalize_search_term(Some(term)) {
+        Some(term) => term,
+        None => return Ok(Vec::new()),
+    };
+    db.query_rows_and_then_named_cached(
+        &format!(
+            "{}
+             WHERE q.term LIKE :pattern ESCAPE '\\'
+             ORDER BY m.total_view_time DESC, m.updated_at DESC
+             LIMIT :limit",
+            SELECT_METADATA_SQL
+        ),
+        &[
+            (":pattern", &format!("%{}%", escape_like(&term))),
+            (":limit", &limit),
+        ],
+        HistoryMetadata::from_row,
+    )
+}
+
+pub(crate) fn escape_like(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == '\\' || c == '%' || c == '_' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Removes metadata for a page, and metadata for other pages which it
+/// referred to.
+pub(crate) fn delete_metadata_for_page(db: &PlacesDb, page_id: RowId) -> Result<()> {
+    db.execute_named_cached(
+        "DELETE FROM moz_places_metadata
+         WHERE place_id = :page_id OR referrer_place_id = :page_id",
+        &[(":page_id", &page_id)],
+    )?;
+    Ok(())
+}
+
+/// Removes metadata for engagement that overlaps a date range.
+pub(crate) fn delete_metadata_between(
+    db: &PlacesDb,
+    start: Timestamp,
+    end: Timestamp,
+) -> Result<()> {
+    db.execute_named_cached(
+        "DELETE FROM moz_places_metadata
+         WHERE created_at <= :end AND updated_at >= :start",
+        &[(":start", &start), (":end", &end)],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::history::{
+        apply_observation, delete_visits_between, delete_visits_for, url_to_guid, wipe_local,
+    };
+    use crate::types::VisitTransition;
+
+    fn observe(conn: &PlacesDb, url: &str, referrer: Option<&str>, term: Option<&str>, at: u64) {
+        let url = Url::parse(url).unwrap();
+        apply_observation(
+            conn,
+            VisitObservation::new(url.clone())
+                .with_at(Timestamp(at))
+                .with_visit_type(VisitTransition::Link),
+        )
+        .unwrap();
+        apply_metadata_observation(
+            conn,
+            HistoryMetadataObservation::new(url)
+                .with_referrer(referrer.map(|r| Url::parse(r).unwrap()))
+                .with_search_term(term.map(str::to_string))
+                .with_view_time(1000)
+                .with_at(Timestamp(at)),
+        )
+        .unwrap();
+    }
+
+    fn count_rows(conn: &PlacesDb, table: &str) -> i64 {
+        conn.query_one(&format!("SELECT COUNT(*) FROM {}", table))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_metadata_queries() {
+        let conn = new_mem_connection();
+        let search = "https://search.example.com/?q=rust";
+        observe(&conn, search, None, None, 500);
+        observe(
+            &conn,
+            "https://example.com/a",
+            Some(search),
+            Some("Rust "),
+            1000,
+        );
+        observe(
+            &conn,
+            "https://example.com/a",
+            Some(search),
+            Some("rust"),
+            2000,
+        );
+        observe(
+            &conn,
+            "https://example.com/b",
+            Some(search),
+            Some("rust lang"),
+            3000,
+        );
+        observe(&conn, "https://example.com/c", None, Some("100%"), 4000);
+        observe(&conn, "https://example.com/d", None, None, 5000);
+
+        // Observations with the same page, referrer, and normalized search
+        // term are grouped together.
+        let recent = get_recently_engaged_pages(&conn, Timestamp(1500), 10).unwrap();
+        let urls = recent.iter().map(|m| m.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            &[
+                "https://example.com/d",
+                "https://example.com/c",
+                "https://example.com/b",
+                "https://example.com/a",
+            ]
+        );
+        let a = &recent[3];
+        assert_eq!(a.total_view_time, 2000);
+        assert_eq!(a.created_at, Timestamp(1000));
+        assert_eq!(a.updated_at, Timestamp(2000));
+        assert_eq!(a.search_term, Some("rust".to_string()));
+        assert_eq!(a.referrer_url, Some(Url::parse(search).unwrap()));
+        assert_eq!(a.document_type, DocumentType::Regular);
+        assert_eq!(recent[0].search_term, None);
+
+        let related = get_pages_for_search_term(&conn, "RUST", 10).unwrap();
+        let urls = related.iter().map(|m| m.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(urls, &["https://example.com/a", "https://example.com/b"]);
+
+        // `%` and `_` aren't wildcards.
+        assert_eq!(get_pages_for_search_term(&conn, "0%", 10).unwrap().len(), 1);
+        assert!(get_pages_for_search_term(&conn, "1_0", 10)
+            .unwrap()
+            .is_empty());
+        assert!(get_pages_for_search_term(&conn, " ", 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_metadata_needs_history() {
+        let conn = new_mem_connection();
+        let url = Url::parse("https://example.com/").unwrap();
+        let observation = HistoryMetadataObservation::new(url.clone())
+            .with_referrer(Url::parse("https://example.com/unvisited").unwrap())
+            .with_view_time(1000);
+        assert!(apply_metadata_observation(&conn, observation.clone()).is_err());
+        assert_eq!(count_rows(&conn, "moz_places"), 0);
+
+        // Once the page is in history, the metadata is recorded, without the
+        // unvisited referrer.
+        apply_observation(
+            &conn,
+            VisitObservation::new(url).with_visit_type(VisitTransition::Link),
+        )
+        .unwrap();
+        apply_metadata_observation(&conn, observation).unwrap();
+        let recent = get_recently_engaged_pages(&conn, Timestamp(0), 10).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].referrer_url, None);
+        assert_eq!(count_rows(&conn, "moz_places"), 1);
+    }
+
+    #[test]
+    fn test_metadata_deletion() {
+        let conn = new_mem_connection();
+        let search = "https://search.example.com/?q=rust";
+        observe(&conn, search, None, None, 1000);
+        observe(
+            &conn,
+            "https://example.com/a",
+            Some(search),
+            Some("rust"),
+            2000,
+        );
+        observe(&conn, "https://example.com/b", None, Some("crab"), 3000);
+        observe(&conn, "https://example.com/c", None, Some("ferris"), 4000);
+        assert_eq!(count_rows(&conn, "moz_places_metadata"), 4);
+        assert_eq!(count_rows(&conn, "moz_places_metadata_search_queries"), 3);
+
+        // Deleting a page also deletes metadata for pages it referred to,
+        // and search terms which are no longer used.
+        let guid = url_to_guid(&conn, &Url::parse(search).unwrap())
+            .unwrap()
+            .unwrap();
+        delete_visits_for(&conn, &guid).unwrap();
+        assert_eq!(count_rows(&conn, "moz_places_metadata"), 2);
+        assert_eq!(count_rows(&conn, "moz_places_metadata_search_queries"), 2);
+
+        delete_visits_between(&conn, Timestamp(2500), Timestamp(3500)).unwrap();
+        let remaining = get_recently_engaged_pages(&conn, Timestamp(0), 10).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].url.as_str(), "https://example.com/c");
+        assert_eq!(count_rows(&conn, "moz_places_metadata_search_queries"), 1);
+
+        wipe_local(&conn).unwrap();
+        assert_eq!(count_rows(&conn, "moz_places_metadata"), 0);
+        assert_eq!(count_rows(&conn, "moz_places_metadata_search_queries"), 0);
+    }
+}
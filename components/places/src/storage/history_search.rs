@@ -0,0 +1,230 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Full-text search over history, using an FTS5 index of page titles, URLs
+//! and tags. The matcher scans `moz_places` for every query, which gets slow
+//! for large profiles; the index makes searching them fast, at the cost of
+//! some disk space and slower writes, so apps have to opt in to it with
+//! `enable_history_search_index`.
+//!
+//! The index is kept up to date by triggers on `moz_places` and
+//! `moz_tags_relation`, which do nothing while it's disabled.
+
+use super::{delete_meta, get_meta, put_meta};
+use crate::db::PlacesDb;
+use crate::error::*;
+use crate::types::Timestamp;
+use rusqlite::Row;
+use sql_support::ConnExt;
+use url::Url;
+
+// Triggers check for this key, so it must match the triggers' SQL.
+const HISTORY_SEARCH_INDEX_META_KEY: &str = "history_search_index";
+
+// How much each column counts towards a match, for `bm25`. Matching words in
+// the title is worth more than matching tags, which are worth more than
+// matching a word in the URL.
+const TITLE_WEIGHT: f64 = 10.0;
+const URL_WEIGHT: f64 = 1.0;
+const TAGS_WEIGHT: f64 = 5.0;
+
+// Frecency boosts a match's score by up to 2x; a page with this frecency gets
+// half of the boost.
+const FRECENCY_SCALE: f64 = 1000.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistorySearchResult {
+    pub url: Url,
+    pub title: String,
+    pub frecency: i64,
+    pub last_visit_date: Timestamp,
+}
+
+impl HistorySearchResult {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        Ok(Self {
+            url: Url::parse(&row.get::<_, String>("url")?)?,
+            title: row.get::<_, Option<String>>("title")?.unwrap_or_default(),
+            frecency: row.get("frecency")?,
+            last_visit_date: row.get("last_visit_date")?,
+        })
+    }
+}
+
+pub fn is_history_search_index_enabled(db: &PlacesDb) -> Result<bool> {
+    Ok(get_meta::<bool>(db, HISTORY_SEARCH_INDEX_META_KEY)?.is_some())
+}
+
+/// Builds the search index, and keeps it up to date from now on. This can
+/// take a while for large profiles, but only needs to be done once.
+pub fn enable_history_search_index(db: &PlacesDb) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    put_meta(db, HISTORY_SEARCH_INDEX_META_KEY, &true)?;
+    db.execute_all(&[
+        "DELETE FROM moz_places_fts",
+        "INSERT INTO moz_places_fts(rowid, title, url, tags)
+         SELECT h.id, h.title, h.url,
+                (SELECT IFNULL(group_concat(t.tag, ' '), '')
+                 FROM moz_tags_relation r
+                 JOIN moz_tags t ON t.id = r.tag_id
+                 WHERE r.place_id = h.id)
+         FROM moz_places h",
+    ])?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Stops updating the search index, and removes it.
+pub fn disable_history_search_index(db: &PlacesDb) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    delete_meta(db, HISTORY_SEARCH_INDEX_META_KEY)?;
+    db.execute_batch("DELETE FROM moz_places_fts")?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Turns a query typed by the user into an FTS5 query, which matches pages
+/// with every word in the query. Each word is quoted, so that FTS5 doesn't
+/// interpret any of its syntax, and matched as a prefix, since the user might
+/// not have finished typing it.
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Searches visited pages for `query`, returning up to `limit` results. Pages
+/// are ranked by how well they match, using FTS5's `bm25`, boosted by their
+/// frecency. Fails with `ErrorKind::HistorySearchIndexDisabled` if the index
+/// isn't enabled.
+pub fn search_history(db: &PlacesDb, query: &str, limit: u32) -> Result<Vec<HistorySearchResult>> {
+    if !is_history_search_index_enabled(db)? {
+        return Err(ErrorKind::HistorySearchIndexDisabled.into());
+    }
+    let fts_query = fts_query(query);
+    if fts_query.is_empty() {
+        return Ok(Vec::new());
+    }
+    // `bm25` is negative, and lower is better, so boosting it makes it more
+    // negative.
+    Ok(db.query_rows_and_then_named_cached(
+        "SELECT h.url, h.title, h.frecency,
+                MAX(h.last_visit_date_local,
+                    h.last_visit_date_remote) AS last_visit_date
+         FROM moz_places_fts f
+         JOIN moz_places h ON h.id = f.rowid
+         WHERE moz_places_fts MATCH :query AND
+               h.hidden = 0 AND
+               (h.last_visit_date_local + h.last_visit_date_remote) > 0
+         ORDER BY bm25(moz_places_fts, :title_weight, :url_weight, :tags_weight) *
+                  (1.0 + MAX(h.frecency, 0) / (MAX(h.frecency, 0) + :frecency_scale))
+         LIMIT :limit",
+        &[
+            (":query", &fts_query),
+            (":title_weight", &TITLE_WEIGHT),
+            (":url_weight", &URL_WEIGHT),
+            (":tags_weight", &TAGS_WEIGHT),
+            (":frecency_scale", &FRECENCY_SCALE),
+            (":limit", &limit),
+        ],
+        HistorySearchResult::from_row,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::history::{apply_observation, delete_visits_for};
+    use crate::storage::{fetch_page_info, tags};
+    use crate::types::VisitTransition;
+
+    fn visit(conn: &PlacesDb, url: &str, title: &str, count: u64) -> Result<Url> {
+        let url = Url::parse(url)?;
+        let now = Timestamp::now();
+        for i in 0..count {
+            apply_observation(
+                conn,
+                VisitObservation::new(url.clone())
+                    .with_title(title.to_string())
+                    .with_at(Timestamp(now.as_millis() - i * 1000))
+                    .with_visit_type(VisitTransition::Typed),
+            )?;
+        }
+        Ok(url)
+    }
+
+    fn search_urls(conn: &PlacesDb, query: &str) -> Result<Vec<String>> {
+        Ok(search_history(conn, query, 10)?
+            .into_iter()
+            .map(|result| result.url.into_string())
+            .collect())
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query(""), "");
+        assert_eq!(fts_query("  foo bar "), r#""foo"* "bar"*"#);
+        assert_eq!(fts_query(r#"a"b OR"#), r#""a""b"* "OR"*"#);
+    }
+
+    #[test]
+    fn test_search_history() -> Result<()> {
+        let conn = new_mem_connection();
+        // Pages visited before the index is enabled are indexed when it is.
+        visit(&conn, "https://example.com/a", "Rust programming", 1)?;
+        assert!(search_history(&conn, "rust", 10).is_err());
+
+        enable_history_search_index(&conn)?;
+        assert!(is_history_search_index_enabled(&conn)?);
+        let frequent = visit(
+            &conn,
+            "https://example.org/frequent",
+            "Rust programming",
+            10,
+        )?;
+        let tagged = visit(&conn, "https://example.net/", "Something else", 1)?;
+
+        // Both pages match equally well, so the one with the higher frecency
+        // comes first. Words can be prefixes, and are case-insensitive.
+        assert_eq!(
+            search_urls(&conn, "RUST prog")?,
+            vec!["https://example.org/frequent", "https://example.com/a"]
+        );
+        // URLs are searched, too.
+        assert_eq!(
+            search_urls(&conn, "frequent")?,
+            vec!["https://example.org/frequent"]
+        );
+        assert!(search_urls(&conn, "rust python")?.is_empty());
+        assert!(search_urls(&conn, "   ")?.is_empty());
+
+        // Tags and title changes are indexed.
+        tags::tag_url(&conn, &tagged, "rustacean")?;
+        assert_eq!(
+            search_urls(&conn, "rustacean")?,
+            vec!["https://example.net/"]
+        );
+        tags::untag_url(&conn, &tagged, "rustacean")?;
+        assert!(search_urls(&conn, "rustacean")?.is_empty());
+        visit(&conn, "https://example.net/", "Renamed", 1)?;
+        assert_eq!(search_urls(&conn, "renamed")?, vec!["https://example.net/"]);
+
+        // So are deletions.
+        let guid = fetch_page_info(&conn, &frequent)?
+            .expect("should exist")
+            .page
+            .guid;
+        delete_visits_for(&conn, &guid)?;
+        assert_eq!(search_urls(&conn, "rust")?, vec!["https://example.com/a"]);
+
+        disable_history_search_index(&conn)?;
+        assert!(!is_history_search_index_enabled(&conn)?);
+        assert!(search_history(&conn, "rust", 10).is_err());
+        Ok(())
+    }
+}
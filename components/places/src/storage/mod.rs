@@ -6,8 +6,12 @@
 // API and the database.
 
 pub mod bookmarks;
+pub mod favicons;
 pub mod history;
+pub mod history_metadata;
+pub mod history_search;
 pub mod tags;
+pub mod undo;
 
 use crate::db::PlacesDb;
 use crate::error::{ErrorKind, InvalidPlaceInfo, Result};
@@ -29,7 +33,9 @@ pub const TAG_LENGTH_MAX: usize = 100;
 // pub const DESCRIPTION_LENGTH_MAX: usize = 256;
 
 // Typesafe way to manage RowIds. Does it make sense? A better way?
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Deserialize, Serialize, Default)]
+#[derive(
+    Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Deserialize, Serialize, Default,
+)]
 pub struct RowId(pub i64);
 
 impl From<RowId> for i64 {
@@ -193,6 +199,7 @@ impl HistoryVisitInfo {
 }
 
 pub fn run_maintenance(conn: &PlacesDb) -> Result<()> {
+    undo::delete_expired_undo_entries(conn)?;
     conn.execute_all(&[
         "VACUUM",
         "PRAGMA optimize",
@@ -5,6 +5,7 @@
 use super::{fetch_page_info, TAG_LENGTH_MAX};
 use crate::db::PlacesDb;
 use crate::error::{InvalidPlaceInfo, Result};
+use crate::msg_types;
 use sql_support::ConnExt;
 use url::Url;
 
@@ -69,8 +70,14 @@ pub fn validate_tag(tag: &str) -> ValidatedTag<'_> {
 ///
 /// There is no success return value.
 pub fn tag_url(db: &PlacesDb, url: &Url, tag: &str) -> Result<()> {
-    let tag = validate_tag(&tag).ensure_valid()?;
     let tx = db.begin_transaction()?;
+    tag_url_in_tx(db, url, tag)?;
+    tx.commit()?;
+    Ok(())
+}
+
+pub(crate) fn tag_url_in_tx(db: &PlacesDb, url: &Url, tag: &str) -> Result<()> {
+    let tag = validate_tag(&tag).ensure_valid()?;
 
     // This function will not create a new place.
     // Fetch the place id, so we (a) avoid creating a new tag when we aren't
@@ -91,7 +98,6 @@ pub fn tag_url(db: &PlacesDb, url: &Url, tag: &str) -> Result<()> {
          VALUES((SELECT id FROM moz_tags WHERE tag = :tag), :place_id)",
         &[(":tag", &tag), (":place_id", &place_id)],
     )?;
-    tx.commit()?;
     Ok(())
 }
 
@@ -228,6 +234,54 @@ pub fn get_tags_for_url(db: &PlacesDb, url: &Url) -> Result<Vec<String>> {
     Ok(tags)
 }
 
+/// A tag, and the number of URLs which have it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TagInfo {
+    pub tag: String,
+    pub url_count: u32,
+}
+
+/// Retrieves all tags which are used by at least one URL.
+///
+/// # Arguments
+///
+/// * `conn` - A database connection on which to operate.
+///
+/// # Returns
+///
+/// * A Vec<TagInfo> with all tags and the number of URLs which have each,
+///   sorted by the number of URLs (most to fewest), then by tag.
+pub fn get_all_tags(db: &PlacesDb) -> Result<Vec<TagInfo>> {
+    db.query_rows_and_then_named_cached(
+        "SELECT t.tag, COUNT(*) AS url_count
+         FROM moz_tags t
+         JOIN moz_tags_relation r ON r.tag_id = t.id
+         GROUP BY t.id
+         ORDER BY url_count DESC, t.tag",
+        &[],
+        |row| -> Result<_> {
+            Ok(TagInfo {
+                tag: row.get("tag")?,
+                url_count: row.get("url_count")?,
+            })
+        },
+    )
+}
+
+impl From<Vec<TagInfo>> for msg_types::TagInfoList {
+    fn from(infos: Vec<TagInfo>) -> Self {
+        Self {
+            tags: infos
+                .into_iter()
+                .map(|info| msg_types::TagInfo {
+                    tag: info.tag,
+                    url_count: info.url_count,
+                })
+                .collect(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -328,6 +382,27 @@ mod tests {
 
         check_urls_with_tag(&conn, "common", vec![url2.clone()]);
 
+        // Tags are sorted by the number of URLs which have them.
+        tag_url(&conn, &url1, "tag-2").expect("should work");
+        assert_eq!(
+            get_all_tags(&conn).expect("should work"),
+            vec![
+                TagInfo {
+                    tag: "tag-2".to_string(),
+                    url_count: 2,
+                },
+                TagInfo {
+                    tag: "common".to_string(),
+                    url_count: 1,
+                },
+                TagInfo {
+                    tag: "tag-1".to_string(),
+                    url_count: 1,
+                },
+            ]
+        );
+        untag_url(&conn, &url1, "tag-2").expect("should work");
+
         remove_tag(&conn, "common").expect("should work");
         check_urls_with_tag(&conn, "common", vec![]);
         assert_eq!(get_foreign_count(&conn, &url2), 1);
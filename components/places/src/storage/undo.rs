@@ -0,0 +1,921 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! An undo journal for deletions.
+//!
+//! Deleting bookmarks with `bookmarks::delete_bookmark`, or history with
+//! `history::delete_visits_for` or `history::delete_visits_between`, records
+//! what was deleted in `moz_undo_journal`, so that `undo` can put it back
+//! with the same GUIDs, positions, tags and keywords. Entries expire after
+//! `UNDO_WINDOW`; once an entry expires, or a sync has uploaded the
+//! tombstones, the deletion is permanent as far as the user is concerned.
+//!
+//! Entries hold copies of what was deleted, so expired entries are removed
+//! whenever the journal is read or written, and by `run_maintenance`.
+//! Deleting more than `MAX_UNDO_VISITS` visits at once isn't journaled at
+//! all, and can't be undone. Neither can `history::delete_visits_for_host`,
+//! which also removes the site's pages from any existing entries.
+//!
+//! Page metadata deleted along with history isn't restored.
+
+use super::bookmarks::{
+    self, BookmarkPosition, InsertableBookmark, InsertableFolder, InsertableItem,
+    InsertableSeparator,
+};
+use super::history::update_frecency;
+use super::{delete_pending_temp_tables, fetch_page_info, new_page_info, tags, RowId};
+use crate::api::places_api::ConnectionType;
+use crate::db::PlacesDb;
+use crate::error::{InvalidPlaceInfo, Result};
+use crate::types::{BookmarkType, SyncStatus, Timestamp};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use rusqlite::Result as RusqliteResult;
+use rusqlite::Row;
+use serde::Serialize;
+use serde_derive::*;
+use sql_support::ConnExt;
+use std::time::Duration;
+use sync_guid::Guid as SyncGuid;
+use url::Url;
+
+/// How long a deletion can be undone for.
+pub const UNDO_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// The most visits a history deletion can remove and still be undone.
+/// Larger deletions, like clearing the last week of history, would copy too
+/// much into the journal.
+pub const MAX_UNDO_VISITS: u32 = 1000;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum UndoKind {
+    Bookmarks = 1,
+    History = 2,
+}
+
+impl FromSql for UndoKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_i64()? {
+            1 => Ok(UndoKind::Bookmarks),
+            2 => Ok(UndoKind::History),
+            v => Err(FromSqlError::OutOfRange(v)),
+        }
+    }
+}
+
+impl ToSql for UndoKind {
+    fn to_sql(&self) -> RusqliteResult<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(*self as u8))
+    }
+}
+
+/// A deletion which can be undone, by passing its `id` to `undo`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UndoEntry {
+    pub id: i64,
+    pub kind: UndoKind,
+    pub created_at: Timestamp,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DeletedBookmark {
+    guid: SyncGuid,
+    item_type: u8,
+    parent_guid: SyncGuid,
+    position: u32,
+    title: Option<String>,
+    url: Option<String>,
+    date_added: Timestamp,
+    last_modified: Timestamp,
+    sync_status: u8,
+    sync_change_counter: u32,
+}
+
+impl DeletedBookmark {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        Ok(Self {
+            guid: row.get("guid")?,
+            item_type: row.get::<_, BookmarkType>("type")? as u8,
+            parent_guid: row.get("parentGuid")?,
+            position: row.get("position")?,
+            title: row.get("title")?,
+            url: row.get("url")?,
+            date_added: row.get("dateAdded")?,
+            last_modified: row.get("lastModified")?,
+            sync_status: row.get::<_, SyncStatus>("syncStatus")? as u8,
+            sync_change_counter: row.get("syncChangeCounter")?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DeletedBookmarks {
+    // Parents always come before their children, and siblings are in order,
+    // so that inserting the items in turn puts them back where they were.
+    items: Vec<DeletedBookmark>,
+    // (url, keyword) and (url, tag) pairs for the deleted bookmarks.
+    keywords: Vec<(String, String)>,
+    tags: Vec<(String, String)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DeletedVisit {
+    date: Timestamp,
+    visit_type: u8,
+    is_local: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DeletedPage {
+    guid: SyncGuid,
+    url: String,
+    title: Option<String>,
+    hidden: bool,
+    typed: u32,
+    sync_status: u8,
+    sync_change_counter: u32,
+    visits: Vec<DeletedVisit>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DeletedHistory {
+    pages: Vec<DeletedPage>,
+}
+
+/// Returns the deletions which can still be undone, newest first.
+pub fn get_undo_entries(db: &PlacesDb) -> Result<Vec<UndoEntry>> {
+    // Read-only connections can't remove expired entries, but still skip
+    // them.
+    if db.conn_type() != ConnectionType::ReadOnly {
+        delete_expired_undo_entries(db)?;
+    }
+    Ok(db.query_rows_and_then_named(
+        "SELECT id, kind, created_at FROM moz_undo_journal
+         WHERE created_at >= :cutoff
+         ORDER BY created_at DESC, id DESC",
+        &[(":cutoff", &expiry_cutoff())],
+        |row| -> RusqliteResult<_> {
+            Ok(UndoEntry {
+                id: row.get("id")?,
+                kind: row.get("kind")?,
+                created_at: row.get("created_at")?,
+            })
+        },
+    )?)
+}
+
+/// Restores the items deleted by an undo entry, and removes the entry.
+/// Bookmarks can't be restored if an item with the same GUID has been added
+/// since, in which case this fails with `InvalidPlaceInfo::UndoConflict`.
+pub fn undo(db: &PlacesDb, id: i64) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    let result = undo_in_tx(db, id);
+    match result {
+        Ok(_) => tx.commit()?,
+        Err(_) => tx.rollback()?,
+    }
+    db.notify_bookmark_observers();
+    result
+}
+
+fn undo_in_tx(db: &PlacesDb, id: i64) -> Result<()> {
+    delete_expired_undo_entries(db)?;
+    let (kind, data) = db
+        .try_query_row(
+            "SELECT kind, data FROM moz_undo_journal
+             WHERE id = :id AND created_at >= :cutoff",
+            &[(":id", &id), (":cutoff", &expiry_cutoff())],
+            |row| -> RusqliteResult<_> {
+                Ok((row.get::<_, UndoKind>(0)?, row.get::<_, String>(1)?))
+            },
+            true,
+        )?
+        .ok_or(InvalidPlaceInfo::NoSuchUndoEntry(id))?;
+    match kind {
+        UndoKind::Bookmarks => restore_bookmarks(db, serde_json::from_str(&data)?)?,
+        UndoKind::History => restore_history(db, serde_json::from_str(&data)?)?,
+    }
+    db.execute_named_cached(
+        "DELETE FROM moz_undo_journal WHERE id = :id",
+        &[(":id", &id)],
+    )?;
+    delete_pending_temp_tables(db)?;
+    Ok(())
+}
+
+/// Removes all undo entries of a kind. Used when wiping bookmarks or history,
+/// since those can't be undone.
+pub(crate) fn delete_undo_entries(db: &PlacesDb, kind: UndoKind) -> Result<()> {
+    db.execute_named_cached(
+        "DELETE FROM moz_undo_journal WHERE kind = :kind",
+        &[(":kind", &kind)],
+    )?;
+    Ok(())
+}
+
+/// Removes pages on `host`, or any of its subdomains, from history undo
+/// entries, so that forgetting about a site also forgets the deletions which
+/// could bring it back. Entries without any pages left are removed. `host`
+/// must already be lowercased.
+pub(crate) fn delete_undo_history_for_host(db: &PlacesDb, host: &str) -> Result<()> {
+    let entries = db.query_rows_and_then_named(
+        "SELECT id, data FROM moz_undo_journal WHERE kind = :kind",
+        &[(":kind", &UndoKind::History)],
+        |row| -> RusqliteResult<_> { Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)) },
+    )?;
+    for (id, data) in entries {
+        let mut deleted: DeletedHistory = serde_json::from_str(&data)?;
+        let page_count = deleted.pages.len();
+        deleted
+            .pages
+            .retain(|page| !is_page_on_host(&page.url, host));
+        if deleted.pages.len() == page_count {
+            continue;
+        }
+        if deleted.pages.is_empty() {
+            db.execute_named_cached(
+                "DELETE FROM moz_undo_journal WHERE id = :id",
+                &[(":id", &id)],
+            )?;
+        } else {
+            db.execute_named_cached(
+                "UPDATE moz_undo_journal SET data = :data WHERE id = :id",
+                &[(":id", &id), (":data", &serde_json::to_string(&deleted)?)],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn is_page_on_host(href: &str, host: &str) -> bool {
+    let url = match Url::parse(href) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+    match url.host_str() {
+        Some(page_host) => {
+            page_host == host
+                || (page_host.ends_with(host)
+                    && page_host[..page_host.len() - host.len()].ends_with('.'))
+        }
+        None => false,
+    }
+}
+
+/// Removes entries which have expired, and can't be undone any more.
+pub(crate) fn delete_expired_undo_entries(db: &PlacesDb) -> Result<()> {
+    db.execute_named_cached(
+        "DELETE FROM moz_undo_journal WHERE created_at < :cutoff",
+        &[(":cutoff", &expiry_cutoff())],
+    )?;
+    Ok(())
+}
+
+fn expiry_cutoff() -> Timestamp {
+    Timestamp::now()
+        .checked_sub(UNDO_WINDOW)
+        .unwrap_or_default()
+}
+
+fn add_undo_entry<T: Serialize>(db: &PlacesDb, kind: UndoKind, data: &T) -> Result<()> {
+    delete_expired_undo_entries(db)?;
+    db.execute_named_cached(
+        "INSERT INTO moz_undo_journal(kind, created_at, data)
+         VALUES(:kind, :created_at, :data)",
+        &[
+            (":kind", &kind),
+            (":created_at", &Timestamp::now()),
+            (":data", &serde_json::to_string(data)?),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Records a bookmark and its descendants, which are about to be deleted.
+/// Must be called in the deleting transaction.
+pub(crate) fn journal_bookmark_deletion(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
+    // Roots don't have a parent, so won't be found here, but they can't be
+    // deleted anyway.
+    let items = db.query_rows_and_then_named(
+        "WITH RECURSIVE
+         descendants(id, level) AS (
+             SELECT id, 0 FROM moz_bookmarks
+             WHERE guid = :guid
+             UNION ALL
+             SELECT b.id, d.level + 1 FROM moz_bookmarks b
+             JOIN descendants d ON b.parent = d.id
+         )
+         SELECT b.guid, b.type, p.guid AS parentGuid, b.position, b.title,
+                h.url, b.dateAdded, b.lastModified, b.syncStatus,
+                b.syncChangeCounter
+         FROM descendants d
+         JOIN moz_bookmarks b ON b.id = d.id
+         JOIN moz_bookmarks p ON p.id = b.parent
+         LEFT JOIN moz_places h ON h.id = b.fk
+         ORDER BY d.level, b.parent, b.position",
+        &[(":guid", guid)],
+        DeletedBookmark::from_row,
+    )?;
+    if items.is_empty() {
+        return Ok(());
+    }
+    let mut urls: Vec<&str> = items
+        .iter()
+        .filter_map(|item| item.url.as_deref())
+        .collect();
+    urls.sort();
+    urls.dedup();
+    let mut keywords = Vec::new();
+    let mut url_tags = Vec::new();
+    for href in urls {
+        let url = Url::parse(href)?;
+        if let Some(keyword) = bookmarks::bookmarks_get_keyword_for_url(db, &url)? {
+            keywords.push((href.to_string(), keyword));
+        }
+        for tag in tags::get_tags_for_url(db, &url)? {
+            url_tags.push((href.to_string(), tag));
+        }
+    }
+    add_undo_entry(
+        db,
+        UndoKind::Bookmarks,
+        &DeletedBookmarks {
+            items,
+            keywords,
+            tags: url_tags,
+        },
+    )
+}
+
+fn restore_bookmarks(db: &PlacesDb, deleted: DeletedBookmarks) -> Result<()> {
+    for item in deleted.items {
+        if bookmarks::get_raw_bookmark(db, &item.guid)?.is_some() {
+            return Err(InvalidPlaceInfo::UndoConflict(item.guid.to_string()).into());
+        }
+        let parent_guid = item.parent_guid;
+        let position = BookmarkPosition::Specific(item.position);
+        let date_added = Some(item.date_added);
+        let last_modified = Some(item.last_modified);
+        let guid = Some(item.guid.clone());
+        let insertable = match BookmarkType::from_u8(item.item_type) {
+            Some(BookmarkType::Bookmark) => InsertableItem::Bookmark(InsertableBookmark {
+                parent_guid,
+                position,
+                date_added,
+                last_modified,
+                guid,
+                url: Url::parse(item.url.as_deref().ok_or(InvalidPlaceInfo::NoUrl)?)?,
+                title: item.title,
+            }),
+            Some(BookmarkType::Folder) => InsertableItem::Folder(InsertableFolder {
+                parent_guid,
+                position,
+                date_added,
+                last_modified,
+                guid,
+                title: item.title,
+            }),
+            Some(BookmarkType::Separator) => InsertableItem::Separator(InsertableSeparator {
+                parent_guid,
+                position,
+                date_added,
+                last_modified,
+                guid,
+            }),
+            // Can't happen, since we read the type from the database.
+            None => continue,
+        };
+        bookmarks::insert_bookmark_in_tx(db, &insertable)?;
+        // Inserting removes the item's tombstone, if it has one. Restore its
+        // sync status, so that syncing uploads it again instead of treating
+        // it as new.
+        db.execute_named_cached(
+            "UPDATE moz_bookmarks SET
+                 syncStatus = :sync_status,
+                 syncChangeCounter = :sync_change_counter + 1
+             WHERE guid = :guid",
+            &[
+                (":sync_status", &item.sync_status),
+                (":sync_change_counter", &item.sync_change_counter),
+                (":guid", &item.guid),
+            ],
+        )?;
+    }
+    for (href, keyword) in deleted.keywords {
+        let url = Url::parse(&href)?;
+        // Don't clobber keywords which were set in the meantime.
+        if bookmarks::bookmarks_get_keyword_for_url(db, &url)?.is_none()
+            && bookmarks::bookmarks_get_url_for_keyword(db, &keyword)?.is_none()
+        {
+            bookmarks::set_keyword_in_tx(db, &url, &keyword)?;
+        }
+    }
+    for (href, tag) in deleted.tags {
+        tags::tag_url_in_tx(db, &Url::parse(&href)?, &tag)?;
+    }
+    Ok(())
+}
+
+/// Records the visits for a page, which are about to be deleted.
+pub(crate) fn journal_history_for_page(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
+    journal_history(db, "h.guid = :guid", &[(":guid", guid)])
+}
+
+/// Records the visits in a date range, which are about to be deleted.
+pub(crate) fn journal_history_between(
+    db: &PlacesDb,
+    start: Timestamp,
+    end: Timestamp,
+) -> Result<()> {
+    journal_history(
+        db,
+        "v.visit_date BETWEEN :start AND :end",
+        &[(":start", &start), (":end", &end)],
+    )
+}
+
+fn journal_history(db: &PlacesDb, filter: &str, params: &[(&str, &dyn ToSql)]) -> Result<()> {
+    let num_visits = db.query_row_and_then_named(
+        &format!(
+            "SELECT COUNT(*)
+             FROM moz_historyvisits v
+             JOIN moz_places h ON h.id = v.place_id
+             WHERE {}",
+            filter
+        ),
+        params,
+        |row| row.get::<_, u32>(0),
+        false,
+    )?;
+    if num_visits > MAX_UNDO_VISITS {
+        log::debug!(
+            "Not journaling deletion of {} visits, which can't be undone",
+            num_visits
+        );
+        return Ok(());
+    }
+    let rows = db.query_rows_and_then_named(
+        &format!(
+            "SELECT h.guid, h.url, h.title, h.hidden, h.typed, h.sync_status,
+                    h.sync_change_counter, v.visit_date, v.visit_type, v.is_local
+             FROM moz_historyvisits v
+             JOIN moz_places h ON h.id = v.place_id
+             WHERE {}
+             ORDER BY h.id, v.visit_date",
+            filter
+        ),
+        params,
+        |row| -> Result<_> {
+            let page = DeletedPage {
+                guid: row.get("guid")?,
+                url: row.get("url")?,
+                title: row.get("title")?,
+                hidden: row.get("hidden")?,
+                typed: row.get("typed")?,
+                sync_status: row.get::<_, SyncStatus>("sync_status")? as u8,
+                sync_change_counter: row.get("sync_change_counter")?,
+                visits: Vec::new(),
+            };
+            let visit = DeletedVisit {
+                date: row.get("visit_date")?,
+                visit_type: row.get("visit_type")?,
+                is_local: row.get("is_local")?,
+            };
+            Ok((page, visit))
+        },
+    )?;
+    // Pages without visits aren't deleted, so there's nothing to restore for
+    // them.
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let mut pages: Vec<DeletedPage> = Vec::new();
+    for (page, visit) in rows {
+        match pages.last_mut() {
+            Some(last) if last.guid == page.guid => last.visits.push(visit),
+            _ => {
+                let mut page = page;
+                page.visits.push(visit);
+                pages.push(page);
+            }
+        }
+    }
+    add_undo_entry(db, UndoKind::History, &DeletedHistory { pages })
+}
+
+fn restore_history(db: &PlacesDb, deleted: DeletedHistory) -> Result<()> {
+    for page in deleted.pages {
+        let url = Url::parse(&page.url)?;
+        let page_id = match fetch_page_info(db, &url)? {
+            Some(info) => info.page.row_id,
+            None => {
+                // The page was removed along with its visits, so recreate it
+                // with its original GUID.
+                let info = new_page_info(db, &url, Some(page.guid.clone()))?;
+                db.execute_named_cached(
+                    "UPDATE moz_places SET
+                         title = :title,
+                         hidden = :hidden,
+                         typed = :typed,
+                         sync_status = :sync_status,
+                         sync_change_counter = :sync_change_counter
+                     WHERE id = :page_id",
+                    &[
+                        (":title", &page.title),
+                        (":hidden", &page.hidden),
+                        (":typed", &page.typed),
+                        (":sync_status", &page.sync_status),
+                        (":sync_change_counter", &page.sync_change_counter),
+                        (":page_id", &info.row_id),
+                    ],
+                )?;
+                info.row_id
+            }
+        };
+        db.execute_named_cached(
+            "DELETE FROM moz_places_tombstones WHERE guid = :guid",
+            &[(":guid", &page.guid)],
+        )?;
+        for visit in &page.visits {
+            restore_visit(db, page_id, visit)?;
+        }
+        // Make sure the restored visits are uploaded on the next sync.
+        db.execute_named_cached(
+            "UPDATE moz_places SET sync_change_counter = sync_change_counter + 1
+             WHERE id = :page_id",
+            &[(":page_id", &page_id)],
+        )?;
+        update_frecency(db, page_id, None)?;
+    }
+    Ok(())
+}
+
+fn restore_visit(db: &PlacesDb, page_id: RowId, visit: &DeletedVisit) -> Result<()> {
+    db.execute_named_cached(
+        "INSERT INTO moz_historyvisits(place_id, visit_date, visit_type, is_local)
+         SELECT :page_id, :visit_date, :visit_type, :is_local
+         WHERE NOT EXISTS(SELECT 1 FROM moz_historyvisits
+                          WHERE place_id = :page_id AND
+                                visit_date = :visit_date)",
+        &[
+            (":page_id", &page_id),
+            (":visit_date", &visit.date),
+            (":visit_type", &visit.visit_type),
+            (":is_local", &visit.is_local),
+        ],
+    )?;
+    db.execute_named_cached(
+        "DELETE FROM moz_historyvisit_tombstones
+         WHERE place_id = :page_id AND visit_date = :visit_date",
+        &[(":page_id", &page_id), (":visit_date", &visit.date)],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::bookmarks::{delete_bookmark, BookmarkRootGuid};
+    use crate::storage::history::{
+        apply_observation, delete_visits_between, delete_visits_for, delete_visits_for_host,
+    };
+    use crate::tests::{assert_json_tree, insert_json_tree};
+    use crate::types::VisitTransition;
+    use rusqlite::NO_PARAMS;
+    use serde_json::json;
+
+    fn count(db: &PlacesDb, sql: &str) -> u32 {
+        db.query_one::<u32>(sql).expect("should work")
+    }
+
+    #[test]
+    fn test_undo_bookmark_deletion() -> Result<()> {
+        let conn = new_mem_connection();
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": &BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    {
+                        "guid": "bookmark1___",
+                        "url": "https://www.example1.com/",
+                    },
+                    {
+                        "guid": "folder1_____",
+                        "title": "A folder",
+                        "children": [
+                            {
+                                "guid": "bookmark2___",
+                                "title": "Second",
+                                "url": "https://www.example2.com/",
+                            },
+                            {
+                                "guid": "separator1__",
+                                "type": BookmarkType::Separator,
+                            },
+                            {
+                                "guid": "bookmark3___",
+                                "url": "https://www.example3.com/",
+                            },
+                        ],
+                    },
+                    {
+                        "guid": "bookmark4___",
+                        "url": "https://www.example4.com/",
+                    },
+                ]
+            }),
+        );
+        let url2 = Url::parse("https://www.example2.com/")?;
+        bookmarks::bookmarks_set_keyword(&conn, &url2, "two")?;
+        tags::tag_url(&conn, &url2, "tag")?;
+        // Pretend everything has been synced, so deleting writes tombstones.
+        conn.execute(
+            &format!(
+                "UPDATE moz_bookmarks SET syncStatus = {}, syncChangeCounter = 0",
+                SyncStatus::Normal as u8
+            ),
+            NO_PARAMS,
+        )?;
+
+        assert!(delete_bookmark(&conn, &"folder1_____".into())?);
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM moz_bookmarks_deleted"),
+            4
+        );
+        assert!(bookmarks::bookmarks_get_keyword_for_url(&conn, &url2)?.is_none());
+
+        let entries = get_undo_entries(&conn)?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, UndoKind::Bookmarks);
+        undo(&conn, entries[0].id)?;
+
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Unfiled.as_guid(),
+            json!({
+                "guid": &BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    {
+                        "guid": "bookmark1___",
+                        "url": "https://www.example1.com/",
+                    },
+                    {
+                        "guid": "folder1_____",
+                        "title": "A folder",
+                        "children": [
+                            {
+                                "guid": "bookmark2___",
+                                "title": "Second",
+                                "url": "https://www.example2.com/",
+                            },
+                            {
+                                "guid": "separator1__",
+                                "type": BookmarkType::Separator,
+                            },
+                            {
+                                "guid": "bookmark3___",
+                                "url": "https://www.example3.com/",
+                            },
+                        ],
+                    },
+                    {
+                        "guid": "bookmark4___",
+                        "url": "https://www.example4.com/",
+                    },
+                ]
+            }),
+        );
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM moz_bookmarks_deleted"),
+            0
+        );
+        assert_eq!(
+            count(
+                &conn,
+                &format!(
+                    "SELECT COUNT(*) FROM moz_bookmarks
+                     WHERE syncStatus = {} AND syncChangeCounter > 0
+                       AND guid IN ('folder1_____', 'bookmark2___',
+                                    'separator1__', 'bookmark3___')",
+                    SyncStatus::Normal as u8
+                )
+            ),
+            4
+        );
+        assert_eq!(
+            bookmarks::bookmarks_get_keyword_for_url(&conn, &url2)?,
+            Some("two".to_string())
+        );
+        assert_eq!(
+            tags::get_tags_for_url(&conn, &url2)?,
+            vec!["tag".to_string()]
+        );
+        assert!(get_undo_entries(&conn)?.is_empty());
+
+        // The entry is gone, so can't be undone twice.
+        assert!(undo(&conn, entries[0].id).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_bookmark_conflict() -> Result<()> {
+        let conn = new_mem_connection();
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": &BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    {
+                        "guid": "bookmark1___",
+                        "url": "https://www.example1.com/",
+                    },
+                ]
+            }),
+        );
+        assert!(delete_bookmark(&conn, &"bookmark1___".into())?);
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": &BookmarkRootGuid::Menu.as_guid(),
+                "children": [
+                    {
+                        "guid": "bookmark1___",
+                        "url": "https://www.example2.com/",
+                    },
+                ]
+            }),
+        );
+        let entries = get_undo_entries(&conn)?;
+        assert_eq!(entries.len(), 1);
+        assert!(undo(&conn, entries[0].id).is_err());
+        // The failed undo doesn't remove the entry.
+        assert_eq!(get_undo_entries(&conn)?, entries);
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_history_deletion() -> Result<()> {
+        let conn = new_mem_connection();
+        let url = Url::parse("https://www.example.com/")?;
+        for when in &[1_000_000_000_000u64, 1_000_000_001_000] {
+            apply_observation(
+                &conn,
+                VisitObservation::new(url.clone())
+                    .with_at(Timestamp(*when))
+                    .with_visit_type(VisitTransition::Link),
+            )?;
+        }
+        conn.execute(
+            &format!(
+                "UPDATE moz_places SET sync_status = {}, sync_change_counter = 0",
+                SyncStatus::Normal as u8
+            ),
+            NO_PARAMS,
+        )?;
+        let guid = fetch_page_info(&conn, &url)?
+            .expect("should exist")
+            .page
+            .guid;
+
+        delete_visits_for(&conn, &guid)?;
+        assert!(fetch_page_info(&conn, &url)?.is_none());
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM moz_places_tombstones"),
+            1
+        );
+
+        let entries = get_undo_entries(&conn)?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, UndoKind::History);
+        undo(&conn, entries[0].id)?;
+
+        let page = fetch_page_info(&conn, &url)?.expect("should exist").page;
+        assert_eq!(page.guid, guid);
+        assert_eq!(page.visit_count_local, 2);
+        assert_eq!(page.sync_status, SyncStatus::Normal);
+        assert_eq!(page.sync_change_counter, 1);
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM moz_places_tombstones"),
+            0
+        );
+        assert!(get_undo_entries(&conn)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_entries_removed() -> Result<()> {
+        let conn = new_mem_connection();
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": &BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    {
+                        "guid": "bookmark1___",
+                        "url": "https://www.example1.com/",
+                    },
+                ]
+            }),
+        );
+        assert!(delete_bookmark(&conn, &"bookmark1___".into())?);
+        let entries = get_undo_entries(&conn)?;
+        assert_eq!(entries.len(), 1);
+
+        // Once an entry expires, reading the journal removes it, along with
+        // the copy of what was deleted.
+        let expired = Timestamp::now()
+            .checked_sub(UNDO_WINDOW + Duration::from_secs(1))
+            .expect("should be valid");
+        conn.execute_named(
+            "UPDATE moz_undo_journal SET created_at = :expired",
+            &[(":expired", &expired)],
+        )?;
+        assert!(get_undo_entries(&conn)?.is_empty());
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM moz_undo_journal"), 0);
+        assert!(undo(&conn, entries[0].id).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_history_deletion_not_journaled() -> Result<()> {
+        let conn = new_mem_connection();
+        let url = Url::parse("https://www.example.com/")?;
+        apply_observation(
+            &conn,
+            VisitObservation::new(url.clone())
+                .with_at(Timestamp(1_000_000_000_000))
+                .with_visit_type(VisitTransition::Link),
+        )?;
+        // Add enough visits, one a second, to go over the limit.
+        conn.execute_named(
+            "WITH RECURSIVE offsets(n) AS (
+                 SELECT 1 UNION ALL SELECT n + 1 FROM offsets WHERE n < :max
+             )
+             INSERT INTO moz_historyvisits(place_id, visit_date, visit_type, is_local)
+             SELECT h.id, 1000000000000 + o.n * 1000, 1, 1
+             FROM moz_places h, offsets o",
+            &[(":max", &MAX_UNDO_VISITS)],
+        )?;
+
+        delete_visits_between(
+            &conn,
+            Timestamp(1_000_000_000_000),
+            Timestamp(2_000_000_000_000),
+        )?;
+        assert!(fetch_page_info(&conn, &url)?.is_none());
+        assert!(get_undo_entries(&conn)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_forgotten_host_removed_from_entries() -> Result<()> {
+        let conn = new_mem_connection();
+        for href in &[
+            "https://example.com/a",
+            "https://sub.example.com:8080/b",
+            "https://example.org/",
+            "https://www.example.com/c",
+        ] {
+            apply_observation(
+                &conn,
+                VisitObservation::new(Url::parse(href)?)
+                    .with_at(Timestamp(1_000_000_000_000))
+                    .with_visit_type(VisitTransition::Link),
+            )?;
+        }
+        let guid = fetch_page_info(&conn, &Url::parse("https://www.example.com/c")?)?
+            .expect("should exist")
+            .page
+            .guid;
+        delete_visits_for(&conn, &guid)?;
+        delete_visits_between(
+            &conn,
+            Timestamp(1_000_000_000_000),
+            Timestamp(2_000_000_000_000),
+        )?;
+        assert_eq!(get_undo_entries(&conn)?.len(), 2);
+
+        // Forgetting the site removes the entry which only had its pages, and
+        // leaves only the other site's page in the mixed one.
+        delete_visits_for_host(&conn, "example.com")?;
+        let entries = get_undo_entries(&conn)?;
+        assert_eq!(entries.len(), 1);
+        undo(&conn, entries[0].id)?;
+        assert!(fetch_page_info(&conn, &Url::parse("https://example.org/")?)?.is_some());
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM moz_places"), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_entry_for_noop_deletion() -> Result<()> {
+        let conn = new_mem_connection();
+        assert!(!delete_bookmark(&conn, &"bookmark1___".into())?);
+        delete_visits_for(&conn, &"page1_______".into())?;
+        assert!(get_undo_entries(&conn)?.is_empty());
+        Ok(())
+    }
+}
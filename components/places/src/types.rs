@@ -303,6 +303,64 @@ impl ToSql for SyncStatus {
     }
 }
 
+/// The kind of document a page was, for history metadata.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum DocumentType {
+    Regular = 0,
+    Media = 1,
+}
+
+impl Default for DocumentType {
+    fn default() -> Self {
+        DocumentType::Regular
+    }
+}
+
+impl FromSql for DocumentType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_i64()? {
+            0 => Ok(DocumentType::Regular),
+            1 => Ok(DocumentType::Media),
+            v => Err(FromSqlError::OutOfRange(v)),
+        }
+    }
+}
+
+impl ToSql for DocumentType {
+    fn to_sql(&self) -> RusqliteResult<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(*self as u8))
+    }
+}
+
+/// Why a site is left out of the top sites returned by
+/// `storage::history::top_frecent_site_infos`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[repr(u8)]
+pub enum TopSiteExclusionKind {
+    /// The app shows the site in a pinned tile.
+    Pinned = 1,
+    /// The user removed the site from their top sites.
+    Blocked = 2,
+}
+
+impl FromSql for TopSiteExclusionKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_i64()? {
+            1 => Ok(TopSiteExclusionKind::Pinned),
+            2 => Ok(TopSiteExclusionKind::Blocked),
+            v => Err(FromSqlError::OutOfRange(v)),
+        }
+    }
+}
+
+impl ToSql for TopSiteExclusionKind {
+    fn to_sql(&self) -> RusqliteResult<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(*self as u8))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,230 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use places::{
+    api::places_api::{ConnectionType, PlacesApi},
+    storage::bookmarks::{public_node::fetch_bookmark, BookmarkRootGuid},
+    types::VisitTransition,
+    ErrorKind, Result, Timestamp,
+};
+use rusqlite::{Connection, NO_PARAMS};
+use sql_support::ConnExt;
+use std::path::Path;
+use tempfile::tempdir;
+use url::Url;
+
+// 2020-09-19T14:40:00Z, as a Chromium timestamp and one of ours.
+const WEBKIT_TIME: i64 = 13_245_000_000_000_000;
+const TIME: Timestamp = Timestamp(1_600_526_400_000);
+
+fn empty_chromium_db(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch(include_str!("./chromium_history_schema.sql"))?;
+    Ok(conn)
+}
+
+fn insert_url(conn: &Connection, id: i64, url: &str, title: &str) -> Result<()> {
+    conn.execute_named(
+        "INSERT INTO urls(id, url, title, last_visit_time)
+         VALUES(:id, :url, :title, 0)",
+        rusqlite::named_params! {
+            ":id": id,
+            ":url": url,
+            ":title": title,
+        },
+    )?;
+    Ok(())
+}
+
+fn insert_visit(conn: &Connection, url_id: i64, visit_time: i64, transition: i64) -> Result<()> {
+    conn.execute_named(
+        "INSERT INTO visits(url, visit_time, transition)
+         VALUES(:url, :visit_time, :transition)",
+        rusqlite::named_params! {
+            ":url": url_id,
+            ":visit_time": visit_time,
+            ":transition": transition,
+        },
+    )?;
+    Ok(())
+}
+
+#[test]
+fn test_import_history_unsupported_db_version() -> Result<()> {
+    let tmpdir = tempdir().unwrap();
+    let chromium_path = tmpdir.path().join("History");
+    let chromium_db = empty_chromium_db(&chromium_path)?;
+    chromium_db.execute(
+        "UPDATE meta SET value = '10' WHERE key = 'version'",
+        NO_PARAMS,
+    )?;
+    let places_api = PlacesApi::new(tmpdir.path().join("places.sqlite"))?;
+    match places::import::import_chromium_history(&places_api, chromium_path)
+        .unwrap_err()
+        .kind()
+    {
+        ErrorKind::UnsupportedDatabaseVersion(10) => {}
+        _ => unreachable!("Should fail with UnsupportedDatabaseVersion!"),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_import_history() -> Result<()> {
+    let tmpdir = tempdir().unwrap();
+    let chromium_path = tmpdir.path().join("History");
+    let chromium_db = empty_chromium_db(&chromium_path)?;
+
+    insert_url(&chromium_db, 1, "https://example.com/", "Example")?;
+    insert_url(&chromium_db, 2, "chrome://newtab/", "New Tab")?;
+    insert_url(&chromium_db, 3, "not a url", "")?;
+    insert_url(&chromium_db, 4, "http://💖.com/💖", "")?;
+    // TYPED, with the chain start and end qualifiers.
+    insert_visit(&chromium_db, 1, WEBKIT_TIME, 0x3000_0001)?;
+    // LINK, a second later.
+    insert_visit(&chromium_db, 1, WEBKIT_TIME + 1_000_000, 0)?;
+    insert_visit(&chromium_db, 2, WEBKIT_TIME, 1)?;
+    insert_visit(&chromium_db, 3, WEBKIT_TIME, 1)?;
+    // A server redirect, with a time Chromium uses for "no time".
+    insert_visit(&chromium_db, 4, 0, 0x8000_0000)?;
+
+    let places_api = PlacesApi::new(tmpdir.path().join("places.sqlite"))?;
+    let metrics = places::import::import_chromium_history(&places_api, &chromium_path)?;
+    assert_eq!(metrics.num_total, 5);
+    assert_eq!(metrics.num_succeeded, 3);
+    assert_eq!(metrics.num_failed, 2);
+
+    let conn = places_api.open_connection(ConnectionType::ReadOnly)?;
+    let visits_for = |url: &str| -> Result<Vec<(Timestamp, u8)>> {
+        let url = Url::parse(url)?;
+        Ok(conn.query_rows_and_then_named(
+            "SELECT v.visit_date, v.visit_type FROM moz_historyvisits v
+             JOIN moz_places h ON h.id = v.place_id
+             WHERE h.url_hash = hash(:url) AND h.url = :url
+             ORDER BY v.visit_date",
+            &[(":url", &url.as_str())],
+            |row| -> rusqlite::Result<_> { Ok((row.get(0)?, row.get(1)?)) },
+        )?)
+    };
+    assert_eq!(
+        visits_for("https://example.com/")?,
+        vec![
+            (TIME, VisitTransition::Typed as u8),
+            (
+                Timestamp(TIME.as_millis() + 1000),
+                VisitTransition::Link as u8
+            ),
+        ]
+    );
+    // The invalid time is replaced with the time of the import.
+    let redirects = visits_for("http://💖.com/💖")?;
+    assert_eq!(redirects.len(), 1);
+    assert!(redirects[0].0 > TIME);
+    assert_eq!(redirects[0].1, VisitTransition::RedirectTemporary as u8);
+    assert!(visits_for("chrome://newtab/")?.is_empty());
+
+    let page = places::storage::fetch_page_info(&conn, &Url::parse("https://example.com/")?)?
+        .expect("should exist")
+        .page;
+    assert_eq!(page.title, "Example");
+    assert_eq!(page.visit_count_local, 2);
+    assert!(page.frecency > 0);
+
+    // Importing again doesn't duplicate visits.
+    let metrics = places::import::import_chromium_history(&places_api, &chromium_path)?;
+    assert_eq!(metrics.num_succeeded, 0);
+    assert_eq!(visits_for("https://example.com/")?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_import_bookmarks() -> Result<()> {
+    let tmpdir = tempdir().unwrap();
+    let bookmarks_path = tmpdir.path().join("Bookmarks");
+    let json = serde_json::json!({
+        "checksum": "0123456789abcdef",
+        "roots": {
+            "bookmark_bar": {
+                "children": [{
+                    "date_added": WEBKIT_TIME.to_string(),
+                    "guid": "0bc5d13f-2cba-5d74-951f-3f233fe6c908",
+                    "id": "5",
+                    "name": "Example",
+                    "type": "url",
+                    "url": "https://example.com/",
+                }, {
+                    "children": [{
+                        "guid": "a3c4ef3e-a4b5-4f14-8c4b-4d4f2c5e5e7a",
+                        "id": "7",
+                        "name": "",
+                        "type": "url",
+                        "url": "https://example.org/",
+                    }, {
+                        "id": "8",
+                        "name": "Broken",
+                        "type": "url",
+                        "url": "not a url",
+                    }],
+                    "date_added": WEBKIT_TIME.to_string(),
+                    "date_modified": "0",
+                    "id": "6",
+                    "name": "A folder",
+                    "type": "folder",
+                }],
+                "date_added": WEBKIT_TIME.to_string(),
+                "id": "1",
+                "name": "Bookmarks bar",
+                "type": "folder",
+            },
+            "other": {
+                "children": [],
+                "id": "2",
+                "name": "Other bookmarks",
+                "type": "folder",
+            },
+            "synced": {
+                "children": [{
+                    "id": "9",
+                    "name": "Mobile",
+                    "type": "url",
+                    "url": "https://example.net/",
+                }],
+                "id": "3",
+                "name": "Mobile bookmarks",
+                "type": "folder",
+            },
+        },
+        "version": 1,
+    });
+    std::fs::write(&bookmarks_path, json.to_string())?;
+
+    let places_api = PlacesApi::new(tmpdir.path().join("places.sqlite"))?;
+    let metrics = places::import::import_chromium_bookmarks(&places_api, &bookmarks_path)?;
+    assert_eq!(metrics.num_total, 5);
+    assert_eq!(metrics.num_succeeded, 4);
+    assert_eq!(metrics.num_failed, 1);
+
+    let conn = places_api.open_connection(ConnectionType::ReadOnly)?;
+    let toolbar =
+        fetch_bookmark(&conn, &BookmarkRootGuid::Toolbar.as_guid(), true)?.expect("should exist");
+    let children = toolbar.child_nodes.expect("should have children");
+    assert_eq!(children.len(), 2);
+    assert_eq!(children[0].url, Some(Url::parse("https://example.com/")?));
+    assert_eq!(children[0].title, Some("Example".to_string()));
+    assert_eq!(children[0].date_added, TIME);
+    assert_eq!(children[1].title, Some("A folder".to_string()));
+
+    let folder = fetch_bookmark(&conn, &children[1].guid, true)?.expect("should exist");
+    let folder_children = folder.child_nodes.expect("should have children");
+    assert_eq!(folder_children.len(), 1);
+    assert_eq!(folder_children[0].title, None);
+
+    let unfiled =
+        fetch_bookmark(&conn, &BookmarkRootGuid::Unfiled.as_guid(), true)?.expect("should exist");
+    assert!(unfiled.child_nodes.unwrap_or_default().is_empty());
+    let mobile =
+        fetch_bookmark(&conn, &BookmarkRootGuid::Mobile.as_guid(), true)?.expect("should exist");
+    assert_eq!(mobile.child_nodes.expect("should have children").len(), 1);
+    Ok(())
+}
@@ -0,0 +1,29 @@
+-- The parts of Chromium's `History` database which we import.
+
+CREATE TABLE meta(
+    key LONGVARCHAR NOT NULL UNIQUE PRIMARY KEY,
+    value LONGVARCHAR
+);
+
+INSERT INTO meta(key, value) VALUES('version', '46');
+
+CREATE TABLE urls(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url LONGVARCHAR,
+    title LONGVARCHAR,
+    visit_count INTEGER DEFAULT 0 NOT NULL,
+    typed_count INTEGER DEFAULT 0 NOT NULL,
+    last_visit_time INTEGER NOT NULL,
+    hidden INTEGER DEFAULT 0 NOT NULL
+);
+
+CREATE TABLE visits(
+    id INTEGER PRIMARY KEY,
+    url INTEGER NOT NULL,
+    visit_time INTEGER NOT NULL,
+    from_visit INTEGER,
+    transition INTEGER DEFAULT 0 NOT NULL,
+    segment_id INTEGER,
+    visit_duration INTEGER DEFAULT 0 NOT NULL,
+    incremented_omnibox_typed_score BOOLEAN DEFAULT FALSE NOT NULL
+);
@@ -15,32 +15,44 @@ use std::{convert::TryFrom, ptr};
 #[derive(Clone, Debug)]
 #[repr(u8)]
 pub enum HashAlgorithm {
+    SHA1,
     SHA256,
 }
 
 impl HashAlgorithm {
     fn result_len(&self) -> u32 {
         match self {
+            HashAlgorithm::SHA1 => nss_sys::SHA1_LENGTH,
             HashAlgorithm::SHA256 => nss_sys::SHA256_LENGTH,
         }
     }
 
     fn as_hmac_mechanism(&self) -> u32 {
         match self {
+            HashAlgorithm::SHA1 => nss_sys::CKM_SHA_1_HMAC,
             HashAlgorithm::SHA256 => nss_sys::CKM_SHA256_HMAC,
         }
     }
 
     pub(crate) fn as_hkdf_mechanism(&self) -> u32 {
         match self {
+            HashAlgorithm::SHA1 => nss_sys::CKM_NSS_HKDF_SHA1,
             HashAlgorithm::SHA256 => nss_sys::CKM_NSS_HKDF_SHA256,
         }
     }
+
+    pub(crate) fn as_hmac_oid_tag(&self) -> nss_sys::SECOidTag::Type {
+        match self {
+            HashAlgorithm::SHA1 => nss_sys::SECOidTag::SEC_OID_HMAC_SHA1,
+            HashAlgorithm::SHA256 => nss_sys::SECOidTag::SEC_OID_HMAC_SHA256,
+        }
+    }
 }
 
 impl From<&HashAlgorithm> for nss_sys::SECOidTag::Type {
     fn from(alg: &HashAlgorithm) -> Self {
         match alg {
+            HashAlgorithm::SHA1 => nss_sys::SECOidTag::SEC_OID_SHA1,
             HashAlgorithm::SHA256 => nss_sys::SECOidTag::SEC_OID_SHA256,
         }
     }
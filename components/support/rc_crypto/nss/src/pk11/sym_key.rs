@@ -4,7 +4,11 @@
 
 use crate::{
     error::*,
-    pk11::{context::HashAlgorithm, slot, types::SymKey},
+    pk11::{
+        context::HashAlgorithm,
+        slot,
+        types::{AlgorithmID, SymKey},
+    },
     util::{ensure_nss_initialized, map_nss_secstatus, sec_item_as_slice, ScopedPtr},
 };
 use std::{
@@ -65,6 +69,62 @@ pub fn hkdf_expand(
     Ok(buf.to_vec())
 }
 
+/// Derives a key from `password` and `salt` with PBKDF2 (RFC 8018), using
+/// HMAC with `digest_alg` as the pseudorandom function, filling `out` with
+/// the derived key.
+pub fn pbkdf2_key_derive(
+    digest_alg: &HashAlgorithm,
+    iterations: u32,
+    salt: &[u8],
+    password: &[u8],
+    out: &mut [u8],
+) -> Result<()> {
+    ensure_nss_initialized();
+    // This follows the PBKDF2 task of the Firefox WebCrypto implementation,
+    // in dom/crypto/WebCryptoTask.cpp.
+    let mut salt_item = nss_sys::SECItem {
+        type_: nss_sys::SECItemType::siBuffer,
+        data: salt.as_ptr() as *mut c_uchar,
+        len: c_uint::try_from(salt.len())?,
+    };
+    // The cipher is ignored when only deriving a key, but it has to be a
+    // valid one.
+    let alg_id = unsafe {
+        AlgorithmID::from_ptr(nss_sys::PK11_CreatePBEV2AlgorithmID(
+            nss_sys::SECOidTag::SEC_OID_PKCS5_PBKDF2,
+            nss_sys::SECOidTag::SEC_OID_HMAC_SHA1,
+            digest_alg.as_hmac_oid_tag(),
+            i32::try_from(out.len())?,
+            i32::try_from(iterations)?,
+            &mut salt_item,
+        ))?
+    };
+    let mut password_item = nss_sys::SECItem {
+        type_: nss_sys::SECItemType::siBuffer,
+        data: password.as_ptr() as *mut c_uchar,
+        len: c_uint::try_from(password.len())?,
+    };
+    let slot = slot::get_internal_slot()?;
+    let sym_key = unsafe {
+        SymKey::from_ptr(nss_sys::PK11_PBEKeyGen(
+            slot.as_mut_ptr(),
+            alg_id.as_mut_ptr(),
+            &mut password_item,
+            nss_sys::PR_FALSE,
+            ptr::null_mut(),
+        ))?
+    };
+    map_nss_secstatus(|| unsafe { nss_sys::PK11_ExtractKeyValue(sym_key.as_mut_ptr()) })?;
+    // As in `hkdf_expand`, this buffer belongs to `sym_key`.
+    let mut key_data = unsafe { *nss_sys::PK11_GetKeyData(sym_key.as_mut_ptr()) };
+    if u32::try_from(out.len())? != key_data.len {
+        return Err(ErrorKind::InternalError.into());
+    }
+    let buf = unsafe { sec_item_as_slice(&mut key_data)? };
+    out.copy_from_slice(buf);
+    Ok(())
+}
+
 /// Safe wrapper around PK11_ImportSymKey that
 /// de-allocates memory when the key goes out of
 /// scope.
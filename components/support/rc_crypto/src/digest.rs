@@ -68,6 +68,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sha1_digest() {
+        assert_eq!(
+            hex::encode(&digest(&SHA1, b"password").unwrap()),
+            "5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8"
+        );
+    }
+
     #[test]
     fn digest_cleanly_rejects_gigantic_messages() {
         let message = vec![0; (std::i32::MAX as usize) + 1];
@@ -0,0 +1,99 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::{digest, error::*};
+
+/// Derive a key from `secret` (typically a user-provided passphrase) and
+/// `salt` as described in RFC 8018, filling `out` with the derived key.
+pub fn derive(
+    digest_alg: &'static digest::Algorithm,
+    iterations: u32,
+    salt: &[u8],
+    secret: &[u8],
+    out: &mut [u8],
+) -> Result<()> {
+    if iterations == 0 {
+        return Err(ErrorKind::InternalError.into());
+    }
+    nss::pk11::sym_key::pbkdf2_key_derive(digest_alg, iterations, salt, secret, out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex;
+
+    #[test]
+    fn pbkdf2_produces_correct_result() {
+        // Test vector from RFC 7914, section 11.
+        let expected = hex::decode(
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+             49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783",
+        )
+        .unwrap();
+        let mut out = vec![0u8; expected.len()];
+        derive(&digest::SHA256, 1, b"salt", b"passwd", &mut out).unwrap();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn pbkdf2_rfc6070_vectors() {
+        let cases: &[(&[u8], &[u8], u32, &str)] = &[
+            (
+                b"password",
+                b"salt",
+                1,
+                "0c60c80f961f0e71f3a9b524af6012062fe037a6",
+            ),
+            (
+                b"password",
+                b"salt",
+                2,
+                "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957",
+            ),
+            (
+                b"password",
+                b"salt",
+                4096,
+                "4b007901b765489abead49d926f721d065a429c1",
+            ),
+            (
+                b"passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                4096,
+                "3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038",
+            ),
+            (
+                b"pass\0word",
+                b"sa\0lt",
+                4096,
+                "56fa6aa75548099dcc37d7f03425e0c3",
+            ),
+        ];
+        for (secret, salt, iterations, expected) in cases {
+            let expected = hex::decode(expected).unwrap();
+            let mut out = vec![0u8; expected.len()];
+            derive(&digest::SHA1, *iterations, salt, secret, &mut out).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn pbkdf2_depends_on_iterations_and_salt() {
+        let mut one = vec![0u8; 32];
+        derive(&digest::SHA256, 2, b"salt", b"passwd", &mut one).unwrap();
+        let mut two = vec![0u8; 32];
+        derive(&digest::SHA256, 3, b"salt", b"passwd", &mut two).unwrap();
+        assert_ne!(one, two);
+        derive(&digest::SHA256, 2, b"pepper", b"passwd", &mut two).unwrap();
+        assert_ne!(one, two);
+    }
+
+    #[test]
+    fn pbkdf2_rejects_zero_iterations() {
+        let mut out = vec![0u8; 32];
+        assert!(derive(&digest::SHA256, 0, b"salt", b"passwd", &mut out).is_err());
+    }
+}
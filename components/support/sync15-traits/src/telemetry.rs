@@ -5,6 +5,7 @@
 //! Manage recording sync telemetry. Assumes some external telemetry
 //! library/code which manages submitting.
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::time;
 
@@ -475,9 +476,12 @@ impl Validation {
         }
     }
 
-    pub fn problem(&mut self, name: &'static str, count: usize) -> &mut Self {
+    pub fn problem(&mut self, name: impl Into<Cow<'static, str>>, count: usize) -> &mut Self {
         if count > 0 {
-            self.problems.push(Problem { name, count });
+            self.problems.push(Problem {
+                name: name.into(),
+                count,
+            });
         }
         self
     }
@@ -485,7 +489,7 @@ impl Validation {
 
 #[derive(Debug, Default, Serialize)]
 pub struct Problem {
-    name: &'static str,
+    name: Cow<'static, str>,
     #[serde(skip_serializing_if = "crate::skip_if_default")]
     count: usize,
 }
@@ -549,6 +553,27 @@ mod engine_tests {
         );
     }
 
+    #[test]
+    fn test_validation() {
+        let mut v = Validation::with_version(1);
+        v.problem("static", 2)
+            .problem(format!("dynamic{}", 1), 1)
+            .problem("none", 0);
+        let mut e = Engine::new("TestEngine");
+        e.validation(v);
+        e.finished();
+        assert_json(
+            &e,
+            serde_json::json!({"name": "TestEngine",
+             "when": 0.0,
+             "validation": {
+                 "version": 1,
+                 "problems": [{"name": "static", "count": 2}, {"name": "dynamic1", "count": 1}]
+             }
+            }),
+        );
+    }
+
     #[test]
     fn test_raw() {
         let mut e = Engine::new("TestEngine");
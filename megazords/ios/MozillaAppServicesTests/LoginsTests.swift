@@ -49,7 +49,7 @@ class LoginsTests: XCTestCase {
         let id0 = try! storage.add(login: LoginRecord(
             id: "",
             password: "hunter2",
-            hostname: "https://www.example.com",
+            origin: "https://www.example.com",
             username: "cooluser33",
             formSubmitURL: "https://www.example.com/login",
             httpRealm: nil,
@@ -69,7 +69,7 @@ class LoginsTests: XCTestCase {
         let id1 = try! storage.add(login: LoginRecord(
             id: "",
             password: "hunter3",
-            hostname: "https://www.example2.com",
+            origin: "https://www.example2.com",
             username: "cooluser44",
             formSubmitURL: nil,
             httpRealm: "Something Something",
@@ -94,7 +94,7 @@ class LoginsTests: XCTestCase {
         let id0 = try! storage.add(login: LoginRecord(
             id: "",
             password: "hunter5",
-            hostname: "https://www.example5.com",
+            origin: "https://www.example5.com",
             username: "cooluser55",
             formSubmitURL: "https://www.example5.com",
             httpRealm: nil,
@@ -109,7 +109,7 @@ class LoginsTests: XCTestCase {
         let dupeLogin = LoginRecord(
             id: "",
             password: "hunter3",
-            hostname: "https://www.example5.com",
+            origin: "https://www.example5.com",
             username: "cooluser55",
             formSubmitURL: "https://www.example5.com",
             httpRealm: nil,
@@ -124,7 +124,7 @@ class LoginsTests: XCTestCase {
         let nullValueLogin = LoginRecord(
             id: "",
             password: "hunter3",
-            hostname: "https://www.example6.com",
+            origin: "https://www.example6.com",
             username: "\0cooluser56",
             formSubmitURL: "https://www.example6.com",
             httpRealm: nil,
@@ -139,4 +139,28 @@ class LoginsTests: XCTestCase {
         XCTAssertThrowsError(try storage.ensureValid(login: dupeLogin))
         XCTAssertThrowsError(try storage.ensureValid(login: nullValueLogin))
     }
+
+    func testGeneratePassword() {
+        XCTAssertEqual(try LoginsStorage.generatePassword().count, 15)
+        let password = try! LoginsStorage.generatePassword(rules: "minlength: 20; required: digit;")
+        XCTAssertEqual(password.count, 20)
+        XCTAssertTrue(password.contains { $0.isNumber })
+
+        XCTAssertThrowsError(
+            try LoginsStorage.generatePassword(rules: "maxlength: 2; required: upper; required: lower; required: digit;")
+        ) { error in
+            guard case LoginsStoreError.invalidPasswordRules = error else {
+                return XCTFail("Unexpected error: \(error)")
+            }
+        }
+    }
+
+    func testLoginRecordOriginJSON() {
+        let record = try! LoginRecord(fromJSONString: "{\"origin\": \"https://www.example.com\"}")
+        XCTAssertEqual(record.origin, "https://www.example.com")
+        XCTAssertEqual(record.toJSONDict()["origin"] as? String, "https://www.example.com")
+        // Records written by older versions use `hostname`.
+        let old = try! LoginRecord(fromJSONString: "{\"hostname\": \"https://www.example.org\"}")
+        XCTAssertEqual(old.origin, "https://www.example.org")
+    }
 }